@@ -0,0 +1,53 @@
+/* C interface of owned-alloc, built with the `ffi` feature. */
+#ifndef OWNED_ALLOC_H
+#define OWNED_ALLOC_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Alignment of the blocks of owned_alloc_alloc, like malloc. */
+#define OWNED_ALLOC_DEFAULT_ALIGN (2 * sizeof(size_t))
+
+/* An arena: blocks live until the arena is reset or destroyed. */
+typedef struct OwnedAllocArena OwnedAllocArena;
+
+/* A pool keeping freed blocks for reuse, in power-of-two size classes. */
+typedef struct OwnedAllocPool OwnedAllocPool;
+
+void *owned_alloc_alloc(size_t size);
+void *owned_alloc_alloc_aligned(size_t size, size_t align);
+void owned_alloc_free(void *ptr);
+void *owned_alloc_realloc(void *ptr, size_t size);
+
+/* A chunk_size of 0 selects the default chunk size. Returns NULL on
+ * allocation failure, as owned_alloc_pool_new does. */
+OwnedAllocArena *owned_alloc_arena_new(size_t chunk_size);
+void *owned_alloc_arena_alloc(OwnedAllocArena *arena, size_t size, size_t align);
+void owned_alloc_arena_reset(OwnedAllocArena *arena);
+void owned_alloc_arena_destroy(OwnedAllocArena *arena);
+
+OwnedAllocPool *owned_alloc_pool_new(void);
+void *owned_alloc_pool_alloc(OwnedAllocPool *pool, size_t size, size_t align);
+/* size and align must be the ones passed to owned_alloc_pool_alloc. */
+void owned_alloc_pool_free(OwnedAllocPool *pool, void *ptr, size_t size, size_t align);
+void owned_alloc_pool_destroy(OwnedAllocPool *pool);
+
+/* Makes the crate take its memory from the host allocator. Must be called
+ * before any allocation; only the first call succeeds. realloc may be NULL. */
+bool owned_alloc_set_allocator(void *(*malloc)(size_t),
+                               void (*free)(void *),
+                               void *(*realloc)(void *, size_t));
+bool owned_alloc_set_allocator_ctx(void *ctx,
+                                   void *(*malloc)(void *ctx, size_t),
+                                   void (*free)(void *ctx, void *),
+                                   void *(*realloc)(void *ctx, void *, size_t));
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
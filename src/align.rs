@@ -0,0 +1,94 @@
+//! Alignment arithmetic on addresses and pointers, so code rounding the
+//! pointers of its allocations shares one checked implementation. The
+//! pointer functions keep the provenance of the pointer they are given: they
+//! only offset it, and never turn an integer back into a pointer.
+//!
+//! Every `align` must be a power of two, or the functions panic.
+//!
+//! # Example
+//! ```rust
+//! extern crate owned_alloc;
+//!
+//! use owned_alloc::{align, RawVec};
+//!
+//! assert_eq!(align::align_up(13, 8), Some(16));
+//! assert_eq!(align::align_down(13, 8), 8);
+//! assert_eq!(align::padding_for(13, 8), 3);
+//! assert_eq!(align::align_up(usize::MAX, 2), None);
+//!
+//! let raw = RawVec::<u8>::with_capacity(64);
+//! let start = raw.raw().as_ptr();
+//! let aligned = align::align_ptr_up(start.wrapping_add(1), 16).unwrap();
+//! assert!(align::is_ptr_aligned_to(aligned, 16));
+//! assert!(aligned > start && aligned <= start.wrapping_add(16));
+//! ```
+
+/// Rounds `addr` up to a multiple of `align`, or returns `None` on overflow.
+#[inline]
+pub const fn align_up(addr: usize, align: usize) -> Option<usize> {
+    addr.checked_add(padding_for(addr, align))
+}
+
+/// Rounds `addr` down to a multiple of `align`.
+#[inline]
+pub const fn align_down(addr: usize, align: usize) -> usize {
+    assert!(align.is_power_of_two(), "Alignment is not a power of two");
+    addr & !(align - 1)
+}
+
+/// Tests if `addr` is a multiple of `align`.
+#[inline]
+pub const fn is_aligned_to(addr: usize, align: usize) -> bool {
+    align_down(addr, align) == addr
+}
+
+/// Bytes to add to `addr` to get to the next multiple of `align`.
+#[inline]
+pub const fn padding_for(addr: usize, align: usize) -> usize {
+    assert!(align.is_power_of_two(), "Alignment is not a power of two");
+    addr.wrapping_neg() & (align - 1)
+}
+
+/// Offsets `ptr` up to the next address multiple of `align`, or returns
+/// `None` if the address would overflow. The pointer is only offset, never
+/// dereferenced: whether the result is in bounds is up to the caller.
+#[inline]
+pub fn align_ptr_up<T>(ptr: *mut T, align: usize) -> Option<*mut T> {
+    align_up(ptr.addr(), align)?;
+    Some(ptr.wrapping_byte_add(padding_for(ptr.addr(), align)))
+}
+
+/// Offsets `ptr` down to the previous address multiple of `align`. The
+/// pointer is only offset, never dereferenced: whether the result is in
+/// bounds is up to the caller.
+#[inline]
+pub fn align_ptr_down<T>(ptr: *mut T, align: usize) -> *mut T {
+    let addr = ptr.addr();
+    ptr.wrapping_byte_sub(addr - align_down(addr, align))
+}
+
+/// Tests if the address of `ptr` is a multiple of `align`.
+#[inline]
+pub fn is_ptr_aligned_to<T>(ptr: *const T, align: usize) -> bool {
+    is_aligned_to(ptr.addr(), align)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{align_down, align_ptr_down, align_up, is_aligned_to, padding_for};
+
+    #[test]
+    fn rounding_edges() {
+        assert_eq!(align_up(0, 4096), Some(0));
+        assert_eq!(align_up(4097, 4096), Some(8192));
+        assert_eq!(align_up(usize::MAX - 6, 8), None);
+        assert_eq!(align_up(usize::MAX, 1), Some(usize::MAX));
+        assert_eq!(align_down(usize::MAX, 16), usize::MAX - 15);
+        assert_eq!(padding_for(24, 8), 0);
+        assert!(is_aligned_to(48, 16) && !is_aligned_to(40, 16));
+
+        let mut words = [0u64; 4];
+        let byte = words.as_mut_ptr().cast::<u8>().wrapping_add(13);
+        assert_eq!(align_ptr_down(byte, 8), words[1 ..].as_mut_ptr().cast());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{AllocError, ALLOCATOR};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// An allocation of a `T` aligned to `ALIGN` bytes, so that the alignment
+/// requirement is part of the type instead of an argument to remember at
+/// every allocation. `ALIGN` must be a power of two, at least the alignment
+/// of `T`; otherwise, the constructors fail to compile.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::AlignedAlloc;
+///
+/// type Descriptor = AlignedAlloc<[u32; 4], 4096>;
+///
+/// let mut desc = Descriptor::new([0; 4]);
+/// desc[1] = 7;
+/// assert_eq!(desc.raw().as_ptr() as usize % 4096, 0);
+/// assert_eq!(desc.into_inner(), [0, 7, 0, 0]);
+/// ```
+///
+/// An alignment below the one of `T` is rejected:
+/// ```rust,compile_fail
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::AlignedAlloc;
+///
+/// let value = AlignedAlloc::<u64, 2>::new(5);
+/// ```
+pub struct AlignedAlloc<T, const ALIGN: usize> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<T>,
+}
+
+impl<T, const ALIGN: usize> AlignedAlloc<T, ALIGN> {
+    /// Layout of the allocation, checked when a constructor is instantiated.
+    const LAYOUT: Layout = {
+        assert!(ALIGN.is_power_of_two(), "ALIGN must be a power of two");
+        assert!(ALIGN >= mem::align_of::<T>(), "ALIGN must be at least the alignment of T");
+        match Layout::from_size_align(mem::size_of::<T>(), ALIGN) {
+            Ok(layout) => layout,
+            Err(_) => panic!("size of T overflows when padded to ALIGN"),
+        }
+    };
+
+    panicking! {
+        /// Allocates `value` at alignment `ALIGN`. In case of allocation error,
+        /// the function panics.
+        #[inline]
+        #[track_caller]
+        pub fn new(value: T) -> Self {
+            Self::try_new(value).unwrap_or_else(|err| panic!("AlignedAlloc::new: {}", err))
+        }
+    }
+
+    /// Allocates `value` at alignment `ALIGN`. In case of allocation error,
+    /// `Err` is returned and `value` is dropped.
+    #[inline]
+    #[track_caller]
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        let layout = Self::LAYOUT;
+        let ptr = if layout.size() == 0 {
+            unsafe { NonNull::new_unchecked(ALIGN as *mut T) }
+        } else {
+            let ptr = NonNull::new(unsafe { ALLOCATOR.alloc(layout) })
+                .ok_or(AllocError { layout })?
+                .cast::<T>();
+            #[cfg(feature = "track-callers")]
+            crate::leak::record(ptr.as_ptr().cast(), layout.size());
+            ptr
+        };
+        unsafe { ptr.as_ptr().write(value) };
+        Ok(Self {
+            ptr,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Layout of the allocation: the size of `T` and alignment `ALIGN`.
+    #[inline]
+    pub const fn layout() -> Layout {
+        Self::LAYOUT
+    }
+
+    /// The pointer to the value, aligned to `ALIGN`.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<T> {
+        self.ptr
+    }
+
+    /// Moves the value out and frees the allocation.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        let value = unsafe { self.ptr.as_ptr().read() };
+        unsafe { self.free() };
+        mem::forget(self);
+        value
+    }
+
+    /// Frees the allocation, without dropping the value.
+    #[inline]
+    unsafe fn free(&self) {
+        let layout = Self::LAYOUT;
+        if layout.size() != 0 {
+            #[cfg(feature = "track-callers")]
+            crate::leak::forget(self.ptr.as_ptr().cast());
+            ALLOCATOR.dealloc(self.ptr.as_ptr().cast(), layout);
+        }
+    }
+}
+
+impl<T, const ALIGN: usize> Deref for AlignedAlloc<T, ALIGN> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, const ALIGN: usize> DerefMut for AlignedAlloc<T, ALIGN> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T, const ALIGN: usize> Drop for AlignedAlloc<T, ALIGN> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            self.ptr.as_ptr().drop_in_place();
+            self.free();
+        }
+    }
+}
+
+impl<T, const ALIGN: usize> fmt::Debug for AlignedAlloc<T, ALIGN>
+where
+    T: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AlignedAlloc {{ value: {:?}, align: {} }}", **self, ALIGN)
+    }
+}
+
+unsafe impl<T, const ALIGN: usize> Send for AlignedAlloc<T, ALIGN> where T: Send {}
+unsafe impl<T, const ALIGN: usize> Sync for AlignedAlloc<T, ALIGN> where T: Sync {}
+
+#[cfg(test)]
+mod test {
+    use super::AlignedAlloc;
+
+    #[test]
+    fn honours_the_type_alignment() {
+        let small = AlignedAlloc::<u8, 256>::new(9);
+        assert_eq!(small.raw().as_ptr() as usize % 256, 0);
+        assert_eq!(AlignedAlloc::<u8, 256>::layout().size(), 1);
+        let empty = AlignedAlloc::<(), 64>::new(());
+        assert_eq!(empty.raw().as_ptr() as usize % 64, 0);
+
+        let mut names = AlignedAlloc::<_, 128>::new(alloc::vec!["a"]);
+        names.push("b");
+        assert_eq!(names.into_inner(), ["a", "b"]);
+        assert_eq!(*small, 9);
+    }
+}
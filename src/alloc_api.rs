@@ -0,0 +1,16 @@
+//! The allocator API the crate implements and consumes: `core`'s with the
+//! `nightly` feature, and its `allocator-api2` mirror on stable Rust.
+
+#[cfg(feature = "nightly")]
+pub use core::alloc::{AllocError, Allocator};
+
+#[cfg(not(feature = "nightly"))]
+pub use allocator_api2::alloc::{AllocError, Allocator};
+
+/// The `Box` and `Vec` taking an allocator parameter of the trait above.
+#[cfg(feature = "nightly")]
+pub use alloc::{boxed::Box, vec::Vec};
+
+/// The `Box` and `Vec` taking an allocator parameter of the trait above.
+#[cfg(not(feature = "nightly"))]
+pub use allocator_api2::{boxed::Box, vec::Vec};
@@ -0,0 +1,144 @@
+use core::{alloc::Layout, fmt, ptr::NonNull};
+
+/// Exposes an allocator of this crate (the `Allocator`, a `FreeListAlloc`,
+/// a `&Bump`, ...) through the `allocator-api2` `Allocator` trait, so crates
+/// standardizing on it, such as `hashbrown`, can use it on stable Rust.
+///
+/// # Example
+/// ```rust
+/// extern crate allocator_api2;
+/// extern crate owned_alloc;
+///
+/// use allocator_api2::vec::Vec;
+/// use owned_alloc::{Allocator, Api2Alloc, FreeListAlloc};
+///
+/// let alloc = FreeListAlloc::new(Allocator::new());
+/// let mut vec = Vec::new_in(Api2Alloc::new(&alloc));
+/// vec.extend_from_slice(&[1, 2, 3]);
+/// assert_eq!(vec, [1, 2, 3]);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct Api2Alloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    inner: A,
+}
+
+impl<A> Api2Alloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Wraps `inner`.
+    #[inline]
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped allocator.
+    #[inline]
+    pub const fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    /// Unwraps the allocator.
+    #[inline]
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+unsafe impl<A> allocator_api2::alloc::Allocator for Api2Alloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        self.inner
+            .allocate(layout)
+            .map_err(|_| allocator_api2::alloc::AllocError)
+    }
+
+    #[inline]
+    fn allocate_zeroed(
+        &self,
+        layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        self.inner
+            .allocate_zeroed(layout)
+            .map_err(|_| allocator_api2::alloc::AllocError)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        self.inner
+            .grow(ptr, old_layout, new_layout)
+            .map_err(|_| allocator_api2::alloc::AllocError)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        self.inner
+            .grow_zeroed(ptr, old_layout, new_layout)
+            .map_err(|_| allocator_api2::alloc::AllocError)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        self.inner
+            .shrink(ptr, old_layout, new_layout)
+            .map_err(|_| allocator_api2::alloc::AllocError)
+    }
+}
+
+impl<A> fmt::Debug for Api2Alloc<A>
+where
+    A: crate::alloc_api::Allocator + fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Api2Alloc({:?})", self.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Api2Alloc;
+    use crate::Bump;
+    use allocator_api2::{boxed::Box, vec::Vec};
+
+    #[test]
+    fn collections_in_a_bump() {
+        let mut bump = Bump::with_chunk_size(256);
+        {
+            let alloc = Api2Alloc::new(&bump);
+            let mut vec = Vec::new_in(alloc);
+            vec.extend(0 .. 20u32);
+            let boxed = Box::new_in([7u8; 16], alloc);
+            assert_eq!(vec.iter().sum::<u32>(), 190);
+            assert_eq!(*boxed, [7; 16]);
+        }
+        assert!(bump.capacity() >= 16 + 20 * 4);
+        bump.reset();
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{Fragmentation, RawVec, Snapshot, UninitAlloc};
+use alloc::vec::Vec;
+use core::{cell::UnsafeCell, fmt, mem};
+
+/// Capacity in bytes of the first chunk of an `Arena`. Each following chunk
+/// is twice as big as the previous one.
+const FIRST_CHUNK_BYTES: usize = 1024;
+
+struct Chunk<T> {
+    storage: UninitAlloc<[T]>,
+    len: usize,
+}
+
+impl<T> Chunk<T> {
+    #[inline]
+    fn new(cap: usize) -> Self {
+        let chunk = Self {
+            storage: UninitAlloc::from(RawVec::with_capacity(cap)),
+            len: 0,
+        };
+        if mem::size_of::<T>() != 0 {
+            crate::valgrind::create_mempool(chunk.ptr().cast());
+        }
+        crate::asan::poison(chunk.ptr().cast(), chunk.cap() * mem::size_of::<T>());
+        chunk
+    }
+
+    #[inline]
+    fn cap(&self) -> usize {
+        unsafe { self.storage.raw().as_ref().len() }
+    }
+
+    #[inline]
+    fn ptr(&self) -> *mut T {
+        self.storage.raw().cast::<T>().as_ptr()
+    }
+
+    /// The values of the chunk.
+    #[inline]
+    fn values_mut(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr(), self.len) }
+    }
+
+    /// Drops the elements from `len` on, last allocated first.
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.len -= 1;
+            unsafe {
+                let ptr = self.ptr().add(self.len);
+                ptr.drop_in_place();
+                if mem::size_of::<T>() != 0 {
+                    crate::valgrind::mempool_free(self.ptr().cast(), ptr.cast());
+                }
+                crate::asan::poison(ptr.cast(), mem::size_of::<T>());
+            }
+        }
+    }
+}
+
+impl<T> Drop for Chunk<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // Values of zero-sized types all live at the same dangling address.
+        if mem::size_of::<T>() != 0 {
+            crate::valgrind::destroy_mempool(self.ptr().cast());
+        }
+        crate::asan::unpoison(self.ptr().cast(), self.cap() * mem::size_of::<T>());
+    }
+}
+
+/// A typed arena: values of type `T` are allocated in chunks which are freed
+/// together with the arena, and unlike in a `Bump`, values are dropped when
+/// freed.
+///
+/// `snapshot` and `rewind` allow rolling back speculative work: rewinding
+/// drops every value allocated after the snapshot, most recent first.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::Arena;
+///
+/// let mut arena = Arena::new();
+/// arena.alloc(String::from("kept"));
+/// let snapshot = arena.snapshot();
+/// arena.alloc(String::from("speculative"));
+/// assert_eq!(arena.len(), 2);
+///
+/// arena.rewind(snapshot);
+/// assert_eq!(arena.len(), 1);
+/// ```
+pub struct Arena<T> {
+    chunks: UnsafeCell<Vec<Chunk<T>>>,
+    limit: usize,
+}
+
+impl<T> Arena<T> {
+    /// Creates an empty arena. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::with_limit(usize::MAX)
+    }
+
+    /// Creates an empty arena holding at most `limit` bytes in chunks. No
+    /// allocation is performed.
+    #[inline]
+    pub const fn with_limit(limit: usize) -> Self {
+        Self {
+            chunks: UnsafeCell::new(Vec::new()),
+            limit,
+        }
+    }
+
+    panicking! {
+        /// Moves `value` into the arena. In case of allocation error, or if the
+        /// limit is reached, the function panics.
+        #[allow(clippy::mut_from_ref)]
+        pub fn alloc(&self, value: T) -> &mut T {
+            match self.try_alloc(value) {
+                Ok(value) => value,
+                Err(_) => panic!("Arena limit of {} bytes reached", self.limit),
+            }
+        }
+    }
+
+    /// Moves `value` into the arena, or gives it back if the limit is
+    /// reached. In case of allocation error, the function panics.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc(&self, value: T) -> Result<&mut T, T> {
+        let chunks = unsafe { &mut *self.chunks.get() };
+        let full = match chunks.last() {
+            Some(chunk) => chunk.len == chunk.cap(),
+            None => true,
+        };
+        if full {
+            let size = mem::size_of::<T>();
+            let cap = match chunks.last() {
+                Some(chunk) => chunk.cap().saturating_mul(2),
+                None if size == 0 => usize::MAX,
+                None => (FIRST_CHUNK_BYTES / size).max(1),
+            };
+            // Zero-sized values take no room.
+            let room = match size {
+                0 => usize::MAX,
+                _ => self.limit.saturating_sub(Self::held(chunks)) / size,
+            };
+            if room == 0 {
+                return Err(value);
+            }
+            chunks.push(Chunk::new(cap.min(room)));
+        }
+        let chunk = chunks.last_mut().unwrap();
+        unsafe {
+            let ptr = chunk.ptr().add(chunk.len);
+            if mem::size_of::<T>() != 0 {
+                crate::valgrind::mempool_alloc(chunk.ptr().cast(), ptr.cast(), mem::size_of::<T>());
+            }
+            crate::asan::unpoison(ptr.cast(), mem::size_of::<T>());
+            ptr.write(value);
+            chunk.len += 1;
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Number of values alive in the arena.
+    #[inline]
+    pub fn len(&self) -> usize {
+        let chunks = unsafe { &*self.chunks.get() };
+        chunks.iter().map(|chunk| chunk.len).sum()
+    }
+
+    /// Bytes of memory held in chunks, used or not.
+    #[inline]
+    pub fn capacity_bytes(&self) -> usize {
+        Self::held(unsafe { &*self.chunks.get() })
+    }
+
+    /// The maximum number of bytes held in chunks.
+    #[inline]
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Iterates over the values of the arena, in allocation order. Takes
+    /// `&mut self` since `alloc` hands out mutable references through shared
+    /// ones: none of them may be alive while iterating.
+    #[inline]
+    pub fn iter(&mut self) -> impl Iterator<Item = &T> {
+        self.iter_mut().map(|value| &*value)
+    }
+
+    /// Iterates mutably over the values of the arena, in allocation order.
+    #[inline]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.chunks.get_mut().iter_mut().flat_map(|chunk| chunk.values_mut())
+    }
+
+    /// Reports the free slots of the last chunk as a single free block, the
+    /// only room left without a new chunk: earlier chunks are always full.
+    /// Slots are never padded, so nothing is wasted.
+    #[inline]
+    pub fn fragmentation(&self) -> Fragmentation {
+        let chunks = unsafe { &*self.chunks.get() };
+        let mut frag = Fragmentation::new();
+        if let Some(chunk) = chunks.last() {
+            frag.add_free((chunk.cap() - chunk.len) * mem::size_of::<T>(), 1);
+        }
+        frag
+    }
+
+    /// Tests if the arena holds no value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The current position of the arena.
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot {
+        let chunks = unsafe { &*self.chunks.get() };
+        match chunks.last() {
+            Some(chunk) => Snapshot::new(chunks.len() - 1, chunk.len),
+            None => Snapshot::START,
+        }
+    }
+
+    /// Drops every value allocated after `snapshot` was taken, most recent
+    /// first, and frees the chunks they used. Panics if the snapshot is ahead
+    /// of the current position, e.g. because the arena was already rewound
+    /// past it.
+    pub fn rewind(&mut self, snapshot: Snapshot) {
+        assert!(
+            snapshot <= self.snapshot(),
+            "Snapshot is ahead of the current position"
+        );
+        let chunks = self.chunks.get_mut();
+        while chunks.len() > snapshot.chunk() + 1 {
+            if let Some(mut chunk) = chunks.pop() {
+                chunk.truncate(0);
+            }
+        }
+        if let Some(chunk) = chunks.last_mut() {
+            chunk.truncate(snapshot.offset());
+        }
+    }
+
+    /// Drops every value of the arena.
+    #[inline]
+    pub fn clear(&mut self) {
+        #[cfg(feature = "tracing")]
+        crate::trace::reset("Arena", self.capacity_bytes());
+        self.rewind(Snapshot::START);
+    }
+
+    #[inline]
+    fn held(chunks: &[Chunk<T>]) -> usize {
+        chunks.iter().map(|chunk| chunk.cap() * mem::size_of::<T>()).sum()
+    }
+}
+
+impl<T> Default for Arena<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Arena<T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> fmt::Debug for Arena<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Arena {{ len: {} }}", self.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Arena;
+    use core::cell::Cell;
+
+    struct Counted<'c>(&'c Cell<usize>);
+
+    impl<'c> Drop for Counted<'c> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn rewind_drops_across_chunks() {
+        let drops = Cell::new(0);
+        let mut arena = Arena::new();
+        for _ in 0 .. 10 {
+            arena.alloc(Counted(&drops));
+        }
+        let snapshot = arena.snapshot();
+        for _ in 0 .. 1000 {
+            arena.alloc(Counted(&drops));
+        }
+
+        arena.rewind(snapshot);
+        assert_eq!(drops.get(), 1000);
+        assert_eq!(arena.len(), 10);
+        drop(arena);
+        assert_eq!(drops.get(), 1010);
+    }
+
+    #[test]
+    fn iterates_in_allocation_order() {
+        let mut arena = Arena::new();
+        for i in 0 .. 1000u64 {
+            arena.alloc(i);
+        }
+        for value in arena.iter_mut() {
+            *value *= 2;
+        }
+        assert!(arena.iter().copied().eq((0 .. 1000).map(|i| i * 2)));
+    }
+
+    #[test]
+    fn limit_shrinks_last_chunk() {
+        let arena = Arena::with_limit(100);
+        for i in 0 .. 12u64 {
+            arena.try_alloc(i).unwrap();
+        }
+        assert_eq!(arena.try_alloc(12), Err(12));
+        assert_eq!(arena.capacity_bytes(), 96);
+    }
+}
@@ -0,0 +1,56 @@
+//! Manual poisoning of memory held by the recycling layers of the crate, for
+//! AddressSanitizer and Valgrind.
+//!
+//! Memory sitting in a free list, a pool or the unused part of an arena is
+//! still allocated as far as ASan knows, so accesses through dangling
+//! pointers into it go unnoticed. With the `sanitizer-detect` feature, in
+//! builds with `-Zsanitizer=address`, such memory is poisoned when it enters
+//! the recycling layer and unpoisoned when it is handed out again, or given
+//! back to the backend. With the `valgrind` feature, the same memory is
+//! marked as unaddressable and undefined for memcheck.
+
+// `cfg(sanitize)` is unstable, and rejected even where the feature is off.
+#[cfg(feature = "sanitizer-detect")]
+mod sanitizer {
+    #[cfg(sanitize = "address")]
+    extern "C" {
+        fn __asan_poison_memory_region(addr: *const core::ffi::c_void, size: usize);
+        fn __asan_unpoison_memory_region(addr: *const core::ffi::c_void, size: usize);
+    }
+
+    #[inline(always)]
+    pub(super) fn poison(ptr: *const u8, size: usize) {
+        #[cfg(sanitize = "address")]
+        unsafe {
+            __asan_poison_memory_region(ptr.cast(), size)
+        }
+        #[cfg(not(sanitize = "address"))]
+        let _ = (ptr, size);
+    }
+
+    #[inline(always)]
+    pub(super) fn unpoison(ptr: *const u8, size: usize) {
+        #[cfg(sanitize = "address")]
+        unsafe {
+            __asan_unpoison_memory_region(ptr.cast(), size)
+        }
+        #[cfg(not(sanitize = "address"))]
+        let _ = (ptr, size);
+    }
+}
+
+/// Marks `size` bytes from `ptr` as unaddressable.
+#[inline(always)]
+pub(crate) fn poison(ptr: *const u8, size: usize) {
+    #[cfg(feature = "sanitizer-detect")]
+    sanitizer::poison(ptr, size);
+    crate::valgrind::make_noaccess(ptr, size);
+}
+
+/// Marks `size` bytes from `ptr` as addressable again.
+#[inline(always)]
+pub(crate) fn unpoison(ptr: *const u8, size: usize) {
+    #[cfg(feature = "sanitizer-detect")]
+    sanitizer::unpoison(ptr, size);
+    crate::valgrind::make_undefined(ptr, size);
+}
@@ -0,0 +1,160 @@
+use crate::RawVec;
+use core::fmt;
+
+/// A guard for writing many elements past the length of a container backed
+/// by a `RawVec`. The length is only advanced by `finish`: if the guard is
+/// dropped before, e.g. because an iterator or a constructor panicked
+/// mid-batch, the elements written so far are dropped and the length is left
+/// untouched, so `extend`-like operations are panic-safe by construction.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{BatchInitGuard, RawVec};
+///
+/// let mut raw = RawVec::<String>::with_capacity(4);
+/// let mut len = 0;
+///
+/// let mut guard = BatchInitGuard::new(&mut raw, &mut len);
+/// guard.extend(["a", "b"].iter().map(|s| String::from(*s)));
+/// assert_eq!(guard.finish(), 2);
+/// assert_eq!(len, 2);
+///
+/// // A panic mid-batch leaves the length and the first elements alone.
+/// let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+///     let mut guard = BatchInitGuard::new(&mut raw, &mut len);
+///     guard.push(String::from("c"));
+///     panic!("constructor failed");
+/// }));
+/// assert!(result.is_err());
+/// assert_eq!(len, 2);
+///
+/// let mut vec = unsafe { raw.into_vec(len) };
+/// assert_eq!(vec, ["a", "b"]);
+/// # vec.clear();
+/// ```
+pub struct BatchInitGuard<'a, T> {
+    raw: &'a mut RawVec<T>,
+    len: &'a mut usize,
+    written: usize,
+}
+
+impl<'a, T> BatchInitGuard<'a, T> {
+    /// Starts a batch writing past `len` elements of `raw`. If `len` is
+    /// greater than the capacity of `raw`, the function panics.
+    #[inline]
+    pub fn new(raw: &'a mut RawVec<T>, len: &'a mut usize) -> Self {
+        assert!(*len <= raw.cap(), "Length is greater than the capacity");
+        Self {
+            raw,
+            len,
+            written: 0,
+        }
+    }
+
+    /// Writes `value` after the last element written. If there is no room
+    /// left, the function panics.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        if self.try_push(value).is_err() {
+            panic!("Batch exceeds the capacity of {}", self.raw.cap());
+        }
+    }
+
+    /// Writes `value` after the last element written, or gives it back if
+    /// there is no room left.
+    #[inline]
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.remaining() == 0 {
+            return Err(value);
+        }
+        unsafe {
+            self.raw.raw().as_ptr().add(*self.len + self.written).write(value);
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Writes every element of `iter`. If there is no room left for one of
+    /// them, the function panics.
+    #[inline]
+    pub fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in iter {
+            self.push(value);
+        }
+    }
+
+    /// Number of elements written so far.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Number of elements which can still be written.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.raw.cap() - *self.len - self.written
+    }
+
+    /// Commits the batch, advancing the length past the elements written,
+    /// and returns how many there were.
+    #[inline]
+    pub fn finish(mut self) -> usize {
+        let written = self.written;
+        *self.len += written;
+        self.written = 0;
+        written
+    }
+}
+
+impl<'a, T> Drop for BatchInitGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        let start = unsafe { self.raw.raw().as_ptr().add(*self.len) };
+        let tail = core::ptr::slice_from_raw_parts_mut(start, self.written);
+        self.written = 0;
+        unsafe { tail.drop_in_place() }
+    }
+}
+
+impl<'a, T> fmt::Debug for BatchInitGuard<'a, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BatchInitGuard {{ len: {}, written: {} }}",
+            self.len, self.written
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BatchInitGuard;
+    use crate::RawVec;
+    use alloc::rc::Rc;
+
+    #[test]
+    fn drop_without_finish_drops_the_tail() {
+        let witness = Rc::new(());
+        let mut raw = RawVec::<Rc<()>>::with_capacity(3);
+        let mut len = 0;
+        let mut guard = BatchInitGuard::new(&mut raw, &mut len);
+        guard.push(witness.clone());
+        assert_eq!(guard.finish(), 1);
+
+        let mut guard = BatchInitGuard::new(&mut raw, &mut len);
+        guard.extend([witness.clone(), witness.clone()]);
+        assert_eq!(guard.try_push(witness.clone()).map_err(drop), Err(()));
+        assert_eq!(Rc::strong_count(&witness), 4);
+        drop(guard);
+        assert_eq!((len, Rc::strong_count(&witness)), (1, 2));
+
+        drop(unsafe { raw.into_vec(len) });
+        assert_eq!(Rc::strong_count(&witness), 1);
+    }
+}
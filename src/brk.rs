@@ -0,0 +1,340 @@
+use crate::alloc_api::AllocError;
+#[cfg(feature = "os")]
+use crate::{page_size, sys, OsError};
+use core::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    fmt,
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+};
+
+/// Size of a WebAssembly page.
+#[cfg(target_arch = "wasm32")]
+pub const WASM_PAGE_SIZE: usize = 65536;
+
+/// A source of memory for a `Brk`, extending a single region.
+///
+/// # Safety
+/// The memory returned by `grow` must be valid for reads and writes, and not
+/// used by anything else, until the source is dropped. It should start right
+/// where the memory returned by the previous call ended: a `Brk` abandons
+/// the rest of its region otherwise.
+pub unsafe trait Grow {
+    /// Extends the region by at least `additional` bytes, returning the new
+    /// memory, or `None` if the region cannot grow anymore.
+    fn grow(&mut self, additional: usize) -> Option<NonNull<[u8]>>;
+}
+
+/// A contiguous region growing monotonically, like the data segment with
+/// `sbrk`: the simplest backend there is, for kernels and WebAssembly
+/// modules which have one growable region and nothing else.
+///
+/// Memory is carved at the break, which only moves up: blocks given back are
+/// not reused. Recycling layers are meant to sit on top, taking big regions
+/// with `sbrk` (e.g. for a `Tlsf`), or blocks through the `Allocator` trait.
+/// The region grows through its `Grow` source when the break reaches its
+/// end.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use core::mem::MaybeUninit;
+/// use owned_alloc::{Brk, StaticRegion, Tlsf};
+///
+/// let memory = Box::leak(Box::new([MaybeUninit::<u8>::uninit(); 8192]));
+/// let brk = Brk::new(StaticRegion::new(memory));
+/// let region = brk.sbrk(4096).unwrap();
+/// let tlsf = unsafe { Tlsf::from_raw(region.cast(), region.len()) };
+/// assert_eq!(brk.size(), 4096);
+/// # drop(tlsf);
+/// ```
+pub struct Brk<G>
+where
+    G: Grow,
+{
+    source: UnsafeCell<G>,
+    brk: Cell<*mut u8>,
+    end: Cell<*mut u8>,
+    size: Cell<usize>,
+}
+
+impl<G> Brk<G>
+where
+    G: Grow,
+{
+    /// Creates a region growing through `source`. Nothing is taken from it
+    /// until the first allocation.
+    #[inline]
+    pub const fn new(source: G) -> Self {
+        Self {
+            source: UnsafeCell::new(source),
+            brk: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+            size: Cell::new(0),
+        }
+    }
+
+    /// Moves the break up by `increment` bytes, returning the memory between
+    /// the old and the new break, or `None` if the region cannot grow enough.
+    #[inline]
+    pub fn sbrk(&self, increment: usize) -> Option<NonNull<[u8]>> {
+        let layout = Layout::from_size_align(increment, 1).ok()?;
+        let ptr = self.carve(layout)?;
+        Some(NonNull::slice_from_raw_parts(ptr, increment))
+    }
+
+    /// The current break: the end of the memory handed out.
+    #[inline]
+    pub fn brk(&self) -> *mut u8 {
+        self.brk.get()
+    }
+
+    /// Bytes handed out so far, alignment padding included.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    /// The source of the region.
+    #[inline]
+    pub fn source(&self) -> &G {
+        unsafe { &*self.source.get() }
+    }
+
+    fn carve(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if let Some(ptr) = self.bump(layout) {
+            return Some(ptr);
+        }
+        let room = self.end.get() as usize - self.brk.get() as usize;
+        let needed = layout.size().checked_add(layout.align() - 1)?.saturating_sub(room);
+        let memory = unsafe { (*self.source.get()).grow(needed.max(1))? };
+        let start = memory.cast::<u8>().as_ptr();
+        if start != self.end.get() {
+            // Not contiguous: the rest of the previous region is abandoned.
+            self.brk.set(start);
+        }
+        self.end.set(unsafe { start.add(memory.len()) });
+        self.bump(layout)
+    }
+
+    #[inline]
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let brk = self.brk.get();
+        if brk.is_null() {
+            return None;
+        }
+        let padding = brk.align_offset(layout.align());
+        let taken = padding.checked_add(layout.size())?;
+        if taken > self.end.get() as usize - brk as usize {
+            return None;
+        }
+        self.brk.set(unsafe { brk.add(taken) });
+        self.size.set(self.size.get() + taken);
+        NonNull::new(unsafe { brk.add(padding) })
+    }
+}
+
+/// Blocks are carved at the break; deallocations are no-ops.
+unsafe impl<G> crate::alloc_api::Allocator for Brk<G>
+where
+    G: Grow,
+{
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.carve(layout).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+impl<G> fmt::Debug for Brk<G>
+where
+    G: Grow,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Brk {{ brk: {:p}, size: {} }}", self.brk.get(), self.size.get())
+    }
+}
+
+/// A fixed region, e.g. the memory between two linker symbols, handed to a
+/// `Brk` as it needs it.
+pub struct StaticRegion {
+    rest: &'static mut [MaybeUninit<u8>],
+}
+
+impl StaticRegion {
+    /// A source handing out `memory`.
+    #[inline]
+    pub fn new(memory: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self { rest: memory }
+    }
+
+    /// Bytes not handed out yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.rest.len()
+    }
+}
+
+unsafe impl Grow for StaticRegion {
+    #[inline]
+    fn grow(&mut self, additional: usize) -> Option<NonNull<[u8]>> {
+        if additional > self.rest.len() {
+            return None;
+        }
+        let (taken, rest) = core::mem::take(&mut self.rest).split_at_mut(additional);
+        self.rest = rest;
+        Some(NonNull::slice_from_raw_parts(NonNull::from(taken).cast::<u8>(), additional))
+    }
+}
+
+impl fmt::Debug for StaticRegion {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StaticRegion {{ remaining: {} }}", self.remaining())
+    }
+}
+
+/// The linear memory of a WebAssembly module, grown with `memory.grow` one
+/// page at a time. Memory grown by anything else (e.g. the Rust global
+/// allocator) breaks the contiguity of the region.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WasmMemory;
+
+#[cfg(target_arch = "wasm32")]
+unsafe impl Grow for WasmMemory {
+    #[inline]
+    fn grow(&mut self, additional: usize) -> Option<NonNull<[u8]>> {
+        let pages = additional.div_ceil(WASM_PAGE_SIZE);
+        let previous = core::arch::wasm32::memory_grow(0, pages);
+        if previous == usize::MAX {
+            return None;
+        }
+        let start = NonNull::new((previous * WASM_PAGE_SIZE) as *mut u8)?;
+        Some(NonNull::slice_from_raw_parts(start, pages * WASM_PAGE_SIZE))
+    }
+}
+
+/// Address space reserved up front and committed page by page as a `Brk`
+/// grows, so the region never moves. Released on drop.
+#[cfg(feature = "os")]
+pub struct ReservedRegion {
+    ptr: NonNull<u8>,
+    committed: usize,
+    reserved: usize,
+}
+
+#[cfg(feature = "os")]
+impl ReservedRegion {
+    /// Reserves address space for `max` bytes, rounded up to whole pages,
+    /// without committing any.
+    pub fn new(max: usize) -> Result<Self, OsError> {
+        let page = page_size();
+        let reserved = max.div_ceil(page).max(1) * page;
+        let ptr = sys::reserve(reserved)?;
+        Ok(Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            committed: 0,
+            reserved,
+        })
+    }
+
+    /// Bytes currently committed.
+    #[inline]
+    pub const fn committed(&self) -> usize {
+        self.committed
+    }
+
+    /// Bytes of address space reserved.
+    #[inline]
+    pub const fn reserved(&self) -> usize {
+        self.reserved
+    }
+}
+
+#[cfg(feature = "os")]
+unsafe impl Grow for ReservedRegion {
+    fn grow(&mut self, additional: usize) -> Option<NonNull<[u8]>> {
+        let page = page_size();
+        let len = additional.checked_next_multiple_of(page)?;
+        if len > self.reserved - self.committed {
+            return None;
+        }
+        unsafe {
+            let start = self.ptr.as_ptr().add(self.committed);
+            sys::commit(start, len).ok()?;
+            self.committed += len;
+            Some(NonNull::slice_from_raw_parts(NonNull::new_unchecked(start), len))
+        }
+    }
+}
+
+#[cfg(feature = "os")]
+impl Drop for ReservedRegion {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { sys::release(self.ptr.as_ptr(), self.reserved) }
+    }
+}
+
+#[cfg(feature = "os")]
+impl fmt::Debug for ReservedRegion {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ReservedRegion {{ pointer: {:?}, committed: {}, reserved: {} }}",
+            self.ptr, self.committed, self.reserved
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Brk, StaticRegion};
+    use crate::alloc_api::Allocator as _;
+    use alloc::boxed::Box;
+    use core::{alloc::Layout, mem::MaybeUninit};
+
+    #[test]
+    fn grows_monotonically_until_exhausted() {
+        let memory = Box::leak(Box::new([MaybeUninit::<u8>::uninit(); 1024]));
+        let start = memory.as_ptr() as usize;
+        let brk = Brk::new(StaticRegion::new(memory));
+
+        let byte = brk.sbrk(1).unwrap();
+        assert_eq!(byte.cast::<u8>().as_ptr() as usize, start);
+        let layout = Layout::from_size_align(100, 64).unwrap();
+        let block = brk.allocate(layout).unwrap().cast::<u8>();
+        assert_eq!(block.as_ptr() as usize % 64, 0);
+        assert_eq!(brk.brk() as usize, block.as_ptr() as usize + 100);
+        assert!(brk.size() <= 1 + 63 + 100);
+
+        assert!(brk.sbrk(2048).is_none());
+        let rest = 1024 - brk.size();
+        assert!(brk.sbrk(rest).is_some());
+        assert_eq!(brk.source().remaining(), 0);
+    }
+
+    #[cfg(feature = "os")]
+    #[test]
+    fn reserved_region_commits_pages() {
+        use super::ReservedRegion;
+        use crate::page_size;
+
+        let brk = Brk::new(ReservedRegion::new(16 * page_size()).unwrap());
+        let first = brk.sbrk(10).unwrap().cast::<u8>();
+        unsafe { first.as_ptr().write_bytes(1, 10) };
+        assert_eq!(brk.source().committed(), page_size());
+        let big = brk.sbrk(3 * page_size()).unwrap().cast::<u8>();
+        assert_eq!(big.as_ptr() as usize, first.as_ptr() as usize + 10);
+        assert_eq!(brk.source().committed(), 4 * page_size());
+        assert!(brk.sbrk(16 * page_size()).is_none());
+    }
+}
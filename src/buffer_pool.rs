@@ -0,0 +1,341 @@
+use crate::{events::Listener, CacheEvent, RawVec};
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+    ptr,
+    slice,
+};
+
+/// Smallest size class of `BufferPool::new`.
+pub const DEFAULT_MIN_BUFFER: usize = 64;
+
+/// Number of size classes of `BufferPool::new`, i.e. up to 64KiB.
+pub const DEFAULT_BUFFER_CLASSES: usize = 11;
+
+#[derive(Debug)]
+struct SizeClass {
+    size: usize,
+    idle: Vec<RawVec<u8>>,
+    outstanding: usize,
+    high_water: usize,
+}
+
+impl SizeClass {
+    /// Frees the idle buffers from `len` on.
+    #[inline]
+    fn release(&mut self, len: usize) {
+        for buf in self.idle.iter().skip(len) {
+            crate::asan::unpoison(buf.raw().as_ptr(), buf.cap());
+        }
+        self.idle.truncate(len);
+    }
+}
+
+/// A pool of reusable byte buffers, bucketed into power-of-two size classes.
+/// Buffers are checked out with at least the requested capacity and checked
+/// back in when no longer needed, so a server churning through request and
+/// response buffers mostly avoids the allocator.
+///
+/// Each class tracks the high-water mark of checked out buffers since the
+/// last `trim`, which releases the idle buffers the workload did not need.
+/// Pooled buffers must be checked in rather than dropped: the pool cannot
+/// see a dropped buffer, which keeps counting as checked out, so its class
+/// keeps that many more idle buffers on every `trim`.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::BufferPool;
+///
+/// let mut pool = BufferPool::new();
+/// let buf = pool.checkout(100);
+/// assert_eq!(buf.cap(), 128);
+/// let ptr = buf.raw();
+/// pool.checkin(buf);
+///
+/// assert_eq!(pool.checkout(120).raw(), ptr);
+/// ```
+#[derive(Debug)]
+pub struct BufferPool {
+    classes: Vec<SizeClass>,
+    listener: Listener,
+}
+
+impl BufferPool {
+    /// Creates a pool with `DEFAULT_BUFFER_CLASSES` classes starting at
+    /// `DEFAULT_MIN_BUFFER` bytes.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_classes(DEFAULT_MIN_BUFFER, DEFAULT_BUFFER_CLASSES)
+    }
+
+    /// Creates a pool with `count` classes, the smallest one holding
+    /// `min_size` bytes rounded up to a power of two.
+    pub fn with_classes(min_size: usize, count: usize) -> Self {
+        let min_size = min_size.max(1).next_power_of_two();
+        let classes = (0 .. count)
+            .map(|i| SizeClass {
+                size: min_size << i,
+                idle: Vec::new(),
+                outstanding: 0,
+                high_water: 0,
+            })
+            .collect();
+        Self {
+            classes,
+            listener: Listener::NONE,
+        }
+    }
+
+    /// Registers `listener` to be called on the events of the pool,
+    /// replacing the previous one: `checkout` reports a hit, a miss, or an
+    /// overflow if no class fits the request, and `trim` reports an
+    /// eviction per buffer released.
+    #[inline]
+    pub fn set_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(CacheEvent) + Send + Sync + 'static,
+    {
+        self.listener = Listener::new(listener);
+    }
+
+    /// The capacity of buffers handed out for a request of `len` bytes, or
+    /// `None` if `len` is bigger than the largest class.
+    #[inline]
+    pub fn class_size(&self, len: usize) -> Option<usize> {
+        self.class_index(len).map(|i| self.classes[i].size)
+    }
+
+    panicking! {
+        /// Checks out a buffer with capacity of at least `len`, to be checked
+        /// back in rather than dropped. Requests bigger than the largest class
+        /// are served by a fresh, unpooled allocation. In case of allocation
+        /// error, the function panics.
+        pub fn checkout(&mut self, len: usize) -> RawVec<u8> {
+            let index = match self.class_index(len) {
+                Some(index) => index,
+                None => {
+                    self.listener.notify(CacheEvent::Overflow { size: len });
+                    return RawVec::with_capacity(len);
+                },
+            };
+            let class = &mut self.classes[index];
+            class.outstanding += 1;
+            class.high_water = class.high_water.max(class.outstanding);
+            match class.idle.pop() {
+                Some(buf) => {
+                    self.listener.notify(CacheEvent::Hit { size: class.size });
+                    crate::asan::unpoison(buf.raw().as_ptr(), buf.cap());
+                    buf
+                },
+                None => {
+                    self.listener.notify(CacheEvent::Miss { size: class.size });
+                    RawVec::with_capacity(class.size)
+                },
+            }
+        }
+    }
+
+    panicking! {
+        /// Checks out a buffer as exactly `len` zeroed bytes. In case of
+        /// allocation error, the function panics.
+        pub fn checkout_owned(&mut self, len: usize) -> OwnedBuffer {
+            let buf = self.checkout(len);
+            unsafe { ptr::write_bytes(buf.raw().as_ptr(), 0, len) }
+            OwnedBuffer { buf, len }
+        }
+    }
+
+    /// Returns a buffer to the pool. Buffers whose capacity is not a class
+    /// size are simply freed.
+    pub fn checkin(&mut self, buf: RawVec<u8>) {
+        let cap = buf.cap();
+        if let Some(class) = self.classes.iter_mut().find(|class| class.size == cap) {
+            class.outstanding = class.outstanding.saturating_sub(1);
+            crate::asan::poison(buf.raw().as_ptr(), cap);
+            class.idle.push(buf);
+        }
+    }
+
+    /// Returns a buffer obtained through `checkout_owned` to the pool. Its
+    /// contents are forgotten.
+    #[inline]
+    pub fn checkin_owned(&mut self, buf: OwnedBuffer) {
+        self.checkin(buf.into_raw_vec())
+    }
+
+    /// Frees idle buffers beyond the high-water mark of each class since the
+    /// last trim, and starts a new measuring period. Returns how many bytes
+    /// were released.
+    pub fn trim(&mut self) -> usize {
+        let mut released = 0;
+        for class in &mut self.classes {
+            let keep = class.high_water.saturating_sub(class.outstanding);
+            if class.idle.len() > keep {
+                let count = class.idle.len() - keep;
+                released += count * class.size;
+                class.release(keep);
+                self.listener
+                    .notify_many(CacheEvent::Evict { size: class.size }, count);
+            }
+            class.high_water = class.outstanding;
+        }
+        #[cfg(feature = "tracing")]
+        if released != 0 {
+            crate::trace::evict("BufferPool", Some(released));
+        }
+        released
+    }
+
+    /// Bytes held by idle buffers.
+    #[inline]
+    pub fn idle_bytes(&self) -> usize {
+        self.classes
+            .iter()
+            .map(|class| class.idle.len() * class.size)
+            .sum()
+    }
+
+    #[inline]
+    fn class_index(&self, len: usize) -> Option<usize> {
+        let min = self.classes.first()?.size;
+        let size = len.max(min).checked_next_power_of_two()?;
+        let index = (size / min).trailing_zeros() as usize;
+        if index < self.classes.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for BufferPool {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for BufferPool {
+    #[inline]
+    fn drop(&mut self) {
+        for class in &mut self.classes {
+            class.release(0);
+        }
+    }
+}
+
+/// Initialized bytes checked out of a `BufferPool` by `checkout_owned`. It
+/// dereferences to the requested length, and keeps the whole buffer of its
+/// class, which goes back to the pool with `checkin_owned`. Dropping it
+/// frees the buffer, but the pool keeps counting it as checked out.
+pub struct OwnedBuffer {
+    buf: RawVec<u8>,
+    len: usize,
+}
+
+impl OwnedBuffer {
+    /// Capacity of the underlying buffer.
+    #[inline]
+    pub fn cap(&self) -> usize {
+        self.buf.cap()
+    }
+
+    /// The whole underlying buffer. Its contents are forgotten.
+    #[inline]
+    pub fn into_raw_vec(self) -> RawVec<u8> {
+        self.buf
+    }
+}
+
+impl Deref for OwnedBuffer {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.buf.raw().as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for OwnedBuffer {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.buf.raw().as_ptr(), self.len) }
+    }
+}
+
+impl fmt::Debug for OwnedBuffer {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BufferPool;
+
+    #[test]
+    fn class_sizes() {
+        let pool = BufferPool::with_classes(50, 3);
+        assert_eq!(pool.class_size(0), Some(64));
+        assert_eq!(pool.class_size(65), Some(128));
+        assert_eq!(pool.class_size(256), Some(256));
+        assert_eq!(pool.class_size(257), None);
+    }
+
+    #[test]
+    fn trim_keeps_high_water() {
+        let mut pool = BufferPool::new();
+        let bufs = [pool.checkout(10), pool.checkout(10), pool.checkout(10)];
+        for buf in bufs {
+            pool.checkin(buf);
+        }
+        assert_eq!(pool.idle_bytes(), 3 * 64);
+
+        // The last period needed all three buffers at once.
+        assert_eq!(pool.trim(), 0);
+        // The current period needed none.
+        assert_eq!(pool.trim(), 3 * 64);
+        assert_eq!(pool.idle_bytes(), 0);
+    }
+
+    #[test]
+    fn owned_round_trip() {
+        let mut pool = BufferPool::new();
+        let buf = pool.checkout_owned(100);
+        assert_eq!((&*buf, buf.cap()), (&[0; 100][..], 128));
+        pool.checkin_owned(buf);
+        assert_eq!(pool.idle_bytes(), 128);
+
+        drop(pool.checkout_owned(0));
+        drop(pool.checkout_owned(100));
+        assert_eq!(pool.idle_bytes(), 0);
+    }
+
+    #[test]
+    fn listener_sees_class_sizes() {
+        use crate::CacheEvent;
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        static SIZES: AtomicUsize = AtomicUsize::new(0);
+
+        let mut pool = BufferPool::with_classes(64, 2);
+        pool.set_listener(|event| match event {
+            CacheEvent::Hit { size } => drop(SIZES.fetch_add(size, Relaxed)),
+            CacheEvent::Miss { size } => drop(SIZES.fetch_add(size << 8, Relaxed)),
+            CacheEvent::Overflow { size } => drop(SIZES.fetch_add(size << 16, Relaxed)),
+            CacheEvent::Evict { size } => drop(SIZES.fetch_add(size << 24, Relaxed)),
+        });
+        let buf = pool.checkout(100);
+        pool.checkin(buf);
+        let buf = pool.checkout(70);
+        pool.checkin(buf);
+        drop(pool.checkout(200));
+        pool.trim();
+        pool.trim();
+        assert_eq!(SIZES.load(Relaxed), 128 | 128 << 8 | 200 << 16 | 128 << 24);
+    }
+}
@@ -0,0 +1,591 @@
+use crate::{AllocError, RawVec, UninitAlloc};
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    fmt,
+    ptr::{self, NonNull},
+};
+
+/// Default size of the chunks of a `Bump`.
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// An opaque position in a `Bump` or an `Arena`, taken by `snapshot` and
+/// restored by `rewind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Snapshot {
+    chunk: usize,
+    offset: usize,
+    /// Number of values of a `Bump` pending drop: zero-sized values do not
+    /// move the position, so the order of allocation is tracked apart.
+    drops: usize,
+}
+
+impl Snapshot {
+    pub(crate) const START: Snapshot = Snapshot {
+        chunk: 0,
+        offset: 0,
+        drops: 0,
+    };
+
+    #[inline]
+    pub(crate) const fn new(chunk: usize, offset: usize) -> Self {
+        Self {
+            chunk,
+            offset,
+            drops: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) const fn chunk(&self) -> usize {
+        self.chunk
+    }
+
+    #[inline]
+    pub(crate) const fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// A value allocated with `alloc_with_drop`, to be dropped when the
+/// allocator is rewound to a snapshot taken before it was allocated, or to
+/// any position before its end.
+struct PendingDrop {
+    ptr: NonNull<u8>,
+    drop: unsafe fn(NonNull<u8>),
+    /// The chunk and the offset right past the value.
+    end: (usize, usize),
+}
+
+/// A bump allocator: memory is handed out by advancing an offset into large
+/// chunks, and only given back all at once, by `reset` or `rewind`. Values
+/// allocated in a `Bump` are never dropped, except for the ones allocated
+/// with `alloc_with_drop`, `alloc_dyn` or `alloc_fn`.
+///
+/// `snapshot` and `rewind` make speculative work cheap: take a snapshot,
+/// allocate freely, and rewind if the work is abandoned. Chunks are kept
+/// around after a rewind, to be reused, until `trim` frees them.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::Bump;
+///
+/// let mut bump = Bump::new();
+/// let kept = *bump.alloc(1u32);
+/// let snapshot = bump.snapshot();
+/// let speculative = bump.alloc_slice_copy(&[2u32, 3, 4]);
+/// assert_eq!(speculative, [2, 3, 4]);
+///
+/// bump.rewind(snapshot);
+/// assert_eq!(bump.snapshot(), snapshot);
+/// assert_eq!(kept, 1);
+/// ```
+pub struct Bump {
+    chunks: UnsafeCell<Vec<UninitAlloc<[u8]>>>,
+    drops: UnsafeCell<Vec<PendingDrop>>,
+    current: Cell<usize>,
+    offset: Cell<usize>,
+    chunk_size: usize,
+    limit: usize,
+}
+
+impl Bump {
+    /// Creates an empty bump allocator with chunks of `DEFAULT_CHUNK_SIZE`
+    /// bytes. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates an empty bump allocator with chunks of at least `chunk_size`
+    /// bytes. Bigger allocations get a chunk of their own size.
+    #[inline]
+    pub const fn with_chunk_size(chunk_size: usize) -> Self {
+        Self::with_limit(chunk_size, usize::MAX)
+    }
+
+    /// Creates an empty bump allocator with chunks of at least `chunk_size`
+    /// bytes, holding at most `limit` bytes in chunks. Allocations which
+    /// would take it past the limit fail.
+    #[inline]
+    pub const fn with_limit(chunk_size: usize, limit: usize) -> Self {
+        Self {
+            chunks: UnsafeCell::new(Vec::new()),
+            drops: UnsafeCell::new(Vec::new()),
+            current: Cell::new(0),
+            offset: Cell::new(0),
+            chunk_size,
+            limit,
+        }
+    }
+
+    panicking! {
+        /// Moves `value` into the allocator. In case of allocation error, the
+        /// function panics. The value is never dropped.
+        #[inline]
+        #[allow(clippy::mut_from_ref)]
+        pub fn alloc<T>(&self, value: T) -> &mut T {
+            let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+            unsafe {
+                ptr.as_ptr().write(value);
+                &mut *ptr.as_ptr()
+            }
+        }
+    }
+
+    panicking! {
+        /// Moves `value` into the allocator, to be dropped when the allocator is
+        /// reset, rewound to a snapshot taken before, or dropped. In case of
+        /// allocation error, the function panics.
+        ///
+        /// The value may outlive the references handed out, hence the `'static`
+        /// bound: closures must own their captures. It is dropped on the thread
+        /// owning the allocator then, hence the `Send` bound. The reference
+        /// coerces to a trait object, e.g. `&mut dyn FnMut()`.
+        #[allow(clippy::mut_from_ref)]
+        pub fn alloc_with_drop<T>(&self, value: T) -> &mut T
+        where
+            T: Send + 'static,
+        {
+            let ptr = self.alloc(value);
+            if core::mem::needs_drop::<T>() {
+                unsafe fn drop_value<T>(ptr: NonNull<u8>) {
+                    ptr.cast::<T>().as_ptr().drop_in_place();
+                }
+                let drops = unsafe { &mut *self.drops.get() };
+                drops.push(PendingDrop {
+                    ptr: NonNull::from(&mut *ptr).cast(),
+                    drop: drop_value::<T>,
+                    end: (self.current.get(), self.offset.get()),
+                });
+            }
+            ptr
+        }
+    }
+
+    panicking! {
+        /// Copies a slice into the allocator. In case of allocation error, the
+        /// function panics.
+        #[inline]
+        #[allow(clippy::mut_from_ref)]
+        pub fn alloc_slice_copy<T>(&self, slice: &[T]) -> &mut [T]
+        where
+            T: Copy,
+        {
+            let ptr = self
+                .alloc_layout(Layout::for_value(slice))
+                .cast::<T>()
+                .as_ptr();
+            unsafe {
+                ptr::copy_nonoverlapping(slice.as_ptr(), ptr, slice.len());
+                core::slice::from_raw_parts_mut(ptr, slice.len())
+            }
+        }
+    }
+
+    panicking! {
+        /// Allocates uninitialized memory for the given layout. In case of
+        /// allocation error, the function panics.
+        #[inline]
+        pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+            match self.try_alloc_layout(layout) {
+                Ok(ptr) => ptr,
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+
+    /// Allocates uninitialized memory for the given layout. In case of
+    /// allocation error, `Err` is returned.
+    pub fn try_alloc_layout(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(unsafe { NonNull::new_unchecked(layout.align() as *mut u8) });
+        }
+        if let Some(ptr) = self.bump(layout) {
+            return Ok(ptr);
+        }
+
+        let chunks = unsafe { &mut *self.chunks.get() };
+        let next = if chunks.is_empty() { 0 } else { self.current.get() + 1 };
+        let fits = matches!(
+            chunks.get(next),
+            Some(chunk) if Self::fit(chunk, 0, layout).is_some()
+        );
+        if !fits {
+            Self::release(chunks, next);
+            let needed = layout.size() + layout.align() - 1;
+            let room = self.limit.saturating_sub(Self::held(chunks));
+            if needed > room {
+                return Err(AllocError { layout });
+            }
+            let size = self.chunk_size.max(needed).min(room);
+            let chunk = RawVec::<u8>::try_with_capacity(size).map_err(|_| AllocError { layout })?;
+            let chunk = UninitAlloc::from(chunk);
+            crate::valgrind::create_mempool(chunk.raw().cast::<u8>().as_ptr());
+            Self::poison_from(&chunk, 0);
+            chunks.push(chunk);
+        }
+        self.current.set(next);
+        self.offset.set(0);
+        Ok(self.bump(layout).unwrap())
+    }
+
+    /// The current position of the allocator.
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            chunk: self.current.get(),
+            offset: self.offset.get(),
+            drops: unsafe { (*self.drops.get()).len() },
+        }
+    }
+
+    /// Frees everything allocated after `snapshot` was taken, dropping the
+    /// values allocated with `alloc_with_drop` most recent first. Panics if
+    /// the snapshot is ahead of the current position, e.g. because the
+    /// allocator was already rewound past it.
+    #[inline]
+    pub fn rewind(&mut self, snapshot: Snapshot) {
+        assert!(
+            snapshot <= self.snapshot(),
+            "Snapshot is ahead of the current position"
+        );
+        Self::run_drops(self.drops.get_mut(), snapshot);
+        let chunks = self.chunks.get_mut();
+        for index in snapshot.chunk() ..= self.current.get() {
+            if let Some(chunk) = chunks.get(index) {
+                let offset = if index == snapshot.chunk() { snapshot.offset() } else { 0 };
+                Self::poison_from(chunk, offset);
+            }
+        }
+        self.current.set(snapshot.chunk());
+        self.offset.set(snapshot.offset());
+    }
+
+    /// Frees everything allocated so far, keeping the chunks for reuse.
+    #[inline]
+    pub fn reset(&mut self) {
+        #[cfg(feature = "tracing")]
+        crate::trace::reset("Bump", self.capacity());
+        self.rewind(Snapshot::START);
+    }
+
+    /// Frees the chunks kept for reuse past the current position, returning
+    /// how many bytes were released.
+    pub fn trim(&mut self) -> usize {
+        let capacity = self.capacity();
+        let keep = self.current.get() + 1;
+        Self::release(self.chunks.get_mut(), keep);
+        capacity - self.capacity()
+    }
+
+    /// Bytes of memory held in chunks, used or not.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        Self::held(unsafe { &*self.chunks.get() })
+    }
+
+    /// The maximum number of bytes held in chunks.
+    #[inline]
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    #[inline]
+    fn held(chunks: &[UninitAlloc<[u8]>]) -> usize {
+        chunks
+            .iter()
+            .map(|chunk| unsafe { chunk.raw().as_ref().len() })
+            .sum()
+    }
+
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let chunks = unsafe { &*self.chunks.get() };
+        let chunk = chunks.get(self.current.get())?;
+        let start = Self::fit(chunk, self.offset.get(), layout)?;
+        self.offset.set(start + layout.size());
+        let base = chunk.raw().cast::<u8>();
+        let ptr = unsafe { base.add(start) };
+        crate::valgrind::mempool_alloc(base.as_ptr(), ptr.as_ptr(), layout.size());
+        crate::asan::unpoison(ptr.as_ptr(), layout.size());
+        Some(ptr)
+    }
+
+    /// Frees and poisons the part of a chunk from `offset` on.
+    #[inline]
+    fn poison_from(chunk: &UninitAlloc<[u8]>, offset: usize) {
+        let len = unsafe { chunk.raw().as_ref().len() };
+        let base = chunk.raw().cast::<u8>().as_ptr();
+        crate::valgrind::mempool_trim(base, base, offset);
+        crate::asan::poison(unsafe { base.add(offset) }, len - offset);
+    }
+
+    /// Drops the values allocated after `snapshot` was taken, and the ones
+    /// whose memory is handed out again from `snapshot` on, which a stale
+    /// snapshot may cover. Values are dropped most recent first, and each one
+    /// is forgotten before being dropped, so a panicking drop is never run
+    /// twice.
+    #[inline]
+    fn run_drops(drops: &mut Vec<PendingDrop>, snapshot: Snapshot) {
+        let position = (snapshot.chunk(), snapshot.offset());
+        while let Some(pending) = drops.last() {
+            if drops.len() <= snapshot.drops && pending.end <= position {
+                break;
+            }
+            if let Some(pending) = drops.pop() {
+                unsafe { (pending.drop)(pending.ptr) }
+            }
+        }
+    }
+
+    /// Frees the chunks from `len` on.
+    #[inline]
+    fn release(chunks: &mut Vec<UninitAlloc<[u8]>>, len: usize) {
+        for chunk in chunks.iter().skip(len) {
+            let size = unsafe { chunk.raw().as_ref().len() };
+            crate::valgrind::destroy_mempool(chunk.raw().cast::<u8>().as_ptr());
+            crate::asan::unpoison(chunk.raw().cast::<u8>().as_ptr(), size);
+        }
+        chunks.truncate(len);
+    }
+
+    #[inline]
+    fn fit(chunk: &UninitAlloc<[u8]>, offset: usize, layout: Layout) -> Option<usize> {
+        let base = chunk.raw().cast::<u8>().as_ptr();
+        let len = unsafe { chunk.raw().as_ref().len() };
+        let start = offset + unsafe { base.add(offset) }.align_offset(layout.align());
+        if start.checked_add(layout.size())? <= len {
+            Some(start)
+        } else {
+            None
+        }
+    }
+}
+
+/// Trait objects and closures allocated in the bump allocator, dropped on
+/// reset, so that event handlers and visitors need no box of their own.
+#[cfg(feature = "nightly")]
+impl Bump {
+    panicking! {
+        /// Moves `value` into the allocator as a `U`, usually a trait object, to
+        /// be dropped as `alloc_with_drop` does. In case of allocation error, the
+        /// function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use core::fmt::Display;
+        /// use owned_alloc::Bump;
+        ///
+        /// let bump = Bump::new();
+        /// let items = [
+        ///     bump.alloc_dyn::<dyn Display, _>(String::from("text")),
+        ///     bump.alloc_dyn::<dyn Display, _>(42),
+        /// ];
+        /// assert_eq!(items.map(|item| item.to_string()), ["text", "42"]);
+        /// ```
+        #[inline]
+        #[allow(clippy::mut_from_ref)]
+        pub fn alloc_dyn<U, T>(&self, value: T) -> &mut U
+        where
+            U: ?Sized,
+            T: core::marker::Unsize<U> + Send + 'static,
+        {
+            self.alloc_with_drop::<T>(value)
+        }
+    }
+
+    panicking! {
+        /// Moves the closure `f` into the allocator, to be dropped as
+        /// `alloc_with_drop` does. In case of allocation error, the function
+        /// panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::Bump;
+        ///
+        /// let mut bump = Bump::new();
+        /// let mut total = 0;
+        /// {
+        ///     let mut count = 0;
+        ///     let handler = bump.alloc_fn(move |step: u32| {
+        ///         count += step;
+        ///         count
+        ///     });
+        ///     handler(2);
+        ///     total += handler(3);
+        /// }
+        /// bump.reset();
+        /// assert_eq!(total, 5);
+        /// ```
+        #[inline]
+        #[allow(clippy::mut_from_ref)]
+        pub fn alloc_fn<Args, F>(&self, f: F) -> &mut dyn FnMut<Args, Output = F::Output>
+        where
+            Args: core::marker::Tuple,
+            F: FnMut<Args> + Send + 'static,
+        {
+            self.alloc_with_drop::<F>(f)
+        }
+    }
+}
+
+impl Drop for Bump {
+    #[inline]
+    fn drop(&mut self) {
+        Self::run_drops(self.drops.get_mut(), Snapshot::START);
+        Self::release(self.chunks.get_mut(), 0);
+    }
+}
+
+impl Default for Bump {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The values pending drop are `Send`.
+unsafe impl Send for Bump {}
+
+/// Allocations are bumped as usual; deallocations are no-ops, the memory
+/// coming back on `reset` or `rewind`.
+unsafe impl crate::alloc_api::Allocator for Bump {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+        let ptr = self
+            .try_alloc_layout(layout)
+            .map_err(|_| crate::alloc_api::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+impl fmt::Debug for Bump {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Bump {{ position: {:?}, capacity: {} }}",
+            self.snapshot(),
+            self.capacity()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Bump;
+    use core::alloc::Layout;
+
+    #[test]
+    fn alignment_and_chunk_reuse() {
+        let mut bump = Bump::with_chunk_size(64);
+        bump.alloc(1u8);
+        let aligned = bump.alloc_layout(Layout::from_size_align(8, 32).unwrap());
+        assert_eq!(aligned.as_ptr() as usize % 32, 0);
+
+        let big = bump.alloc([7u64; 32]);
+        assert_eq!(big[31], 7);
+        let capacity = bump.capacity();
+
+        bump.reset();
+        bump.alloc([0u8; 60]);
+        bump.alloc([0u64; 32]);
+        assert_eq!(bump.capacity(), capacity);
+
+        bump.reset();
+        assert_eq!(bump.trim(), capacity - 64);
+        assert_eq!(bump.capacity(), 64);
+    }
+
+    #[test]
+    fn limit_caps_chunks() {
+        let bump = Bump::with_limit(64, 100);
+        bump.alloc([0u8; 60]);
+        assert!(bump.try_alloc_layout(Layout::new::<[u8; 40]>()).is_err());
+        bump.alloc([0u8; 30]);
+        assert_eq!(bump.capacity(), 100);
+    }
+
+    #[test]
+    fn rewind_drops_owned_values() {
+        use alloc::sync::Arc;
+
+        let witness = Arc::new(());
+        let mut bump = Bump::with_chunk_size(64);
+        bump.alloc_with_drop(witness.clone());
+        let snapshot = bump.snapshot();
+        for _ in 0 .. 20 {
+            bump.alloc_with_drop([witness.clone(), witness.clone()]);
+        }
+        assert_eq!(Arc::strong_count(&witness), 42);
+
+        bump.rewind(snapshot);
+        assert_eq!(Arc::strong_count(&witness), 2);
+        let handler: &mut dyn FnMut() -> usize = bump.alloc_with_drop({
+            let witness = witness.clone();
+            move || Arc::strong_count(&witness)
+        });
+        assert_eq!(handler(), 3);
+        drop(bump);
+        assert_eq!(Arc::strong_count(&witness), 1);
+    }
+
+    #[test]
+    fn zero_sized_values_drop_in_order() {
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tick;
+
+        impl Drop for Tick {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Relaxed);
+            }
+        }
+
+        let mut bump = Bump::new();
+        bump.alloc_with_drop(Tick);
+        let snapshot = bump.snapshot();
+        bump.rewind(snapshot);
+        assert_eq!(DROPS.load(Relaxed), 0);
+
+        bump.alloc_with_drop(Tick);
+        bump.rewind(snapshot);
+        assert_eq!(DROPS.load(Relaxed), 1);
+        bump.reset();
+        assert_eq!(DROPS.load(Relaxed), 2);
+    }
+
+    #[test]
+    fn stale_snapshot_drops_overwritten_values() {
+        use alloc::sync::Arc;
+
+        let witness = Arc::new(());
+        let mut bump = Bump::new();
+        bump.alloc_with_drop(witness.clone());
+        bump.alloc_with_drop(witness.clone());
+        bump.alloc([0u8; 84]);
+        let stale = bump.snapshot();
+        bump.reset();
+
+        bump.alloc_with_drop(witness.clone());
+        bump.alloc([0u8; 100]);
+        bump.alloc_with_drop(witness.clone());
+        bump.rewind(stale);
+        assert_eq!(Arc::strong_count(&witness), 2);
+        bump.alloc([0xFFu8; 64]);
+        bump.reset();
+        assert_eq!(Arc::strong_count(&witness), 1);
+    }
+}
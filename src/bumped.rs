@@ -0,0 +1,290 @@
+use crate::{
+    alloc_api::{Box, Vec},
+    error,
+    AllocError,
+    Bump,
+    LayoutError,
+    RawVecError,
+    WriteFmtError,
+};
+use core::{
+    alloc::Layout,
+    fmt,
+    ops::{Deref, DerefMut},
+    str,
+};
+
+/// A `Box` allocated in a `Bump`. The value is dropped with the box, but the
+/// memory only comes back with the bump allocator's.
+pub type ABox<'a, T> = Box<T, &'a Bump>;
+
+/// A `Vec` allocated in a `Bump`. Growing it leaves the old buffer behind
+/// until the bump allocator is reset.
+pub type AVec<'a, T> = Vec<T, &'a Bump>;
+
+impl Bump {
+    panicking! {
+        /// Moves `value` into a box allocated in the bump allocator. In case of
+        /// allocation error, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::{ABox, AVec, Bump};
+        ///
+        /// struct Node<'a> {
+        ///     name: &'a str,
+        ///     children: AVec<'a, ABox<'a, Node<'a>>>,
+        /// }
+        ///
+        /// let bump = Bump::new();
+        /// let mut root = Node { name: "root", children: bump.vec() };
+        /// root.children.push(bump.boxed(Node { name: "leaf", children: bump.vec() }));
+        /// assert_eq!(root.children[0].name, "leaf");
+        /// ```
+        #[inline]
+        pub fn boxed<T>(&self, value: T) -> ABox<'_, T> {
+            Box::new_in(value, self)
+        }
+    }
+
+    /// Creates an empty vector allocated in the bump allocator. No
+    /// allocation is performed.
+    #[inline]
+    pub fn vec<T>(&self) -> AVec<'_, T> {
+        Vec::new_in(self)
+    }
+
+    panicking! {
+        /// Creates a vector allocated in the bump allocator, with room for `cap`
+        /// elements. In case of allocation error, the function panics.
+        #[inline]
+        pub fn vec_with_capacity<T>(&self, cap: usize) -> AVec<'_, T> {
+            Vec::with_capacity_in(cap, self)
+        }
+    }
+
+    /// Creates an empty string allocated in the bump allocator. No
+    /// allocation is performed.
+    #[inline]
+    pub fn string(&self) -> AString<'_> {
+        AString::new_in(self)
+    }
+}
+
+/// A `String` allocated in a `Bump`, for there is no `String` taking an
+/// allocator parameter.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use core::fmt::Write;
+/// use owned_alloc::Bump;
+///
+/// let bump = Bump::new();
+/// let mut greeting = bump.string();
+/// greeting.push_str("hello");
+/// write!(greeting, ", {}", 42).unwrap();
+/// assert_eq!(greeting, "hello, 42");
+/// ```
+pub struct AString<'a> {
+    bytes: AVec<'a, u8>,
+}
+
+impl<'a> AString<'a> {
+    /// Creates an empty string allocated in `bump`. No allocation is
+    /// performed.
+    #[inline]
+    pub fn new_in(bump: &'a Bump) -> Self {
+        Self {
+            bytes: Vec::new_in(bump),
+        }
+    }
+
+    panicking! {
+        /// Creates a string allocated in `bump`, with room for `cap` bytes. In
+        /// case of allocation error, the function panics.
+        #[inline]
+        pub fn with_capacity_in(cap: usize, bump: &'a Bump) -> Self {
+            Self {
+                bytes: Vec::with_capacity_in(cap, bump),
+            }
+        }
+    }
+
+    panicking! {
+        /// Copies `string` into `bump`. In case of allocation error, the
+        /// function panics.
+        #[inline]
+        pub fn from_str_in(string: &str, bump: &'a Bump) -> Self {
+            let mut this = Self::with_capacity_in(string.len(), bump);
+            this.push_str(string);
+            this
+        }
+    }
+
+    panicking! {
+        /// Appends a character. In case of allocation error, the function
+        /// panics.
+        #[inline]
+        pub fn push(&mut self, ch: char) {
+            self.push_str(ch.encode_utf8(&mut [0; 4]));
+        }
+    }
+
+    panicking! {
+        /// Appends a string slice. In case of allocation error, the function
+        /// panics.
+        #[inline]
+        pub fn push_str(&mut self, string: &str) {
+            self.bytes.extend_from_slice(string.as_bytes());
+        }
+    }
+
+    /// Appends a string slice. In case of allocation error or overflow, `Err`
+    /// is returned and the string is left untouched.
+    #[inline]
+    pub fn try_push_str(&mut self, string: &str) -> Result<(), RawVecError> {
+        let layout = self
+            .len()
+            .checked_add(string.len())
+            .and_then(|len| Layout::array::<u8>(len).ok())
+            .ok_or(LayoutError)?;
+        self.bytes
+            .try_reserve(string.len())
+            .map_err(|_| AllocError { layout })?;
+        self.push_str(string);
+        Ok(())
+    }
+
+    /// Formats `args` into the string, like `write!` through `fmt::Write`,
+    /// but returning allocation errors instead of aborting. The text
+    /// formatted before the error stays written.
+    #[inline]
+    pub fn try_write_fmt(&mut self, args: fmt::Arguments) -> Result<(), WriteFmtError> {
+        error::try_write_fmt(|string| Ok(self.try_push_str(string)?), args)
+    }
+
+    /// The string slice of the whole string.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(&self.bytes) }
+    }
+
+    /// The mutable string slice of the whole string.
+    #[inline]
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { str::from_utf8_unchecked_mut(&mut self.bytes) }
+    }
+
+    /// Number of bytes the string holds without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.bytes.capacity()
+    }
+
+    /// Truncates the string to zero length, keeping its capacity.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.bytes.clear()
+    }
+
+    /// Converts the string into its UTF-8 bytes.
+    #[inline]
+    pub fn into_bytes(self) -> AVec<'a, u8> {
+        self.bytes
+    }
+}
+
+impl<'a> Deref for AString<'a> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> DerefMut for AString<'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<'a> fmt::Write for AString<'a> {
+    #[inline]
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.push_str(string);
+        Ok(())
+    }
+}
+
+impl<'a> PartialEq for AString<'a> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<'a> Eq for AString<'a> {}
+
+impl<'a> PartialEq<str> for AString<'a> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a, 'b> PartialEq<&'b str> for AString<'a> {
+    #[inline]
+    fn eq(&self, other: &&'b str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'a> fmt::Display for AString<'a> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<'a> fmt::Debug for AString<'a> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Bump;
+
+    #[test]
+    fn containers_share_the_bump() {
+        let bump = Bump::with_chunk_size(256);
+        let mut words = bump.vec_with_capacity(2);
+        for word in ["arena", "backed", "strings"] {
+            let mut string = bump.string();
+            string.push_str(word);
+            string.push('!');
+            words.push(bump.boxed(string));
+        }
+        assert_eq!(*words[2], "strings!");
+        assert_eq!(words.iter().map(|word| word.len()).sum::<usize>(), 21);
+        assert_eq!(bump.capacity(), 256);
+    }
+
+    #[test]
+    fn fallible_formatting() {
+        let bump = Bump::with_limit(16, 16);
+        let mut string = bump.string();
+        string.try_write_fmt(format_args!("{}:{}", 12, 34)).unwrap();
+        assert_eq!(string, "12:34");
+        assert!(string.try_write_fmt(format_args!("{:32}", 5)).is_err());
+        assert!(string.try_push_str(&"x".repeat(32)).is_err());
+    }
+}
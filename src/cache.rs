@@ -1,3 +1,6 @@
+use crate::{events::Listener, Allocator, CacheEvent, UninitAlloc};
+use core::mem;
+
 /// A general purpouse cache suitable for saving discarted memory allocations in
 /// a tight loop.
 ///
@@ -38,43 +41,164 @@
 /// ```
 
 #[derive(Debug)]
-pub struct Cache<A> {
-    stored: Option<A>,
+pub struct Cache<T, A = Allocator>
+where
+    A: crate::alloc_api::Allocator,
+{
+    stored: Option<T>,
+    alloc: A,
+    listener: Listener,
 }
 
-impl<A> Cache<A> {
+impl<T> Cache<T> {
     /// Creates a new cache with no data.
     #[inline]
     pub const fn new() -> Self {
-        Self { stored: None }
+        Self::new_in(Allocator::new())
+    }
+}
+
+impl<T, A> Cache<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Creates a new cache with no data, whose allocations are made in
+    /// `alloc`.
+    #[inline]
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            stored: None,
+            alloc,
+            listener: Listener::NONE,
+        }
+    }
+
+    /// The allocator the allocations of the cache are made in.
+    #[inline]
+    pub const fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Registers `listener` to be called on the events of the cache,
+    /// replacing the previous one: `store` replacing data reports an
+    /// eviction, and `take_or` reports a hit or a miss. `take` reports
+    /// nothing.
+    #[inline]
+    pub fn set_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(CacheEvent) + Send + Sync + 'static,
+    {
+        self.listener = Listener::new(listener);
     }
 
     /// Stores data into the cache.
     #[inline]
-    pub fn store(&mut self, val: A) {
+    pub fn store(&mut self, val: T) {
+        if self.stored.is_some() {
+            #[cfg(feature = "tracing")]
+            crate::trace::evict(core::any::type_name::<T>(), None);
+            self.listener.notify(CacheEvent::Evict {
+                size: mem::size_of::<T>(),
+            });
+        }
         self.stored = Some(val);
     }
 
     /// Takes the data from the cache.
     #[inline]
-    pub const fn take(&mut self) -> Option<A> {
+    pub const fn take(&mut self) -> Option<T> {
         self.stored.take()
     }
 
     /// Takes the data from the cache. If there was no data, the passed closure
     /// is called to produce the returned data.
     #[inline]
-    pub fn take_or<F>(&mut self, create: F) -> A
+    pub fn take_or<F>(&mut self, create: F) -> T
     where
-        F: FnOnce() -> A,
+        F: FnOnce() -> T,
     {
-        self.take().unwrap_or_else(create)
+        let size = mem::size_of::<T>();
+        match self.take() {
+            Some(val) => {
+                self.listener.notify(CacheEvent::Hit { size });
+                val
+            },
+            None => {
+                self.listener.notify(CacheEvent::Miss { size });
+                create()
+            },
+        }
     }
 }
 
-impl<A> const Default for Cache<A> {
-    #[inline]
-    fn default() -> Self {
-        Self::new()
+impl<T, A> Cache<UninitAlloc<T, A>, A>
+where
+    A: crate::alloc_api::Allocator + Clone,
+{
+    panicking! {
+        /// Takes the cached allocation, or makes a new one in the allocator of
+        /// the cache. A replaced allocation is freed into the allocator it was
+        /// made in. In case of allocation error, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::{Allocator, Cache, LimitedAlloc};
+        ///
+        /// let limited = LimitedAlloc::new(Allocator::new(), 64);
+        /// let mut cache = Cache::new_in(&limited);
+        /// for i in 0 .. 100u64 {
+        ///     let block = cache.take_or_alloc().init([i; 8]);
+        ///     assert_eq!(block[7], i);
+        ///     cache.store(block.drop_in_place());
+        /// }
+        /// assert_eq!(limited.used(), 64);
+        /// drop(cache);
+        /// assert_eq!(limited.used(), 0);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn take_or_alloc(&mut self) -> UninitAlloc<T, A> {
+            let alloc = self.alloc.clone();
+            self.take_or(|| UninitAlloc::new_in(alloc))
+        }
+    }
+}
+
+const_impl! {
+    impl<T> Default for Cache<T> {
+        #[inline]
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cache;
+    use crate::CacheEvent;
+    use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    #[test]
+    fn listener_sees_hits_misses_and_evictions() {
+        static EVENTS: [AtomicUsize; 3] = [const { AtomicUsize::new(0) }; 3];
+
+        let mut cache = Cache::new();
+        cache.set_listener(|event| {
+            let index = match event {
+                CacheEvent::Hit { size: 8 } => 0,
+                CacheEvent::Miss { size: 8 } => 1,
+                CacheEvent::Evict { size: 8 } => 2,
+                _ => unreachable!(),
+            };
+            EVENTS[index].fetch_add(1, Relaxed);
+        });
+        let first = cache.take_or(|| 1u64);
+        cache.store(first);
+        cache.store(2);
+        assert_eq!(cache.take_or(|| 3), 2);
+        assert_eq!(EVENTS.each_ref().map(|count| count.load(Relaxed)), [1, 1, 1]);
     }
 }
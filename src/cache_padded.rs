@@ -0,0 +1,122 @@
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// Size of a cache line, as far as false sharing is concerned. Modern x86-64
+/// and AArch64 cores prefetch cache lines in pairs, and POWER has 128-byte
+/// lines, so those get 128 bytes.
+#[cfg(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "powerpc64"
+))]
+pub const CACHE_LINE: usize = 128;
+
+/// Size of a cache line, as far as false sharing is concerned. Modern x86-64
+/// and AArch64 cores prefetch cache lines in pairs, and POWER has 128-byte
+/// lines, so those get 128 bytes.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "powerpc64"
+)))]
+pub const CACHE_LINE: usize = 64;
+
+/// Aligns and pads a value to `CACHE_LINE` bytes, so that it never shares a
+/// cache line with another value. Useful for per-core counters or the head
+/// and tail indices of a queue.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{CachePadded, OwnedAlloc, CACHE_LINE};
+///
+/// let counters = OwnedAlloc::new_cache_aligned([0u64; 4]);
+/// assert_eq!(counters.raw().as_ptr() as usize % CACHE_LINE, 0);
+/// assert_eq!(core::mem::size_of::<CachePadded<u8>>(), CACHE_LINE);
+/// ```
+#[cfg_attr(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64"
+    ),
+    repr(align(128))
+)]
+#[cfg_attr(
+    not(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64"
+    )),
+    repr(align(64))
+)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    /// Pads the given value.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    /// Returns the padded value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> From<T> for CachePadded<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> fmt::Debug for CachePadded<T>
+where
+    T: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CachePadded({:?})", self.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CachePadded, CACHE_LINE};
+    use core::mem;
+
+    #[test]
+    fn padded_to_a_line() {
+        assert_eq!(mem::align_of::<CachePadded<u8>>(), CACHE_LINE);
+        assert_eq!(mem::size_of::<CachePadded<[u8; 3]>>(), CACHE_LINE);
+        assert_eq!(
+            mem::size_of::<CachePadded<[u8; CACHE_LINE + 1]>>(),
+            2 * CACHE_LINE
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    alloc_api::AllocError,
+    tag::{current_index, tag_at},
+    Tag,
+};
+use core::{
+    alloc::Layout,
+    fmt,
+    mem,
+    ptr::NonNull,
+};
+
+/// Value of the canary words written around each allocation of a
+/// `CanaryAlloc`.
+pub const CANARY: usize = 0xA5A5_5A5A_C0DE_FEED_u64 as usize;
+
+/// Bookkeeping placed right before the block handed out.
+#[repr(C)]
+struct Header {
+    size: usize,
+    tag: usize,
+    checksum: usize,
+    front: usize,
+}
+
+/// The part of an allocation of a `CanaryAlloc` found corrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// The header (size, tag) does not match its checksum or the layout.
+    Header,
+    /// The canary right before the block was overwritten.
+    Front,
+    /// The canary right after the block was overwritten.
+    Back,
+}
+
+/// Error reported when the canaries of an allocation are corrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanaryError {
+    /// The corrupted part.
+    pub corruption: Corruption,
+    /// Size of the allocation.
+    pub size: usize,
+    /// Tag current when the block was allocated.
+    pub tag: Tag,
+}
+
+impl fmt::Display for CanaryError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Heap corruption ({:?}) of an allocation of {} bytes tagged {}",
+            self.corruption, self.size, self.tag
+        )
+    }
+}
+
+/// A wrapper writing a canary word on both sides of every allocation, and
+/// optionally a checksum of the allocation's header, verified when the
+/// block is freed: corruption panics with the tag current at allocation
+/// time. Meant for debug builds, to catch out-of-bounds writes of unsafe
+/// code close to where they happen.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, CanaryAlloc, Corruption};
+///
+/// let alloc = CanaryAlloc::with_checksum(Allocator::new());
+/// let layout = Layout::new::<[u8; 10]>();
+/// let block = alloc.allocate(layout).unwrap().cast::<u8>();
+///
+/// let past_end = unsafe { block.as_ptr().add(10) };
+/// let saved = unsafe { past_end.read() };
+/// unsafe { past_end.write(!saved) };
+/// let err = unsafe { alloc.verify(block, layout) }.unwrap_err();
+/// assert_eq!(err.corruption, Corruption::Back);
+///
+/// unsafe { past_end.write(saved) };
+/// unsafe { alloc.deallocate(block, layout) };
+/// ```
+#[derive(Debug)]
+pub struct CanaryAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    backend: A,
+    checksum: bool,
+}
+
+impl<A> CanaryAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Guards the allocations of `backend` with canaries.
+    #[inline]
+    pub const fn new(backend: A) -> Self {
+        Self {
+            backend,
+            checksum: false,
+        }
+    }
+
+    /// Guards the allocations of `backend` with canaries and a checksum of
+    /// their header.
+    #[inline]
+    pub const fn with_checksum(backend: A) -> Self {
+        Self {
+            backend,
+            checksum: true,
+        }
+    }
+
+    /// The allocator behind the canaries.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// Checks the canaries (and the checksum, if enabled) of a block.
+    ///
+    /// # Safety
+    /// `ptr` must be a block allocated by this allocator with `layout`, not
+    /// freed yet.
+    pub unsafe fn verify(&self, ptr: NonNull<u8>, layout: Layout) -> Result<(), CanaryError> {
+        let header = &*Self::header(ptr);
+        let corruption = if header.front != CANARY {
+            Some(Corruption::Front)
+        } else if ptr.as_ptr().add(layout.size()).cast::<usize>().read_unaligned() != CANARY {
+            Some(Corruption::Back)
+        } else if header.size != layout.size()
+            || self.checksum && header.checksum != checksum(ptr, header.size, header.tag)
+        {
+            Some(Corruption::Header)
+        } else {
+            None
+        };
+        match corruption {
+            Some(corruption) => Err(CanaryError {
+                corruption,
+                size: layout.size(),
+                tag: tag_at(header.tag),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    unsafe fn header(ptr: NonNull<u8>) -> *mut Header {
+        ptr.as_ptr().sub(mem::size_of::<Header>()).cast()
+    }
+
+    /// The layout of the block allocated from the backend, and the offset of
+    /// the block handed out in it.
+    #[inline]
+    fn outer(layout: Layout) -> Option<(Layout, usize)> {
+        let (with_header, offset) = Layout::new::<Header>().extend(layout).ok()?;
+        let back = Layout::new::<[u8; mem::size_of::<usize>()]>();
+        let (outer, _) = with_header.extend(back).ok()?;
+        Some((outer.pad_to_align(), offset))
+    }
+}
+
+#[inline]
+fn checksum(ptr: NonNull<u8>, size: usize, tag: usize) -> usize {
+    let mixed = ptr.as_ptr() as u64 ^ (size as u64).rotate_left(17) ^ (tag as u64).rotate_left(41);
+    mixed.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(32) as usize
+}
+
+unsafe impl<A> crate::alloc_api::Allocator for CanaryAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (outer, offset) = Self::outer(layout).ok_or(AllocError)?;
+        let base = self.backend.allocate(outer)?.cast::<u8>();
+        unsafe {
+            let ptr = NonNull::new_unchecked(base.as_ptr().add(offset));
+            let tag = current_index();
+            Self::header(ptr).write(Header {
+                size: layout.size(),
+                tag,
+                checksum: if self.checksum { checksum(ptr, layout.size(), tag) } else { 0 },
+                front: CANARY,
+            });
+            let back = ptr.as_ptr().add(layout.size()).cast::<usize>();
+            back.write_unaligned(CANARY);
+            Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if let Err(err) = self.verify(ptr, layout) {
+            panic!("{}", err);
+        }
+        let (outer, offset) = Self::outer(layout).unwrap();
+        let base = NonNull::new_unchecked(ptr.as_ptr().sub(offset));
+        self.backend.deallocate(base, outer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CanaryAlloc, Corruption};
+    use crate::{alloc_api::Allocator as _, Allocator, Tag};
+    use core::alloc::Layout;
+
+    #[test]
+    fn reports_tag_of_underflowed_block() {
+        let alloc = CanaryAlloc::new(Allocator::new());
+        let layout = Layout::from_size_align(24, 32).unwrap();
+        let block = {
+            let _scope = Tag::Name("parser").scope();
+            alloc.allocate(layout).unwrap().cast::<u8>()
+        };
+        assert_eq!(block.as_ptr() as usize % 32, 0);
+
+        unsafe {
+            let before = block.as_ptr().sub(1);
+            let saved = before.read();
+            before.write(!saved);
+            let err = alloc.verify(block, layout).unwrap_err();
+            assert_eq!(err.corruption, Corruption::Front);
+            assert_eq!(err.tag, Tag::Name("parser"));
+            before.write(saved);
+            alloc.deallocate(block, layout);
+        }
+    }
+}
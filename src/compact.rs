@@ -0,0 +1,306 @@
+use crate::RawVec;
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ptr,
+};
+
+/// Marks free slots and holes in the storage of a `CompactArena`.
+const VACANT: usize = usize::MAX;
+
+/// A handle to a value of a `CompactArena`. Unlike a pointer, a handle stays
+/// valid when the arena moves its values around.
+pub struct Handle<T> {
+    slot: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// The index of the handle in the arena's handle table.
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.slot
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.slot == other.slot
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    #[inline]
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.slot.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle {{ index: {} }}", self.slot)
+    }
+}
+
+/// An arena accessed through handles rather than pointers, so it can move
+/// its values to get rid of the holes left by removals.
+///
+/// Removing a value leaves a hole in the storage; `compact` moves the live
+/// values together, in storage order, shrinks the storage to fit them and
+/// updates the handle table. Handles of removed values are reused by later
+/// insertions, and must not be used after a removal.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::CompactArena;
+///
+/// let mut arena = CompactArena::new();
+/// let handles: Vec<_> = (0 .. 4).map(|i| arena.insert(i)).collect();
+/// arena.remove(handles[1]);
+/// arena.remove(handles[2]);
+/// assert_eq!(arena.holes(), 2);
+///
+/// arena.compact();
+/// assert_eq!(arena.holes(), 0);
+/// assert_eq!(arena.get(handles[3]), Some(&3));
+/// ```
+pub struct CompactArena<T> {
+    storage: RawVec<T>,
+    /// The slot owning each used position of the storage.
+    owners: Vec<usize>,
+    /// The position of the value of each slot.
+    slots: Vec<usize>,
+    free_slots: Vec<usize>,
+    len: usize,
+}
+
+impl<T> CompactArena<T> {
+    /// Creates an empty arena. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            storage: RawVec::new(),
+            owners: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            len: 0,
+        }
+    }
+
+    panicking! {
+        /// Moves `value` into the arena. In case of allocation error, the
+        /// function panics.
+        pub fn insert(&mut self, value: T) -> Handle<T> {
+            if self.owners.len() == self.storage.cap() {
+                let cap = (self.storage.cap() * 2).max(4);
+                self.relocate(cap, false);
+            }
+            let position = self.owners.len();
+            let slot = match self.free_slots.pop() {
+                Some(slot) => {
+                    self.slots[slot] = position;
+                    slot
+                },
+                None => {
+                    self.slots.push(position);
+                    self.slots.len() - 1
+                },
+            };
+            unsafe { self.storage.raw().as_ptr().add(position).write(value) }
+            self.owners.push(slot);
+            self.len += 1;
+            Handle {
+                slot,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// The value of `handle`, if it was not removed.
+    #[inline]
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        let position = self.position(handle)?;
+        unsafe { Some(&*self.storage.raw().as_ptr().add(position)) }
+    }
+
+    /// The value of `handle`, if it was not removed.
+    #[inline]
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        let position = self.position(handle)?;
+        unsafe { Some(&mut *self.storage.raw().as_ptr().add(position)) }
+    }
+
+    /// Moves the value of `handle` out of the arena, leaving a hole in the
+    /// storage.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let position = self.position(handle)?;
+        self.slots[handle.slot] = VACANT;
+        self.owners[position] = VACANT;
+        self.free_slots.push(handle.slot);
+        self.len -= 1;
+        while self.owners.last() == Some(&VACANT) {
+            self.owners.pop();
+        }
+        unsafe { Some(self.storage.raw().as_ptr().add(position).read()) }
+    }
+
+    /// Iterates over the live values and their handles, in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        let base = self.storage.raw().as_ptr();
+        self.owners
+            .iter()
+            .enumerate()
+            .filter(|&(_, &slot)| slot != VACANT)
+            .map(move |(position, &slot)| {
+                let handle = Handle {
+                    slot,
+                    _marker: PhantomData,
+                };
+                (handle, unsafe { &*base.add(position) })
+            })
+    }
+
+    /// Number of live values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if the arena holds no value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of holes left in the storage by removals since the last
+    /// compaction.
+    #[inline]
+    pub fn holes(&self) -> usize {
+        self.owners.len() - self.len
+    }
+
+    /// Number of values the storage can hold without growing.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.storage.cap()
+    }
+
+    panicking! {
+        /// Moves the live values together, in storage order, into a storage
+        /// exactly big enough for them. Handles stay valid. In case of
+        /// allocation error, the function panics.
+        #[inline]
+        pub fn compact(&mut self) {
+            self.relocate(self.len, true);
+        }
+    }
+
+    #[inline]
+    fn position(&self, handle: Handle<T>) -> Option<usize> {
+        match self.slots.get(handle.slot) {
+            Some(&position) if position != VACANT => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Moves the values into a new storage of `cap` values, skipping the
+    /// holes if `compact` is set.
+    fn relocate(&mut self, cap: usize, compact: bool) {
+        let storage = RawVec::<T>::with_capacity(cap);
+        let src = self.storage.raw().as_ptr();
+        let dst = storage.raw().as_ptr();
+        if compact {
+            let mut next = 0;
+            for (position, &slot) in self.owners.iter().enumerate() {
+                if slot != VACANT {
+                    unsafe { ptr::copy_nonoverlapping(src.add(position), dst.add(next), 1) }
+                    self.slots[slot] = next;
+                    next += 1;
+                }
+            }
+            self.owners.retain(|&slot| slot != VACANT);
+        } else {
+            unsafe { ptr::copy_nonoverlapping(src, dst, self.owners.len()) }
+        }
+        self.storage = storage;
+    }
+}
+
+impl<T> Default for CompactArena<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for CompactArena<T> {
+    fn drop(&mut self) {
+        let base = self.storage.raw().as_ptr();
+        for (position, &slot) in self.owners.iter().enumerate() {
+            if slot != VACANT {
+                unsafe { base.add(position).drop_in_place() }
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for CompactArena<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CompactArena {{ len: {}, holes: {}, capacity: {} }}",
+            self.len,
+            self.holes(),
+            self.capacity()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CompactArena;
+    use alloc::{string::String, vec::Vec};
+
+    #[test]
+    fn handles_survive_compaction() {
+        let mut arena = CompactArena::new();
+        let handles: Vec<_> = (0 .. 100).map(|i| arena.insert("x".repeat(i + 1))).collect();
+        for handle in handles.iter().step_by(2) {
+            arena.remove(*handle);
+        }
+        assert_eq!(arena.len(), 50);
+        assert_eq!(arena.holes(), 50);
+
+        arena.compact();
+        assert_eq!(arena.holes(), 0);
+        assert_eq!(arena.capacity(), 50);
+        for (i, handle) in handles.iter().enumerate().skip(1).step_by(2) {
+            assert_eq!(arena.get(*handle).map(String::len), Some(i + 1));
+        }
+
+        let reused = arena.insert(String::new());
+        assert_eq!(reused, handles[98]);
+        assert_eq!(arena.iter().count(), 51);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{AtomicShared, OwnedAlloc};
+use core::{fmt, ops::Deref};
+
+enum Repr<T> {
+    Shared(AtomicShared<T>),
+    Owned(OwnedAlloc<T>),
+}
+
+/// A copy-on-write allocation: handles share a read-only allocation until
+/// one of them is mutated, at which point it clones the value into a private
+/// `OwnedAlloc`. A handle which is the last one sharing the value mutates it
+/// in place instead of cloning it.
+///
+/// Cloning a handle which owns its value clones the value.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::CowAlloc;
+///
+/// let defaults = CowAlloc::new(vec![1, 2, 3]);
+/// let mut patched = defaults.clone();
+/// assert!(!patched.is_owned());
+///
+/// patched.to_mut().push(4);
+/// assert!(patched.is_owned());
+/// assert_eq!(*defaults, [1, 2, 3]);
+/// assert_eq!(*patched, [1, 2, 3, 4]);
+/// ```
+pub struct CowAlloc<T>
+where
+    T: Clone,
+{
+    repr: Repr<T>,
+}
+
+impl<T> CowAlloc<T>
+where
+    T: Clone,
+{
+    panicking! {
+        /// Moves `value` into a new shared allocation. In case of allocation
+        /// error, the function panics.
+        #[inline]
+        pub fn new(value: T) -> Self {
+            Self::from_shared(AtomicShared::new(value))
+        }
+    }
+
+    /// Shares the value of an existing `AtomicShared`.
+    #[inline]
+    pub fn from_shared(shared: AtomicShared<T>) -> Self {
+        Self {
+            repr: Repr::Shared(shared),
+        }
+    }
+
+    /// Tests if the handle has a private copy of the value, made by a
+    /// mutation while the value was shared.
+    #[inline]
+    pub fn is_owned(&self) -> bool {
+        matches!(self.repr, Repr::Owned(_))
+    }
+
+    /// A mutable reference to the value, cloning it into a private
+    /// allocation first if other handles share it.
+    pub fn to_mut(&mut self) -> &mut T {
+        if let Repr::Shared(shared) = &mut self.repr {
+            if AtomicShared::get_mut(shared).is_none() {
+                self.repr = Repr::Owned(OwnedAlloc::new(T::clone(shared)));
+            }
+        }
+        match &mut self.repr {
+            Repr::Shared(shared) => AtomicShared::get_mut(shared).unwrap(),
+            Repr::Owned(owned) => owned,
+        }
+    }
+
+    /// Converts the handle into a private allocation, cloning the value if it
+    /// is still shared.
+    pub fn into_owned(self) -> OwnedAlloc<T> {
+        match self.repr {
+            Repr::Owned(owned) => owned,
+            Repr::Shared(shared) => match AtomicShared::try_unwrap(shared) {
+                Ok(value) => OwnedAlloc::new(value),
+                Err(shared) => OwnedAlloc::new(T::clone(&shared)),
+            },
+        }
+    }
+}
+
+impl<T> Clone for CowAlloc<T>
+where
+    T: Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        let repr = match &self.repr {
+            Repr::Shared(shared) => Repr::Shared(shared.clone()),
+            Repr::Owned(owned) => Repr::Owned(OwnedAlloc::new(T::clone(owned))),
+        };
+        Self { repr }
+    }
+}
+
+impl<T> Deref for CowAlloc<T>
+where
+    T: Clone,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        match &self.repr {
+            Repr::Shared(shared) => shared,
+            Repr::Owned(owned) => owned,
+        }
+    }
+}
+
+impl<T> From<AtomicShared<T>> for CowAlloc<T>
+where
+    T: Clone,
+{
+    #[inline]
+    fn from(shared: AtomicShared<T>) -> Self {
+        Self::from_shared(shared)
+    }
+}
+
+impl<T> fmt::Debug for CowAlloc<T>
+where
+    T: Clone + fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CowAlloc {{ owned: {}, value: {:?} }}", self.is_owned(), **self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CowAlloc;
+    use alloc::string::String;
+
+    #[test]
+    fn last_sharer_takes_over() {
+        let cow = CowAlloc::new(String::from("config"));
+        let mut other = cow.clone();
+        drop(cow);
+        other.to_mut().push('!');
+        assert!(!other.is_owned());
+        assert_eq!(*other.into_owned(), "config!");
+    }
+}
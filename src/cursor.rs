@@ -0,0 +1,384 @@
+use crate::{error, LayoutError, OwnedAlloc, RawVec, RawVecError, WriteFmtError};
+use core::{cmp, fmt, mem, ptr::NonNull, slice};
+
+/// Byte buffers a `Cursor` reads and writes.
+///
+/// # Safety
+/// `bytes` must return memory valid for reads and writes, whose first
+/// `init_len` bytes are initialized, and which stays valid until the buffer
+/// is mutated. `try_reserve` must keep the first `len` bytes.
+pub unsafe trait CursorBuf {
+    /// Number of bytes initialized when the buffer is handed to a cursor.
+    fn init_len(&self) -> usize;
+
+    /// The whole buffer, including its uninitialized bytes.
+    fn bytes(&self) -> NonNull<[u8]>;
+
+    /// Makes room for at least `cap` bytes, keeping the first `len` ones, if
+    /// the buffer can grow. Fixed-size buffers are left untouched.
+    #[inline]
+    fn try_reserve(&mut self, len: usize, cap: usize) -> Result<(), RawVecError> {
+        let _ = (len, cap);
+        Ok(())
+    }
+}
+
+/// Starts out empty, and grows to at least double its capacity when full.
+unsafe impl CursorBuf for RawVec<u8> {
+    #[inline]
+    fn init_len(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn bytes(&self) -> NonNull<[u8]> {
+        self.raw_slice()
+    }
+
+    fn try_reserve(&mut self, len: usize, cap: usize) -> Result<(), RawVecError> {
+        if cap <= self.cap() {
+            return Ok(());
+        }
+        let new = Self::try_with_capacity(cmp::max(cap, self.cap().saturating_mul(2)))?;
+        unsafe {
+            new.raw()
+                .as_ptr()
+                .copy_from_nonoverlapping(self.raw().as_ptr(), len)
+        };
+        drop(mem::replace(self, new));
+        Ok(())
+    }
+}
+
+/// Starts out full, and never grows: writes past the end are cut short.
+unsafe impl CursorBuf for OwnedAlloc<[u8]> {
+    #[inline]
+    fn init_len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn bytes(&self) -> NonNull<[u8]> {
+        self.raw()
+    }
+}
+
+/// A read/write position over a byte buffer of the crate, so parsers and
+/// encoders can work on it in place rather than on a `Vec<u8>` copy. Over a
+/// `RawVec<u8>`, the cursor starts with no bytes, and writes grow the
+/// buffer; over an `OwnedAlloc<[u8]>`, it starts with the whole slice, and
+/// writes stop at its end.
+///
+/// `read_bytes` and `write_bytes` work without `std`. With the `std` feature,
+/// the cursor implements `Read`, `BufRead`, `Write` and `Seek`, and writes
+/// failing to grow the buffer report `ErrorKind::OutOfMemory`.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{Cursor, RawVec};
+///
+/// let mut cursor = Cursor::new(RawVec::<u8>::new());
+/// cursor.write_bytes(b"hello, world").unwrap();
+/// assert_eq!(cursor.as_slice(), b"hello, world");
+///
+/// cursor.set_position(7);
+/// let mut word = [0; 8];
+/// let read = cursor.read_bytes(&mut word);
+/// assert_eq!(&word[.. read], b"world");
+/// ```
+pub struct Cursor<B>
+where
+    B: CursorBuf,
+{
+    buf: B,
+    pos: usize,
+    len: usize,
+}
+
+impl<B> Cursor<B>
+where
+    B: CursorBuf,
+{
+    /// Creates a cursor at the start of `buf`.
+    #[inline]
+    pub fn new(buf: B) -> Self {
+        let len = buf.init_len();
+        Self { buf, pos: 0, len }
+    }
+
+    /// The position of the cursor, in bytes from the start.
+    #[inline]
+    pub const fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves the cursor. Writing past the end fills the gap with zeros.
+    #[inline]
+    pub fn set_position(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    /// Number of initialized bytes: the ones read from or written to.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no initialized bytes.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The underlying buffer.
+    #[inline]
+    pub const fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// The initialized bytes.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.buf.bytes().cast::<u8>().as_ptr(), self.len) }
+    }
+
+    /// The initialized bytes from the position of the cursor on.
+    #[inline]
+    pub fn remaining_slice(&self) -> &[u8] {
+        let slice = self.as_slice();
+        &slice[cmp::min(self.pos, slice.len()) ..]
+    }
+
+    /// Copies bytes from the cursor to `out`, returning how many were read:
+    /// fewer than `out.len()` at the end of the initialized bytes.
+    #[inline]
+    pub fn read_bytes(&mut self, out: &mut [u8]) -> usize {
+        let remaining = self.remaining_slice();
+        let count = cmp::min(remaining.len(), out.len());
+        out[.. count].copy_from_slice(&remaining[.. count]);
+        self.pos += count;
+        count
+    }
+
+    /// Copies `bytes` to the cursor, returning how many were written: fewer
+    /// than `bytes.len()` at the end of a fixed-size buffer. In case of
+    /// allocation error growing the buffer, `Err` is returned and nothing is
+    /// written.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<usize, RawVecError> {
+        let end = self.pos.checked_add(bytes.len()).ok_or(LayoutError)?;
+        self.buf.try_reserve(self.len, end)?;
+        let dest = self.buf.bytes();
+        let count = cmp::min(bytes.len(), dest.len().saturating_sub(self.pos));
+        if count == 0 {
+            return Ok(0);
+        }
+        let dest = dest.cast::<u8>().as_ptr();
+        unsafe {
+            if self.pos > self.len {
+                dest.add(self.len).write_bytes(0, self.pos - self.len);
+            }
+            dest.add(self.pos).copy_from_nonoverlapping(bytes.as_ptr(), count);
+        }
+        self.pos += count;
+        self.len = cmp::max(self.len, self.pos);
+        Ok(count)
+    }
+
+    /// Formats `args` into the cursor, like `write!` through `fmt::Write`, but
+    /// telling a failure to grow the buffer and a full buffer apart. The text
+    /// formatted before the error stays written.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::{Cursor, OwnedAlloc, WriteFmtError};
+    ///
+    /// let mut cursor = Cursor::new(OwnedAlloc::collect_slice([0u8; 8]));
+    /// cursor.try_write_fmt(format_args!("{}", 1234)).unwrap();
+    /// let res = cursor.try_write_fmt(format_args!("{}", 56789));
+    /// assert!(matches!(res, Err(WriteFmtError::Full)));
+    /// assert_eq!(cursor.as_slice(), b"12345678");
+    /// ```
+    pub fn try_write_fmt(&mut self, args: fmt::Arguments) -> Result<(), WriteFmtError> {
+        error::try_write_fmt(
+            |string| match self.write_bytes(string.as_bytes())? {
+                count if count < string.len() => Err(WriteFmtError::Full),
+                _ => Ok(()),
+            },
+            args,
+        )
+    }
+
+    /// Takes the cursor apart into the buffer and the number of its
+    /// initialized bytes.
+    #[inline]
+    pub fn into_parts(self) -> (B, usize) {
+        (self.buf, self.len)
+    }
+}
+
+impl<B> fmt::Debug for Cursor<B>
+where
+    B: CursorBuf,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Cursor {{ position: {}, len: {}, cap: {} }}",
+            self.pos,
+            self.len,
+            self.buf.bytes().len()
+        )
+    }
+}
+
+/// Fails if the buffer is full or cannot grow; `try_write_fmt` tells why.
+impl<B> fmt::Write for Cursor<B>
+where
+    B: CursorBuf,
+{
+    #[inline]
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        match self.write_bytes(string.as_bytes()) {
+            Ok(count) if count == string.len() => Ok(()),
+            _ => Err(fmt::Error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod io {
+    use super::{Cursor, CursorBuf};
+    use crate::RawVecError;
+    use std::io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom, Write};
+
+    impl<B> Read for Cursor<B>
+    where
+        B: CursorBuf,
+    {
+        #[inline]
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            Ok(self.read_bytes(buf))
+        }
+    }
+
+    impl<B> BufRead for Cursor<B>
+    where
+        B: CursorBuf,
+    {
+        #[inline]
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Ok(self.remaining_slice())
+        }
+
+        #[inline]
+        fn consume(&mut self, amt: usize) {
+            self.pos += amt;
+        }
+    }
+
+    impl<B> Write for Cursor<B>
+    where
+        B: CursorBuf,
+    {
+        #[inline]
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write_bytes(buf).map_err(|err| match err {
+                RawVecError::Alloc(_) => ErrorKind::OutOfMemory.into(),
+                RawVecError::Layout(_) => ErrorKind::InvalidInput.into(),
+            })
+        }
+
+        #[inline]
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<B> Seek for Cursor<B>
+    where
+        B: CursorBuf,
+    {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            let (base, offset) = match pos {
+                SeekFrom::Start(pos) => (0, pos as i64),
+                SeekFrom::End(offset) => (self.len, offset),
+                SeekFrom::Current(offset) => (self.pos, offset),
+            };
+            let pos = (base as i64)
+                .checked_add(offset)
+                .filter(|&pos| pos >= 0)
+                .ok_or_else(|| io::Error::from(ErrorKind::InvalidInput))?;
+            self.pos = pos as usize;
+            Ok(pos as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cursor;
+    use crate::{OwnedAlloc, RawVec};
+
+    #[test]
+    fn grows_or_cuts_writes_short() {
+        let mut growing = Cursor::new(RawVec::<u8>::new());
+        assert_eq!(growing.write_bytes(b"abc").unwrap(), 3);
+        growing.set_position(5);
+        assert_eq!(growing.write_bytes(b"f").unwrap(), 1);
+        assert_eq!(growing.as_slice(), b"abc\0\0f");
+
+        let mut fixed = Cursor::new(OwnedAlloc::collect_slice(*b"xyz"));
+        fixed.set_position(1);
+        assert_eq!(fixed.write_bytes(b"ABCD").unwrap(), 2);
+        assert_eq!(fixed.write_bytes(b"E").unwrap(), 0);
+        assert_eq!(fixed.as_slice(), b"xAB");
+    }
+
+    #[test]
+    fn formats_into_buffers() {
+        use crate::WriteFmtError;
+        use core::fmt::{self, Write};
+
+        struct Failing;
+
+        impl fmt::Display for Failing {
+            fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let mut cursor = Cursor::new(RawVec::<u8>::new());
+        write!(cursor, "id-{:02}", 7).unwrap();
+        cursor.try_write_fmt(format_args!("/{:x}", 255)).unwrap();
+        assert_eq!(cursor.as_slice(), b"id-07/ff");
+        let res = cursor.try_write_fmt(format_args!("{}", Failing));
+        assert!(matches!(res, Err(WriteFmtError::Fmt)));
+
+        let mut fixed = Cursor::new(OwnedAlloc::collect_slice([0u8; 2]));
+        assert!(write!(fixed, "{}", 100).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn std_io_traits() {
+        use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+
+        let mut cursor = Cursor::new(RawVec::<u8>::new());
+        writeln!(cursor, "first").unwrap();
+        writeln!(cursor, "second").unwrap();
+        assert_eq!(cursor.seek(SeekFrom::End(-7)).unwrap(), 6);
+        let mut line = std::string::String::new();
+        cursor.read_line(&mut line).unwrap();
+        assert_eq!(line, "second\n");
+        cursor.rewind().unwrap();
+        let mut first = [0; 5];
+        cursor.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"first");
+        assert!(cursor.seek(SeekFrom::Current(-6)).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{OwnedAlloc, ALLOCATOR};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Intrusive header placed at the start of every deferred node. The
+/// `reclaim` function knows the concrete type of the node and frees it.
+#[repr(C)]
+struct Header {
+    next: *mut Header,
+    addr: *mut u8,
+    reclaim: unsafe fn(NonNull<Header>),
+}
+
+#[repr(C)]
+struct Node<P> {
+    header: Header,
+    payload: P,
+}
+
+/// A raw memory block deferred through `DeferQueue::defer_raw`.
+struct RawBlock {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Drop for RawBlock {
+    #[inline]
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe { ALLOCATOR.dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
+
+unsafe fn reclaim_node<P>(header: NonNull<Header>) {
+    drop(OwnedAlloc::from_raw(header.cast::<Node<P>>()));
+}
+
+/// A lock-free queue of allocations whose deallocation is deferred until a
+/// designated reclaimer reaches a quiescent point, i.e. a point where no
+/// thread can still be reading the retired memory.
+///
+/// Any number of threads may push concurrently. Reclamation detaches the
+/// whole queue with a single atomic swap, so pushes never contend with it
+/// beyond a failed compare-and-swap.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{DeferQueue, OwnedAlloc};
+///
+/// let queue = DeferQueue::new();
+/// queue.defer(OwnedAlloc::new(5u64));
+/// queue.defer(OwnedAlloc::new([1u8; 16]));
+/// assert!(!queue.is_empty());
+///
+/// // No other thread holds references to the deferred values.
+/// assert_eq!(unsafe { queue.reclaim() }, 2);
+/// assert!(queue.is_empty());
+/// ```
+pub struct DeferQueue {
+    head: AtomicPtr<Header>,
+}
+
+impl DeferQueue {
+    /// Creates a new empty queue.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    panicking! {
+        /// Defers dropping the given allocation (and its contents) until the next
+        /// call to `reclaim`. The queue drops what is left when it is dropped
+        /// itself, hence the `'static` bound.
+        #[inline]
+        pub fn defer<T>(&self, alloc: OwnedAlloc<T>)
+        where
+            T: ?Sized + Send + 'static,
+        {
+            let addr = alloc.raw().cast::<u8>().as_ptr();
+            self.push(addr, alloc);
+        }
+    }
+
+    /// Defers deallocating a raw block of memory until the next call to
+    /// `reclaim`. No destructor is run on the block's contents.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because `ptr` must have been allocated
+    /// through the crate's allocator with the given `layout`, and must not be
+    /// deallocated by anyone else.
+    #[inline]
+    pub unsafe fn defer_raw(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.push(ptr.as_ptr(), RawBlock { ptr, layout });
+    }
+
+    /// Tests if there is nothing waiting to be reclaimed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+
+    /// Frees everything deferred so far and returns how many entries were
+    /// reclaimed. Entries deferred concurrently with this call are either
+    /// reclaimed now or left for the next call.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because the caller must be at a quiescent
+    /// point: no thread may still hold a reference into any of the deferred
+    /// allocations.
+    #[inline]
+    pub unsafe fn reclaim(&self) -> usize {
+        let mut curr = self.head.swap(ptr::null_mut(), Ordering::AcqRel);
+        let mut count = 0;
+        while let Some(header) = NonNull::new(curr) {
+            curr = header.as_ref().next;
+            (header.as_ref().reclaim)(header);
+            count += 1;
+        }
+        count
+    }
+
+    /// Like `reclaim`, but entries whose address satisfies `keep` are put
+    /// back into the queue instead of being freed.
+    pub(crate) unsafe fn reclaim_unless<F>(&self, mut keep: F) -> usize
+    where
+        F: FnMut(*mut u8) -> bool,
+    {
+        let mut curr = self.head.swap(ptr::null_mut(), Ordering::AcqRel);
+        let mut count = 0;
+        while let Some(header) = NonNull::new(curr) {
+            curr = header.as_ref().next;
+            if keep(header.as_ref().addr) {
+                self.push_header(header.as_ptr());
+            } else {
+                (header.as_ref().reclaim)(header);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn push<P>(&self, addr: *mut u8, payload: P) {
+        let node = OwnedAlloc::new(Node {
+            header: Header {
+                next: ptr::null_mut(),
+                addr,
+                reclaim: reclaim_node::<P>,
+            },
+            payload,
+        });
+        self.push_header(node.into_raw().cast::<Header>().as_ptr());
+    }
+
+    fn push_header(&self, header: *mut Header) {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*header).next = head }
+            match self.head.compare_exchange_weak(
+                head,
+                header,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(new) => head = new,
+            }
+        }
+    }
+}
+
+impl Default for DeferQueue {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DeferQueue {
+    #[inline]
+    fn drop(&mut self) {
+        // Having `&mut self` means no other thread can reach the entries.
+        unsafe {
+            self.reclaim();
+        }
+    }
+}
+
+impl fmt::Debug for DeferQueue {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DeferQueue {{ head: {:?} }}", self.head.load(Ordering::Relaxed))
+    }
+}
+
+unsafe impl Send for DeferQueue {}
+unsafe impl Sync for DeferQueue {}
+
+#[cfg(test)]
+mod test {
+    use super::DeferQueue;
+    use crate::{OwnedAlloc, UninitAlloc};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountDrop<'a>(&'a AtomicUsize);
+
+    impl<'a> Drop for CountDrop<'a> {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn drops_only_on_reclaim() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        let queue = DeferQueue::new();
+        for _ in 0 .. 3 {
+            queue.defer(OwnedAlloc::new(CountDrop(&DROPS)));
+        }
+        assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+        assert_eq!(unsafe { queue.reclaim() }, 3);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+        assert_eq!(unsafe { queue.reclaim() }, 0);
+    }
+
+    #[test]
+    fn raw_blocks_and_drop() {
+        let queue = DeferQueue::new();
+        let raw = UninitAlloc::<[u64; 4]>::new().into_raw();
+        unsafe {
+            queue.defer_raw(raw.cast(), core::alloc::Layout::new::<[u64; 4]>());
+        }
+        assert!(!queue.is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    alloc_api::{AllocError, Allocator as _},
+    Tlsf,
+};
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    fmt,
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+};
+
+/// Granularity of the spacers scattering allocations.
+const SPACER_UNIT: usize = 16;
+
+/// A deterministic allocator for tests and fuzzing: given the same seed and
+/// the same sequence of requests, it places blocks at the same offsets of
+/// its region and fails at the same points, so allocation-order bugs of the
+/// code under test reproduce across runs.
+///
+/// Blocks come from a caller-provided region managed by a `Tlsf`. The seed
+/// drives a pseudo-random generator which scatters blocks by leaving gaps
+/// between them, and optionally injects failures. Offsets, as returned by
+/// `offset_of`, are reproducible; addresses are too if the region is (e.g. a
+/// `static`).
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::mem::MaybeUninit;
+/// use owned_alloc::{alloc_api, DetAlloc};
+///
+/// let run = |seed| {
+///     let mut region = vec![MaybeUninit::uninit(); 1 << 16];
+///     let alloc = DetAlloc::new(&mut region, seed);
+///     alloc.fail_at(3);
+///     let mut vec = alloc_api::Vec::new_in(&alloc);
+///     let mut log = Vec::new();
+///     for i in 0 .. 100u32 {
+///         if vec.try_reserve(1).is_err() {
+///             log.push(None);
+///             break;
+///         }
+///         vec.push(i);
+///         log.push(Some(alloc.offset_of(vec.as_ptr().cast())));
+///     }
+///     log
+/// };
+/// assert_eq!(run(42), run(42));
+/// ```
+pub struct DetAlloc<'r> {
+    tlsf: Tlsf<'r>,
+    base: usize,
+    seed: u64,
+    state: Cell<u64>,
+    allocations: Cell<u64>,
+    fail_at: Cell<Option<u64>>,
+    fail_one_in: Cell<u64>,
+    spacers: Cell<*mut u8>,
+}
+
+impl<'r> DetAlloc<'r> {
+    /// Creates an allocator managing the given region, with the given seed.
+    #[inline]
+    pub fn new(region: &'r mut [MaybeUninit<u8>], seed: u64) -> Self {
+        let base = region.as_ptr() as usize;
+        Self {
+            tlsf: Tlsf::new(region),
+            base,
+            seed,
+            state: Cell::new(seed),
+            allocations: Cell::new(0),
+            fail_at: Cell::new(None),
+            fail_one_in: Cell::new(0),
+            spacers: Cell::new(ptr::null_mut()),
+        }
+    }
+
+    /// The seed of the allocator.
+    #[inline]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of allocations requested so far, failed ones included.
+    #[inline]
+    pub fn allocations(&self) -> u64 {
+        self.allocations.get()
+    }
+
+    /// Makes the allocation of index `index` (counting from `0`, as
+    /// `allocations` does) fail.
+    #[inline]
+    pub fn fail_at(&self, index: u64) {
+        self.fail_at.set(Some(index));
+    }
+
+    /// Makes each allocation fail with a probability of `1 / n`, drawn from
+    /// the seeded generator. `0` disables random failures.
+    #[inline]
+    pub fn fail_one_in(&self, n: u64) {
+        self.fail_one_in.set(n);
+    }
+
+    /// The offset of `ptr` from the start of the region.
+    #[inline]
+    pub fn offset_of(&self, ptr: *const u8) -> usize {
+        ptr as usize - self.base
+    }
+
+    /// Next value of the generator (SplitMix64).
+    #[inline]
+    fn next(&self) -> u64 {
+        let state = self.state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        self.state.set(state);
+        let mut mixed = state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        mixed ^ (mixed >> 31)
+    }
+
+    /// Leaves a gap of random size before the next block, half of the time.
+    /// Gaps are held until the allocator is dropped.
+    fn scatter(&self) {
+        let draw = self.next();
+        if draw & 1 == 0 {
+            return;
+        }
+        let size = SPACER_UNIT * (1 + (draw >> 1) as usize % 16);
+        let layout = unsafe { Layout::from_size_align_unchecked(size, SPACER_UNIT) };
+        if let Ok(spacer) = self.tlsf.allocate(layout) {
+            let spacer = spacer.cast::<u8>().as_ptr();
+            unsafe { spacer.cast::<*mut u8>().write(self.spacers.get()) };
+            self.spacers.set(spacer);
+        }
+    }
+}
+
+unsafe impl<'r> crate::alloc_api::Allocator for DetAlloc<'r> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let index = self.allocations.get();
+        self.allocations.set(index + 1);
+        // Always draws, so the sequence does not depend on the settings.
+        let draw = self.next();
+        let one_in = self.fail_one_in.get();
+        if self.fail_at.get() == Some(index) || one_in != 0 && draw % one_in == one_in - 1 {
+            return Err(AllocError);
+        }
+        self.scatter();
+        self.tlsf.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.tlsf.deallocate(ptr, layout)
+    }
+}
+
+impl<'r> fmt::Debug for DetAlloc<'r> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DetAlloc {{ seed: {}, allocations: {} }}",
+            self.seed,
+            self.allocations()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DetAlloc;
+    use crate::alloc_api::Allocator;
+    use alloc::vec::Vec;
+    use core::{
+        alloc::Layout,
+        mem::MaybeUninit,
+    };
+
+    fn trace(seed: u64) -> Vec<Option<usize>> {
+        let mut region = alloc::vec![MaybeUninit::<u8>::uninit(); 1 << 16];
+        let alloc = DetAlloc::new(&mut region, seed);
+        alloc.fail_one_in(5);
+        let mut live = Vec::new();
+        let mut trace = Vec::new();
+        for i in 1 .. 60 {
+            let layout = Layout::from_size_align(i * 8, 8).unwrap();
+            match alloc.allocate(layout) {
+                Ok(block) => {
+                    trace.push(Some(alloc.offset_of(block.cast::<u8>().as_ptr())));
+                    live.push((block, layout));
+                },
+                Err(_) => trace.push(None),
+            }
+            if i % 3 == 0 && !live.is_empty() {
+                let (block, layout) = live.swap_remove(live.len() / 2);
+                unsafe { alloc.deallocate(block.cast(), layout) };
+            }
+        }
+        trace
+    }
+
+    #[test]
+    fn same_seed_same_trace() {
+        let first = trace(7);
+        assert_eq!(first, trace(7));
+        assert!(first.contains(&None));
+        assert_ne!(first, trace(8));
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{AllocError, LayoutError, RawVecError, ALLOCATOR};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt, mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// Platform hooks needed to share memory with a DMA-capable device. The
+/// defaults describe a cache-coherent system where the device sees the same
+/// addresses as the CPU, which is what `Coherent` implements.
+///
+/// # Safety
+/// Implementors must return addresses the device can actually use, and the
+/// cache operations must make CPU writes visible to the device (`clean`) and
+/// device writes visible to the CPU (`invalidate`).
+pub unsafe trait DmaPlatform {
+    /// Translates a CPU pointer to the address the device uses for it.
+    #[inline]
+    fn bus_address(&self, ptr: *const u8) -> usize {
+        ptr as usize
+    }
+
+    /// Writes back cached CPU writes to memory, before the device reads it.
+    #[inline]
+    fn clean(&self, _ptr: *const u8, _len: usize) {}
+
+    /// Discards cached lines, before the CPU reads what the device wrote.
+    #[inline]
+    fn invalidate(&self, _ptr: *const u8, _len: usize) {}
+
+    /// Makes a freshly allocated range uncached. Called only for allocations
+    /// requested with `DmaConstraints::uncached`.
+    #[inline]
+    fn set_uncached(&self, _ptr: *mut u8, _len: usize) {}
+
+    /// Restores normal caching of a range before it is freed.
+    #[inline]
+    fn set_cached(&self, _ptr: *mut u8, _len: usize) {}
+}
+
+/// A cache-coherent platform with identity bus addresses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Coherent;
+
+unsafe impl DmaPlatform for Coherent {}
+
+/// Constraints a device imposes on a DMA buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmaConstraints {
+    /// Minimum alignment of the buffer. Must be a power of two.
+    pub align: usize,
+    /// If non-zero, a power of two the buffer must not cross (e.g. `4096`
+    /// for devices that cannot cross a page in a single transfer).
+    pub boundary: usize,
+    /// Whether the buffer should be mapped uncached through the platform.
+    pub uncached: bool,
+}
+
+impl DmaConstraints {
+    /// Constraints only asking for the given alignment.
+    #[inline]
+    pub const fn aligned(align: usize) -> Self {
+        Self {
+            align,
+            boundary: 0,
+            uncached: false,
+        }
+    }
+
+    /// Computes the layout of a `size` bytes buffer honoring the constraints.
+    /// The boundary is honored by aligning the buffer to its own size rounded
+    /// up to a power of two, which never straddles a larger power of two.
+    pub const fn layout(&self, size: usize, align: usize) -> Result<Layout, LayoutError> {
+        let mut align = if self.align > align { self.align } else { align };
+        if self.boundary != 0 {
+            if size > self.boundary || !self.boundary.is_power_of_two() {
+                return Err(LayoutError);
+            }
+            let span = size.next_power_of_two();
+            if span > align {
+                align = span;
+            }
+        }
+        match Layout::from_size_align(size, align) {
+            Ok(layout) => Ok(layout),
+            Err(_) => Err(LayoutError),
+        }
+    }
+}
+
+/// An owned allocation meant to be shared with a DMA-capable device, such as
+/// a descriptor ring. Alignment, boundary and caching requirements are given
+/// by the caller through `DmaConstraints`, and cache maintenance is explicit:
+/// call `sync_for_device` after the CPU writes and before the device reads,
+/// and `sync_for_cpu` after the device writes and before the CPU reads.
+///
+/// Memory comes from the crate's allocator, so it is physically contiguous
+/// only where the heap is, e.g. on targets without an MMU.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{Coherent, DmaAlloc, DmaConstraints};
+///
+/// let constraints = DmaConstraints {
+///     boundary: 4096,
+///     ..DmaConstraints::aligned(64)
+/// };
+/// let mut ring = DmaAlloc::new([0u32; 16], constraints, Coherent);
+/// ring[0] = 0xdead_beef;
+/// ring.sync_for_device();
+///
+/// assert_eq!(ring.bus_address() % 64, 0);
+/// assert_eq!(ring.bus_address() / 4096, (ring.bus_address() + 63) / 4096);
+/// ```
+pub struct DmaAlloc<T, P = Coherent>
+where
+    P: DmaPlatform,
+{
+    ptr: NonNull<T>,
+    layout: Layout,
+    uncached: bool,
+    platform: P,
+}
+
+impl<T, P> DmaAlloc<T, P>
+where
+    P: DmaPlatform,
+{
+    panicking! {
+        /// Allocates and initializes a DMA buffer. In case of allocation error or
+        /// unsatisfiable constraints, the function panics.
+        #[inline]
+        pub fn new(value: T, constraints: DmaConstraints, platform: P) -> Self {
+            match Self::try_new(value, constraints, platform) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => panic!("Unsatisfiable DMA constraints: {}", err),
+            }
+        }
+    }
+
+    /// Allocates and initializes a DMA buffer. In case of allocation error or
+    /// unsatisfiable constraints, `Err` is returned.
+    pub fn try_new(value: T, constraints: DmaConstraints, platform: P) -> Result<Self, RawVecError> {
+        let layout = constraints.layout(mem::size_of::<T>(), mem::align_of::<T>())?;
+        let ptr = if layout.size() == 0 {
+            unsafe { NonNull::new_unchecked(layout.align() as *mut T) }
+        } else {
+            NonNull::new(unsafe { ALLOCATOR.alloc(layout) })
+                .ok_or(AllocError { layout })?
+                .cast::<T>()
+        };
+        unsafe { ptr.as_ptr().write(value) };
+        let uncached = constraints.uncached && layout.size() != 0;
+        if uncached {
+            platform.set_uncached(ptr.cast().as_ptr(), layout.size());
+        }
+        Ok(Self {
+            ptr,
+            layout,
+            uncached,
+            platform,
+        })
+    }
+
+    /// The address the device should use for this buffer.
+    #[inline]
+    pub fn bus_address(&self) -> usize {
+        self.platform.bus_address(self.ptr.cast().as_ptr())
+    }
+
+    /// The layout the buffer was allocated with.
+    #[inline]
+    pub const fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The raw non-null pointer to the buffer.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<T> {
+        self.ptr
+    }
+
+    /// The platform hooks of this buffer.
+    #[inline]
+    pub const fn platform(&self) -> &P {
+        &self.platform
+    }
+
+    /// Makes CPU writes visible to the device.
+    #[inline]
+    pub fn sync_for_device(&self) {
+        self.platform
+            .clean(self.ptr.cast().as_ptr(), mem::size_of::<T>());
+    }
+
+    /// Makes device writes visible to the CPU. Takes `&mut self` since the
+    /// contents may change under any outstanding reference.
+    #[inline]
+    pub fn sync_for_cpu(&mut self) -> &mut T {
+        self.platform
+            .invalidate(self.ptr.cast().as_ptr(), mem::size_of::<T>());
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T, P> Deref for DmaAlloc<T, P>
+where
+    P: DmaPlatform,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, P> DerefMut for DmaAlloc<T, P>
+where
+    P: DmaPlatform,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T, P> Drop for DmaAlloc<T, P>
+where
+    P: DmaPlatform,
+{
+    fn drop(&mut self) {
+        unsafe {
+            self.ptr.as_ptr().drop_in_place();
+            if self.layout.size() != 0 {
+                if self.uncached {
+                    self.platform
+                        .set_cached(self.ptr.cast().as_ptr(), self.layout.size());
+                }
+                ALLOCATOR.dealloc(self.ptr.cast().as_ptr(), self.layout);
+            }
+        }
+    }
+}
+
+impl<T, P> fmt::Debug for DmaAlloc<T, P>
+where
+    P: DmaPlatform,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DmaAlloc {{ pointer: {:?}, bus address: {:#x} }}",
+            self.ptr,
+            self.bus_address()
+        )
+    }
+}
+
+unsafe impl<T, P> Send for DmaAlloc<T, P>
+where
+    T: Send,
+    P: DmaPlatform + Send,
+{
+}
+unsafe impl<T, P> Sync for DmaAlloc<T, P>
+where
+    T: Sync,
+    P: DmaPlatform + Sync,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DmaAlloc, DmaConstraints, DmaPlatform};
+    use core::cell::Cell;
+
+    #[derive(Default)]
+    struct Counting {
+        cleans: Cell<usize>,
+        invalidates: Cell<usize>,
+        uncached: Cell<isize>,
+    }
+
+    unsafe impl DmaPlatform for &Counting {
+        fn bus_address(&self, ptr: *const u8) -> usize {
+            ptr as usize + 0x1000
+        }
+
+        fn clean(&self, _ptr: *const u8, _len: usize) {
+            self.cleans.set(self.cleans.get() + 1);
+        }
+
+        fn invalidate(&self, _ptr: *const u8, _len: usize) {
+            self.invalidates.set(self.invalidates.get() + 1);
+        }
+
+        fn set_uncached(&self, _ptr: *mut u8, _len: usize) {
+            self.uncached.set(self.uncached.get() + 1);
+        }
+
+        fn set_cached(&self, _ptr: *mut u8, _len: usize) {
+            self.uncached.set(self.uncached.get() - 1);
+        }
+    }
+
+    #[test]
+    fn platform_hooks_are_called() {
+        let platform = Counting::default();
+        let constraints = DmaConstraints {
+            uncached: true,
+            ..DmaConstraints::aligned(128)
+        };
+        let mut buf = DmaAlloc::new([1u8; 32], constraints, &platform);
+        assert_eq!(buf.raw().as_ptr() as usize % 128, 0);
+        assert_eq!(buf.bus_address() - buf.raw().as_ptr() as usize, 0x1000);
+        assert_eq!(platform.uncached.get(), 1);
+
+        buf.sync_for_device();
+        buf.sync_for_cpu()[0] = 2;
+        assert_eq!(platform.cleans.get(), 1);
+        assert_eq!(platform.invalidates.get(), 1);
+
+        drop(buf);
+        assert_eq!(platform.uncached.get(), 0);
+    }
+
+    #[test]
+    fn boundary_too_small() {
+        let constraints = DmaConstraints {
+            boundary: 16,
+            ..DmaConstraints::aligned(1)
+        };
+        assert!(DmaAlloc::try_new([0u8; 32], constraints, super::Coherent).is_err());
+    }
+}
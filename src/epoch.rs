@@ -0,0 +1,258 @@
+use crate::{
+    slots::{Slot, SlotList},
+    DeferQueue, OwnedAlloc,
+};
+use core::{
+    fmt,
+    sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Number of deferred deallocations after which `EpochGuard::defer_dealloc`
+/// tries to advance the epoch on its own.
+pub const ADVANCE_THRESHOLD: usize = 64;
+
+/// Bit set in a participant's epoch word while it is pinned.
+const PINNED: usize = 1;
+
+/// The collector used by the free functions of this module.
+static GLOBAL: Collector = Collector::new();
+
+/// Epoch-based reclamation. Threads `pin` the collector while they access a
+/// shared structure, and unlinked allocations are deferred into the bag of
+/// the current epoch. The global epoch only advances once every pinned
+/// thread has observed it, and garbage is freed two epochs after it was
+/// deferred, when no pinned thread can still reach it.
+///
+/// Compared to hazard pointers (see the `hazard` module), pinning is cheaper
+/// and protects everything at once, but a single stalled pinned thread
+/// blocks all reclamation.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{epoch::Collector, OwnedAlloc};
+///
+/// let collector = Collector::new();
+/// let guard = collector.pin();
+/// guard.defer_dealloc(OwnedAlloc::new(5));
+/// drop(guard);
+///
+/// // Garbage deferred in epoch `0` is freed when leaving epoch `2`.
+/// assert_eq!(collector.advance(), Some(0));
+/// assert_eq!(collector.advance(), Some(0));
+/// assert_eq!(collector.advance(), Some(1));
+/// assert_eq!(collector.epoch(), 3);
+/// ```
+pub struct Collector {
+    epoch: AtomicUsize,
+    advancing: AtomicBool,
+    deferred: AtomicUsize,
+    participants: SlotList<AtomicUsize>,
+    bags: [DeferQueue; 3],
+}
+
+impl Collector {
+    /// Creates a new collector at epoch `0`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            epoch: AtomicUsize::new(0),
+            advancing: AtomicBool::new(false),
+            deferred: AtomicUsize::new(0),
+            participants: SlotList::new(),
+            bags: [DeferQueue::new(), DeferQueue::new(), DeferQueue::new()],
+        }
+    }
+
+    /// The current global epoch.
+    #[inline]
+    pub fn epoch(&self) -> usize {
+        self.epoch.load(Ordering::Acquire)
+    }
+
+    /// Pins the current thread. Allocations reachable from the shared
+    /// structure while the guard is alive are not freed before it is dropped.
+    pub fn pin(&self) -> EpochGuard<'_> {
+        let slot = self.participants.acquire(|| AtomicUsize::new(0));
+        let epoch = self.publish(slot);
+        EpochGuard {
+            collector: self,
+            slot,
+            epoch,
+        }
+    }
+
+    /// Tries to advance the global epoch. This succeeds only if every pinned
+    /// thread has observed the current epoch and no other thread is
+    /// advancing. On success, the garbage deferred two epochs ago is freed,
+    /// and the number of freed allocations is returned.
+    pub fn advance(&self) -> Option<usize> {
+        if self.advancing.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        fence(Ordering::SeqCst);
+        let epoch = self.epoch.load(Ordering::Relaxed);
+        let lagging = self.participants.iter().any(|slot| {
+            let word = slot.value.load(Ordering::Relaxed);
+            slot.is_active() && word & PINNED != 0 && word >> 1 != epoch
+        });
+        let res = if lagging {
+            None
+        } else {
+            // The bag of `epoch + 1` still holds garbage from `epoch - 2`.
+            // Nobody defers into it until the new epoch is published.
+            let freed = unsafe { self.bags[(epoch + 1) % 3].reclaim() };
+            self.epoch.store(epoch.wrapping_add(1), Ordering::Release);
+            Some(freed)
+        };
+        self.advancing.store(false, Ordering::Release);
+        res
+    }
+
+    /// Pins `slot` at the current epoch, returning it. The epoch is checked
+    /// again after the fence: if it moved meanwhile, an advance may have
+    /// missed the slot and freed the bag the stale epoch still reads from.
+    fn publish(&self, slot: &Slot<AtomicUsize>) -> usize {
+        let mut epoch = self.epoch.load(Ordering::Relaxed);
+        loop {
+            slot.value.store(epoch << 1 | PINNED, Ordering::Relaxed);
+            fence(Ordering::SeqCst);
+            let current = self.epoch.load(Ordering::Relaxed);
+            if current == epoch {
+                return epoch;
+            }
+            epoch = current;
+        }
+    }
+}
+
+impl Default for Collector {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Collector {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Collector {{ epoch: {}, participants: {:?} }}",
+            self.epoch.load(Ordering::Relaxed),
+            self.participants.head()
+        )
+    }
+}
+
+unsafe impl Send for Collector {}
+unsafe impl Sync for Collector {}
+
+/// A pinned participant of a `Collector`. Dropping the guard unpins it.
+pub struct EpochGuard<'c> {
+    collector: &'c Collector,
+    slot: &'c Slot<AtomicUsize>,
+    epoch: usize,
+}
+
+impl<'c> EpochGuard<'c> {
+    panicking! {
+        /// Defers dropping an allocation that has already been unlinked from the
+        /// shared structure until no pinned thread can reach it anymore. Every
+        /// `ADVANCE_THRESHOLD` deferrals, an advance is attempted. The value may
+        /// be dropped at any later advance, hence the `'static` bound.
+        pub fn defer_dealloc<T>(&self, alloc: OwnedAlloc<T>)
+        where
+            T: ?Sized + Send + 'static,
+        {
+            self.collector.bags[self.epoch % 3].defer(alloc);
+            let count = self.collector.deferred.fetch_add(1, Ordering::Relaxed) + 1;
+            if count >= ADVANCE_THRESHOLD {
+                self.collector.deferred.store(0, Ordering::Relaxed);
+                self.collector.advance();
+            }
+        }
+    }
+
+    /// The epoch observed when pinning.
+    #[inline]
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Unpins and pins again, observing the latest epoch. Useful for long
+    /// running operations that would otherwise block reclamation.
+    pub fn repin(&mut self) {
+        self.epoch = self.collector.publish(self.slot);
+    }
+}
+
+impl<'c> Drop for EpochGuard<'c> {
+    #[inline]
+    fn drop(&mut self) {
+        self.slot.value.store(0, Ordering::Release);
+        self.slot.release();
+    }
+}
+
+impl<'c> fmt::Debug for EpochGuard<'c> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EpochGuard {{ epoch: {} }}", self.epoch)
+    }
+}
+
+/// Pins the current thread on the global collector.
+#[inline]
+pub fn pin() -> EpochGuard<'static> {
+    GLOBAL.pin()
+}
+
+panicking! {
+    /// Defers dropping an allocation on the global collector.
+    #[inline]
+    pub fn defer_dealloc<T>(alloc: OwnedAlloc<T>)
+    where
+        T: ?Sized + Send + 'static,
+    {
+        pin().defer_dealloc(alloc)
+    }
+}
+
+/// Tries to advance the global collector's epoch. See `Collector::advance`.
+#[inline]
+pub fn advance() -> Option<usize> {
+    GLOBAL.advance()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Collector;
+    use crate::OwnedAlloc;
+
+    #[test]
+    fn pinned_thread_blocks_advance() {
+        let collector = Collector::new();
+        let mut guard = collector.pin();
+        guard.defer_dealloc(OwnedAlloc::new(1));
+
+        assert_eq!(collector.advance(), Some(0));
+        assert_eq!(collector.advance(), None);
+
+        guard.repin();
+        assert_eq!(guard.epoch(), 1);
+        assert_eq!(collector.advance(), Some(0));
+
+        drop(guard);
+        assert_eq!(collector.advance(), Some(1));
+    }
+
+    #[test]
+    fn slots_are_reused() {
+        let collector = Collector::new();
+        drop(collector.pin());
+        drop(collector.pin());
+        assert_eq!(collector.participants.iter().count(), 1);
+    }
+}
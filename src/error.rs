@@ -27,10 +27,26 @@ impl core::fmt::Display for LayoutError {
     }
 }
 
-impl const From<CoreLayoutError> for LayoutError {
+const_impl! {
+    impl From<CoreLayoutError> for LayoutError {
+        #[inline]
+        fn from(_: CoreLayoutError) -> Self {
+            LayoutError
+        }
+    }
+}
+
+/// Error reported by the operating system, carrying the raw `errno` (Unix)
+/// or `GetLastError` (Windows) code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsError {
+    pub code: i32,
+}
+
+impl core::fmt::Display for OsError {
     #[inline]
-    fn from(_: CoreLayoutError) -> Self {
-        LayoutError
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "operating system error, code: {}", self.code)
     }
 }
 
@@ -53,16 +69,132 @@ impl core::fmt::Display for RawVecError {
     }
 }
 
-impl const From<AllocError> for RawVecError {
+const_impl! {
+    impl From<AllocError> for RawVecError {
+        #[inline]
+        fn from(err: AllocError) -> Self {
+            RawVecError::Alloc(err)
+        }
+    }
+}
+
+const_impl! {
+    impl From<LayoutError> for RawVecError {
+        #[inline]
+        fn from(err: LayoutError) -> Self {
+            RawVecError::Layout(err)
+        }
+    }
+}
+
+/// Reasons `RawVec::try_cast_vec` refuses a `Vec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VecCastError {
+    /// The alignments of the element types differ, so the buffer could not
+    /// be freed with the layout it was allocated with.
+    Align,
+    /// The buffer is not a whole number of elements of the new type, or one
+    /// of the types is zero-sized.
+    Size,
+    /// The crate does not allocate from the global allocator, as `Vec` does.
+    Allocator,
+}
+
+impl core::fmt::Display for VecCastError {
     #[inline]
-    fn from(err: AllocError) -> Self {
-        RawVecError::Alloc(err)
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(match self {
+            VecCastError::Align => "alignments of the element types differ",
+            VecCastError::Size => "buffer size is not a multiple of the element size",
+            VecCastError::Allocator => "crate allocator is not the global allocator",
+        })
     }
 }
 
-impl const From<LayoutError> for RawVecError {
+/// Errors returned when materializing plain-old-data values from bytes.
+#[derive(Debug, Clone)]
+pub enum FromBytesError {
+    /// The bytes are not a whole number of values of `size` bytes (exactly
+    /// one value, for a single value).
+    Size {
+        /// Size of a value.
+        size: usize,
+        /// Number of bytes given.
+        len: usize,
+    },
+    /// Allocating the values failed.
+    Alloc(RawVecError),
+}
+
+impl core::fmt::Display for FromBytesError {
     #[inline]
-    fn from(err: LayoutError) -> Self {
-        RawVecError::Layout(err)
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            FromBytesError::Size { size, len } => {
+                write!(f, "{} bytes do not make values of {} bytes", len, size)
+            },
+            FromBytesError::Alloc(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Errors returned by `try_write_fmt`. The text formatted before the error
+/// stays written.
+#[derive(Debug, Clone)]
+pub enum WriteFmtError {
+    /// Growing the buffer failed.
+    Grow(RawVecError),
+    /// The buffer is full and cannot grow.
+    Full,
+    /// A formatting trait implementation returned an error.
+    Fmt,
+}
+
+impl core::fmt::Display for WriteFmtError {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            WriteFmtError::Grow(err) => write!(f, "{}", err),
+            WriteFmtError::Full => f.write_str("buffer is full"),
+            WriteFmtError::Fmt => f.write_str("formatting trait implementation returned an error"),
+        }
     }
 }
+
+const_impl! {
+    impl From<RawVecError> for WriteFmtError {
+        #[inline]
+        fn from(err: RawVecError) -> Self {
+            WriteFmtError::Grow(err)
+        }
+    }
+}
+
+/// Formats `args` through `write`, keeping the error it returns rather than
+/// the bare `fmt::Error`.
+pub(crate) fn try_write_fmt<F>(write: F, args: core::fmt::Arguments) -> Result<(), WriteFmtError>
+where
+    F: FnMut(&str) -> Result<(), WriteFmtError>,
+{
+    struct Adapter<F> {
+        write: F,
+        err: Option<WriteFmtError>,
+    }
+
+    impl<F> core::fmt::Write for Adapter<F>
+    where
+        F: FnMut(&str) -> Result<(), WriteFmtError>,
+    {
+        #[inline]
+        fn write_str(&mut self, string: &str) -> core::fmt::Result {
+            (self.write)(string).map_err(|err| {
+                self.err = Some(err);
+                core::fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter { write, err: None };
+    core::fmt::Write::write_fmt(&mut adapter, args)
+        .map_err(|_| adapter.err.take().unwrap_or(WriteFmtError::Fmt))
+}
@@ -0,0 +1,77 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+/// A recycling event of a `Cache`, `Pool` or `BufferPool`, reported to the
+/// listener registered with their `set_listener`, so sizing logic can react
+/// to recycling behavior instead of polling counters. The size is the one of
+/// the recycled objects: the size of the type for a `Cache` or a `Pool`, and
+/// the size class, or the requested length if there is none, for a
+/// `BufferPool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheEvent {
+    /// A request was served by a recycled object.
+    Hit {
+        /// Size of the object.
+        size: usize,
+    },
+    /// A request found no object to recycle, and a new one was made.
+    Miss {
+        /// Size of the object.
+        size: usize,
+    },
+    /// An idle object was dropped: replaced, cleared or trimmed.
+    Evict {
+        /// Size of the object.
+        size: usize,
+    },
+    /// A request could not be pooled: the limit was reached, or no size
+    /// class fits it.
+    Overflow {
+        /// Size of the request.
+        size: usize,
+    },
+}
+
+/// A registered event callback. Listeners are called on the thread causing
+/// the event, with no lock held, so they may use the cache or pool again.
+pub(crate) struct Listener(Option<Box<dyn Fn(CacheEvent) + Send + Sync>>);
+
+impl Listener {
+    pub(crate) const NONE: Listener = Listener(None);
+
+    #[inline]
+    pub(crate) fn new<F>(listener: F) -> Self
+    where
+        F: Fn(CacheEvent) + Send + Sync + 'static,
+    {
+        Listener(Some(Box::new(listener)))
+    }
+
+    /// Reports `event`, if a callback is registered.
+    #[inline]
+    pub(crate) fn notify(&self, event: CacheEvent) {
+        if let Some(listener) = &self.0 {
+            listener(event);
+        }
+    }
+
+    /// Reports `count` times the same event.
+    #[inline]
+    pub(crate) fn notify_many(&self, event: CacheEvent, count: usize) {
+        if self.0.is_some() {
+            for _ in 0 .. count {
+                self.notify(event);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Listener {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Some(..)"),
+            None => write!(f, "None"),
+        }
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{
+    alloc_api::{AllocError, Allocator as _},
+    Allocator,
+    Bump,
+    FreeListAlloc,
+    OwnedAlloc,
+};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    ffi::c_void,
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// Alignment of the blocks returned by `owned_alloc_alloc`, like `malloc`.
+pub const OWNED_ALLOC_DEFAULT_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+/// The size and alignment of a block of the C functions, stored right before
+/// the pointer handed out, so `owned_alloc_free` only needs the pointer.
+#[repr(C)]
+struct Header {
+    size: usize,
+    align: usize,
+}
+
+/// The layout of a block of `size` bytes aligned to `align`, and the offset
+/// of the pointer handed out in it.
+#[inline]
+fn block_layout(size: usize, align: usize) -> Option<(Layout, usize)> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+    let align = align.max(mem::align_of::<Header>());
+    let header = Layout::new::<Header>().align_to(align).ok()?.pad_to_align();
+    let (layout, offset) = header.extend(Layout::from_size_align(size, align).ok()?).ok()?;
+    Some((layout, offset))
+}
+
+/// An arena for C code: a `Bump` behind an opaque pointer. Created by
+/// `owned_alloc_arena_new`, destroyed by `owned_alloc_arena_destroy`.
+pub struct OwnedAllocArena {
+    bump: Bump,
+}
+
+/// A pool for C code: a `FreeListAlloc` behind an opaque pointer. Created by
+/// `owned_alloc_pool_new`, destroyed by `owned_alloc_pool_destroy`.
+pub struct OwnedAllocPool {
+    alloc: FreeListAlloc<Allocator>,
+}
+
+/// Allocates `size` bytes aligned to `OWNED_ALLOC_DEFAULT_ALIGN`, like
+/// `malloc`. Returns null in case of allocation error.
+#[no_mangle]
+pub extern "C" fn owned_alloc_alloc(size: usize) -> *mut c_void {
+    owned_alloc_alloc_aligned(size, OWNED_ALLOC_DEFAULT_ALIGN)
+}
+
+/// Allocates `size` bytes aligned to `align`, which must be a power of two.
+/// Returns null in case of allocation error or invalid alignment.
+#[no_mangle]
+pub extern "C" fn owned_alloc_alloc_aligned(size: usize, align: usize) -> *mut c_void {
+    let (layout, offset) = match block_layout(size, align) {
+        Some(block) => block,
+        None => return ptr::null_mut(),
+    };
+    match Allocator::new().allocate(layout) {
+        Ok(block) => unsafe {
+            let ptr = block.cast::<u8>().as_ptr().add(offset);
+            ptr.cast::<Header>().sub(1).write(Header { size, align });
+            ptr.cast()
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Frees a block of `owned_alloc_alloc`, `owned_alloc_alloc_aligned` or
+/// `owned_alloc_realloc`. Does nothing if `ptr` is null.
+///
+/// # Safety
+/// `ptr` must be null or a live block of those functions.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_free(ptr: *mut c_void) {
+    let ptr = match NonNull::new(ptr.cast::<u8>()) {
+        Some(ptr) => ptr,
+        None => return,
+    };
+    let Header { size, align } = ptr.as_ptr().cast::<Header>().sub(1).read();
+    let (layout, offset) = block_layout(size, align).unwrap();
+    let base = NonNull::new_unchecked(ptr.as_ptr().sub(offset));
+    crate::alloc_api::Allocator::deallocate(&Allocator::new(), base, layout);
+}
+
+/// Resizes a block to `size` bytes, keeping its alignment and contents, like
+/// `realloc`: a null `ptr` allocates, a `size` of `0` frees and returns
+/// null. In case of allocation error, null is returned and the block is
+/// untouched.
+///
+/// # Safety
+/// `ptr` must be null or a live block of the `owned_alloc_alloc` functions.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    if ptr.is_null() {
+        return owned_alloc_alloc(size);
+    }
+    if size == 0 {
+        owned_alloc_free(ptr);
+        return ptr::null_mut();
+    }
+    let old = ptr.cast::<Header>().sub(1).read();
+    let new = owned_alloc_alloc_aligned(size, old.align);
+    if !new.is_null() {
+        new.cast::<u8>().copy_from_nonoverlapping(ptr.cast(), old.size.min(size));
+        owned_alloc_free(ptr);
+    }
+    new
+}
+
+/// Creates an arena with chunks of at least `chunk_size` bytes (the default
+/// size if `0`). Returns null in case of allocation error.
+#[no_mangle]
+pub extern "C" fn owned_alloc_arena_new(chunk_size: usize) -> *mut OwnedAllocArena {
+    let bump = match chunk_size {
+        0 => Bump::new(),
+        size => Bump::with_chunk_size(size),
+    };
+    match OwnedAlloc::try_new(OwnedAllocArena { bump }) {
+        Ok(arena) => arena.into_raw().as_ptr(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Allocates `size` bytes aligned to `align` in the arena. The block lives
+/// until the arena is reset or destroyed. Returns null in case of
+/// allocation error or invalid alignment.
+///
+/// # Safety
+/// `arena` must come from `owned_alloc_arena_new` and not be destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_arena_alloc(
+    arena: *mut OwnedAllocArena,
+    size: usize,
+    align: usize,
+) -> *mut c_void {
+    let layout = match Layout::from_size_align(size, align) {
+        Ok(layout) => layout,
+        Err(_) => return ptr::null_mut(),
+    };
+    match (*arena).bump.try_alloc_layout(layout) {
+        Ok(ptr) => ptr.as_ptr().cast(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Frees everything allocated in the arena, keeping its memory for reuse.
+///
+/// # Safety
+/// `arena` must come from `owned_alloc_arena_new` and not be destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_arena_reset(arena: *mut OwnedAllocArena) {
+    (*arena).bump.reset();
+}
+
+/// Destroys the arena and everything allocated in it. Does nothing if
+/// `arena` is null.
+///
+/// # Safety
+/// `arena` must be null or come from `owned_alloc_arena_new`, and not be
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_arena_destroy(arena: *mut OwnedAllocArena) {
+    if let Some(arena) = NonNull::new(arena) {
+        drop(OwnedAlloc::from_raw(arena));
+    }
+}
+
+/// Creates a pool keeping freed blocks for reuse, in power-of-two size
+/// classes. Returns null in case of allocation error.
+#[no_mangle]
+pub extern "C" fn owned_alloc_pool_new() -> *mut OwnedAllocPool {
+    let alloc = FreeListAlloc::new(Allocator::new());
+    match OwnedAlloc::try_new(OwnedAllocPool { alloc }) {
+        Ok(pool) => pool.into_raw().as_ptr(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Allocates `size` bytes aligned to `align` from the pool. Returns null in
+/// case of allocation error or invalid alignment.
+///
+/// # Safety
+/// `pool` must come from `owned_alloc_pool_new` and not be destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_pool_alloc(
+    pool: *mut OwnedAllocPool,
+    size: usize,
+    align: usize,
+) -> *mut c_void {
+    let layout = match Layout::from_size_align(size, align) {
+        Ok(layout) => layout,
+        Err(_) => return ptr::null_mut(),
+    };
+    match (*pool).alloc.allocate(layout) {
+        Ok(block) => block.as_ptr().cast(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Gives a block back to the pool. Does nothing if `ptr` is null.
+///
+/// # Safety
+/// `pool` must come from `owned_alloc_pool_new` and not be destroyed, and
+/// `ptr` must be null or a live block of `owned_alloc_pool_alloc` on the
+/// same pool, with the same `size` and `align`.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_pool_free(
+    pool: *mut OwnedAllocPool,
+    ptr: *mut c_void,
+    size: usize,
+    align: usize,
+) {
+    if let Some(ptr) = NonNull::new(ptr.cast::<u8>()) {
+        let layout = Layout::from_size_align_unchecked(size, align);
+        (*pool).alloc.deallocate(ptr, layout);
+    }
+}
+
+/// Destroys the pool, giving the blocks it keeps back to the system. Blocks
+/// still allocated from the pool must be freed before. Does nothing if
+/// `pool` is null.
+///
+/// # Safety
+/// `pool` must be null or come from `owned_alloc_pool_new`, and not be
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_pool_destroy(pool: *mut OwnedAllocPool) {
+    if let Some(pool) = NonNull::new(pool) {
+        drop(OwnedAlloc::from_raw(pool));
+    }
+}
+
+/// A C `malloc`.
+pub type MallocFn = unsafe extern "C" fn(size: usize) -> *mut c_void;
+/// A C `free`.
+pub type FreeFn = unsafe extern "C" fn(ptr: *mut c_void);
+/// A C `realloc`.
+pub type ReallocFn = unsafe extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void;
+/// A `malloc` taking a context pointer first.
+pub type ContextMallocFn = unsafe extern "C" fn(ctx: *mut c_void, size: usize) -> *mut c_void;
+/// A `free` taking a context pointer first.
+pub type ContextFreeFn = unsafe extern "C" fn(ctx: *mut c_void, ptr: *mut c_void);
+/// A `realloc` taking a context pointer first.
+pub type ContextReallocFn =
+    unsafe extern "C" fn(ctx: *mut c_void, ptr: *mut c_void, size: usize) -> *mut c_void;
+
+#[derive(Debug, Clone, Copy)]
+enum Functions {
+    Plain {
+        malloc: MallocFn,
+        free: FreeFn,
+        realloc: Option<ReallocFn>,
+    },
+    Context {
+        ctx: *mut c_void,
+        malloc: ContextMallocFn,
+        free: ContextFreeFn,
+        realloc: Option<ContextReallocFn>,
+    },
+}
+
+/// A C allocator, given as a `malloc`/`free`/`realloc` triple, possibly
+/// taking a context pointer (as Lua or SQLite hosts provide), used as a
+/// backend. Blocks are assumed aligned to `OWNED_ALLOC_DEFAULT_ALIGN`, like
+/// `malloc`'s; more aligned blocks are carved out of bigger ones.
+///
+/// With `register`, the crate `Allocator`, and thus `OwnedAlloc`, `RawVec`
+/// and the other types built on it, takes its memory from the C allocator,
+/// e.g. from the host application of a plugin. A C host can also register
+/// its allocator with `owned_alloc_set_allocator` or
+/// `owned_alloc_set_allocator_ctx`.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::{alloc::Layout, ffi::c_void};
+/// use owned_alloc::{
+///     alloc_api::Allocator as _,
+///     ffi::{owned_alloc_alloc, owned_alloc_free, ForeignAlloc},
+/// };
+///
+/// // Stands for the allocator of the host application.
+/// unsafe extern "C" fn host_malloc(size: usize) -> *mut c_void {
+///     owned_alloc_alloc(size)
+/// }
+///
+/// let alloc = unsafe { ForeignAlloc::new(host_malloc, owned_alloc_free, None) };
+/// let layout = Layout::from_size_align(100, 64).unwrap();
+/// let block = alloc.allocate(layout).unwrap();
+/// assert_eq!(block.cast::<u8>().as_ptr() as usize % 64, 0);
+/// unsafe { alloc.deallocate(block.cast(), layout) };
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ForeignAlloc {
+    functions: Functions,
+}
+
+impl ForeignAlloc {
+    /// Wraps a C `malloc`, `free` and, optionally, `realloc`.
+    ///
+    /// # Safety
+    /// The functions must behave like the C ones, and be callable from any
+    /// thread.
+    #[inline]
+    pub const unsafe fn new(malloc: MallocFn, free: FreeFn, realloc: Option<ReallocFn>) -> Self {
+        Self {
+            functions: Functions::Plain {
+                malloc,
+                free,
+                realloc,
+            },
+        }
+    }
+
+    /// Wraps functions like `malloc`, `free` and, optionally, `realloc`,
+    /// taking `ctx` as their first argument.
+    ///
+    /// # Safety
+    /// The functions must behave like the C ones when given `ctx`, and be
+    /// callable from any thread.
+    #[inline]
+    pub const unsafe fn with_context(
+        ctx: *mut c_void,
+        malloc: ContextMallocFn,
+        free: ContextFreeFn,
+        realloc: Option<ContextReallocFn>,
+    ) -> Self {
+        Self {
+            functions: Functions::Context {
+                ctx,
+                malloc,
+                free,
+                realloc,
+            },
+        }
+    }
+
+    /// Makes the crate `Allocator` take its memory from this allocator. Only
+    /// the first registration succeeds; others return `Err` with the
+    /// allocator.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because every block freed through the crate
+    /// `Allocator` goes to this allocator once it is registered: no block to
+    /// be freed through the `Allocator` may exist yet (one allocated by it,
+    /// or a `Vec` buffer taken over by `RawVec::try_from_vec`), or it would
+    /// be freed with the wrong functions.
+    pub unsafe fn register(self) -> Result<(), Self> {
+        let claimed = REGISTERED
+            .state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Acquire)
+            .is_ok();
+        if !claimed {
+            return Err(self);
+        }
+        unsafe { (*REGISTERED.alloc.get()).write(self) };
+        REGISTERED.state.store(READY, Ordering::Release);
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn malloc(&self, size: usize) -> *mut u8 {
+        match self.functions {
+            Functions::Plain { malloc, .. } => malloc(size).cast(),
+            Functions::Context { ctx, malloc, .. } => malloc(ctx, size).cast(),
+        }
+    }
+
+    #[inline]
+    unsafe fn free(&self, ptr: *mut u8) {
+        match self.functions {
+            Functions::Plain { free, .. } => free(ptr.cast()),
+            Functions::Context { ctx, free, .. } => free(ctx, ptr.cast()),
+        }
+    }
+
+    /// Calls the C `realloc`, if there is one.
+    #[inline]
+    unsafe fn c_realloc(&self, ptr: *mut u8, size: usize) -> Option<*mut u8> {
+        match self.functions {
+            Functions::Plain { realloc, .. } => Some(realloc?(ptr.cast(), size).cast()),
+            Functions::Context { ctx, realloc, .. } => Some(realloc?(ctx, ptr.cast(), size).cast()),
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for ForeignAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.align() <= OWNED_ALLOC_DEFAULT_ALIGN {
+            return self.malloc(layout.size());
+        }
+        // Room to align the block, with the pointer to free before it.
+        let header = mem::size_of::<*mut u8>();
+        let raw = match layout.size().checked_add(layout.align() + header) {
+            Some(size) => self.malloc(size),
+            None => return ptr::null_mut(),
+        };
+        if raw.is_null() {
+            return raw;
+        }
+        let offset = raw.add(header).align_offset(layout.align()) + header;
+        let ptr = raw.add(offset);
+        ptr.cast::<*mut u8>().sub(1).write_unaligned(raw);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if layout.align() <= OWNED_ALLOC_DEFAULT_ALIGN {
+            self.free(ptr);
+        } else {
+            self.free(ptr.cast::<*mut u8>().sub(1).read_unaligned());
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if layout.align() <= OWNED_ALLOC_DEFAULT_ALIGN {
+            if let Some(new) = self.c_realloc(ptr, new_size) {
+                return new;
+            }
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new = self.alloc(new_layout);
+        if !new.is_null() {
+            new.copy_from_nonoverlapping(ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new
+    }
+}
+
+unsafe impl crate::alloc_api::Allocator for ForeignAlloc {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+        let ptr = NonNull::new(unsafe { self.alloc(layout) }).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.dealloc(ptr.as_ptr(), layout);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if old_layout.size() == 0 || old_layout.align() != new_layout.align() {
+            let new = self.allocate(new_layout)?;
+            new.cast::<u8>().as_ptr().copy_from_nonoverlapping(ptr.as_ptr(), old_layout.size());
+            self.deallocate(ptr, old_layout);
+            return Ok(new);
+        }
+        let new = self.realloc(ptr.as_ptr(), old_layout, new_layout.size());
+        let new = NonNull::new(new).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(new, new_layout.size()))
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if new_layout.size() == 0 || old_layout.align() != new_layout.align() {
+            let new = self.allocate(new_layout)?;
+            new.cast::<u8>().as_ptr().copy_from_nonoverlapping(ptr.as_ptr(), new_layout.size());
+            self.deallocate(ptr, old_layout);
+            return Ok(new);
+        }
+        let new = self.realloc(ptr.as_ptr(), old_layout, new_layout.size());
+        let new = NonNull::new(new).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(new, new_layout.size()))
+    }
+}
+
+unsafe impl Send for ForeignAlloc {}
+unsafe impl Sync for ForeignAlloc {}
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+static REGISTERED: Registered = Registered {
+    state: AtomicU8::new(EMPTY),
+    alloc: UnsafeCell::new(MaybeUninit::uninit()),
+};
+
+/// The allocator given to `ForeignAlloc::register`, written once.
+struct Registered {
+    state: AtomicU8,
+    alloc: UnsafeCell<MaybeUninit<ForeignAlloc>>,
+}
+
+unsafe impl Sync for Registered {}
+
+/// The allocator given to `ForeignAlloc::register`, if any.
+#[inline]
+pub(crate) fn registered() -> Option<&'static ForeignAlloc> {
+    if REGISTERED.state.load(Ordering::Acquire) == READY {
+        Some(unsafe { (*REGISTERED.alloc.get()).assume_init_ref() })
+    } else {
+        None
+    }
+}
+
+/// Makes the crate take its memory from the given C `malloc`, `free` and
+/// `realloc` (which may be null). Returns `false` if an allocator was
+/// already registered. See `ForeignAlloc::register`.
+///
+/// # Safety
+/// The functions must behave like the C ones, and be callable from any
+/// thread. No block to be freed through the crate must exist yet, as with
+/// `ForeignAlloc::register`.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_set_allocator(
+    malloc: MallocFn,
+    free: FreeFn,
+    realloc: Option<ReallocFn>,
+) -> bool {
+    ForeignAlloc::new(malloc, free, realloc).register().is_ok()
+}
+
+/// Makes the crate take its memory from the given functions, called with
+/// `ctx` first. `realloc` may be null. Returns `false` if an allocator was
+/// already registered. See `ForeignAlloc::register`.
+///
+/// # Safety
+/// The functions must behave like the C ones when given `ctx`, and be
+/// callable from any thread. No block to be freed through the crate must
+/// exist yet, as with `ForeignAlloc::register`.
+#[no_mangle]
+pub unsafe extern "C" fn owned_alloc_set_allocator_ctx(
+    ctx: *mut c_void,
+    malloc: ContextMallocFn,
+    free: ContextFreeFn,
+    realloc: Option<ContextReallocFn>,
+) -> bool {
+    ForeignAlloc::with_context(ctx, malloc, free, realloc).register().is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        owned_alloc_alloc, owned_alloc_alloc_aligned, owned_alloc_free, owned_alloc_pool_alloc,
+        owned_alloc_pool_destroy, owned_alloc_pool_free, owned_alloc_pool_new,
+        owned_alloc_realloc, ForeignAlloc, OWNED_ALLOC_DEFAULT_ALIGN,
+    };
+    use crate::alloc_api::Allocator;
+    use core::{
+        alloc::Layout,
+        ffi::c_void,
+        sync::atomic::{AtomicUsize, Ordering::*},
+    };
+
+    #[test]
+    fn realloc_keeps_alignment_and_contents() {
+        unsafe {
+            let ptr = owned_alloc_alloc_aligned(5, 256).cast::<u8>();
+            assert_eq!(ptr as usize % 256, 0);
+            ptr.copy_from_nonoverlapping(b"hello".as_ptr(), 5);
+
+            let ptr = owned_alloc_realloc(ptr.cast(), 3000).cast::<u8>();
+            assert_eq!(ptr as usize % 256, 0);
+            assert_eq!(core::slice::from_raw_parts(ptr, 5), b"hello");
+            assert!(owned_alloc_realloc(ptr.cast(), 0).is_null());
+
+            let ptr = owned_alloc_alloc(0);
+            assert_eq!(ptr as usize % OWNED_ALLOC_DEFAULT_ALIGN, 0);
+            owned_alloc_free(ptr);
+            owned_alloc_free(core::ptr::null_mut());
+            assert!(owned_alloc_alloc_aligned(8, 3).is_null());
+        }
+    }
+
+    #[test]
+    fn pool_reuses_blocks() {
+        unsafe {
+            let pool = owned_alloc_pool_new();
+            let first = owned_alloc_pool_alloc(pool, 24, 8);
+            owned_alloc_pool_free(pool, first, 24, 8);
+            let second = owned_alloc_pool_alloc(pool, 20, 4);
+            assert_eq!(first, second);
+            owned_alloc_pool_free(pool, second, 20, 4);
+            owned_alloc_pool_destroy(pool);
+        }
+    }
+
+    unsafe extern "C" fn counted_malloc(ctx: *mut c_void, size: usize) -> *mut c_void {
+        (*ctx.cast::<AtomicUsize>()).fetch_add(1, Relaxed);
+        owned_alloc_alloc(size)
+    }
+
+    unsafe extern "C" fn counted_free(ctx: *mut c_void, ptr: *mut c_void) {
+        (*ctx.cast::<AtomicUsize>()).fetch_sub(1, Relaxed);
+        owned_alloc_free(ptr)
+    }
+
+    unsafe extern "C" fn counted_realloc(
+        _: *mut c_void,
+        ptr: *mut c_void,
+        size: usize,
+    ) -> *mut c_void {
+        owned_alloc_realloc(ptr, size)
+    }
+
+    #[test]
+    fn foreign_blocks_are_aligned_and_grow() {
+        let live = AtomicUsize::new(0);
+        let ctx = &live as *const AtomicUsize as *mut c_void;
+        let alloc = unsafe {
+            ForeignAlloc::with_context(ctx, counted_malloc, counted_free, Some(counted_realloc))
+        };
+
+        let aligned = Layout::from_size_align(24, 128).unwrap();
+        let block = alloc.allocate(aligned).unwrap().cast::<u8>();
+        assert_eq!(block.as_ptr() as usize % 128, 0);
+
+        let small = Layout::from_size_align(4, 4).unwrap();
+        let grown = Layout::from_size_align(4000, 4).unwrap();
+        let word = alloc.allocate(small).unwrap().cast::<u32>();
+        unsafe { word.as_ptr().write(0xC0FFEE) };
+        let word = unsafe { alloc.grow(word.cast(), small, grown) }.unwrap().cast::<u32>();
+        assert_eq!(unsafe { word.as_ptr().read() }, 0xC0FFEE);
+        assert_eq!(live.load(Relaxed), 2);
+
+        unsafe {
+            alloc.deallocate(block, aligned);
+            alloc.deallocate(word.cast(), grown);
+        }
+        assert_eq!(live.load(Relaxed), 0);
+    }
+}
@@ -0,0 +1,112 @@
+/// Number of buckets of the free-block histogram of a `Fragmentation`: one
+/// per power of two.
+pub const FRAG_BUCKETS: usize = usize::BITS as usize;
+
+/// A snapshot of the fragmentation of an allocator, as returned by the
+/// `fragmentation` methods of `Tlsf`, `FreeListAlloc` and `Arena`.
+///
+/// External fragmentation is described by the free blocks: how many there
+/// are, how big the biggest one is, and how their sizes are spread. Internal
+/// fragmentation is the `wasted` bytes: padding handed out with live
+/// allocations, beyond the sizes requested.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::{alloc::Layout, mem::MaybeUninit};
+/// use owned_alloc::{alloc_api::Allocator, Tlsf};
+///
+/// let mut region = [MaybeUninit::<u8>::uninit(); 4096];
+/// let tlsf = Tlsf::new(&mut region);
+/// let layout = Layout::from_size_align(100, 8).unwrap();
+/// let block = tlsf.allocate(layout).unwrap();
+///
+/// let frag = tlsf.fragmentation();
+/// assert_eq!(frag.free_blocks, 1);
+/// assert_eq!(frag.largest_free, frag.free_bytes);
+/// assert_eq!(frag.wasted, block.len() - 100);
+/// unsafe { tlsf.deallocate(block.cast(), layout) };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fragmentation {
+    /// Bytes sitting in free blocks.
+    pub free_bytes: usize,
+    /// Number of free blocks.
+    pub free_blocks: usize,
+    /// Size of the biggest free block: the biggest allocation which can be
+    /// served without getting more memory.
+    pub largest_free: usize,
+    /// Number of free blocks per size: bucket `i` counts the blocks of
+    /// `2^i` to `2^(i + 1) - 1` bytes.
+    pub histogram: [usize; FRAG_BUCKETS],
+    /// Bytes handed out with live allocations beyond the sizes requested,
+    /// e.g. rounding to a size class.
+    pub wasted: usize,
+}
+
+impl Fragmentation {
+    /// A report of no free block and no waste.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            free_bytes: 0,
+            free_blocks: 0,
+            largest_free: 0,
+            histogram: [0; FRAG_BUCKETS],
+            wasted: 0,
+        }
+    }
+
+    /// External fragmentation, between 0 and 1: the part of the free bytes
+    /// outside of the biggest free block. 0 when nothing is free.
+    #[inline]
+    pub fn external(&self) -> f64 {
+        if self.free_bytes == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free as f64 / self.free_bytes as f64
+        }
+    }
+
+    /// Accounts for `count` free blocks of `size` bytes.
+    #[inline]
+    pub(crate) fn add_free(&mut self, size: usize, count: usize) {
+        if size == 0 || count == 0 {
+            return;
+        }
+        self.free_bytes += size * count;
+        self.free_blocks += count;
+        self.largest_free = self.largest_free.max(size);
+        self.histogram[size.ilog2() as usize] += count;
+    }
+}
+
+impl Default for Fragmentation {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Fragmentation;
+
+    #[test]
+    fn buckets_are_powers_of_two() {
+        let mut frag = Fragmentation::new();
+        frag.add_free(1, 1);
+        frag.add_free(48, 2);
+        frag.add_free(64, 1);
+        frag.add_free(0, 5);
+        assert_eq!(frag.histogram[0], 1);
+        assert_eq!(frag.histogram[5], 2);
+        assert_eq!(frag.histogram[6], 1);
+        assert_eq!(frag.free_blocks, 4);
+        assert_eq!(frag.free_bytes, 161);
+        assert_eq!(frag.largest_free, 64);
+        assert!((frag.external() - 97.0 / 161.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,470 @@
+use crate::{alloc_api::AllocError, Fragmentation, ReleaseFree, ReleaseReport};
+use core::{
+    alloc::Layout,
+    fmt, mem,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// Smallest size class of a `FreeListAlloc`, in bytes.
+pub const MIN_FREE_LIST_CLASS: usize = 16;
+
+/// Number of power-of-two size classes of a `FreeListAlloc`, i.e. up to
+/// 4KiB. Bigger requests go straight to the backend.
+pub const FREE_LIST_CLASSES: usize = 9;
+
+/// Default number of blocks a `FreeListAlloc` keeps per size class.
+pub const DEFAULT_FREE_LIST_CAP: usize = 64;
+
+struct Node {
+    next: *mut Node,
+}
+
+/// A chain of free blocks detached from a `FreeList`.
+pub(crate) struct Chain {
+    first: *mut Node,
+    last: *mut Node,
+    len: usize,
+}
+
+impl Chain {
+    /// Separates the first block from the rest of the chain.
+    #[inline]
+    pub(crate) fn split_first(self) -> (NonNull<u8>, Option<Chain>) {
+        let first = unsafe { NonNull::new_unchecked(self.first.cast()) };
+        if self.len == 1 {
+            (first, None)
+        } else {
+            let rest = Chain {
+                first: unsafe { (*self.first).next },
+                last: self.last,
+                len: self.len - 1,
+            };
+            (first, Some(rest))
+        }
+    }
+
+    /// Separates the first `len` blocks from the rest of the chain.
+    pub(crate) fn split_at(self, len: usize) -> (Option<Chain>, Option<Chain>) {
+        if len == 0 {
+            return (None, Some(self));
+        }
+        if len >= self.len {
+            return (Some(self), None);
+        }
+        let mut last = self.first;
+        unsafe {
+            for _ in 1 .. len {
+                last = (*last).next;
+            }
+            let rest = Chain {
+                first: (*last).next,
+                last: self.last,
+                len: self.len - len,
+            };
+            (*last).next = ptr::null_mut();
+            (Some(Chain { first: self.first, last, len }), Some(rest))
+        }
+    }
+
+    /// Gives every block of the chain back to `backend`, returning how many
+    /// bytes were released.
+    pub(crate) unsafe fn free<A>(self, backend: &A, layout: Layout) -> usize
+    where
+        A: crate::alloc_api::Allocator,
+    {
+        let mut node = self.first;
+        for _ in 0 .. self.len {
+            let next = (*node).next;
+            crate::asan::unpoison(node.cast(), layout.size());
+            backend.deallocate(NonNull::new_unchecked(node.cast()), layout);
+            node = next;
+        }
+        self.len * layout.size()
+    }
+}
+
+/// Usage of one size class of a `FreeListAlloc`, as yielded by
+/// `FreeListAlloc::class_usage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeClassUsage {
+    /// Size of the blocks of the class, in bytes.
+    pub size: usize,
+    /// Number of blocks kept in the free list of the class.
+    pub cached: usize,
+}
+
+/// A lock-free stack of free blocks of one size class.
+///
+/// Popping a single node off a Treiber stack is subject to ABA when blocks
+/// are recycled right away, so consumers detach the whole list with a swap,
+/// keep its first block and push the rest back. Both operations are immune
+/// to ABA. The list is short (it is capped), so pushing back is cheap.
+pub(crate) struct FreeList {
+    head: AtomicPtr<Node>,
+    len: AtomicUsize,
+}
+
+impl FreeList {
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub(crate) const EMPTY: FreeList = FreeList {
+        head: AtomicPtr::new(ptr::null_mut()),
+        len: AtomicUsize::new(0),
+    };
+
+    /// Number of blocks in the list. Only approximate under contention.
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub(crate) fn push(&self, block: NonNull<u8>) {
+        let node = block.cast::<Node>().as_ptr();
+        self.push_all(Chain {
+            first: node,
+            last: node,
+            len: 1,
+        });
+    }
+
+    pub(crate) fn pop(&self) -> Option<NonNull<u8>> {
+        let (first, rest) = self.take_all()?.split_first();
+        if let Some(rest) = rest {
+            self.push_all(rest);
+        }
+        Some(first)
+    }
+
+    /// Detaches every block of the list.
+    pub(crate) fn take_all(&self) -> Option<Chain> {
+        let first = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        if first.is_null() {
+            return None;
+        }
+        let mut last = first;
+        let mut len = 1;
+        unsafe {
+            while !(*last).next.is_null() {
+                last = (*last).next;
+                len += 1;
+            }
+        }
+        self.len.fetch_sub(len, Ordering::Relaxed);
+        Some(Chain { first, last, len })
+    }
+
+    pub(crate) fn push_all(&self, chain: Chain) {
+        self.len.fetch_add(chain.len, Ordering::Relaxed);
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*chain.last).next = head }
+            match self.head.compare_exchange_weak(
+                head,
+                chain.first,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(new) => head = new,
+            }
+        }
+    }
+
+    /// Gives every block back to `backend`, returning how many bytes were
+    /// released.
+    #[inline]
+    pub(crate) unsafe fn flush<A>(&self, backend: &A, layout: Layout) -> usize
+    where
+        A: crate::alloc_api::Allocator,
+    {
+        match self.take_all() {
+            Some(chain) => chain.free(backend, layout),
+            None => 0,
+        }
+    }
+
+    /// Keeps as many blocks as fit in `retain` bytes, lowering it by their
+    /// size, and gives the rest back to `backend`.
+    pub(crate) unsafe fn release<A>(
+        &self,
+        backend: &A,
+        layout: Layout,
+        retain: &mut usize,
+    ) -> ReleaseReport
+    where
+        A: crate::alloc_api::Allocator,
+    {
+        let chain = match self.take_all() {
+            Some(chain) => chain,
+            None => return ReleaseReport::default(),
+        };
+        let keep = (*retain / layout.size()).min(chain.len);
+        *retain -= keep * layout.size();
+        let (kept, rest) = chain.split_at(keep);
+        if let Some(kept) = kept {
+            self.push_all(kept);
+        }
+        ReleaseReport {
+            released: rest.map_or(0, |rest| rest.free(backend, layout)),
+            retained: keep * layout.size(),
+        }
+    }
+}
+
+/// A wrapper fronting any allocator with lock-free, multi-producer
+/// multi-consumer free lists, one per power-of-two size class. Freed blocks
+/// are kept for reuse (up to a cap per class) instead of going back to the
+/// backend, and can be reused by any thread.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, FreeListAlloc};
+///
+/// let alloc = FreeListAlloc::new(Allocator::new());
+/// let layout = Layout::new::<[u64; 3]>();
+/// let first = alloc.allocate(layout).unwrap();
+/// assert_eq!(first.len(), 32);
+/// unsafe { alloc.deallocate(first.cast(), layout) };
+///
+/// let second = alloc.allocate(layout).unwrap();
+/// assert_eq!(first, second);
+/// unsafe { alloc.deallocate(second.cast(), layout) };
+/// ```
+pub struct FreeListAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    backend: A,
+    classes: [FreeList; FREE_LIST_CLASSES],
+    cap: usize,
+    wasted: AtomicUsize,
+}
+
+impl<A> FreeListAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Fronts `backend` with free lists keeping up to `DEFAULT_FREE_LIST_CAP`
+    /// blocks each.
+    #[inline]
+    pub const fn new(backend: A) -> Self {
+        Self::with_cap(backend, DEFAULT_FREE_LIST_CAP)
+    }
+
+    /// Fronts `backend` with free lists keeping up to `cap` blocks each.
+    #[inline]
+    pub const fn with_cap(backend: A, cap: usize) -> Self {
+        Self {
+            backend,
+            classes: [FreeList::EMPTY; FREE_LIST_CLASSES],
+            cap,
+            wasted: AtomicUsize::new(0),
+        }
+    }
+
+    /// The allocator behind the free lists.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// Number of blocks kept in all free lists.
+    #[inline]
+    pub fn cached(&self) -> usize {
+        self.classes.iter().map(FreeList::len).sum()
+    }
+
+    /// Usage of every size class, smallest first.
+    #[inline]
+    pub fn class_usage(&self) -> impl Iterator<Item = SizeClassUsage> + '_ {
+        self.classes.iter().enumerate().map(|(index, class)| SizeClassUsage {
+            size: class_layout(index).size(),
+            cached: class.len(),
+        })
+    }
+
+    /// Reports the cached blocks as free blocks, and the bytes lost rounding
+    /// live allocations up to their size class as waste. Only approximate
+    /// under contention.
+    pub fn fragmentation(&self) -> Fragmentation {
+        let mut frag = Fragmentation::new();
+        for usage in self.class_usage() {
+            frag.add_free(usage.size, usage.cached);
+        }
+        frag.wasted = self.wasted.load(Ordering::Relaxed);
+        frag
+    }
+
+    /// Gives every cached block back to the backend, returning how many bytes
+    /// were released. Suitable as a trim handler.
+    #[inline]
+    pub fn flush(&self) -> usize {
+        self.classes
+            .iter()
+            .enumerate()
+            .map(|(index, class)| unsafe { class.flush(&self.backend, class_layout(index)) })
+            .sum()
+    }
+}
+
+/// Smaller classes are retained first, as they are the most reused.
+impl<A> ReleaseFree for FreeListAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn release_free_memory(&self, mut retain: usize) -> ReleaseReport {
+        let mut report = ReleaseReport::default();
+        for (index, class) in self.classes.iter().enumerate() {
+            report += unsafe { class.release(&self.backend, class_layout(index), &mut retain) };
+        }
+        report
+    }
+}
+
+/// The size class serving `layout`, if any.
+#[inline]
+pub(crate) fn class_of(layout: Layout) -> Option<usize> {
+    let size = layout.size().max(layout.align()).max(MIN_FREE_LIST_CLASS);
+    let index = (size.next_power_of_two() / MIN_FREE_LIST_CLASS).trailing_zeros() as usize;
+    if index < FREE_LIST_CLASSES {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+/// The layout of blocks of a class, aligned to their size so they serve any
+/// alignment up to it.
+#[inline]
+pub(crate) fn class_layout(index: usize) -> Layout {
+    let size = MIN_FREE_LIST_CLASS << index;
+    unsafe { Layout::from_size_align_unchecked(size, size) }
+}
+
+unsafe impl<A> crate::alloc_api::Allocator for FreeListAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match class_of(layout) {
+            Some(index) => {
+                let class_layout = class_layout(index);
+                let block = match self.classes[index].pop() {
+                    Some(block) => {
+                        crate::asan::unpoison(block.as_ptr(), class_layout.size());
+                        NonNull::slice_from_raw_parts(block, class_layout.size())
+                    },
+                    None => self.backend.allocate(class_layout)?,
+                };
+                self.wasted.fetch_add(class_layout.size() - layout.size(), Ordering::Relaxed);
+                Ok(block)
+            },
+            None => self.backend.allocate(layout),
+        }
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let index = match class_of(layout) {
+            Some(index) => index,
+            None => return self.backend.deallocate(ptr, layout),
+        };
+        let size = class_layout(index).size();
+        self.wasted.fetch_sub(size - layout.size(), Ordering::Relaxed);
+        if self.classes[index].len() < self.cap {
+            // The link to the next block stays addressable.
+            let link = mem::size_of::<Node>();
+            crate::asan::poison(ptr.as_ptr().add(link), size - link);
+            self.classes[index].push(ptr)
+        } else {
+            #[cfg(feature = "tracing")]
+            crate::trace::evict("FreeListAlloc", Some(size));
+            self.backend.deallocate(ptr, class_layout(index))
+        }
+    }
+}
+
+impl<A> Drop for FreeListAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl<A> fmt::Debug for FreeListAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FreeListAlloc {{ cached: {}, cap: {} }}", self.cached(), self.cap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FreeListAlloc;
+    use crate::{alloc_api::Allocator as _, Allocator};
+    use core::alloc::Layout;
+
+    #[test]
+    fn capped_per_class() {
+        let alloc = FreeListAlloc::with_cap(Allocator::new(), 1);
+        let small = Layout::new::<u8>();
+        let a = alloc.allocate(small).unwrap();
+        let b = alloc.allocate(small).unwrap();
+        assert_eq!(a.len(), 16);
+        assert_eq!(alloc.fragmentation().wasted, 30);
+        unsafe {
+            alloc.deallocate(a.cast(), small);
+            alloc.deallocate(b.cast(), small);
+        }
+        assert_eq!(alloc.cached(), 1);
+        let frag = alloc.fragmentation();
+        assert_eq!((frag.free_blocks, frag.largest_free, frag.wasted), (1, 16, 0));
+
+        let big = Layout::from_size_align(1 << 20, 8).unwrap();
+        let block = alloc.allocate(big).unwrap();
+        unsafe { alloc.deallocate(block.cast(), big) };
+        assert_eq!(alloc.cached(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn blocks_cross_threads() {
+        use alloc::{sync::Arc, vec::Vec};
+
+        let alloc = Arc::new(FreeListAlloc::with_cap(Allocator::new(), 1000));
+        let layout = Layout::from_size_align(100, 8).unwrap();
+        let threads: Vec<_> = (0 .. 4)
+            .map(|_| {
+                let alloc = alloc.clone();
+                std::thread::spawn(move || {
+                    for _ in 0 .. 1000 {
+                        let blocks: Vec<_> =
+                            (0 .. 8).map(|_| alloc.allocate(layout).unwrap()).collect();
+                        for block in blocks {
+                            assert_eq!(block.len(), 128);
+                            unsafe { alloc.deallocate(block.cast(), layout) }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let cached = alloc.cached();
+        assert_eq!(alloc.flush(), cached * 128);
+        assert_eq!(alloc.cached(), 0);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::RawVec;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ptr,
+};
+
+/// A handle to a value of a `GenPool`, made of an index and the generation
+/// of the slot when the value was inserted.
+pub struct GenHandle<T> {
+    index: usize,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> GenHandle<T> {
+    /// The index of the slot of the value.
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.index
+    }
+
+    /// The generation of the slot when the value was inserted.
+    #[inline]
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for GenHandle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for GenHandle<T> {}
+
+impl<T> PartialEq for GenHandle<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for GenHandle<T> {}
+
+impl<T> Hash for GenHandle<T> {
+    #[inline]
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for GenHandle<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GenHandle {{ index: {}, generation: {} }}",
+            self.index, self.generation
+        )
+    }
+}
+
+union Payload<T> {
+    value: ManuallyDrop<T>,
+    next_free: usize,
+}
+
+struct Slot<T> {
+    /// Even while the slot is vacant, odd while it holds a value.
+    generation: u32,
+    payload: Payload<T>,
+}
+
+impl<T> Slot<T> {
+    #[inline]
+    fn is_occupied(&self) -> bool {
+        self.generation & 1 == 1
+    }
+}
+
+/// Marks the end of the free list of a `GenPool`.
+const NO_FREE: usize = usize::MAX;
+
+/// A pool of values reached through generational handles. Removing a value
+/// bumps the generation of its slot, so handles to removed values are
+/// detected at lookup time, even after the slot is reused.
+///
+/// Generations are 32 bits wide: a handle is only mistaken for a newer one
+/// after its slot was reused 2^31 times.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::GenPool;
+///
+/// let mut pool = GenPool::new();
+/// let stale = pool.insert("old");
+/// pool.remove(stale);
+/// let fresh = pool.insert("new");
+///
+/// assert_eq!(stale.index(), fresh.index());
+/// assert_eq!(pool.get(stale), None);
+/// assert_eq!(pool.get(fresh), Some(&"new"));
+/// ```
+pub struct GenPool<T> {
+    slots: RawVec<Slot<T>>,
+    /// Number of slots ever used.
+    used: usize,
+    free: usize,
+    len: usize,
+}
+
+impl<T> GenPool<T> {
+    /// Creates an empty pool. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            slots: RawVec::new(),
+            used: 0,
+            free: NO_FREE,
+            len: 0,
+        }
+    }
+
+    panicking! {
+        /// Creates an empty pool with room for `cap` values. In case of
+        /// allocation error or overflow, the function panics.
+        #[inline]
+        pub fn with_capacity(cap: usize) -> Self {
+            Self {
+                slots: RawVec::with_capacity(cap),
+                used: 0,
+                free: NO_FREE,
+                len: 0,
+            }
+        }
+    }
+
+    panicking! {
+        /// Moves `value` into the pool. In case of allocation error, the function
+        /// panics.
+        pub fn insert(&mut self, value: T) -> GenHandle<T> {
+            let index = if self.free != NO_FREE {
+                let index = self.free;
+                let slot = unsafe { &mut *self.slot_ptr(index) };
+                self.free = unsafe { slot.payload.next_free };
+                slot.generation = slot.generation.wrapping_add(1);
+                slot.payload.value = ManuallyDrop::new(value);
+                index
+            } else {
+                if self.used == self.slots.cap() {
+                    self.grow();
+                }
+                let index = self.used;
+                let slot = Slot {
+                    generation: 1,
+                    payload: Payload {
+                        value: ManuallyDrop::new(value),
+                    },
+                };
+                unsafe { self.slot_ptr(index).write(slot) }
+                self.used += 1;
+                index
+            };
+            self.len += 1;
+            GenHandle {
+                index,
+                generation: unsafe { (*self.slot_ptr(index)).generation },
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// The value of `handle`, or `None` if it was removed.
+    #[inline]
+    pub fn get(&self, handle: GenHandle<T>) -> Option<&T> {
+        let slot = self.slot(handle)?;
+        unsafe { Some(&(*slot).payload.value) }
+    }
+
+    /// The value of `handle`, or `None` if it was removed.
+    #[inline]
+    pub fn get_mut(&mut self, handle: GenHandle<T>) -> Option<&mut T> {
+        let slot = self.slot(handle)?;
+        unsafe { Some(&mut (*slot).payload.value) }
+    }
+
+    /// Tests if `handle` refers to a value of the pool.
+    #[inline]
+    pub fn contains(&self, handle: GenHandle<T>) -> bool {
+        self.slot(handle).is_some()
+    }
+
+    /// Moves the value of `handle` out of the pool, or returns `None` if it
+    /// was already removed.
+    pub fn remove(&mut self, handle: GenHandle<T>) -> Option<T> {
+        let slot = unsafe { &mut *self.slot(handle)? };
+        let value = unsafe { ManuallyDrop::take(&mut slot.payload.value) };
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.payload.next_free = self.free;
+        self.free = handle.index;
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Iterates over the values and their handles.
+    pub fn iter(&self) -> impl Iterator<Item = (GenHandle<T>, &T)> {
+        (0 .. self.used).filter_map(move |index| {
+            let slot = unsafe { &*self.slot_ptr(index) };
+            if slot.is_occupied() {
+                let handle = GenHandle {
+                    index,
+                    generation: slot.generation,
+                    _marker: PhantomData,
+                };
+                Some((handle, unsafe { &*slot.payload.value }))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Number of values in the pool.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if the pool holds no value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of values the pool can hold without growing.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.slots.cap()
+    }
+
+    #[inline]
+    fn slot_ptr(&self, index: usize) -> *mut Slot<T> {
+        unsafe { self.slots.raw().as_ptr().add(index) }
+    }
+
+    #[inline]
+    fn slot(&self, handle: GenHandle<T>) -> Option<*mut Slot<T>> {
+        if handle.index >= self.used {
+            return None;
+        }
+        let slot = self.slot_ptr(handle.index);
+        let generation = unsafe { (*slot).generation };
+        if generation == handle.generation {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+
+    /// Moves the slots into a storage twice as big.
+    fn grow(&mut self) {
+        let cap = (self.slots.cap() * 2).max(4);
+        let slots = RawVec::<Slot<T>>::with_capacity(cap);
+        unsafe {
+            ptr::copy_nonoverlapping(self.slots.raw().as_ptr(), slots.raw().as_ptr(), self.used)
+        }
+        self.slots = slots;
+    }
+}
+
+impl<T> Default for GenPool<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for GenPool<T> {
+    fn drop(&mut self) {
+        for index in 0 .. self.used {
+            let slot = unsafe { &mut *self.slot_ptr(index) };
+            if slot.is_occupied() {
+                unsafe { ManuallyDrop::drop(&mut slot.payload.value) }
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for GenPool<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GenPool {{ len: {}, capacity: {} }}", self.len, self.capacity())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GenPool;
+    use alloc::{rc::Rc, vec::Vec};
+
+    #[test]
+    fn stale_handles_and_drops() {
+        let tracker = Rc::new(());
+        let mut pool = GenPool::new();
+        let handles: Vec<_> = (0 .. 10).map(|_| pool.insert(tracker.clone())).collect();
+        for handle in &handles[.. 5] {
+            assert!(pool.remove(*handle).is_some());
+            assert!(pool.remove(*handle).is_none());
+        }
+        assert_eq!(Rc::strong_count(&tracker), 6);
+
+        let reused = pool.insert(tracker.clone());
+        assert_eq!(reused.index(), handles[4].index());
+        assert_eq!(reused.generation(), 3);
+        assert!(!pool.contains(handles[4]));
+        assert_eq!(pool.iter().count(), 6);
+
+        drop(pool);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    slots::{Slot, SlotList},
+    DeferQueue, OwnedAlloc,
+};
+use core::{
+    fmt, ptr,
+    sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// Number of retired allocations after which `Domain::retire` triggers a
+/// reclamation pass on its own.
+pub const RECLAIM_THRESHOLD: usize = 64;
+
+/// A hazard pointer domain. Readers of a lock-free structure publish the
+/// pointers they are about to dereference through a `HazardGuard`, and
+/// writers hand unlinked nodes to `retire`. A retired `OwnedAlloc` is only
+/// deallocated once no guard of the same domain protects it.
+///
+/// Hazard slots are allocated on demand, recycled when their guard is
+/// dropped, and only freed together with the domain.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use core::{
+///     ptr::NonNull,
+///     sync::atomic::{AtomicPtr, Ordering},
+/// };
+/// use owned_alloc::{hazard::Domain, OwnedAlloc};
+///
+/// let domain = Domain::new();
+/// let shared = AtomicPtr::new(OwnedAlloc::new(10).into_raw().as_ptr());
+///
+/// let guard = domain.guard();
+/// let ptr = guard.protect(&shared);
+///
+/// // A writer replaces the value and retires the old one.
+/// let new = OwnedAlloc::new(20).into_raw().as_ptr();
+/// let old = shared.swap(new, Ordering::AcqRel);
+/// domain.retire(unsafe { OwnedAlloc::from_raw(NonNull::new_unchecked(old)) });
+///
+/// // Still protected: not freed yet.
+/// assert_eq!(domain.reclaim(), 0);
+/// assert_eq!(unsafe { *ptr }, 10);
+///
+/// drop(guard);
+/// assert_eq!(domain.reclaim(), 1);
+/// # drop(unsafe { OwnedAlloc::from_raw(NonNull::new_unchecked(shared.into_inner())) });
+/// ```
+pub struct Domain {
+    slots: SlotList<AtomicPtr<u8>>,
+    retired: DeferQueue,
+    retired_count: AtomicUsize,
+}
+
+impl Domain {
+    /// Creates a new domain with no hazard slots and nothing retired.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            slots: SlotList::new(),
+            retired: DeferQueue::new(),
+            retired_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Acquires a hazard slot, reusing an inactive one if possible.
+    pub fn guard(&self) -> HazardGuard<'_> {
+        let slot = self.slots.acquire(|| AtomicPtr::new(ptr::null_mut()));
+        HazardGuard { domain: self, slot }
+    }
+
+    panicking! {
+        /// Retires an allocation that has already been unlinked from the shared
+        /// structure. It is dropped by a later reclamation pass once no guard
+        /// protects it. Every `RECLAIM_THRESHOLD` retirements, a pass is run
+        /// automatically. The value may be dropped at any later pass, hence the
+        /// `'static` bound.
+        pub fn retire<T>(&self, alloc: OwnedAlloc<T>)
+        where
+            T: ?Sized + Send + 'static,
+        {
+            // Counting before pushing keeps the counter from underflowing when a
+            // concurrent pass frees the entry right away.
+            let count = self.retired_count.fetch_add(1, Ordering::Relaxed) + 1;
+            self.retired.defer(alloc);
+            if count >= RECLAIM_THRESHOLD {
+                self.reclaim();
+            }
+        }
+    }
+
+    /// Drops every retired allocation not currently protected by a guard and
+    /// returns how many were freed.
+    pub fn reclaim(&self) -> usize {
+        fence(Ordering::SeqCst);
+        let freed = unsafe { self.retired.reclaim_unless(|addr| self.is_protected(addr)) };
+        self.retired_count.fetch_sub(freed, Ordering::Relaxed);
+        freed
+    }
+
+    fn is_protected(&self, addr: *mut u8) -> bool {
+        self.slots
+            .iter()
+            .any(|slot| slot.value.load(Ordering::SeqCst) == addr)
+    }
+}
+
+impl Default for Domain {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Domain {
+    fn drop(&mut self) {
+        // No guard can outlive the domain, so nothing is protected anymore.
+        unsafe {
+            self.retired.reclaim();
+        }
+    }
+}
+
+impl fmt::Debug for Domain {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Domain {{ slots: {:?}, retired: {:?} }}",
+            self.slots.head(),
+            self.retired
+        )
+    }
+}
+
+unsafe impl Send for Domain {}
+unsafe impl Sync for Domain {}
+
+/// A hazard slot owned by the current thread. While a pointer is published
+/// through `protect`, the domain will not free the allocation it points to.
+pub struct HazardGuard<'d> {
+    domain: &'d Domain,
+    slot: &'d Slot<AtomicPtr<u8>>,
+}
+
+impl<'d> HazardGuard<'d> {
+    /// Loads a pointer from `src` and protects it. The load is repeated until
+    /// the published hazard matches the current value, so the returned
+    /// pointer is safe to dereference until the guard is reset, reused or
+    /// dropped, as long as writers only free it through `Domain::retire`.
+    pub fn protect<T>(&self, src: &AtomicPtr<T>) -> *mut T {
+        let mut ptr = src.load(Ordering::Relaxed);
+        loop {
+            self.slot.value.store(ptr.cast(), Ordering::SeqCst);
+            let reloaded = src.load(Ordering::SeqCst);
+            if reloaded == ptr {
+                return ptr;
+            }
+            ptr = reloaded;
+        }
+    }
+
+    /// Publishes an already known pointer as a hazard. Unlike `protect`, this
+    /// does not validate that the pointer is still reachable.
+    #[inline]
+    pub fn protect_raw<T>(&self, ptr: *mut T) {
+        self.slot.value.store(ptr.cast(), Ordering::SeqCst);
+    }
+
+    /// Clears the published hazard without releasing the slot.
+    #[inline]
+    pub fn reset(&self) {
+        self.slot.value.store(ptr::null_mut(), Ordering::Release);
+    }
+
+    /// The domain this guard belongs to.
+    #[inline]
+    pub fn domain(&self) -> &'d Domain {
+        self.domain
+    }
+}
+
+impl<'d> Drop for HazardGuard<'d> {
+    #[inline]
+    fn drop(&mut self) {
+        self.reset();
+        self.slot.release();
+    }
+}
+
+impl<'d> fmt::Debug for HazardGuard<'d> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HazardGuard {{ hazard: {:?} }}",
+            self.slot.value.load(Ordering::Relaxed)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Domain;
+    use crate::OwnedAlloc;
+    use core::{
+        ptr::NonNull,
+        sync::atomic::{AtomicPtr, Ordering},
+    };
+
+    #[test]
+    fn slots_are_recycled() {
+        let domain = Domain::new();
+        let first = domain.guard().slot as *const _;
+        let second = domain.guard().slot as *const _;
+        assert_eq!(first, second);
+
+        let a = domain.guard();
+        let b = domain.guard();
+        assert_ne!(a.slot as *const _, b.slot as *const _);
+    }
+
+    #[test]
+    fn protected_survives_reclaim() {
+        let domain = Domain::new();
+        let shared = AtomicPtr::new(OwnedAlloc::new(1u32).into_raw().as_ptr());
+        let guard = domain.guard();
+        let ptr = guard.protect(&shared);
+
+        let old = shared.load(Ordering::Relaxed);
+        domain.retire(unsafe { OwnedAlloc::from_raw(NonNull::new_unchecked(old)) });
+        domain.retire(OwnedAlloc::new(2u32));
+        assert_eq!(domain.reclaim(), 1);
+        assert_eq!(unsafe { *ptr }, 1);
+
+        guard.reset();
+        assert_eq!(domain.reclaim(), 1);
+    }
+}
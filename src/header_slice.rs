@@ -0,0 +1,278 @@
+use crate::{AllocError, Allocator, LayoutError, OwnedAlloc, RawVecError};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt,
+    ptr::{self, NonNull},
+};
+
+/// A header followed by a slice in the same block, like a C struct ending
+/// with a flexible array member. `HeaderSlice<H, T>` is a dynamically sized
+/// type, so it is used behind a fat pointer, e.g. in an `OwnedAlloc`.
+///
+/// When the header stores the length (see `LenHeader`), a thin pointer to
+/// the header is enough to recover the whole value, as C code expects.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{HeaderSlice, OwnedAlloc};
+///
+/// let node = OwnedAlloc::<HeaderSlice<u32, u8>>::from_header_slice(7, b"inline");
+/// assert_eq!(node.header, 7);
+/// assert_eq!(&node.slice, b"inline");
+/// assert_eq!(core::mem::size_of_val(&*node), 12);
+/// ```
+#[repr(C)]
+pub struct HeaderSlice<H, T> {
+    /// The header.
+    pub header: H,
+    /// The trailing array.
+    pub slice: [T],
+}
+
+impl<H, T> HeaderSlice<H, T> {
+    /// The layout of a value whose slice has `len` elements.
+    #[inline]
+    pub fn layout(len: usize) -> Result<Layout, LayoutError> {
+        let (layout, _) = Layout::new::<H>().extend(Layout::array::<T>(len)?)?;
+        Ok(layout.pad_to_align())
+    }
+
+    /// The fat pointer to a value whose slice has `len` elements, starting
+    /// at `ptr`.
+    #[inline]
+    pub fn from_raw_parts(ptr: NonNull<u8>, len: usize) -> NonNull<Self> {
+        let fat = ptr::slice_from_raw_parts_mut(ptr.as_ptr(), len) as *mut Self;
+        unsafe { NonNull::new_unchecked(fat) }
+    }
+}
+
+impl<H, T> HeaderSlice<H, T>
+where
+    H: LenHeader,
+{
+    /// Recovers the fat pointer to a value from a thin pointer to its
+    /// header, which stores the length.
+    ///
+    /// # Safety
+    /// `header` must point to the header of a valid value.
+    #[inline]
+    pub unsafe fn from_thin(header: NonNull<H>) -> NonNull<Self> {
+        Self::from_raw_parts(header.cast(), header.as_ref().len())
+    }
+
+    /// The thin pointer to the header, e.g. to pass the value to C code.
+    #[inline]
+    pub fn as_thin(&self) -> NonNull<H> {
+        NonNull::from(&self.header)
+    }
+}
+
+impl<H, T> fmt::Debug for HeaderSlice<H, T>
+where
+    H: fmt::Debug,
+    T: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HeaderSlice {{ header: {:?}, slice: {:?} }}", self.header, &self.slice)
+    }
+}
+
+/// A header storing the length of the slice following it in a
+/// `HeaderSlice`. `OwnedAlloc::into_thin` checks the length against the
+/// slice, which `from_thin` then relies on.
+///
+/// # Safety
+/// `len` must keep returning the same value as long as the header is not
+/// mutated.
+pub unsafe trait LenHeader {
+    /// The length of the slice following the header.
+    fn len(&self) -> usize;
+
+    /// Tests if the slice following the header is empty.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<H, T> OwnedAlloc<HeaderSlice<H, T>> {
+    panicking! {
+        /// Allocates `header` followed by the items of `iter` in one block. In
+        /// case of allocation error or overflow, or if `iter` yields fewer items
+        /// than its length, the function panics.
+        #[inline]
+        #[track_caller]
+        pub fn from_header_iter<I>(header: H, iter: I) -> Self
+        where
+            I: IntoIterator<Item = T>,
+            I::IntoIter: ExactSizeIterator,
+        {
+            match Self::try_from_header_iter(header, iter) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Allocates `header` followed by the items of `iter` in one block. In
+    /// case of allocation error or overflow, `Err` is returned. If `iter`
+    /// yields fewer items than its length, the function panics.
+    #[track_caller]
+    pub fn try_from_header_iter<I>(header: H, iter: I) -> Result<Self, RawVecError>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let len = iter.len();
+        let layout = HeaderSlice::<H, T>::layout(len)?;
+        let block = if layout.size() == 0 {
+            unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+        } else {
+            let ptr = unsafe { GlobalAlloc::alloc(&Allocator::new(), layout) };
+            let block = NonNull::new(ptr).ok_or(AllocError { layout })?;
+            #[cfg(feature = "track-callers")]
+            crate::leak::record(block.as_ptr(), layout.size());
+            block
+        };
+
+        let mut partial = Partial {
+            ptr: HeaderSlice::<H, T>::from_raw_parts(block, len),
+            layout,
+            header: false,
+            len: 0,
+        };
+        unsafe {
+            let raw = partial.ptr.as_ptr();
+            ptr::addr_of_mut!((*raw).header).write(header);
+            partial.header = true;
+            let slice = ptr::addr_of_mut!((*raw).slice).cast::<T>();
+            for item in iter.take(len) {
+                slice.add(partial.len).write(item);
+                partial.len += 1;
+            }
+        }
+        assert!(partial.len == len, "Iterator yielded fewer items than its length");
+        let ptr = partial.ptr;
+        core::mem::forget(partial);
+        Ok(unsafe { Self::from_raw(ptr) })
+    }
+
+    panicking! {
+        /// Allocates `header` followed by clones of the elements of `slice` in
+        /// one block. In case of allocation error or overflow, the function
+        /// panics.
+        #[inline]
+        #[track_caller]
+        pub fn from_header_slice(header: H, slice: &[T]) -> Self
+        where
+            T: Clone,
+        {
+            Self::from_header_iter(header, slice.iter().cloned())
+        }
+    }
+}
+
+impl<H, T> OwnedAlloc<HeaderSlice<H, T>>
+where
+    H: LenHeader,
+{
+    /// "Forgets" dropping the allocation and returns a thin pointer to the
+    /// header. If the length stored in the header is not the one of the
+    /// slice, the function panics.
+    #[inline]
+    #[track_caller]
+    pub fn into_thin(self) -> NonNull<H> {
+        assert_eq!(
+            self.header.len(),
+            self.slice.len(),
+            "length of the header and of the slice differ"
+        );
+        self.into_raw().cast()
+    }
+
+    /// Recreates the allocation from a thin pointer to its header.
+    ///
+    /// # Safety
+    /// `header` must come from `into_thin`.
+    #[inline]
+    pub unsafe fn from_thin(header: NonNull<H>) -> Self {
+        Self::from_raw(HeaderSlice::from_thin(header))
+    }
+}
+
+/// A block being initialized by `OwnedAlloc::try_from_header_iter`, freed
+/// with what was written in it if an item panics.
+struct Partial<H, T> {
+    ptr: NonNull<HeaderSlice<H, T>>,
+    layout: Layout,
+    header: bool,
+    len: usize,
+}
+
+impl<H, T> Drop for Partial<H, T> {
+    fn drop(&mut self) {
+        unsafe {
+            let raw = self.ptr.as_ptr();
+            if self.header {
+                ptr::addr_of_mut!((*raw).header).drop_in_place();
+            }
+            let slice = ptr::addr_of_mut!((*raw).slice).cast::<T>();
+            ptr::slice_from_raw_parts_mut(slice, self.len).drop_in_place();
+            if self.layout.size() != 0 {
+                #[cfg(feature = "track-callers")]
+                crate::leak::forget(raw.cast());
+                GlobalAlloc::dealloc(&Allocator::new(), raw.cast(), self.layout);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HeaderSlice, LenHeader};
+    use crate::OwnedAlloc;
+
+    #[repr(C)]
+    struct Packet {
+        len: u32,
+        kind: u8,
+    }
+
+    unsafe impl LenHeader for Packet {
+        fn len(&self) -> usize {
+            self.len as usize
+        }
+    }
+
+    #[test]
+    fn thin_pointer_round_trip() {
+        let payload = [1u16, 2, 3, 4, 5];
+        let header = Packet {
+            len: payload.len() as u32,
+            kind: 9,
+        };
+        let packet = OwnedAlloc::<HeaderSlice<Packet, u16>>::from_header_slice(header, &payload);
+        assert_eq!(packet.as_thin().cast::<u8>(), packet.raw().cast::<u8>());
+
+        let thin = packet.into_thin();
+        let packet = unsafe { OwnedAlloc::<HeaderSlice<Packet, u16>>::from_thin(thin) };
+        assert_eq!(packet.header.kind, 9);
+        assert_eq!(packet.slice, payload);
+        assert_eq!(core::mem::size_of_val(&*packet), 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "length of the header and of the slice differ")]
+    fn mismatched_length_stays_fat() {
+        let header = Packet { len: 100, kind: 0 };
+        let packet = OwnedAlloc::<HeaderSlice<Packet, u16>>::from_header_slice(header, &[1, 2]);
+        packet.into_thin();
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{alloc_api::Allocator as _, Tlsf};
+use crate::AllocError;
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    fmt, hint,
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+    slice,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+/// Installs the region between the linker symbols `__heap_start` and
+/// `__heap_end` as the heap of the crate `Allocator`. Expands to a call to
+/// `init_heap_range`, so it evaluates to its `Result`, and must be used in
+/// an `unsafe` context.
+///
+/// # Example
+/// ```rust,ignore
+/// #[macro_use]
+/// extern crate owned_alloc;
+///
+/// #[global_allocator]
+/// static GLOBAL: owned_alloc::Allocator = owned_alloc::Allocator::new();
+///
+/// fn main() {
+///     unsafe { init_linker_heap!() }.unwrap();
+///     let boxed = owned_alloc::OwnedAlloc::new(5u32);
+/// }
+/// ```
+#[macro_export]
+macro_rules! init_linker_heap {
+    () => {{
+        extern "C" {
+            static mut __heap_start: u8;
+            static mut __heap_end: u8;
+        }
+        $crate::init_heap_range(
+            ::core::ptr::addr_of_mut!(__heap_start),
+            ::core::ptr::addr_of_mut!(__heap_end),
+        )
+    }};
+}
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+static HEAP: Heap = Heap::new();
+
+/// Installs `region` as the heap of the crate `Allocator`, for bare-metal
+/// targets without any system allocator. From then on, every allocation of
+/// the crate (`OwnedAlloc`, `RawVec`, the `Allocator` itself, and anything
+/// else if it is the `#[global_allocator]`) is served from the region by a
+/// `Tlsf`, behind a spin lock, or in a critical section with the
+/// `critical-section` feature.
+///
+/// The heap can only be installed once: the region is given back if one
+/// already is. An allocator registered through the `ffi` feature takes
+/// precedence over the heap.
+///
+/// A spin lock deadlocks if an interrupt handler allocates while the code it
+/// interrupted holds the lock, and needs atomic compare-and-swap, which some
+/// microcontrollers lack. With the `critical-section` feature, the heap is
+/// instead accessed inside `critical_section::with`, so the same heap is safe
+/// to use from interrupts on a Cortex-M as from RTOS threads, whatever the
+/// `critical-section` implementation the application links in.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use core::{mem::MaybeUninit, ptr};
+/// use owned_alloc::OwnedAlloc;
+///
+/// static mut REGION: [MaybeUninit<u8>; 4096] = [MaybeUninit::uninit(); 4096];
+///
+/// let region = unsafe { &mut *ptr::addr_of_mut!(REGION) };
+/// unsafe { owned_alloc::init_heap(region) }.unwrap();
+/// assert_eq!(owned_alloc::heap_size(), Some(4096));
+///
+/// let boxed = OwnedAlloc::new(5u32);
+/// let start = unsafe { ptr::addr_of!(REGION) } as usize;
+/// assert!((start .. start + 4096).contains(&(boxed.raw().as_ptr() as usize)));
+/// ```
+///
+/// # Safety
+/// This function is `unsafe` because every block freed through the crate
+/// `Allocator` goes to the heap once it is installed: no block to be freed
+/// through the `Allocator` may exist yet (one allocated by it, for the
+/// crate's types or as the global allocator, or a `Vec` buffer taken over
+/// by `RawVec::try_from_vec`), so no block from another source is ever
+/// given to the heap.
+#[inline]
+pub unsafe fn init_heap(
+    region: &'static mut [MaybeUninit<u8>],
+) -> Result<(), &'static mut [MaybeUninit<u8>]> {
+    HEAP.install(region)
+}
+
+/// Installs the memory between `start` and `end` as the heap of the crate
+/// `Allocator`. See `init_heap`.
+///
+/// # Safety
+/// This function is `unsafe` because the memory must be valid for reads and
+/// writes and not used by anything else for the rest of the program, and
+/// `start` must not be after `end`. As with `init_heap`, no allocation may
+/// have been made through the `Allocator` yet.
+#[inline]
+pub unsafe fn init_heap_range(
+    start: *mut u8,
+    end: *mut u8,
+) -> Result<(), &'static mut [MaybeUninit<u8>]> {
+    let len = end as usize - start as usize;
+    init_heap(slice::from_raw_parts_mut(start.cast(), len))
+}
+
+/// Size of the region installed as the heap of the crate `Allocator`, if
+/// any.
+#[inline]
+pub fn heap_size() -> Option<usize> {
+    HEAP.size()
+}
+
+/// Allocates a block of the heap installed with `init_heap`, for interrupt
+/// handlers. It never waits: it fails right away if the heap is in use (by
+/// the code the handler interrupted, or by another core), or if no heap is
+/// installed. The normal allocation path is unaffected.
+///
+/// The block must be freed with `try_free_isr`, or through the crate
+/// `Allocator` outside of interrupt handlers. For fixed-size blocks, a
+/// preallocated `StaticPool` is lock-free and can be used from interrupt
+/// handlers as well.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use core::{alloc::Layout, mem::MaybeUninit, ptr};
+/// use owned_alloc::{try_alloc_isr, try_free_isr};
+///
+/// let layout = Layout::new::<[u32; 4]>();
+/// assert!(try_alloc_isr(layout).is_err());
+///
+/// static mut REGION: [MaybeUninit<u8>; 1024] = [MaybeUninit::uninit(); 1024];
+/// unsafe { owned_alloc::init_heap(&mut *ptr::addr_of_mut!(REGION)) }.unwrap();
+///
+/// let block = try_alloc_isr(layout).unwrap();
+/// assert!(unsafe { try_free_isr(block, layout) });
+/// ```
+#[inline]
+pub fn try_alloc_isr(layout: Layout) -> Result<NonNull<u8>, AllocError> {
+    HEAP.try_alloc(layout).ok_or(AllocError { layout })
+}
+
+/// Gives a block back to the heap installed with `init_heap`, for interrupt
+/// handlers. Returns `false`, without freeing the block, if the heap is in
+/// use: the block must then be kept, and freed later.
+///
+/// # Safety
+/// This function is `unsafe` because the block must have been allocated from
+/// the heap with the given layout, and not be used anymore.
+#[inline]
+pub unsafe fn try_free_isr(ptr: NonNull<u8>, layout: Layout) -> bool {
+    HEAP.try_dealloc(ptr, layout)
+}
+
+/// The heap installed with `init_heap`, if any.
+#[inline]
+pub(crate) fn installed() -> Option<&'static Heap> {
+    if HEAP.state.load(Ordering::Acquire) == READY {
+        Some(&HEAP)
+    } else {
+        None
+    }
+}
+
+/// A `Tlsf` over a region given once, shared behind a spin lock or a
+/// critical section.
+pub(crate) struct Heap {
+    state: AtomicU8,
+    locked: AtomicBool,
+    size: UnsafeCell<usize>,
+    tlsf: UnsafeCell<MaybeUninit<Tlsf<'static>>>,
+}
+
+impl Heap {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            locked: AtomicBool::new(false),
+            size: UnsafeCell::new(0),
+            tlsf: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    fn install(
+        &self,
+        region: &'static mut [MaybeUninit<u8>],
+    ) -> Result<(), &'static mut [MaybeUninit<u8>]> {
+        if !self.claim() {
+            return Err(region);
+        }
+        unsafe {
+            *self.size.get() = region.len();
+            (*self.tlsf.get()).write(Tlsf::new(region));
+        }
+        self.state.store(READY, Ordering::Release);
+        Ok(())
+    }
+
+    /// Moves the state from empty to writing, if it is empty.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    fn claim(&self) -> bool {
+        self.state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// Moves the state from empty to writing, if it is empty.
+    #[cfg(feature = "critical-section")]
+    #[inline]
+    fn claim(&self) -> bool {
+        critical_section::with(|_| {
+            let claimed = self.state.load(Ordering::Acquire) == EMPTY;
+            if claimed {
+                self.state.store(WRITING, Ordering::Relaxed);
+            }
+            claimed
+        })
+    }
+
+    #[inline]
+    fn size(&self) -> Option<usize> {
+        if self.state.load(Ordering::Acquire) == READY {
+            Some(unsafe { *self.size.get() })
+        } else {
+            None
+        }
+    }
+
+    /// Allocates a block of the installed heap, or returns null.
+    #[inline]
+    pub(crate) fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.with_tlsf(|tlsf| match tlsf.allocate(layout) {
+            Ok(block) => block.cast::<u8>().as_ptr(),
+            Err(_) => ptr::null_mut(),
+        });
+        if !ptr.is_null() {
+            crate::valgrind::malloclike(ptr, layout.size(), false);
+        }
+        ptr
+    }
+
+    /// Gives a block back to the installed heap.
+    #[inline]
+    pub(crate) unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        crate::valgrind::freelike(ptr);
+        self.with_tlsf(|tlsf| tlsf.deallocate(NonNull::new_unchecked(ptr), layout))
+    }
+
+    /// Gives the whole free pages of the installed heap back to the system.
+    #[cfg(feature = "os")]
+    #[inline]
+    pub(crate) fn decommit_free(&self) -> usize {
+        self.with_tlsf(|tlsf| tlsf.decommit_free())
+    }
+
+    /// Allocates a block of the installed heap if it is not in use, without
+    /// waiting.
+    #[inline]
+    fn try_alloc(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let ptr = self.try_with_tlsf(|tlsf| tlsf.allocate(layout).ok())??.cast::<u8>();
+        crate::valgrind::malloclike(ptr.as_ptr(), layout.size(), false);
+        Some(ptr)
+    }
+
+    /// Gives a block back to the installed heap if it is not in use, without
+    /// waiting.
+    #[inline]
+    unsafe fn try_dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let freed = self.try_with_tlsf(|tlsf| tlsf.deallocate(ptr, layout)).is_some();
+        if freed {
+            crate::valgrind::freelike(ptr.as_ptr());
+        }
+        freed
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[inline]
+    fn lock(&self) {
+        while !self.try_lock() {
+            hint::spin_loop();
+        }
+    }
+
+    #[cfg(not(feature = "critical-section"))]
+    fn with_tlsf<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Tlsf<'static>) -> R,
+    {
+        self.lock();
+        let ret = self.run(f);
+        self.locked.store(false, Ordering::Release);
+        ret
+    }
+
+    /// Only contended by the interrupt-safe path on other cores: on a single
+    /// core, the critical section keeps interrupts from taking the lock.
+    #[cfg(feature = "critical-section")]
+    #[inline]
+    fn with_tlsf<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Tlsf<'static>) -> R,
+    {
+        critical_section::with(|_| {
+            self.lock();
+            let ret = self.run(f);
+            self.locked.store(false, Ordering::Release);
+            ret
+        })
+    }
+
+    /// Runs `f` on the heap if the lock is free, or fails right away.
+    fn try_with_tlsf<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&Tlsf<'static>) -> R,
+    {
+        if self.state.load(Ordering::Acquire) != READY || !self.try_lock() {
+            return None;
+        }
+        let ret = self.run(f);
+        self.locked.store(false, Ordering::Release);
+        Some(ret)
+    }
+
+    /// Runs `f` on the heap, which the caller locked. The allocator reads and
+    /// writes the free blocks it told Valgrind about.
+    #[inline]
+    fn run<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Tlsf<'static>) -> R,
+    {
+        crate::valgrind::disable_errors();
+        let ret = f(unsafe { (*self.tlsf.get()).assume_init_ref() });
+        crate::valgrind::enable_errors();
+        ret
+    }
+}
+
+unsafe impl Sync for Heap {}
+
+impl fmt::Debug for Heap {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Heap {{ size: {:?} }}", self.size())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Heap;
+    use alloc::boxed::Box;
+    use core::{alloc::Layout, mem::MaybeUninit};
+
+    #[test]
+    fn installed_once() {
+        let heap = Heap::new();
+        let region = Box::leak(Box::new([MaybeUninit::<u8>::uninit(); 1024]));
+        let start = region.as_ptr() as usize;
+        heap.install(region).unwrap();
+        let other = Box::leak(Box::new([MaybeUninit::<u8>::uninit(); 16]));
+        assert!(heap.install(other).is_err());
+        assert_eq!(heap.size(), Some(1024));
+
+        let layout = Layout::from_size_align(100, 16).unwrap();
+        let ptr = heap.alloc(layout);
+        assert!((start .. start + 1024).contains(&(ptr as usize)));
+        assert!(heap.alloc(Layout::from_size_align(2048, 8).unwrap()).is_null());
+        unsafe { heap.dealloc(ptr, layout) };
+    }
+
+    #[test]
+    fn isr_path_fails_fast() {
+        let heap = Heap::new();
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        assert!(heap.try_alloc(layout).is_none());
+
+        heap.install(Box::leak(Box::new([MaybeUninit::<u8>::uninit(); 1024]))).unwrap();
+        assert!(heap.with_tlsf(|_| heap.try_alloc(layout)).is_none());
+        let block = heap.try_alloc(layout).unwrap();
+        assert!(!heap.with_tlsf(|_| unsafe { heap.try_dealloc(block, layout) }));
+        assert!(unsafe { heap.try_dealloc(block, layout) });
+    }
+}
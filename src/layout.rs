@@ -0,0 +1,131 @@
+//! Layout arithmetic usable in `const` contexts, returning the crate's
+//! `LayoutError`, so unsafe code computing the layout of arrays, headers and
+//! trailing data shares the crate's checked math instead of reimplementing
+//! it.
+//!
+//! # Example
+//! ```rust
+//! extern crate owned_alloc;
+//!
+//! use core::alloc::Layout;
+//! use owned_alloc::layout;
+//!
+//! // A header followed by 10 `u64`s, computed at compile time.
+//! const BLOCK: (Layout, usize) = match layout::extend(
+//!     Layout::new::<u16>(),
+//!     match layout::array::<u64>(10) {
+//!         Ok(array) => array,
+//!         Err(_) => panic!("array too big"),
+//!     },
+//! ) {
+//!     Ok(block) => block,
+//!     Err(_) => panic!("block too big"),
+//! };
+//!
+//! assert_eq!(BLOCK.1, 8);
+//! assert_eq!(layout::pad_to_align(BLOCK.0).size(), 88);
+//! assert!(layout::array::<u32>(usize::MAX).is_err());
+//! ```
+
+use crate::LayoutError;
+use core::{alloc::Layout, mem};
+
+/// Layout of an array of `n` values of type `T`.
+#[inline]
+pub const fn array<T>(n: usize) -> Result<Layout, LayoutError> {
+    match mem::size_of::<T>().checked_mul(n) {
+        Some(size) => from_size_align(size, mem::align_of::<T>()),
+        None => Err(LayoutError),
+    }
+}
+
+/// Layout of `size` bytes at alignment `align`, which must be a power of
+/// two; the size, rounded up to the alignment, must not overflow `isize`.
+#[inline]
+pub const fn from_size_align(size: usize, align: usize) -> Result<Layout, LayoutError> {
+    match Layout::from_size_align(size, align) {
+        Ok(layout) => Ok(layout),
+        Err(_) => Err(LayoutError),
+    }
+}
+
+/// Bytes of padding needed after `layout` for the next address to be a
+/// multiple of `align`, which must be a power of two.
+#[inline]
+pub const fn padding_needed_for(layout: Layout, align: usize) -> usize {
+    let size = layout.size();
+    let rounded = size.wrapping_add(align - 1) & !(align - 1);
+    rounded.wrapping_sub(size)
+}
+
+/// `layout` with its size rounded up to a multiple of its alignment, as for
+/// the stride of an array.
+#[inline]
+pub const fn pad_to_align(layout: Layout) -> Layout {
+    let size = layout.size() + padding_needed_for(layout, layout.align());
+    // Cannot overflow: the rounded size of a `Layout` fits in `isize`.
+    unsafe { Layout::from_size_align_unchecked(size, layout.align()) }
+}
+
+/// `layout` with its alignment raised to at least `align`.
+#[inline]
+pub const fn align_to(layout: Layout, align: usize) -> Result<Layout, LayoutError> {
+    let align = if align > layout.align() { align } else { layout.align() };
+    from_size_align(layout.size(), align)
+}
+
+/// Layout of `next` placed right after `layout`, at its alignment, with the
+/// offset of `next` in it. The result is not padded: use `pad_to_align` to
+/// get the layout of a `repr(C)` struct.
+#[inline]
+pub const fn extend(layout: Layout, next: Layout) -> Result<(Layout, usize), LayoutError> {
+    let offset = match layout.size().checked_add(padding_needed_for(layout, next.align())) {
+        Some(offset) => offset,
+        None => return Err(LayoutError),
+    };
+    let size = match offset.checked_add(next.size()) {
+        Some(size) => size,
+        None => return Err(LayoutError),
+    };
+    let align = if next.align() > layout.align() { next.align() } else { layout.align() };
+    match from_size_align(size, align) {
+        Ok(layout) => Ok((layout, offset)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Layout of `n` copies of `layout`, each padded to its alignment, with the
+/// distance between two copies.
+#[inline]
+pub const fn repeat(layout: Layout, n: usize) -> Result<(Layout, usize), LayoutError> {
+    let stride = pad_to_align(layout).size();
+    match stride.checked_mul(n) {
+        Some(size) => match from_size_align(size, layout.align()) {
+            Ok(layout) => Ok((layout, stride)),
+            Err(err) => Err(err),
+        },
+        None => Err(LayoutError),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{align_to, array, extend, pad_to_align, padding_needed_for, repeat};
+    use core::alloc::Layout;
+
+    #[test]
+    fn matches_the_core_layout_math() {
+        let odd = Layout::from_size_align(5, 4).unwrap();
+        assert_eq!(padding_needed_for(odd, 4), 3);
+        assert_eq!(padding_needed_for(odd, 1), 0);
+        assert_eq!(pad_to_align(odd), odd.pad_to_align());
+        assert_eq!(array::<u32>(7).unwrap(), Layout::array::<u32>(7).unwrap());
+
+        let next = Layout::new::<u64>();
+        assert_eq!(extend(odd, next).unwrap(), odd.extend(next).unwrap());
+        assert_eq!(repeat(odd, 3).unwrap(), (Layout::from_size_align(24, 4).unwrap(), 8));
+        assert_eq!(align_to(odd, 64).unwrap(), odd.align_to(64).unwrap());
+        assert!(repeat(odd, usize::MAX / 4).is_err());
+        assert!(extend(Layout::new::<u8>(), array::<u8>(isize::MAX as usize).unwrap()).is_err());
+    }
+}
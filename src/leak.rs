@@ -0,0 +1,187 @@
+use crate::{
+    tag::{current_index, tag_at},
+    Tag,
+};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::{
+    any, fmt,
+    panic::Location,
+    sync::atomic::{AtomicBool, Ordering::*},
+};
+use std::{
+    backtrace::Backtrace,
+    sync::{Mutex, MutexGuard},
+};
+
+static LIVE: Mutex<BTreeMap<usize, Site>> = Mutex::new(BTreeMap::new());
+
+static CAPTURE_BACKTRACES: AtomicBool = AtomicBool::new(false);
+
+/// Where a live block was allocated.
+struct Site {
+    size: usize,
+    tag: usize,
+    location: &'static Location<'static>,
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+/// A block allocated by `OwnedAlloc`, `UninitAlloc` or `RawVec` that was not
+/// freed yet, with the call that allocated it.
+#[derive(Debug, Clone)]
+pub struct LiveAllocation {
+    /// Address of the block.
+    pub address: usize,
+    /// Size of the block in bytes.
+    pub size: usize,
+    /// Tag current when the block was allocated.
+    pub tag: Tag,
+    /// The call to the allocating function (e.g. `OwnedAlloc::new` or
+    /// `RawVec::with_capacity`).
+    pub location: &'static Location<'static>,
+    /// The stack at allocation time, if backtraces were captured then.
+    pub backtrace: Option<Arc<Backtrace>>,
+}
+
+impl fmt::Display for LiveAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at {:#x} tagged {} allocated at {}",
+            self.size, self.address, self.tag, self.location
+        )?;
+        if let Some(backtrace) = &self.backtrace {
+            write!(f, "\n{}", backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// Enables or disables capturing a backtrace for each allocation recorded
+/// from now on. Disabled by default, since it is slow; the call site is
+/// always recorded.
+#[inline]
+pub fn capture_backtraces(enabled: bool) {
+    CAPTURE_BACKTRACES.store(enabled, Relaxed);
+}
+
+/// The blocks allocated by this crate and not freed yet, by address. Called
+/// at the end of a program or test, it names the call that produced each
+/// leaked block.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{leak::live_allocations, RawVec};
+///
+/// let line = line!() + 1;
+/// let buf = RawVec::<u64>::with_capacity(16);
+///
+/// let address = buf.raw().as_ptr() as usize;
+/// let site = live_allocations().into_iter().find(|live| live.address == address).unwrap();
+/// assert_eq!(site.size, 128);
+/// assert_eq!(site.location.line(), line);
+/// ```
+pub fn live_allocations() -> Vec<LiveAllocation> {
+    lock()
+        .iter()
+        .map(|(&address, site)| LiveAllocation {
+            address,
+            size: site.size,
+            tag: tag_at(site.tag),
+            location: site.location,
+            backtrace: site.backtrace.clone(),
+        })
+        .collect()
+}
+
+/// Records a block of `size` bytes at `ptr`, allocated by the caller.
+#[track_caller]
+pub(crate) fn record(ptr: *const u8, size: usize) {
+    let site = Site {
+        size,
+        tag: current_index(),
+        location: Location::caller(),
+        backtrace: if CAPTURE_BACKTRACES.load(Relaxed) {
+            Some(Arc::new(Backtrace::force_capture()))
+        } else {
+            None
+        },
+    };
+    lock().insert(ptr as usize, site);
+}
+
+/// Moves the record of the block at `old` to `new`, now of `size` bytes.
+pub(crate) fn relocate(old: *const u8, new: *const u8, size: usize) {
+    let mut live = lock();
+    if let Some(mut site) = live.remove(&(old as usize)) {
+        site.size = size;
+        live.insert(new as usize, site);
+    }
+}
+
+/// Drops the record of the block at `ptr`, freed or handed over to a type
+/// that does not track allocations.
+pub(crate) fn forget(ptr: *const u8) {
+    lock().remove(&(ptr as usize));
+}
+
+/// Tests if the blocks of allocator `A` are recorded: only those of the
+/// global `Allocator` are. Other allocators may carve their blocks out of a
+/// recorded one, at the same address, so they must not touch the records.
+#[inline]
+pub(crate) fn tracks<A: ?Sized>() -> bool {
+    any::type_name::<A>() == any::type_name::<crate::Allocator>()
+}
+
+#[inline]
+fn lock() -> MutexGuard<'static, BTreeMap<usize, Site>> {
+    LIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::live_allocations;
+    use crate::{alloc_api, OwnedAlloc, RawVec, UninitAlloc};
+    use core::{alloc::Layout, ptr::NonNull};
+
+    #[test]
+    fn freed_blocks_are_forgotten() {
+        let line = line!() + 1;
+        let alloc = OwnedAlloc::new([0u32; 8]);
+        let address = alloc.raw().as_ptr() as usize;
+        let find = || live_allocations().into_iter().find(|live| live.address == address);
+
+        let site = find().unwrap();
+        assert_eq!(site.location.file(), file!());
+        assert_eq!(site.location.line(), line);
+
+        drop(alloc.drop_in_place());
+        assert!(find().is_none());
+    }
+
+    /// Hands out the start of a block it does not own.
+    struct Carve(NonNull<u8>);
+
+    unsafe impl alloc_api::Allocator for Carve {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, alloc_api::AllocError> {
+            Ok(NonNull::slice_from_raw_parts(self.0, layout.size()))
+        }
+
+        unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+    }
+
+    #[test]
+    fn other_allocators_keep_the_records() {
+        let buf = RawVec::<u64>::with_capacity(4);
+        let address = buf.raw().as_ptr() as usize;
+        let find = || live_allocations().into_iter().find(|live| live.address == address);
+
+        let carve = Carve(buf.raw().cast());
+        drop(UninitAlloc::<u64, _>::new_in(&carve));
+        drop(RawVec::<u64, _>::with_capacity_in(2, &carve));
+        assert_eq!(find().unwrap().size, 32);
+        drop(buf);
+        assert!(find().is_none());
+    }
+}
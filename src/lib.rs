@@ -1,74 +1,342 @@
 #![no_std]
 #![allow(clippy::missing_safety_doc)]
-#![feature(allocator_api)]
-#![feature(const_option_ext)]
-#![feature(const_ptr_offset)]
-#![feature(const_convert)]
-#![feature(const_try)]
-#![feature(const_mut_refs)]
-#![feature(const_slice_from_raw_parts)]
-#![feature(alloc_layout_extra)]
-#![feature(nonnull_slice_from_raw_parts)]
-#![feature(const_ptr_as_ref)]
-#![feature(const_maybe_uninit_write)]
-#![feature(const_box)]
-#![feature(const_ptr_write)]
-#![feature(box_into_inner)]
-#![feature(const_ptr_read)]
-#![feature(const_maybe_uninit_as_mut_ptr)]
-#![feature(const_eval_select)]
-#![feature(const_precise_live_drops)]
-#![feature(const_trait_impl)]
-#![feature(const_fn_trait_bound)]
-#![feature(const_option)]
-#![feature(unboxed_closures)]
-#![feature(slice_ptr_get)]
-#![feature(slice_ptr_len)]
+#![cfg_attr(feature = "panic-free", allow(deprecated))]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+#![cfg_attr(feature = "nightly", feature(const_convert))]
+#![cfg_attr(feature = "nightly", feature(const_precise_live_drops))]
+#![cfg_attr(feature = "nightly", feature(const_trait_impl, const_default))]
+#![cfg_attr(feature = "nightly", feature(unboxed_closures, fn_traits))]
+#![cfg_attr(feature = "nightly", feature(tuple_trait))]
+#![cfg_attr(feature = "nightly", feature(unsize))]
+#![cfg_attr(feature = "nightly", feature(coerce_unsized, dispatch_from_dyn))]
+#![cfg_attr(feature = "nightly", feature(clone_to_uninit, layout_for_ptr))]
+#![cfg_attr(all(feature = "nightly", feature = "std"), feature(read_buf, core_io_borrowed_buf))]
+#![cfg_attr(feature = "sanitizer-detect", feature(cfg_sanitize))]
+#![cfg_attr(feature = "const-heap", feature(core_intrinsics, const_heap, const_eval_select))]
+#![cfg_attr(feature = "const-heap", allow(internal_features))]
 
+/// A `const` trait impl with the `nightly` feature, and a plain one on stable
+/// Rust, which rejects the syntax even in code configured out.
+#[cfg(feature = "nightly")]
+macro_rules! const_impl {
+    (impl<$($param:ident),*> $($rest:tt)*) => {
+        impl<$($param),*> const $($rest)*
+    };
+    (impl $($rest:tt)*) => {
+        impl const $($rest)*
+    };
+}
+
+#[cfg(not(feature = "nightly"))]
+macro_rules! const_impl {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
+
+/// A `const fn` with the `nightly` feature, and a plain one on stable Rust,
+/// for functions relying on unstable `const` features.
+#[cfg(feature = "nightly")]
+macro_rules! const_fn {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "nightly"))]
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis const $($rest:tt)*) => {
+        $(#[$attr])* $vis $($rest)*
+    };
+}
+
+/// A function panicking on allocation failure or overflow, deprecated with
+/// the `panic-free` feature so that callers are warned of every use, without
+/// removing anything from the API. Trait impls (`Clone`, `Default`, `From`,
+/// `FromIterator`) cannot be flagged, and still allocate infallibly.
+#[cfg(not(feature = "panic-free"))]
+macro_rules! panicking {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(feature = "panic-free")]
+macro_rules! panicking {
+    ($($item:tt)*) => {
+        #[deprecated(note = "panics on allocation failure or overflow")]
+        $($item)*
+    };
+}
+
+/// The methods of an `alloc_api::Allocator` impl forwarding every call to the
+/// allocator `$inner` evaluates to, with `self` bound to `$this`.
+macro_rules! forward_allocator {
+    (|$this:ident| $inner:expr) => {
+        #[inline]
+        fn allocate(
+            &self,
+            layout: Layout,
+        ) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+            let $this = self;
+            $inner.allocate(layout)
+        }
+
+        #[inline]
+        fn allocate_zeroed(
+            &self,
+            layout: Layout,
+        ) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+            let $this = self;
+            $inner.allocate_zeroed(layout)
+        }
+
+        #[inline]
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            let $this = self;
+            $inner.deallocate(ptr, layout)
+        }
+
+        #[inline]
+        unsafe fn grow(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+            let $this = self;
+            $inner.grow(ptr, old_layout, new_layout)
+        }
+
+        #[inline]
+        unsafe fn grow_zeroed(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+            let $this = self;
+            $inner.grow_zeroed(ptr, old_layout, new_layout)
+        }
+
+        #[inline]
+        unsafe fn shrink(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+            let $this = self;
+            $inner.shrink(ptr, old_layout, new_layout)
+        }
+    };
+}
+
+pub mod align;
+pub mod aligned;
+pub mod alloc_api;
+#[cfg(feature = "allocator-api2")]
+pub mod api2;
+pub mod arena;
+mod asan;
+pub mod batch;
+pub mod batch_init;
+pub mod brk;
+pub mod buffer_pool;
+pub mod bump;
+pub mod bumped;
 pub mod cache;
+pub mod cache_padded;
+pub mod canary;
+pub mod compact;
+pub mod cow;
+pub mod cursor;
+pub mod defer;
+pub mod deterministic;
+pub mod dma;
+pub mod epoch;
 pub mod error;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod frag;
+pub mod freelist;
+pub mod gen_pool;
+pub mod hazard;
+pub mod header_slice;
+pub mod heap;
+pub mod layout;
+#[cfg(feature = "track-callers")]
+pub mod leak;
+pub mod lifetime;
+pub mod limit;
+#[cfg(feature = "std")]
+pub mod local_cache;
 pub mod maybe_uninit;
+pub mod meta;
+#[cfg(feature = "metrics")]
+pub mod metered;
+#[cfg(feature = "os")]
+pub mod mmap;
 pub mod owned;
+pub mod page;
+#[cfg(feature = "bytemuck")]
+mod pod;
+pub mod pool;
+pub mod raw_buckets;
+pub mod raw_grid;
+pub mod raw_ring;
+pub mod raw_soa;
 pub mod raw_vec;
+pub mod region32;
+pub mod retry;
+pub mod rt;
+pub mod scratch;
+pub mod scope;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod sharded;
+pub mod shared;
+pub mod size_profile;
+#[cfg(feature = "os")]
+pub mod shm;
+pub mod slab;
+mod slots;
+pub mod snapshot;
+pub mod static_pool;
+pub mod str_arena;
+#[cfg(feature = "os")]
+pub mod system;
+pub mod tag;
+pub mod tlsf;
+#[cfg(feature = "tracing")]
+pub mod trace;
+pub mod trim;
+pub mod try_clone;
+pub mod tuple;
+pub mod type_stats;
+#[cfg(feature = "os")]
+mod sys;
 pub mod uninit;
+mod valgrind;
+pub mod vectored;
+#[cfg(feature = "os")]
+pub mod virtual_vec;
 use core::{
     alloc::{GlobalAlloc, Layout},
     ptr::NonNull,
 };
 
 use alloc::alloc::{alloc_zeroed, dealloc};
+pub use aligned::*;
+#[cfg(feature = "allocator-api2")]
+pub use api2::*;
+pub use arena::*;
+pub use batch::*;
+pub use batch_init::*;
+pub use brk::*;
+pub use buffer_pool::*;
+pub use bump::*;
+pub use bumped::*;
 pub use cache::*;
+pub use cache_padded::*;
+pub use canary::*;
+pub use compact::*;
+pub use cow::*;
+pub use cursor::*;
+pub use defer::*;
+pub use deterministic::*;
+pub use dma::*;
 pub use error::*;
+pub use events::*;
+pub use frag::*;
+pub use freelist::*;
+pub use gen_pool::*;
+pub use header_slice::*;
+pub use heap::*;
+pub use lifetime::*;
+pub use limit::*;
+#[cfg(feature = "std")]
+pub use local_cache::*;
 pub use maybe_uninit::*;
+pub use meta::*;
+#[cfg(feature = "metrics")]
+pub use metered::*;
+#[cfg(feature = "os")]
+pub use mmap::*;
 pub use owned::*;
+pub use page::*;
+pub use pool::*;
+pub use raw_buckets::*;
+pub use raw_grid::*;
+pub use raw_ring::*;
+pub use raw_soa::*;
 pub use raw_vec::*;
+pub use region32::*;
+pub use retry::*;
+pub use rt::*;
+pub use scratch::*;
+pub use scope::*;
+pub use sharded::*;
+pub use shared::*;
+pub use size_profile::*;
+#[cfg(feature = "os")]
+pub use shm::*;
+pub use slab::*;
+pub use snapshot::*;
+pub use static_pool::*;
+pub use str_arena::*;
+#[cfg(feature = "os")]
+pub use system::*;
+pub use tag::{Tag, TagReport, TagScope, TaggedAlloc};
+pub use tlsf::*;
+pub use trim::*;
+pub use try_clone::*;
+pub use tuple::*;
+pub use type_stats::*;
 pub use uninit::*;
+pub use vectored::*;
+#[cfg(feature = "os")]
+pub use virtual_vec::*;
 
 extern crate alloc;
-pub struct Allocator {}
+#[cfg(feature = "std")]
+extern crate std;
 
-static mut ALLOCATOR: Allocator = Allocator {};
+#[derive(Debug, Clone, Copy)]
+pub struct Allocator {}
 
-///
-///
-/// #[global_allocator]
-/// static ALLOCATOR: SimpleAllocator = SimpleAllocator {
-///     arena: UnsafeCell::new([0x55; ARENA_SIZE]),
+static ALLOCATOR: Allocator = Allocator {};
 
 unsafe impl alloc::alloc::GlobalAlloc for Allocator {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        scope::record(layout.size());
+        #[cfg(feature = "ffi")]
+        if let Some(foreign) = ffi::registered() {
+            return foreign.alloc_zeroed(layout);
+        }
+        if let Some(heap) = heap::installed() {
+            let ptr = heap.alloc(layout);
+            if !ptr.is_null() {
+                core::ptr::write_bytes(ptr, 0, layout.size());
+            }
+            return ptr;
+        }
         let (size, align) = (layout.size(), layout.align());
         let ptr = alloc_zeroed(layout);
         if !ptr.is_null() {
             let offset = ptr.align_offset(align);
-            if offset == 0 {
+            let ptr = if offset == 0 {
                 ptr
             } else {
                 let new_ptr = ptr.add(offset);
                 // SAFETY: the region from `new_ptr` of size `size` is guaranteed to be valid for writes.
                 core::ptr::write_bytes(new_ptr, 0, size);
                 new_ptr
-            }
+            };
+            #[cfg(feature = "tracing")]
+            trace::alloc(ptr, size);
+            ptr
         } else {
             ptr
         }
@@ -76,8 +344,17 @@ unsafe impl alloc::alloc::GlobalAlloc for Allocator {
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         let (size, _) = (layout.size(), layout.align());
+        #[cfg(feature = "tracing")]
+        trace::free(ptr, size);
         // SAFETY: the region from `ptr` of size `size` is guaranteed to be valid for writes.
         core::ptr::write_bytes(ptr, 0, size);
+        #[cfg(feature = "ffi")]
+        if let Some(foreign) = ffi::registered() {
+            return foreign.dealloc(ptr, layout);
+        }
+        if let Some(heap) = heap::installed() {
+            return heap.dealloc(ptr, layout);
+        }
         // SAFETY: the region from `ptr` of size `size` is guaranteed to be valid for writes.
         dealloc(ptr, layout)
     }
@@ -115,33 +392,45 @@ unsafe impl alloc::alloc::GlobalAlloc for Allocator {
     }
 }
 
-unsafe impl core::alloc::Allocator for Allocator {
-    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+unsafe impl crate::alloc_api::Allocator for Allocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
         self.alloc_impl(layout, false)
     }
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
-        if layout.size() == 0 {
+        if layout.size() != 0 {
             GlobalAlloc::dealloc(&ALLOCATOR, ptr.as_ptr(), layout);
         }
     }
     fn allocate_zeroed(
         &self,
         layout: Layout,
-    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+    ) -> Result<core::ptr::NonNull<[u8]>, crate::alloc_api::AllocError> {
         self.alloc_impl(layout, true)
     }
 }
 
 impl Allocator {
-    pub fn new() -> Allocator {
+    pub const fn new() -> Allocator {
         Allocator {}
     }
 
+    /// Whether the memory comes from the global allocator, as the one of
+    /// `Vec` and `Box` does, rather than from a registered foreign allocator
+    /// or an installed heap.
+    #[inline]
+    pub(crate) fn is_global() -> bool {
+        #[cfg(feature = "ffi")]
+        if ffi::registered().is_some() {
+            return false;
+        }
+        heap::installed().is_none()
+    }
+
     fn alloc_impl(
         &self,
         layout: Layout,
         zeroed: bool,
-    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+    ) -> Result<core::ptr::NonNull<[u8]>, crate::alloc_api::AllocError> {
         match layout.size() {
             0 => Ok(NonNull::slice_from_raw_parts(
                 unsafe { NonNull::new_unchecked(layout.align() as *mut u8) },
@@ -156,36 +445,12 @@ impl Allocator {
                 };
                 let ptr = match NonNull::new(raw_ptr) {
                     Some(ptr) => ptr,
-                    None => return Err(core::alloc::AllocError),
+                    None => return Err(crate::alloc_api::AllocError),
                 };
                 Ok(NonNull::slice_from_raw_parts(ptr, size))
             },
         }
     }
-
-    unsafe fn deallocate<T>(&self, ptr: *mut T, layout: Layout) {
-        GlobalAlloc::dealloc(self, ptr as *mut u8, layout)
-    }
-
-    unsafe fn reallocate<T>(
-        &self,
-        ptr: *mut T,
-        layout: core::alloc::Layout,
-        new_size: usize,
-    ) -> *mut T {
-        // SAFETY: the caller must ensure that the `new_size` does not overflow.
-        // `layout.align()` comes from a `Layout` and is thus guaranteed to be valid.
-        let new_layout = core::alloc::Layout::from_size_align_unchecked(new_size, layout.align());
-        // SAFETY: the caller must ensure that `new_layout` is greater than zero.
-        let new_ptr = self.alloc(new_layout) as *mut T;
-        if !new_ptr.is_null() {
-            // SAFETY: the previously allocated block cannot overlap the newly allocated block.
-            // The safety contract for `dealloc` must be upheld by the caller.
-            core::ptr::copy_nonoverlapping(ptr, new_ptr, core::cmp::min(layout.size(), new_size));
-            self.deallocate(ptr, layout);
-        }
-        new_ptr
-    }
 }
 
 impl Default for Allocator {
@@ -0,0 +1,437 @@
+use crate::{
+    alloc_api::AllocError,
+    tag::{current_index, tag_at, MAX_TAGS},
+    Tag,
+};
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    fmt,
+    hint,
+    mem,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::*},
+};
+
+/// Number of buckets of a lifetime histogram: bucket `i` counts the
+/// lifetimes of `2^i` to `2^(i + 1) - 1` ticks, bucket 0 counting zero too.
+pub const LIFETIME_BUCKETS: usize = u64::BITS as usize;
+
+/// Groups are power-of-two size classes (up to `2^63` bytes) or tags.
+const GROUPS: usize = 64;
+
+const _: () = assert!(MAX_TAGS <= GROUPS);
+
+/// A source of time for a `LifetimeProfiler`, in ticks of any unit.
+pub trait Clock {
+    /// The current time. Must never decrease.
+    fn now(&self) -> u64;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> u64,
+{
+    #[inline]
+    fn now(&self) -> u64 {
+        self()
+    }
+}
+
+/// A logical clock ticking once per allocation or deallocation made through
+/// the profiler, so lifetimes are counted in allocator events. Deterministic,
+/// and needs no timer.
+#[derive(Debug, Default)]
+pub struct EventClock {
+    events: AtomicUsize,
+}
+
+impl EventClock {
+    /// A clock starting at zero.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            events: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Clock for EventClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        self.events.fetch_add(1, Relaxed) as u64
+    }
+}
+
+/// A clock counting nanoseconds since its creation.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl MonotonicClock {
+    /// A clock starting now.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for MonotonicClock {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for MonotonicClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_nanos() as u64
+    }
+}
+
+/// How a `LifetimeProfiler` groups allocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// By size, rounded up to a power of two.
+    Size,
+    /// By the tag current at allocation time.
+    Tag,
+}
+
+/// A group of allocations, as reported by a `LifetimeProfiler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifetimeGroup {
+    /// Allocations of more than half this size, and up to this size.
+    Size(usize),
+    /// Allocations made with this tag current.
+    Tag(Tag),
+}
+
+/// Lifetimes of the allocations of one group, as returned by
+/// `LifetimeProfiler::report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifetimeReport {
+    /// The group.
+    pub group: LifetimeGroup,
+    /// How long freed blocks lived, per bucket.
+    pub freed: [usize; LIFETIME_BUCKETS],
+    /// How old live blocks are, per bucket.
+    pub live: [usize; LIFETIME_BUCKETS],
+}
+
+impl LifetimeReport {
+    /// Number of blocks freed so far.
+    #[inline]
+    pub fn freed_count(&self) -> usize {
+        self.freed.iter().sum()
+    }
+
+    /// Number of blocks alive.
+    #[inline]
+    pub fn live_count(&self) -> usize {
+        self.live.iter().sum()
+    }
+}
+
+/// Bookkeeping placed right before the block handed out. Live blocks are
+/// linked together so their ages can be reported.
+#[repr(C)]
+struct Header {
+    prev: *mut Header,
+    next: *mut Header,
+    born: u64,
+    group: usize,
+}
+
+/// A wrapper measuring how long allocations live, grouped by size class or
+/// by tag: one histogram of the lifetimes of freed blocks, and one of the
+/// ages of live blocks, per group. Short-lived groups are good arena
+/// material; long-lived groups of one size, pool material.
+///
+/// Every block carries a header with its birth time, and live blocks are
+/// linked in a list behind a spin lock, so this is a profiling tool rather
+/// than something to ship.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, EventClock, Grouping, LifetimeGroup, LifetimeProfiler};
+///
+/// let alloc = LifetimeProfiler::new(Allocator::new(), EventClock::new(), Grouping::Size);
+/// let layout = Layout::new::<[u8; 48]>();
+/// let kept = alloc.allocate(layout).unwrap();
+/// for _ in 0 .. 10 {
+///     let temporary = alloc.allocate(layout).unwrap();
+///     unsafe { alloc.deallocate(temporary.cast(), layout) };
+/// }
+///
+/// let report = alloc.report();
+/// assert_eq!(report[0].group, LifetimeGroup::Size(64));
+/// assert_eq!(report[0].freed_count(), 10);
+/// assert_eq!(report[0].live_count(), 1);
+/// unsafe { alloc.deallocate(kept.cast(), layout) };
+/// ```
+pub struct LifetimeProfiler<A, C>
+where
+    A: crate::alloc_api::Allocator,
+    C: Clock,
+{
+    backend: A,
+    clock: C,
+    grouping: Grouping,
+    freed: [[AtomicUsize; LIFETIME_BUCKETS]; GROUPS],
+    live: LiveList,
+}
+
+/// Head of the list of live blocks, with its lock.
+struct LiveList {
+    locked: AtomicBool,
+    head: UnsafeCell<*mut Header>,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ZEROS: [AtomicUsize; LIFETIME_BUCKETS] = [ZERO; LIFETIME_BUCKETS];
+
+impl<A, C> LifetimeProfiler<A, C>
+where
+    A: crate::alloc_api::Allocator,
+    C: Clock,
+{
+    /// Profiles the allocations of `backend`, timed with `clock`.
+    #[inline]
+    pub const fn new(backend: A, clock: C, grouping: Grouping) -> Self {
+        Self {
+            backend,
+            clock,
+            grouping,
+            freed: [ZEROS; GROUPS],
+            live: LiveList {
+                locked: AtomicBool::new(false),
+                head: UnsafeCell::new(ptr::null_mut()),
+            },
+        }
+    }
+
+    /// The allocator being profiled.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// The clock timing the allocations.
+    #[inline]
+    pub const fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// How allocations are grouped.
+    #[inline]
+    pub const fn grouping(&self) -> Grouping {
+        self.grouping
+    }
+
+    /// Reports the lifetimes of every group which saw an allocation, with
+    /// the ages of live blocks measured now. Size groups come smallest
+    /// first, tags in registration order.
+    pub fn report(&self) -> Vec<LifetimeReport> {
+        let mut live = alloc::vec![[0; LIFETIME_BUCKETS]; GROUPS];
+        self.with_live(|head| {
+            let now = self.clock.now();
+            let mut header = *head;
+            while !header.is_null() {
+                unsafe {
+                    let age = now.saturating_sub((*header).born);
+                    live[(*header).group][bucket(age)] += 1;
+                    header = (*header).next;
+                }
+            }
+        });
+        live.into_iter()
+            .enumerate()
+            .map(|(index, live)| LifetimeReport {
+                group: match self.grouping {
+                    Grouping::Size => LifetimeGroup::Size(1 << index),
+                    Grouping::Tag => LifetimeGroup::Tag(tag_at(index)),
+                },
+                freed: self.freed[index].each_ref().map(|count| count.load(Relaxed)),
+                live,
+            })
+            .filter(|report| report.freed_count() != 0 || report.live_count() != 0)
+            .collect()
+    }
+
+    /// The group of an allocation made now.
+    #[inline]
+    fn group(&self, layout: Layout) -> usize {
+        match self.grouping {
+            Grouping::Size => layout.size().next_power_of_two().trailing_zeros() as usize,
+            Grouping::Tag => current_index(),
+        }
+    }
+
+    fn with_live<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut *mut Header) -> R,
+    {
+        while self
+            .live
+            .locked
+            .compare_exchange_weak(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let ret = f(unsafe { &mut *self.live.head.get() });
+        self.live.locked.store(false, Release);
+        ret
+    }
+
+    #[inline]
+    unsafe fn header(ptr: NonNull<u8>) -> *mut Header {
+        ptr.as_ptr().sub(mem::size_of::<Header>()).cast()
+    }
+
+    /// The layout of the block allocated from the backend, and the offset of
+    /// the block handed out in it.
+    #[inline]
+    fn outer(layout: Layout) -> Option<(Layout, usize)> {
+        let (outer, offset) = Layout::new::<Header>().extend(layout).ok()?;
+        Some((outer.pad_to_align(), offset))
+    }
+}
+
+/// The histogram bucket of a lifetime.
+#[inline]
+fn bucket(ticks: u64) -> usize {
+    (ticks | 1).ilog2() as usize
+}
+
+unsafe impl<A, C> crate::alloc_api::Allocator for LifetimeProfiler<A, C>
+where
+    A: crate::alloc_api::Allocator,
+    C: Clock,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (outer, offset) = Self::outer(layout).ok_or(AllocError)?;
+        let base = self.backend.allocate(outer)?.cast::<u8>();
+        unsafe {
+            let ptr = NonNull::new_unchecked(base.as_ptr().add(offset));
+            let header = Self::header(ptr);
+            header.write(Header {
+                prev: ptr::null_mut(),
+                next: ptr::null_mut(),
+                born: self.clock.now(),
+                group: self.group(layout),
+            });
+            self.with_live(|head| {
+                (*header).next = *head;
+                if !head.is_null() {
+                    (**head).prev = header;
+                }
+                *head = header;
+            });
+            Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let header = Self::header(ptr);
+        self.with_live(|head| {
+            let (prev, next) = ((*header).prev, (*header).next);
+            if !next.is_null() {
+                (*next).prev = prev;
+            }
+            if prev.is_null() {
+                *head = next;
+            } else {
+                (*prev).next = next;
+            }
+        });
+        let lifetime = self.clock.now().saturating_sub((*header).born);
+        self.freed[(*header).group][bucket(lifetime)].fetch_add(1, Relaxed);
+        let (outer, offset) = Self::outer(layout).unwrap();
+        let base = NonNull::new_unchecked(ptr.as_ptr().sub(offset));
+        self.backend.deallocate(base, outer);
+    }
+}
+
+unsafe impl<A, C> Send for LifetimeProfiler<A, C>
+where
+    A: crate::alloc_api::Allocator + Send,
+    C: Clock + Send,
+{
+}
+
+unsafe impl<A, C> Sync for LifetimeProfiler<A, C>
+where
+    A: crate::alloc_api::Allocator + Sync,
+    C: Clock + Sync,
+{
+}
+
+impl<A, C> fmt::Debug for LifetimeProfiler<A, C>
+where
+    A: crate::alloc_api::Allocator,
+    C: Clock,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LifetimeProfiler {{ grouping: {:?} }}", self.grouping)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EventClock, Grouping, LifetimeGroup, LifetimeProfiler};
+    use crate::{alloc_api::Allocator as _, Allocator, Tag};
+    use alloc::vec::Vec;
+    use core::alloc::Layout;
+
+    #[test]
+    fn tags_split_short_and_long_lived() {
+        let alloc = LifetimeProfiler::new(Allocator::new(), EventClock::new(), Grouping::Tag);
+        let layout = Layout::from_size_align(24, 64).unwrap();
+        let frame = Tag::Name("lifetime-frame");
+        let assets = Tag::Name("lifetime-assets");
+
+        let kept: Vec<_> = {
+            let _scope = assets.scope();
+            (0 .. 3).map(|_| alloc.allocate(layout).unwrap()).collect()
+        };
+        {
+            let _scope = frame.scope();
+            for _ in 0 .. 100 {
+                let block = alloc.allocate(layout).unwrap();
+                assert_eq!(block.cast::<u8>().as_ptr() as usize % 64, 0);
+                unsafe { alloc.deallocate(block.cast(), layout) };
+            }
+        }
+
+        let report = alloc.report();
+        let frame = report.iter().find(|r| r.group == LifetimeGroup::Tag(frame)).unwrap();
+        assert_eq!(frame.freed[0], 100);
+        assert_eq!(frame.live_count(), 0);
+        let assets = report.iter().find(|r| r.group == LifetimeGroup::Tag(assets)).unwrap();
+        assert_eq!(assets.freed_count(), 0);
+        assert_eq!(assets.live_count(), 3);
+        assert_eq!(assets.live[7], 3);
+
+        for block in kept {
+            unsafe { alloc.deallocate(block.cast(), layout) };
+        }
+    }
+}
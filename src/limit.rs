@@ -0,0 +1,215 @@
+use crate::alloc_api::AllocError;
+use core::{
+    alloc::Layout,
+    fmt,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
+
+/// A wrapper capping the bytes allocated through it, so one subsystem can't
+/// starve the rest of the process: requests that would take the usage past
+/// the limit fail with `AllocError`, and the usage, peak and failures are
+/// counted.
+///
+/// The counters account the size of the layouts requested, not the memory
+/// actually used by the backend.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, LimitedAlloc};
+///
+/// let alloc = LimitedAlloc::new(Allocator::new(), 100);
+/// let layout = Layout::new::<[u8; 60]>();
+/// let first = alloc.allocate(layout).unwrap();
+/// assert!(alloc.allocate(layout).is_err());
+/// assert_eq!(alloc.used(), 60);
+/// assert_eq!(alloc.failures(), 1);
+///
+/// unsafe { alloc.deallocate(first.cast(), layout) };
+/// assert!(alloc.allocate(layout).is_ok());
+/// ```
+pub struct LimitedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    backend: A,
+    limit: AtomicUsize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl<A> LimitedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Caps the allocations made through `backend` to `limit` bytes.
+    #[inline]
+    pub const fn new(backend: A, limit: usize) -> Self {
+        Self {
+            backend,
+            limit: AtomicUsize::new(limit),
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// The allocator behind the limit.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// The maximum number of bytes allocated at once.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit.load(Relaxed)
+    }
+
+    /// Changes the limit. Lowering it below the usage frees nothing, but
+    /// makes allocations fail until the usage drops below the new limit.
+    #[inline]
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Relaxed);
+    }
+
+    /// Bytes currently allocated.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.load(Relaxed)
+    }
+
+    /// Bytes which can still be allocated.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.limit().saturating_sub(self.used())
+    }
+
+    /// The highest usage seen so far.
+    #[inline]
+    pub fn peak(&self) -> usize {
+        self.peak.load(Relaxed)
+    }
+
+    /// Number of allocations refused, by the limit or the backend.
+    #[inline]
+    pub fn failures(&self) -> usize {
+        self.failures.load(Relaxed)
+    }
+
+    /// Takes `bytes` off the available bytes, if there are enough of them.
+    fn reserve(&self, bytes: usize) -> Result<(), AllocError> {
+        let limit = self.limit();
+        let reserved = self.used.fetch_update(Relaxed, Relaxed, |used| {
+            used.checked_add(bytes).filter(|&total| total <= limit)
+        });
+        match reserved {
+            Ok(used) => {
+                self.peak.fetch_max(used + bytes, Relaxed);
+                Ok(())
+            },
+            Err(_) => Err(self.fail()),
+        }
+    }
+
+    #[inline]
+    fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Relaxed);
+    }
+
+    #[inline]
+    fn fail(&self) -> AllocError {
+        self.failures.fetch_add(1, Relaxed);
+        AllocError
+    }
+}
+
+unsafe impl<A> crate::alloc_api::Allocator for LimitedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.reserve(layout.size())?;
+        self.backend.allocate(layout).map_err(|_| {
+            self.release(layout.size());
+            self.fail()
+        })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.backend.deallocate(ptr, layout);
+        self.release(layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let extra = new_layout.size() - old_layout.size();
+        self.reserve(extra)?;
+        self.backend.grow(ptr, old_layout, new_layout).map_err(|_| {
+            self.release(extra);
+            self.fail()
+        })
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.backend.shrink(ptr, old_layout, new_layout)?;
+        self.release(old_layout.size() - new_layout.size());
+        Ok(block)
+    }
+}
+
+impl<A> fmt::Debug for LimitedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LimitedAlloc {{ used: {}, limit: {}, peak: {} }}",
+            self.used(),
+            self.limit(),
+            self.peak()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LimitedAlloc;
+    use crate::{alloc_api::Allocator as _, Allocator};
+    use core::alloc::Layout;
+
+    #[test]
+    fn grow_and_shrink_are_accounted() {
+        let alloc = LimitedAlloc::new(Allocator::new(), 64);
+        let small = Layout::from_size_align(16, 8).unwrap();
+        let big = Layout::from_size_align(48, 8).unwrap();
+        let block = alloc.allocate(small).unwrap().cast::<u8>();
+
+        let block = unsafe { alloc.grow(block, small, big) }.unwrap().cast::<u8>();
+        assert_eq!(alloc.used(), 48);
+        let too_big = Layout::from_size_align(80, 8).unwrap();
+        assert!(unsafe { alloc.grow(block, big, too_big) }.is_err());
+        assert_eq!(alloc.used(), 48);
+
+        let block = unsafe { alloc.shrink(block, big, small) }.unwrap().cast::<u8>();
+        assert_eq!((alloc.used(), alloc.peak(), alloc.failures()), (16, 48, 1));
+        unsafe { alloc.deallocate(block, small) };
+        assert_eq!(alloc.available(), 64);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{Cache, Pool, Pooled};
+use alloc::vec::Vec;
+use core::{
+    cell::{RefCell, UnsafeCell},
+    fmt,
+    hint,
+    mem,
+    ops::Deref,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Declares a lazily initialized per-thread `LocalCache` or `LocalPool`,
+/// along with the `Depot` it drains into when the thread exits, so objects
+/// recycled by short-lived threads are not lost. The static is a
+/// `std::thread::LocalKey`, used through `with`.
+///
+/// A `Cache` takes its element type; a `Pool` also takes the function
+/// creating its objects, as `Pool::new` does.
+///
+/// # Example
+/// ```rust
+/// #[macro_use]
+/// extern crate owned_alloc;
+///
+/// thread_local_cache! {
+///     static BUFFERS: Cache<Vec<u8>>;
+///     static SCRATCH: Pool<Vec<u32>> = Vec::new;
+/// }
+///
+/// fn main() {
+///     BUFFERS.with(|cache| cache.store(Vec::with_capacity(4096)));
+///     let buf = BUFFERS.with(|cache| cache.take_or(Vec::new));
+///     assert_eq!(buf.capacity(), 4096);
+///
+///     let depot = std::thread::spawn(|| {
+///         BUFFERS.with(|cache| {
+///             cache.store(vec![1, 2, 3]);
+///             cache.depot()
+///         })
+///     })
+///     .join()
+///     .unwrap();
+///     assert_eq!(depot.len(), 1);
+///     assert_eq!(BUFFERS.with(|cache| cache.take()), Some(vec![1, 2, 3]));
+///
+///     SCRATCH.with(|pool| pool.try_acquire().unwrap().push(7));
+///     assert_eq!(SCRATCH.with(|pool| pool.idle()), 1);
+/// }
+/// ```
+#[macro_export]
+macro_rules! thread_local_cache {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident: Cache<$ty:ty>; $($rest:tt)*) => {
+        ::std::thread_local! {
+            $(#[$attr])*
+            $vis static $name: $crate::LocalCache<$ty> = {
+                static DEPOT: $crate::Depot<$ty> = $crate::Depot::new();
+                $crate::LocalCache::new(&DEPOT)
+            };
+        }
+        $crate::thread_local_cache!($($rest)*);
+    };
+    (
+        $(#[$attr:meta])* $vis:vis static $name:ident: Pool<$ty:ty> = $create:expr;
+        $($rest:tt)*
+    ) => {
+        ::std::thread_local! {
+            $(#[$attr])*
+            $vis static $name: $crate::LocalPool<$ty> = {
+                static DEPOT: $crate::Depot<$ty> = $crate::Depot::new();
+                $crate::LocalPool::new($create, &DEPOT)
+            };
+        }
+        $crate::thread_local_cache!($($rest)*);
+    };
+}
+
+/// Objects shared by the threads of a `thread_local_cache!`: their caches
+/// and pools move their objects here when the threads exit, and take them
+/// back before creating new ones.
+pub struct Depot<T> {
+    locked: AtomicBool,
+    items: UnsafeCell<Vec<T>>,
+}
+
+impl<T> Depot<T> {
+    /// Creates an empty depot. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            items: UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    /// Moves `value` into the depot.
+    #[inline]
+    pub fn push(&self, value: T) {
+        self.with_items(|items| items.push(value))
+    }
+
+    /// Takes the object moved into the depot last, if any.
+    #[inline]
+    pub fn pop(&self) -> Option<T> {
+        self.with_items(Vec::pop)
+    }
+
+    /// Number of objects in the depot.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.with_items(|items| items.len())
+    }
+
+    /// Tests if the depot holds no object.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the objects of the depot, returning how many there were.
+    #[inline]
+    pub fn clear(&self) -> usize {
+        let items = self.with_items(mem::take);
+        items.len()
+    }
+
+    fn with_items<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Vec<T>) -> R,
+    {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let ret = f(unsafe { &mut *self.items.get() });
+        self.locked.store(false, Ordering::Release);
+        ret
+    }
+}
+
+impl<T> Default for Depot<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Depot<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Depot {{ len: {} }}", self.len())
+    }
+}
+
+unsafe impl<T> Send for Depot<T> where T: Send {}
+unsafe impl<T> Sync for Depot<T> where T: Send {}
+
+/// A per-thread `Cache` declared by `thread_local_cache!`. It falls back to
+/// its `Depot` when empty, overflows into it instead of evicting, and moves
+/// its data there when dropped with its thread.
+pub struct LocalCache<T>
+where
+    T: 'static,
+{
+    cache: RefCell<Cache<T>>,
+    depot: &'static Depot<T>,
+}
+
+impl<T> LocalCache<T> {
+    /// Creates an empty cache draining into `depot`.
+    #[inline]
+    pub const fn new(depot: &'static Depot<T>) -> Self {
+        Self {
+            cache: RefCell::new(Cache::new()),
+            depot,
+        }
+    }
+
+    /// The depot of the cache.
+    #[inline]
+    pub const fn depot(&self) -> &'static Depot<T> {
+        self.depot
+    }
+
+    /// Stores data into the cache. If it already held some, the new data
+    /// goes to the depot.
+    #[inline]
+    pub fn store(&self, val: T) {
+        let mut cache = self.cache.borrow_mut();
+        match cache.take() {
+            Some(stored) => {
+                cache.store(stored);
+                self.depot.push(val);
+            },
+            None => cache.store(val),
+        }
+    }
+
+    /// Takes the data from the cache, or from the depot if the cache is
+    /// empty.
+    #[inline]
+    pub fn take(&self) -> Option<T> {
+        let stored = self.cache.borrow_mut().take();
+        stored.or_else(|| self.depot.pop())
+    }
+
+    /// Takes the data from the cache, or from the depot if the cache is
+    /// empty. If there was no data, the passed closure is called to produce
+    /// the returned data.
+    #[inline]
+    pub fn take_or<F>(&self, create: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        self.take().unwrap_or_else(create)
+    }
+}
+
+impl<T> Drop for LocalCache<T> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(stored) = self.cache.get_mut().take() {
+            self.depot.push(stored);
+        }
+    }
+}
+
+impl<T> fmt::Debug for LocalCache<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LocalCache {{ depot: {:?} }}", self.depot)
+    }
+}
+
+/// A per-thread `Pool` declared by `thread_local_cache!`. Acquiring takes an
+/// object from its `Depot` before creating one, and the idle objects move
+/// to the depot when the pool is dropped with its thread. The methods of
+/// `Pool` are reachable through `Deref`.
+pub struct LocalPool<T>
+where
+    T: 'static,
+{
+    pool: Pool<T>,
+    depot: &'static Depot<T>,
+}
+
+impl<T> LocalPool<T> {
+    /// Creates a pool making new objects with `create`, draining into
+    /// `depot`.
+    #[inline]
+    pub fn new(create: fn() -> T, depot: &'static Depot<T>) -> Self {
+        Self {
+            pool: Pool::new(create),
+            depot,
+        }
+    }
+
+    /// The depot of the pool.
+    #[inline]
+    pub const fn depot(&self) -> &'static Depot<T> {
+        self.depot
+    }
+
+    /// Checks out an idle object, one from the depot, or a new one.
+    #[inline]
+    pub fn try_acquire(&self) -> Option<Pooled<'_, T>> {
+        if self.pool.idle() == 0 {
+            if let Some(value) = self.depot.pop() {
+                self.pool.push_idle(value);
+            }
+        }
+        self.pool.try_acquire()
+    }
+}
+
+impl<T> Deref for LocalPool<T> {
+    type Target = Pool<T>;
+
+    #[inline]
+    fn deref(&self) -> &Pool<T> {
+        &self.pool
+    }
+}
+
+impl<T> Drop for LocalPool<T> {
+    #[inline]
+    fn drop(&mut self) {
+        for value in self.pool.take_idle() {
+            self.depot.push(value);
+        }
+    }
+}
+
+impl<T> fmt::Debug for LocalPool<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LocalPool {{ pool: {:?}, depot: {:?} }}", self.pool, self.depot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    thread_local_cache! {
+        static NUMBERS: Pool<Vec<u64>> = Vec::new;
+    }
+
+    #[test]
+    fn exiting_threads_refill_others() {
+        let depot = std::thread::spawn(|| {
+            NUMBERS.with(|pool| {
+                let mut held = [pool.try_acquire().unwrap(), pool.try_acquire().unwrap()];
+                held[0].push(1);
+                held[1].push(2);
+                pool.depot()
+            })
+        })
+        .join()
+        .unwrap();
+        assert_eq!(depot.len(), 2);
+
+        let total = std::thread::spawn(|| {
+            NUMBERS.with(|pool| {
+                let (a, b) = (pool.try_acquire().unwrap(), pool.try_acquire().unwrap());
+                a[0] + b[0]
+            })
+        })
+        .join()
+        .unwrap();
+        assert_eq!(total, 3);
+    }
+}
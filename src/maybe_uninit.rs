@@ -90,12 +90,14 @@ where
         }
     }
 
-    /// Encodes this type as a `Result` with an `UninitAlloc` as `Ok`.
-    #[inline]
-    pub const fn uninit_as_ok(self) -> Result<UninitAlloc<T>, OwnedAlloc<T>> {
-        match self {
-            MaybeUninitAlloc::Init(ptr) => Err(ptr),
-            MaybeUninitAlloc::Uninit(ptr) => Ok(ptr),
+    const_fn! {
+        /// Encodes this type as a `Result` with an `UninitAlloc` as `Ok`.
+        #[inline]
+        pub const fn uninit_as_ok(self) -> Result<UninitAlloc<T>, OwnedAlloc<T>> {
+            match self {
+                MaybeUninitAlloc::Init(ptr) => Err(ptr),
+                MaybeUninitAlloc::Uninit(ptr) => Ok(ptr),
+            }
         }
     }
 
@@ -121,23 +123,27 @@ impl<T> From<T> for MaybeUninitAlloc<T> {
     }
 }
 
-impl<T> const From<OwnedAlloc<T>> for MaybeUninitAlloc<T>
-where
-    T: ?Sized,
-{
-    #[inline]
-    fn from(alloc: OwnedAlloc<T>) -> Self {
-        MaybeUninitAlloc::Init(alloc)
+const_impl! {
+    impl<T> From<OwnedAlloc<T>> for MaybeUninitAlloc<T>
+    where
+        T: ?Sized,
+    {
+        #[inline]
+        fn from(alloc: OwnedAlloc<T>) -> Self {
+            MaybeUninitAlloc::Init(alloc)
+        }
     }
 }
 
-impl<T> const From<UninitAlloc<T>> for MaybeUninitAlloc<T>
-where
-    T: ?Sized,
-{
-    #[inline]
-    fn from(alloc: UninitAlloc<T>) -> Self {
-        MaybeUninitAlloc::Uninit(alloc)
+const_impl! {
+    impl<T> From<UninitAlloc<T>> for MaybeUninitAlloc<T>
+    where
+        T: ?Sized,
+    {
+        #[inline]
+        fn from(alloc: UninitAlloc<T>) -> Self {
+            MaybeUninitAlloc::Uninit(alloc)
+        }
     }
 }
 
@@ -0,0 +1,206 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    hint,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static GLOBAL: MetaTable = MetaTable::new();
+
+/// An allocation registered in a `MetaTable`, with its metadata word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocMeta {
+    /// Address of the allocation.
+    pub address: usize,
+    /// Size of the allocation in bytes.
+    pub size: usize,
+    /// The word given when the allocation was registered: an owner id, an
+    /// arena id, a debug tag...
+    pub meta: usize,
+}
+
+impl AllocMeta {
+    /// Tests if `ptr` points inside the allocation.
+    #[inline]
+    pub fn contains(&self, ptr: *const u8) -> bool {
+        let addr = ptr as usize;
+        addr == self.address || addr.wrapping_sub(self.address) < self.size
+    }
+}
+
+/// A side table associating a metadata word with allocations, so that any
+/// pointer into a registered allocation, not only its start, can be
+/// attributed back to its owner, e.g. by crash-dump tooling.
+///
+/// Allocations are registered and unregistered explicitly: the table knows
+/// nothing of the allocator, and an allocation freed without being removed
+/// keeps its entry until its address is registered again.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{MetaTable, RawVec};
+///
+/// const PARSER: usize = 7;
+///
+/// let table = MetaTable::new();
+/// let buf = RawVec::<u32>::with_capacity(16);
+/// let start = buf.raw().as_ptr().cast::<u8>();
+/// table.insert(start, 64, PARSER);
+///
+/// let field = unsafe { start.add(40) };
+/// assert_eq!(table.lookup(field).unwrap().meta, PARSER);
+/// assert!(table.lookup(unsafe { start.add(64) }).is_none());
+///
+/// assert_eq!(table.remove(start).unwrap().size, 64);
+/// assert!(table.is_empty());
+/// ```
+pub struct MetaTable {
+    locked: AtomicBool,
+    entries: UnsafeCell<BTreeMap<usize, (usize, usize)>>,
+}
+
+impl MetaTable {
+    /// Creates an empty table.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            entries: UnsafeCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Registers the allocation of `size` bytes at `ptr` with the word
+    /// `meta`, returning the word it replaced if the allocation was already
+    /// registered.
+    #[inline]
+    pub fn insert(&self, ptr: *const u8, size: usize, meta: usize) -> Option<usize> {
+        self.with_entries(|entries| entries.insert(ptr as usize, (size, meta)))
+            .map(|(_, meta)| meta)
+    }
+
+    /// Unregisters the allocation starting at `ptr`, returning its entry.
+    #[inline]
+    pub fn remove(&self, ptr: *const u8) -> Option<AllocMeta> {
+        let address = ptr as usize;
+        self.with_entries(|entries| entries.remove(&address))
+            .map(|(size, meta)| AllocMeta { address, size, meta })
+    }
+
+    /// Finds the registered allocation `ptr` points into.
+    #[inline]
+    pub fn lookup(&self, ptr: *const u8) -> Option<AllocMeta> {
+        let entry = self.with_entries(|entries| {
+            let (&address, &(size, meta)) = entries.range(..= ptr as usize).next_back()?;
+            Some(AllocMeta { address, size, meta })
+        })?;
+        Some(entry).filter(|entry| entry.contains(ptr))
+    }
+
+    /// Every registered allocation, by address.
+    pub fn entries(&self) -> Vec<AllocMeta> {
+        self.with_entries(|entries| {
+            entries
+                .iter()
+                .map(|(&address, &(size, meta))| AllocMeta { address, size, meta })
+                .collect()
+        })
+    }
+
+    /// Number of registered allocations.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.with_entries(|entries| entries.len())
+    }
+
+    /// Tests if no allocation is registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn with_entries<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut BTreeMap<usize, (usize, usize)>) -> R,
+    {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let ret = f(unsafe { &mut *self.entries.get() });
+        self.locked.store(false, Ordering::Release);
+        ret
+    }
+}
+
+unsafe impl Send for MetaTable {}
+unsafe impl Sync for MetaTable {}
+
+impl Default for MetaTable {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MetaTable {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MetaTable {{ entries: {} }}", self.len())
+    }
+}
+
+/// Registers an allocation in the global table. See `MetaTable::insert`.
+#[inline]
+pub fn set_alloc_meta(ptr: *const u8, size: usize, meta: usize) -> Option<usize> {
+    GLOBAL.insert(ptr, size, meta)
+}
+
+/// Unregisters an allocation from the global table. See
+/// `MetaTable::remove`.
+#[inline]
+pub fn remove_alloc_meta(ptr: *const u8) -> Option<AllocMeta> {
+    GLOBAL.remove(ptr)
+}
+
+/// Finds the allocation of the global table `ptr` points into. See
+/// `MetaTable::lookup`.
+#[inline]
+pub fn alloc_meta(ptr: *const u8) -> Option<AllocMeta> {
+    GLOBAL.lookup(ptr)
+}
+
+/// Every allocation of the global table, by address, e.g. to write them in
+/// a crash dump.
+#[inline]
+pub fn alloc_meta_entries() -> Vec<AllocMeta> {
+    GLOBAL.entries()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{alloc_meta, remove_alloc_meta, set_alloc_meta};
+    use crate::OwnedAlloc;
+
+    #[test]
+    fn interior_pointers_are_attributed() {
+        let first = OwnedAlloc::new([0u64; 4]);
+        let second = OwnedAlloc::new(0u8);
+        let first_ptr = first.raw().as_ptr().cast::<u8>();
+        let second_ptr = second.raw().as_ptr().cast_const();
+        assert_eq!(set_alloc_meta(first_ptr, 32, 1), None);
+        assert_eq!(set_alloc_meta(second_ptr, 1, 2), None);
+        assert_eq!(set_alloc_meta(second_ptr, 1, 3), Some(2));
+
+        assert_eq!(alloc_meta(unsafe { first_ptr.add(31) }).unwrap().meta, 1);
+        assert_eq!(alloc_meta(second_ptr).unwrap().meta, 3);
+        assert_eq!(remove_alloc_meta(first_ptr).unwrap().meta, 1);
+        assert!(alloc_meta(unsafe { first_ptr.add(8) }).is_none());
+        assert!(remove_alloc_meta(second_ptr).is_some());
+    }
+}
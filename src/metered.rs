@@ -0,0 +1,272 @@
+use crate::{alloc_api::AllocError, tag, Tag};
+use core::{
+    alloc::Layout,
+    fmt,
+    ptr::NonNull,
+};
+use metrics::{Counter, Gauge, SharedString};
+
+/// Counter of the allocations made through a `MeteredAlloc`.
+pub const ALLOCATIONS_METRIC: &str = "owned_alloc_allocations_total";
+/// Counter of the deallocations made through a `MeteredAlloc`.
+pub const DEALLOCATIONS_METRIC: &str = "owned_alloc_deallocations_total";
+/// Counter of the allocations a `MeteredAlloc` failed to serve.
+pub const FAILURES_METRIC: &str = "owned_alloc_allocation_failures_total";
+/// Gauge of the bytes currently allocated through a `MeteredAlloc`.
+pub const LIVE_BYTES_METRIC: &str = "owned_alloc_live_bytes";
+/// Gauge of the bytes currently charged to a tag.
+pub const TAG_LIVE_BYTES_METRIC: &str = "owned_alloc_tag_live_bytes";
+/// Gauge of the allocations currently charged to a tag.
+pub const TAG_LIVE_COUNT_METRIC: &str = "owned_alloc_tag_live_allocations";
+
+/// A wrapper reporting the allocations made through it to the `metrics`
+/// facade, so whatever recorder the application installed (e.g. a
+/// Prometheus exporter) picks them up. Every metric carries an `allocator`
+/// label with the name given at construction.
+///
+/// The metric handles are registered when the wrapper is created, so the
+/// recorder must be installed before. The live bytes account the size of
+/// the layouts requested, not the memory actually used by the backend.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, MeteredAlloc};
+///
+/// let alloc = MeteredAlloc::new(Allocator::new(), "parser");
+/// let layout = Layout::new::<[u8; 60]>();
+/// let block = alloc.allocate(layout).unwrap();
+/// unsafe { alloc.deallocate(block.cast(), layout) };
+/// assert_eq!(alloc.name(), "parser");
+/// ```
+pub struct MeteredAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    backend: A,
+    name: &'static str,
+    allocations: Counter,
+    deallocations: Counter,
+    failures: Counter,
+    live_bytes: Gauge,
+}
+
+impl<A> MeteredAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Reports the allocations made through `backend` under the label
+    /// `allocator = name`.
+    pub fn new(backend: A, name: &'static str) -> Self {
+        let labels = [("allocator", name)];
+        Self {
+            backend,
+            name,
+            allocations: metrics::counter!(ALLOCATIONS_METRIC, &labels),
+            deallocations: metrics::counter!(DEALLOCATIONS_METRIC, &labels),
+            failures: metrics::counter!(FAILURES_METRIC, &labels),
+            live_bytes: metrics::gauge!(LIVE_BYTES_METRIC, &labels),
+        }
+    }
+
+    /// The allocator being metered.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// The value of the `allocator` label.
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    #[inline]
+    fn counted(
+        &self,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if res.is_err() {
+            self.failures.increment(1);
+        }
+        res
+    }
+}
+
+unsafe impl<A> crate::alloc_api::Allocator for MeteredAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.counted(self.backend.allocate(layout))?;
+        self.allocations.increment(1);
+        self.live_bytes.increment(layout.size() as f64);
+        Ok(block)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.backend.deallocate(ptr, layout);
+        self.deallocations.increment(1);
+        self.live_bytes.decrement(layout.size() as f64);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.counted(self.backend.grow(ptr, old_layout, new_layout))?;
+        self.live_bytes.increment((new_layout.size() - old_layout.size()) as f64);
+        Ok(block)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.counted(self.backend.shrink(ptr, old_layout, new_layout))?;
+        self.live_bytes.decrement((old_layout.size() - new_layout.size()) as f64);
+        Ok(block)
+    }
+}
+
+impl<A> fmt::Debug for MeteredAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MeteredAlloc {{ name: {} }}", self.name)
+    }
+}
+
+/// Sets the per-tag gauges to the live memory of every tag (see
+/// `tag::report`), under a `tag` label. Meant to be called periodically or
+/// right before the recorder is scraped.
+pub fn publish_tag_metrics() {
+    for report in tag::report() {
+        let label = match report.tag {
+            Tag::Name(name) => SharedString::const_str(name),
+            Tag::Id(id) => alloc::format!("#{}", id).into(),
+        };
+        let labels = [("tag", label)];
+        metrics::gauge!(TAG_LIVE_BYTES_METRIC, &labels).set(report.live_bytes as f64);
+        metrics::gauge!(TAG_LIVE_COUNT_METRIC, &labels).set(report.live_count as f64);
+    }
+}
+
+/// Registers a description of every metric of this module with the
+/// installed recorder.
+pub fn describe_metrics() {
+    metrics::describe_counter!(ALLOCATIONS_METRIC, "Allocations made through the allocator");
+    metrics::describe_counter!(DEALLOCATIONS_METRIC, "Deallocations made through the allocator");
+    metrics::describe_counter!(FAILURES_METRIC, "Allocations the allocator failed to serve");
+    metrics::describe_gauge!(
+        LIVE_BYTES_METRIC,
+        metrics::Unit::Bytes,
+        "Bytes currently allocated through the allocator"
+    );
+    metrics::describe_gauge!(
+        TAG_LIVE_BYTES_METRIC,
+        metrics::Unit::Bytes,
+        "Bytes currently allocated under the tag"
+    );
+    metrics::describe_gauge!(TAG_LIVE_COUNT_METRIC, "Allocations currently live under the tag");
+}
+
+#[cfg(test)]
+mod test {
+    use super::{publish_tag_metrics, MeteredAlloc, LIVE_BYTES_METRIC, TAG_LIVE_BYTES_METRIC};
+    use crate::{alloc_api::Allocator as _, Allocator, Tag, TaggedAlloc};
+    use alloc::{string::String, sync::Arc, vec::Vec};
+    use core::{
+        alloc::Layout,
+        sync::atomic::{AtomicU64, Ordering::*},
+    };
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use std::sync::Mutex;
+
+    /// Keeps the value of every metric, keyed by name and label values.
+    #[derive(Default)]
+    struct TestRecorder {
+        values: Mutex<Vec<(String, Arc<AtomicU64>)>>,
+    }
+
+    impl TestRecorder {
+        fn value(&self, key: &str) -> Arc<AtomicU64> {
+            let mut values = self.values.lock().unwrap();
+            if let Some((_, value)) = values.iter().find(|(name, _)| name == key) {
+                return value.clone();
+            }
+            let value = Arc::new(AtomicU64::new(0));
+            values.push((key.into(), value.clone()));
+            value
+        }
+
+        fn gauge(&self, key: &str) -> f64 {
+            f64::from_bits(self.value(key).load(Relaxed))
+        }
+
+        fn key(key: &Key) -> String {
+            let mut name = String::from(key.name());
+            for label in key.labels() {
+                name.push(' ');
+                name.push_str(label.value());
+            }
+            name
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata) -> Counter {
+            Counter::from_arc(self.value(&Self::key(key)))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata) -> Gauge {
+            Gauge::from_arc(self.value(&Self::key(key)))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn allocator_and_tags_are_reported() {
+        let recorder = TestRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            let alloc = MeteredAlloc::new(Allocator::new(), "metered-test");
+            let small = Layout::from_size_align(16, 8).unwrap();
+            let big = Layout::from_size_align(40, 8).unwrap();
+            let block = alloc.allocate(small).unwrap().cast::<u8>();
+            let block = unsafe { alloc.grow(block, small, big) }.unwrap().cast::<u8>();
+            let other = alloc.allocate(small).unwrap().cast::<u8>();
+            unsafe { alloc.deallocate(block, big) };
+
+            let _tagged = TaggedAlloc::with_tag([0u8; 300], Tag::Id(4510));
+            publish_tag_metrics();
+
+            let live = alloc::format!("{} metered-test", LIVE_BYTES_METRIC);
+            assert_eq!(recorder.gauge(&live), 16.0);
+            let count = recorder.value("owned_alloc_allocations_total metered-test");
+            assert_eq!(count.load(Relaxed), 2);
+            let tag = alloc::format!("{} #4510", TAG_LIVE_BYTES_METRIC);
+            assert_eq!(recorder.gauge(&tag), 300.0);
+
+            unsafe { alloc.deallocate(other, small) };
+            assert_eq!(recorder.gauge(&live), 0.0);
+        });
+    }
+}
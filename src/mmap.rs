@@ -0,0 +1,216 @@
+use crate::{sys, OsError};
+use core::{
+    fmt,
+    ops::Deref,
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// The operating system's handle to an open file: a file descriptor on Unix,
+/// a `HANDLE` on Windows.
+pub type FileHandle = sys::FileHandle;
+
+/// How a file is mapped into memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MapOptions {
+    /// Offset into the file where the mapping starts. Must be a multiple of
+    /// the allocation granularity of the system (the page size on Unix).
+    pub offset: u64,
+    /// Whether the mapping can be written to. The file must have been opened
+    /// for writing.
+    pub writable: bool,
+    /// Whether writes are private to this mapping (copy-on-write) instead of
+    /// being carried through to the file.
+    pub private: bool,
+}
+
+impl MapOptions {
+    /// Read-only, shared mapping starting at offset `0`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            offset: 0,
+            writable: false,
+            private: false,
+        }
+    }
+}
+
+/// Expected access pattern of a mapping, passed to `FileMap::advise`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// No particular pattern.
+    Normal,
+    /// Pages will be accessed in order, so read ahead aggressively.
+    Sequential,
+    /// Pages will be accessed in random order, so read ahead is wasteful.
+    Random,
+    /// Pages will be needed soon.
+    WillNeed,
+    /// Pages will not be needed soon.
+    DontNeed,
+}
+
+/// An owned, memory-mapped view of a file, unmapped on drop. Large
+/// read-mostly datasets can then be handled as a byte slice, just like heap
+/// buffers of the crate.
+///
+/// Mappings cannot be expressed as an `OwnedAlloc<[u8]>`, since dropping one
+/// would hand the memory to the heap allocator instead of unmapping it.
+pub struct FileMap {
+    ptr: NonNull<u8>,
+    len: usize,
+    writable: bool,
+}
+
+impl FileMap {
+    /// Maps `len` bytes of the given file.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because the file must not be truncated or
+    /// modified by anyone else while the mapping is alive, since the mapping
+    /// is exposed as a plain slice.
+    pub unsafe fn map_file(
+        file: FileHandle,
+        len: usize,
+        options: MapOptions,
+    ) -> Result<Self, OsError> {
+        let ptr = if len == 0 {
+            NonNull::dangling()
+        } else {
+            let raw = sys::map_file(file, options.offset, len, options.writable, options.private)?;
+            NonNull::new_unchecked(raw)
+        };
+        Ok(Self {
+            ptr,
+            len,
+            writable: options.writable,
+        })
+    }
+
+    /// Maps `len` bytes of an open `File`.
+    ///
+    /// # Safety
+    /// See `map_file`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub unsafe fn map(
+        file: &std::fs::File,
+        len: usize,
+        options: MapOptions,
+    ) -> Result<Self, OsError> {
+        #[cfg(unix)]
+        let handle = std::os::unix::io::AsRawFd::as_raw_fd(file);
+        #[cfg(windows)]
+        let handle = std::os::windows::io::AsRawHandle::as_raw_handle(file) as FileHandle;
+        Self::map_file(handle, len, options)
+    }
+
+    /// Length of the mapping in bytes.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if the mapping is empty.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The raw non-null pointer to the mapped bytes.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<[u8]> {
+        unsafe { NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len)) }
+    }
+
+    /// A mutable view of the mapped bytes, or `None` if the mapping is
+    /// read-only.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if self.writable {
+            Some(unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) })
+        } else {
+            None
+        }
+    }
+
+    /// Writes modified pages back to the file, blocking until done.
+    #[inline]
+    pub fn flush(&self) -> Result<(), OsError> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        unsafe { sys::flush(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Tells the system how the mapping will be accessed.
+    #[inline]
+    pub fn advise(&self, advice: Advice) -> Result<(), OsError> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        unsafe { sys::advise(self.ptr.as_ptr(), self.len, advice) }
+    }
+}
+
+impl Deref for FileMap {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for FileMap {
+    #[inline]
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe { sys::unmap(self.ptr.as_ptr(), self.len) }
+        }
+    }
+}
+
+impl fmt::Debug for FileMap {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileMap {{ pointer: {:?}, len: {} }}", self.ptr, self.len)
+    }
+}
+
+unsafe impl Send for FileMap {}
+unsafe impl Sync for FileMap {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{Advice, FileMap, MapOptions};
+    use std::{fs::OpenOptions, io::Write};
+
+    #[test]
+    fn map_and_write_back() {
+        let path = std::env::temp_dir().join("owned_alloc_mmap_test");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"hello world").unwrap();
+
+        let options = MapOptions {
+            writable: true,
+            ..MapOptions::new()
+        };
+        let mut map = unsafe { FileMap::map(&file, 11, options).unwrap() };
+        assert_eq!(&*map, b"hello world");
+        map.advise(Advice::Sequential).unwrap();
+        map.as_mut_slice().unwrap()[.. 5].copy_from_slice(b"HELLO");
+        map.flush().unwrap();
+        drop(map);
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"HELLO world");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,41 +1,158 @@
 extern crate alloc;
-use crate::{AllocError, UninitAlloc};
+use crate::{
+    AllocError,
+    Allocator,
+    CachePadded,
+    LayoutError,
+    RawVec,
+    RawVecError,
+    UninitAlloc,
+    ALLOCATOR,
+};
 use alloc::boxed::Box;
 use core::{
-    alloc::Layout,
+    alloc::{GlobalAlloc, Layout},
+    borrow::{Borrow, BorrowMut},
+    cmp::Ordering,
+    fmt,
+    future::Future,
+    hash::{Hash, Hasher},
+    iter::{FromIterator, FusedIterator},
     marker::PhantomData,
     mem,
     ops::{Deref, DerefMut},
+    pin::Pin,
     ptr::NonNull,
+    task::{Context, Poll},
 };
 
-pub struct OwnedAlloc<T>
+/// An initialized allocation, freed through `A`, by default the crate's
+/// `Allocator`, when dropped.
+pub struct OwnedAlloc<T, A = Allocator>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator,
 {
     ptr: NonNull<T>,
+    alloc: A,
     _marker: PhantomData<T>,
 }
 
 impl<T> OwnedAlloc<T> {
-    /// Creates an allocation and initializes it to the passed argument. In case
-    /// of allocation error, the handler registered via stdlib is called.
-    #[inline]
-    pub fn new(value: T) -> Self {
-        UninitAlloc::new().init(value)
+    panicking! {
+        /// Creates an allocation and initializes it to the passed argument. In case
+        /// of allocation error, the handler registered via stdlib is called.
+        #[inline]
+        #[track_caller]
+        pub fn new(value: T) -> Self {
+            UninitAlloc::new().init(value)
+        }
     }
 
     #[inline]
+    #[track_caller]
     pub fn try_new(value: T) -> Result<Self, AllocError> {
         UninitAlloc::try_new().map(|alloc| alloc.init(value))
     }
 
+    panicking! {
+        /// Creates an allocation aligned and padded to a cache line, so the value
+        /// never shares a line with other data.
+        #[inline]
+        #[track_caller]
+        pub fn new_cache_aligned(value: T) -> OwnedAlloc<CachePadded<T>> {
+            OwnedAlloc::new(CachePadded::new(value))
+        }
+    }
+
+    panicking! {
+        /// Creates a pinned allocation initialized to the passed argument, as
+        /// `Box::pin` does. The value stays at the same address until it is
+        /// dropped, so it may hold pointers to itself, or be linked into
+        /// intrusive structures. In case of allocation error, the function
+        /// panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use core::{marker::PhantomPinned, pin::Pin, ptr};
+        /// use owned_alloc::OwnedAlloc;
+        ///
+        /// struct Node {
+        ///     value: u32,
+        ///     this: *const Node,
+        ///     _pinned: PhantomPinned,
+        /// }
+        ///
+        /// let mut node = OwnedAlloc::pin(Node {
+        ///     value: 1,
+        ///     this: ptr::null(),
+        ///     _pinned: PhantomPinned,
+        /// });
+        /// let this: *const Node = &*node;
+        /// unsafe { node.as_mut().get_unchecked_mut().this = this };
+        ///
+        /// let moved = node;
+        /// assert!(ptr::eq(moved.this, &*moved));
+        /// assert_eq!(unsafe { (*moved.this).value }, 1);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn pin(value: T) -> Pin<Self> {
+            Self::new(value).into_pin()
+        }
+    }
+
+    /// Creates a pinned allocation initialized to the passed argument. In case
+    /// of allocation error, `Err` is returned.
     #[inline]
-    pub const fn move_inner(self) -> (T, UninitAlloc<T>) {
-        let val = unsafe { self.ptr.as_ptr().read() };
-        let alloc = unsafe { UninitAlloc::from_raw(self.ptr) };
-        mem::forget(self);
-        (val, alloc)
+    #[track_caller]
+    pub fn try_pin(value: T) -> Result<Pin<Self>, AllocError> {
+        Self::try_new(value).map(Self::into_pin)
+    }
+}
+
+impl<T, A> OwnedAlloc<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    panicking! {
+        /// Creates an allocation in `alloc` and initializes it to the passed
+        /// argument. In case of allocation error, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::{Bump, OwnedAlloc};
+        ///
+        /// let bump = Bump::new();
+        /// let alloc = OwnedAlloc::new_in([1u32, 2, 3], &bump);
+        /// assert_eq!(*alloc, [1, 2, 3]);
+        /// assert_eq!(bump.capacity(), owned_alloc::DEFAULT_CHUNK_SIZE);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn new_in(value: T, alloc: A) -> Self {
+            match Self::try_new_in(value, alloc) {
+                Ok(this) => this,
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+
+    /// Creates an allocation in `alloc` and initializes it to the passed
+    /// argument. In case of allocation error, `Err` is returned.
+    #[inline]
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, AllocError> {
+        let layout = Layout::new::<T>();
+        let ptr = alloc.allocate(layout).map_err(|_| AllocError { layout })?;
+        let ptr = ptr.cast::<T>();
+        unsafe {
+            ptr.as_ptr().write(value);
+            Ok(Self::from_raw_in(ptr, alloc))
+        }
     }
 }
 
@@ -45,20 +162,13 @@ where
 {
     #[inline]
     pub const unsafe fn from_raw(ptr: NonNull<T>) -> Self {
-        Self {
-            ptr,
-            _marker: PhantomData,
-        }
+        Self::from_raw_in(ptr, Allocator::new())
     }
     #[inline]
     pub unsafe fn from_box(boxed: Box<T>) -> Self {
         Self::from_raw(NonNull::<T>::new_unchecked(Box::into_raw(boxed)))
     }
     #[inline]
-    pub const fn raw(&self) -> NonNull<T> {
-        self.ptr
-    }
-    #[inline]
     pub const fn into_raw(self) -> NonNull<T> {
         let ptr = self.ptr;
         mem::forget(self);
@@ -66,28 +176,314 @@ where
     }
     #[inline]
     pub unsafe fn into_box(self) -> Box<T> {
-        Box::from_raw(self.ptr.as_ptr())
+        #[cfg(feature = "track-callers")]
+        crate::leak::forget(self.ptr.as_ptr().cast());
+        Box::from_raw(self.into_raw().as_ptr())
+    }
+}
+
+impl<T> OwnedAlloc<[T]> {
+    panicking! {
+        /// Collects the items of `iter` into a single allocation of exactly their
+        /// number. When the size hint of the iterator is exact, as for an
+        /// `ExactSizeIterator`, no reallocation is performed. In case of
+        /// allocation error or overflow, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::OwnedAlloc;
+        ///
+        /// let squares = OwnedAlloc::collect_slice((1 .. 5).map(|i| i * i));
+        /// assert_eq!(*squares, [1, 4, 9, 16]);
+        ///
+        /// let evens = OwnedAlloc::collect_slice((0 .. 10).filter(|i| i % 2 == 0));
+        /// assert_eq!(*evens, [0, 2, 4, 6, 8]);
+        ///
+        /// let words: OwnedAlloc<[&str]> = "to be or not".split(' ').collect();
+        /// assert_eq!(words.len(), 4);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn collect_slice<I>(iter: I) -> Self
+        where
+            I: IntoIterator<Item = T>,
+        {
+            match Self::try_collect_slice(iter) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Collects the items of `iter` into a single allocation of exactly their
+    /// number. When the size hint of the iterator is exact, as for an
+    /// `ExactSizeIterator`, no reallocation is performed. In case of
+    /// allocation error or overflow, `Err` is returned and the items
+    /// collected so far are dropped.
+    #[track_caller]
+    pub fn try_collect_slice<I>(iter: I) -> Result<Self, RawVecError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+        let mut partial = Partial::<T> {
+            storage: RawVec::try_with_capacity(iter.size_hint().0)?,
+            len: 0,
+        };
+        for item in iter {
+            if partial.len == partial.storage.cap() {
+                let cap = partial.storage.cap().checked_mul(2).ok_or(LayoutError)?;
+                partial.storage.try_resize(cap.max(4))?;
+            }
+            unsafe { partial.storage.raw().as_ptr().add(partial.len).write(item) };
+            partial.len += 1;
+        }
+        if partial.len != partial.storage.cap() {
+            partial.storage.try_resize(partial.len)?;
+        }
+        let storage = mem::take(&mut partial.storage);
+        mem::forget(partial);
+        Ok(unsafe { Self::from_raw(storage.into_raw_slice()) })
+    }
+
+    /// The raw non-null pointer to the slice, length included.
+    #[inline]
+    pub const fn as_non_null_slice(&self) -> NonNull<[T]> {
+        self.ptr
+    }
+
+    /// Number of elements of the slice.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ptr.len()
+    }
+
+    /// Whether the slice has no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    panicking! {
+        /// Splits the allocation in two at `mid`: the first `mid` elements stay
+        /// in the original block, shrunk, and the others are moved to a new
+        /// allocation. To borrow the halves instead, use `split_at` on the slice.
+        /// In case of allocation error, or if `mid > len`, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::OwnedAlloc;
+        ///
+        /// let alloc = OwnedAlloc::collect_slice(0 .. 5);
+        /// let (head, tail) = alloc.split_at_owned(2);
+        /// assert_eq!((&*head, &*tail), (&[0, 1][..], &[2, 3, 4][..]));
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn split_at_owned(self, mid: usize) -> (Self, Self) {
+            match self.try_split_at_owned(mid) {
+                Ok(halves) => halves,
+                Err((err, _)) => panic!("{}", err),
+            }
+        }
+    }
+
+    /// Splits the allocation in two at `mid`: the first `mid` elements stay
+    /// in the original block, shrunk, and the others are moved to a new
+    /// allocation. In case of allocation error, the allocation is given back
+    /// untouched. If `mid > len`, the function panics.
+    #[track_caller]
+    pub fn try_split_at_owned(self, mid: usize) -> Result<(Self, Self), (AllocError, Self)> {
+        let len = self.len();
+        assert!(mid <= len, "mid {} out of range for slice of length {}", mid, len);
+        let tail = match RawVec::<T>::try_with_capacity(len - mid) {
+            Ok(tail) => tail,
+            Err(RawVecError::Alloc(err)) => return Err((err, self)),
+            Err(RawVecError::Layout(_)) => unreachable!("the tail fits in the slice"),
+        };
+        let head = self.ptr.cast::<T>();
+        unsafe { tail.raw().as_ptr().copy_from_nonoverlapping(head.as_ptr().add(mid), len - mid) };
+
+        let layout = Layout::for_value(&*self);
+        let head_size = mem::size_of::<T>() * mid;
+        let head = if layout.size() == 0 {
+            head
+        } else if head_size == 0 {
+            #[cfg(feature = "track-callers")]
+            crate::leak::forget(head.as_ptr().cast());
+            unsafe { ALLOCATOR.dealloc(head.as_ptr().cast(), layout) };
+            NonNull::dangling()
+        } else {
+            let new = unsafe { ALLOCATOR.realloc(head.as_ptr().cast(), layout, head_size) };
+            match NonNull::new(new) {
+                Some(new) => {
+                    #[cfg(feature = "track-callers")]
+                    crate::leak::relocate(head.as_ptr().cast(), new.as_ptr(), head_size);
+                    new.cast()
+                },
+                None => {
+                    let layout =
+                        unsafe { Layout::from_size_align_unchecked(head_size, layout.align()) };
+                    return Err((AllocError { layout }, self));
+                },
+            }
+        };
+        mem::forget(self);
+        unsafe {
+            Ok((
+                Self::from_raw(NonNull::slice_from_raw_parts(head, mid)),
+                Self::from_raw(tail.into_raw_slice()),
+            ))
+        }
+    }
+}
+
+impl<T, A> OwnedAlloc<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    /// Takes ownership of a value allocated in `alloc`.
+    ///
+    /// # Safety
+    /// `ptr` must point to an initialized value allocated in `alloc` with
+    /// the layout of the value.
+    #[inline]
+    pub const unsafe fn from_raw_in(ptr: NonNull<T>, alloc: A) -> Self {
+        Self {
+            ptr,
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub const fn raw(&self) -> NonNull<T> {
+        self.ptr
     }
 
+    /// The allocator the value is freed through.
     #[inline]
-    pub fn drop_in_place(self) -> UninitAlloc<T> {
+    pub const fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// "Forgets" dropping the value and freeing the allocation, and returns
+    /// the raw pointer along with the allocator.
+    #[inline]
+    pub const fn into_raw_with_allocator(self) -> (NonNull<T>, A) {
+        let ptr = self.ptr;
+        let alloc = unsafe { (&self.alloc as *const A).read() };
+        mem::forget(self);
+        (ptr, alloc)
+    }
+
+    #[inline]
+    pub fn drop_in_place(self) -> UninitAlloc<T, A> {
         unsafe {
             self.ptr.as_ptr().drop_in_place();
-            UninitAlloc::from_raw(self.into_raw())
+            self.forget_inner()
         }
     }
 
     /// "Forgets" about dropping the inner value and returns an uninitialized
     /// allocation.
     #[inline]
-    pub const fn forget_inner(self) -> UninitAlloc<T> {
-        unsafe { UninitAlloc::from_raw(self.into_raw()) }
+    pub const fn forget_inner(self) -> UninitAlloc<T, A> {
+        let ptr = self.ptr;
+        unsafe {
+            let alloc = (&self.alloc as *const A).read();
+            mem::forget(self);
+            UninitAlloc::from_raw_in(ptr, alloc)
+        }
     }
 }
 
-impl<T> Drop for OwnedAlloc<T>
+impl<T, A> OwnedAlloc<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    pub const fn move_inner(self) -> (T, UninitAlloc<T, A>) {
+        let val = unsafe { self.ptr.as_ptr().read() };
+        (val, self.forget_inner())
+    }
+
+    /// Moves the value out and frees the allocation, as `move_inner` followed
+    /// by dropping the `UninitAlloc` does. Freeing cannot happen in constant
+    /// evaluation, so unlike `move_inner`, this is not a `const fn`.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::{Allocator, LimitedAlloc, OwnedAlloc};
+    ///
+    /// let limited = LimitedAlloc::new(Allocator::new(), 64);
+    /// let name = OwnedAlloc::new_in(String::from("inner"), &limited);
+    /// assert_eq!(limited.used(), core::mem::size_of::<String>());
+    /// assert_eq!(name.into_inner(), "inner");
+    /// assert_eq!(limited.used(), 0);
+    /// ```
+    #[inline]
+    pub fn into_inner(self) -> T {
+        let (val, _) = self.move_inner();
+        val
+    }
+}
+
+/// Items collected so far by `OwnedAlloc::try_collect_slice`, dropped if
+/// collecting fails or panics.
+struct Partial<T> {
+    storage: RawVec<T>,
+    len: usize,
+}
+
+impl<T> Drop for Partial<T> {
+    fn drop(&mut self) {
+        let items = core::ptr::slice_from_raw_parts_mut(self.storage.raw().as_ptr(), self.len);
+        unsafe { items.drop_in_place() }
+    }
+}
+
+impl<T, A> OwnedAlloc<T, A>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator + 'static,
+{
+    /// Pins the allocation, as `Box::into_pin` does: the value is never moved
+    /// by the allocation. The allocator must be `'static`, since the memory
+    /// of a borrowed one could be reclaimed without the value being dropped.
+    #[inline]
+    pub fn into_pin(self) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(self) }
+    }
+}
+
+impl<T> FromIterator<T> for OwnedAlloc<[T]> {
+    /// Collects the items with `collect_slice`. In case of allocation error
+    /// or overflow, the function panics.
+    #[inline]
+    #[track_caller]
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::collect_slice(iter)
+    }
+}
+
+impl<T, A> Drop for OwnedAlloc<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
 {
     #[inline]
     fn drop(&mut self) {
@@ -95,37 +491,171 @@ where
             let layout = Layout::for_value(self.ptr.as_ref());
             self.ptr.as_ptr().drop_in_place();
             if layout.size() != 0 {
-                //ALLOCATOR.dealloc(self.ptr.cast().as_ptr(), layout);
+                #[cfg(feature = "track-callers")]
+                if crate::leak::tracks::<A>() {
+                    crate::leak::forget(self.ptr.as_ptr().cast());
+                }
+                self.alloc.deallocate(self.ptr.cast(), layout);
             }
         }
     }
 }
 
-impl<T> const Deref for OwnedAlloc<T>
+const_impl! {
+    impl<T, A> Deref for OwnedAlloc<T, A>
+    where
+        T: ?Sized,
+        A: crate::alloc_api::Allocator,
+    {
+        type Target = T;
+
+        #[inline]
+        fn deref(&self) -> &T {
+            unsafe { self.ptr.as_ref() }
+        }
+    }
+}
+
+const_impl! {
+    impl<T, A> DerefMut for OwnedAlloc<T, A>
+    where
+        T: ?Sized,
+        A: crate::alloc_api::Allocator,
+    {
+        #[inline]
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { self.ptr.as_mut() }
+        }
+    }
+}
+
+/// An allocator moved to the heap, so containers owning it stay small.
+unsafe impl<T, A> crate::alloc_api::Allocator for OwnedAlloc<T, A>
 where
-    T: ?Sized,
+    T: ?Sized + crate::alloc_api::Allocator,
+    A: crate::alloc_api::Allocator,
+{
+    forward_allocator!(|this| (**this));
+}
+
+/// Polls the allocated future, as `Box` does. The allocation is not pinned
+/// by polling, so the future must be `Unpin`; other futures are polled
+/// through a `Pin<OwnedAlloc<F>>`, made by `OwnedAlloc::pin`. `IntoFuture`
+/// comes with `Future`, so an allocated future can be awaited directly.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use core::{
+///     future::{ready, Future},
+///     pin::Pin,
+///     task::{Context, Poll, Waker},
+/// };
+/// use owned_alloc::OwnedAlloc;
+///
+/// let mut future = OwnedAlloc::new(ready(7));
+/// let mut cx = Context::from_waker(Waker::noop());
+/// assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(7));
+/// ```
+impl<F, A> Future for OwnedAlloc<F, A>
+where
+    F: ?Sized + Future + Unpin,
+    A: crate::alloc_api::Allocator,
 {
-    type Target = T;
+    type Output = F::Output;
 
     #[inline]
-    fn deref(&self) -> &T {
-        unsafe { self.ptr.as_ref() }
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        Pin::new(&mut **self.get_mut()).poll(cx)
     }
 }
 
-impl<T> const DerefMut for OwnedAlloc<T>
+/// Moving an allocation never moves its value, so the allocation is `Unpin`
+/// whatever the value is, as `Box` is. A `Pin<OwnedAlloc<T>>` pins the value
+/// instead.
+impl<T, A> Unpin for OwnedAlloc<T, A>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+}
+
+/// Iterates the allocated iterator, as `Box` does, so that an
+/// `OwnedAlloc<dyn Iterator<Item = T>>` can be used as an iterator directly.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::OwnedAlloc;
+///
+/// let mut iter = OwnedAlloc::new((1 .. 5).map(|i| i * 10));
+/// assert_eq!(iter.len(), 4);
+/// assert_eq!(iter.next_back(), Some(40));
+/// assert_eq!(iter.sum::<u32>(), 60);
+/// ```
+impl<I, A> Iterator for OwnedAlloc<I, A>
+where
+    I: ?Sized + Iterator,
+    A: crate::alloc_api::Allocator,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        (**self).next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (**self).size_hint()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<I::Item> {
+        (**self).nth(n)
+    }
+}
+
+impl<I, A> DoubleEndedIterator for OwnedAlloc<I, A>
+where
+    I: ?Sized + DoubleEndedIterator,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<I::Item> {
+        (**self).next_back()
+    }
+
+    #[inline]
+    fn nth_back(&mut self, n: usize) -> Option<I::Item> {
+        (**self).nth_back(n)
+    }
+}
+
+impl<I, A> ExactSizeIterator for OwnedAlloc<I, A>
+where
+    I: ?Sized + ExactSizeIterator,
+    A: crate::alloc_api::Allocator,
 {
     #[inline]
-    fn deref_mut(&mut self) -> &mut T {
-        unsafe { self.ptr.as_mut() }
+    fn len(&self) -> usize {
+        (**self).len()
     }
 }
 
-impl<T> core::fmt::Debug for OwnedAlloc<T>
+impl<I, A> FusedIterator for OwnedAlloc<I, A>
+where
+    I: ?Sized + FusedIterator,
+    A: crate::alloc_api::Allocator,
+{
+}
+
+impl<T, A> core::fmt::Debug for OwnedAlloc<T, A>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator,
 {
     #[inline]
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
@@ -133,6 +663,127 @@ where
     }
 }
 
+/// Compares the values, not the allocations, as `Box` does.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::OwnedAlloc;
+/// use std::collections::BTreeSet;
+///
+/// let names: BTreeSet<_> = ["b", "a", "b"].into_iter().map(OwnedAlloc::new).collect();
+/// assert_eq!(names.len(), 2);
+/// assert!(names.contains(&"a"));
+/// assert_eq!(names.first().unwrap().to_string(), "a");
+/// ```
+impl<T, A> PartialEq for OwnedAlloc<T, A>
+where
+    T: ?Sized + PartialEq,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T, A> Eq for OwnedAlloc<T, A>
+where
+    T: ?Sized + Eq,
+    A: crate::alloc_api::Allocator,
+{
+}
+
+impl<T, A> PartialOrd for OwnedAlloc<T, A>
+where
+    T: ?Sized + PartialOrd,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+
+    #[inline]
+    fn lt(&self, other: &Self) -> bool {
+        **self < **other
+    }
+
+    #[inline]
+    fn le(&self, other: &Self) -> bool {
+        **self <= **other
+    }
+
+    #[inline]
+    fn gt(&self, other: &Self) -> bool {
+        **self > **other
+    }
+
+    #[inline]
+    fn ge(&self, other: &Self) -> bool {
+        **self >= **other
+    }
+}
+
+impl<T, A> Ord for OwnedAlloc<T, A>
+where
+    T: ?Sized + Ord,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T, A> Hash for OwnedAlloc<T, A>
+where
+    T: ?Sized + Hash,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        (**self).hash(state)
+    }
+}
+
+impl<T, A> Borrow<T> for OwnedAlloc<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T, A> BorrowMut<T> for OwnedAlloc<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T, A> fmt::Display for OwnedAlloc<T, A>
+where
+    T: ?Sized + fmt::Display,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
 impl<T> Clone for OwnedAlloc<T>
 where
     T: Clone,
@@ -143,6 +794,60 @@ where
     }
 }
 
+#[cfg(feature = "nightly")]
+impl<T> OwnedAlloc<T>
+where
+    T: ?Sized + core::clone::CloneToUninit,
+{
+    /// Clones the value into `dest` instead of a new allocation, so that
+    /// pre-allocated or recycled blocks can be reused, even for dynamically
+    /// sized values. If the layout of `dest` is not the one of the value
+    /// (e.g. a slice of another length), the function panics. If cloning
+    /// panics, `dest` is freed.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::{OwnedAlloc, UninitAlloc};
+    ///
+    /// let frame = OwnedAlloc::collect_slice((0 .. 4).map(|i| i * 10));
+    /// let old = OwnedAlloc::collect_slice(0 .. 4);
+    /// let copy = frame.clone_into_uninit(old.drop_in_place());
+    /// assert_eq!(*copy, [0, 10, 20, 30]);
+    ///
+    /// let name = UninitAlloc::<str>::new_bytes(5).init_from_str("owned");
+    /// let copy = name.clone_into_uninit(UninitAlloc::<str>::new_bytes(5));
+    /// assert_eq!(&*copy, "owned");
+    /// ```
+    #[track_caller]
+    pub fn clone_into_uninit(&self, dest: UninitAlloc<T>) -> OwnedAlloc<T> {
+        assert_eq!(
+            Layout::for_value(&**self),
+            unsafe { Layout::for_value_raw(dest.raw().as_ptr()) },
+            "layout of the value and of the destination differ"
+        );
+        unsafe {
+            (**self).clone_to_uninit(dest.raw().as_ptr().cast());
+            OwnedAlloc::from_raw(dest.into_raw())
+        }
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T> OwnedAlloc<T>
+where
+    T: Clone,
+{
+    /// Clones the value into `dest` instead of a new allocation, so that
+    /// pre-allocated or recycled blocks can be reused. With the `nightly`
+    /// feature, dynamically sized values can be cloned as well.
+    #[inline]
+    pub fn clone_into_uninit(&self, dest: UninitAlloc<T>) -> OwnedAlloc<T> {
+        dest.init((**self).clone())
+    }
+}
+
 impl<T> From<T> for OwnedAlloc<T> {
     #[inline]
     fn from(value: T) -> Self {
@@ -150,8 +855,119 @@ impl<T> From<T> for OwnedAlloc<T> {
     }
 }
 
-unsafe impl<T> const Send for OwnedAlloc<T> where T: ?Sized + Send {}
-unsafe impl<T> const Sync for OwnedAlloc<T> where T: ?Sized + Sync {}
+unsafe impl<T, A> Send for OwnedAlloc<T, A>
+where
+    T: ?Sized + Send,
+    A: crate::alloc_api::Allocator + Send,
+{
+}
+
+unsafe impl<T, A> Sync for OwnedAlloc<T, A>
+where
+    T: ?Sized + Sync,
+    A: crate::alloc_api::Allocator + Sync,
+{
+}
+
+/// Coerces an allocation to one of an unsized type, as `Box` does, e.g.
+/// `OwnedAlloc<[T; N]>` to `OwnedAlloc<[T]>`, or `OwnedAlloc<T>` to
+/// `OwnedAlloc<dyn Trait>`.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::OwnedAlloc;
+/// use std::fmt::Display;
+///
+/// let slice: OwnedAlloc<[u8]> = OwnedAlloc::new([1, 2, 3]);
+/// assert_eq!(slice.len(), 3);
+///
+/// let items: [OwnedAlloc<dyn Display>; 2] = [OwnedAlloc::new(1), OwnedAlloc::new("two")];
+/// assert_eq!(format!("{} {}", items[0], items[1]), "1 two");
+/// ```
+#[cfg(feature = "nightly")]
+impl<T, U, A> core::ops::CoerceUnsized<OwnedAlloc<U, A>> for OwnedAlloc<T, A>
+where
+    T: ?Sized + core::marker::Unsize<U>,
+    U: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+}
+
+/// Allows `self: OwnedAlloc<Self>` methods of object-safe traits to be
+/// called on an `OwnedAlloc<dyn Trait>`. As for `Box`, only allocations of
+/// the crate's allocator, which takes no room, can be dispatched on.
+#[cfg(feature = "nightly")]
+impl<T, U> core::ops::DispatchFromDyn<OwnedAlloc<U>> for OwnedAlloc<T>
+where
+    T: ?Sized + core::marker::Unsize<U>,
+    U: ?Sized,
+{
+}
+
+/// Calls the allocated closure, so that an `OwnedAlloc<dyn FnMut(..)>` can be
+/// stored as a callback and invoked directly. Calling by value only needs
+/// `FnMut`, so closures which can only be called once must be moved out with
+/// `move_inner` first.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::OwnedAlloc;
+///
+/// let mut total = 0;
+/// let mut add: OwnedAlloc<dyn FnMut(u32) -> u32> = OwnedAlloc::new(|x| {
+///     total += x;
+///     total
+/// });
+/// assert_eq!((add(2), add(3)), (2, 5));
+///
+/// let double: OwnedAlloc<dyn Fn(u32) -> u32> = OwnedAlloc::new(|x| x * 2);
+/// let doubled: Vec<_> = (1 .. 4).map(&double).collect();
+/// assert_eq!(doubled, [2, 4, 6]);
+/// ```
+#[cfg(feature = "nightly")]
+impl<Args, F, A> FnOnce<Args> for OwnedAlloc<F, A>
+where
+    Args: core::marker::Tuple,
+    F: ?Sized + FnMut<Args>,
+    A: crate::alloc_api::Allocator,
+{
+    type Output = F::Output;
+
+    #[inline]
+    extern "rust-call" fn call_once(mut self, args: Args) -> F::Output {
+        (*self).call_mut(args)
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<Args, F, A> FnMut<Args> for OwnedAlloc<F, A>
+where
+    Args: core::marker::Tuple,
+    F: ?Sized + FnMut<Args>,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    extern "rust-call" fn call_mut(&mut self, args: Args) -> F::Output {
+        (**self).call_mut(args)
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<Args, F, A> Fn<Args> for OwnedAlloc<F, A>
+where
+    Args: core::marker::Tuple,
+    F: ?Sized + Fn<Args>,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    extern "rust-call" fn call(&self, args: Args) -> F::Output {
+        (**self).call(args)
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -172,6 +988,125 @@ mod test {
 
         assert_eq!(alloc.move_inner().0, 20);
     }
+    #[test]
+    fn collect_slice_with_wrong_hint() {
+        struct Lying(u32);
+
+        impl Iterator for Lying {
+            type Item = u32;
+
+            fn next(&mut self) -> Option<u32> {
+                self.0 += 1;
+                (self.0 <= 7).then_some(self.0)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (2, Some(2))
+            }
+        }
+
+        let alloc = OwnedAlloc::collect_slice(Lying(0));
+        assert_eq!(*alloc, [1, 2, 3, 4, 5, 6, 7]);
+        let empty = OwnedAlloc::collect_slice(Lying(7));
+        assert!(empty.is_empty());
+    }
+    #[test]
+    fn split_at_owned_moves_tail() {
+        use alloc::rc::Rc;
+
+        let tracker = Rc::new(());
+        let alloc = OwnedAlloc::collect_slice((0 .. 5).map(|_| tracker.clone()));
+        let start = alloc.as_non_null_slice().cast::<Rc<()>>();
+        let (head, tail) = alloc.split_at_owned(1);
+        assert_eq!((head.len(), tail.len()), (1, 4));
+        assert_eq!(Rc::strong_count(&tracker), 6);
+        assert_ne!(tail.as_non_null_slice().cast(), start);
+
+        let (empty, rest) = tail.split_at_owned(0);
+        assert!(empty.is_empty());
+        assert_eq!(rest.len(), 4);
+        let (all, none) = head.split_at_owned(1);
+        assert_eq!((all.len(), none.len()), (1, 0));
+    }
+    #[test]
+    fn drop_frees_through_the_allocator() {
+        use crate::{Allocator, LimitedAlloc};
+
+        let limited = LimitedAlloc::new(Allocator::new(), 64);
+        let alloc = OwnedAlloc::new_in([7u64; 4], &limited);
+        assert_eq!((*alloc, limited.used()), ([7; 4], 32));
+        assert!(OwnedAlloc::try_new_in([0u64; 5], &limited).is_err());
+        drop(alloc);
+        assert_eq!(limited.used(), 0);
+    }
+
+    #[test]
+    fn await_allocated_futures() {
+        use core::{
+            future::{ready, Future, IntoFuture},
+            pin::{pin, Pin},
+            task::{Context, Poll, Waker},
+        };
+
+        let futures = [OwnedAlloc::new(ready(1)), OwnedAlloc::new(ready(2))];
+        let sum = async move {
+            let mut sum = 0;
+            for future in futures {
+                sum += future.await;
+            }
+            sum
+        };
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(pin!(sum).poll(&mut cx), Poll::Ready(3));
+        let mut future = OwnedAlloc::new(ready('a')).into_future();
+        assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready('a'));
+    }
+
+    #[test]
+    fn iterate_trait_objects() {
+        use alloc::{boxed::Box, vec::Vec};
+
+        let mut iters: [OwnedAlloc<dyn DoubleEndedIterator<Item = u8>>; 2] = unsafe {
+            [
+                OwnedAlloc::from_box(Box::new(0 .. 3)),
+                OwnedAlloc::from_box(Box::new([7, 8, 9].into_iter())),
+            ]
+        };
+        assert_eq!(iters[1].nth_back(1), Some(8));
+        let all: Vec<u8> = iters.iter_mut().flat_map(|iter| iter.rev()).collect();
+        assert_eq!(all, [2, 1, 0, 7]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn compare_and_hash_by_value() {
+        use core::hash::BuildHasher;
+        use std::collections::hash_map::RandomState;
+
+        let (one, other_one) = (OwnedAlloc::new(1u32), OwnedAlloc::new(1u32));
+        assert_ne!(one.raw(), other_one.raw());
+        assert_eq!(one, other_one);
+        assert!(OwnedAlloc::new(2u32) > one);
+
+        let state = RandomState::new();
+        assert_eq!(state.hash_one(&one), state.hash_one(1u32));
+    }
+
+    #[test]
+    fn poll_pinned_futures() {
+        use core::{
+            future::Future,
+            task::{Context, Poll, Waker},
+        };
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let ready = |value| async move { value };
+        let mut futures = [OwnedAlloc::pin(ready(1)), OwnedAlloc::pin(ready(2))];
+        futures.swap(0, 1);
+        let polled = futures.each_mut().map(|future| future.as_mut().poll(&mut cx));
+        assert_eq!(polled, [Poll::Ready(2), Poll::Ready(1)]);
+    }
+
     #[test]
     fn from_into_std_box() {
         let boxed = unsafe { OwnedAlloc::new([5u128; 32]).into_box() };
@@ -179,4 +1114,23 @@ mod test {
         let raw = unsafe { OwnedAlloc::from_box(boxed) };
         assert_eq!(*raw, [5; 32]);
     }
+
+    #[test]
+    fn clone_into_recycled_block() {
+        use alloc::string::String;
+
+        let text = OwnedAlloc::new(String::from("recycled"));
+        let (old, block) = OwnedAlloc::new(String::new()).move_inner();
+        drop(old);
+        let block_ptr = block.raw();
+        let copy = text.clone_into_uninit(block);
+        assert_eq!((copy.raw(), copy.as_str()), (block_ptr, "recycled"));
+
+        #[cfg(feature = "nightly")]
+        {
+            let words = OwnedAlloc::collect_slice(["a", "b"].map(String::from));
+            let dest = crate::UninitAlloc::from(crate::RawVec::with_capacity(2));
+            assert_eq!(*words.clone_into_uninit(dest), ["a", "b"]);
+        }
+    }
 }
@@ -0,0 +1,304 @@
+use crate::{RawVec, RawVecError, UninitAlloc};
+#[cfg(feature = "os")]
+use crate::{sys, OsError};
+#[cfg(feature = "os")]
+use core::{
+    fmt,
+    ops::Range,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The page size of the target, known at compile time. The actual page size
+/// of the running system can be queried with `page_size`.
+#[cfg(all(target_arch = "aarch64", any(target_os = "macos", target_os = "ios")))]
+pub const PAGE_SIZE: usize = 16384;
+
+/// The page size of the target, known at compile time. The actual page size
+/// of the running system can be queried with `page_size`.
+#[cfg(not(all(target_arch = "aarch64", any(target_os = "macos", target_os = "ios"))))]
+pub const PAGE_SIZE: usize = 4096;
+
+/// A single page of memory, aligned to `PAGE_SIZE`. Allocating `[Page]`
+/// slices keeps the page alignment in the allocation's layout, so the memory
+/// is released with the same layout it was obtained with.
+///
+/// `PAGE_SIZE` is fixed at compile time: on systems with bigger pages (e.g.
+/// 16K or 64K pages on some aarch64 and ppc64 Linux systems), a `Page` is
+/// only part of a system page.
+#[cfg_attr(
+    all(target_arch = "aarch64", any(target_os = "macos", target_os = "ios")),
+    repr(C, align(16384))
+)]
+#[cfg_attr(
+    not(all(target_arch = "aarch64", any(target_os = "macos", target_os = "ios"))),
+    repr(C, align(4096))
+)]
+pub struct Page(pub [u8; PAGE_SIZE]);
+
+/// Access protection of a range of pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protection {
+    /// Any access faults.
+    NoAccess,
+    /// Pages can be read, but not written nor executed.
+    ReadOnly,
+    /// Pages can be read and written, but not executed.
+    ReadWrite,
+    /// Pages can be read and executed, but not written.
+    ReadExecute,
+    /// Pages can be read, written and executed.
+    ReadWriteExecute,
+}
+
+/// Returns the page size of the running system. Without the `os` feature,
+/// this is always `PAGE_SIZE`.
+#[cfg(feature = "os")]
+#[inline]
+pub fn page_size() -> usize {
+    static CACHED: AtomicUsize = AtomicUsize::new(0);
+    match CACHED.load(Ordering::Relaxed) {
+        0 => {
+            let size = sys::page_size();
+            CACHED.store(size, Ordering::Relaxed);
+            size
+        }
+        size => size,
+    }
+}
+
+/// Returns the page size of the running system. Without the `os` feature,
+/// this is always `PAGE_SIZE`.
+#[cfg(not(feature = "os"))]
+#[inline]
+pub const fn page_size() -> usize {
+    PAGE_SIZE
+}
+
+/// An allocator of pages. `alloc` and `alloc_bytes` take `Page`s from the
+/// heap, aligned to the compile-time `PAGE_SIZE`, which is not necessarily
+/// the page size of the running system. Only `map`, under the `os` feature,
+/// gives whole system pages, whose protection can be changed, as JIT
+/// buffers and guard-page tricks need.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{PageAlloc, PAGE_SIZE};
+///
+/// let pages = PageAlloc::new().alloc_bytes(PAGE_SIZE + 1);
+/// assert_eq!(unsafe { pages.raw().as_ref() }.len(), 2);
+/// assert_eq!(pages.raw().as_ptr() as *mut u8 as usize % PAGE_SIZE, 0);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PageAlloc {
+    page_size: usize,
+}
+
+impl PageAlloc {
+    /// Creates a page allocator, querying the page size of the system.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            page_size: page_size(),
+        }
+    }
+
+    /// The page size of the system, as queried when creating the allocator.
+    #[inline]
+    pub const fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// How many `Page`s, of `PAGE_SIZE` bytes rather than the system page
+    /// size, are needed to hold `bytes` bytes.
+    #[inline]
+    pub const fn pages_for(&self, bytes: usize) -> usize {
+        bytes.div_ceil(PAGE_SIZE)
+    }
+
+    panicking! {
+        /// Allocates `pages` uninitialized `Page`s from the heap. In case of
+        /// allocation error or overflow, the function panics.
+        #[inline]
+        pub fn alloc(&self, pages: usize) -> UninitAlloc<[Page]> {
+            UninitAlloc::from(RawVec::with_capacity(pages))
+        }
+    }
+
+    /// Allocates `pages` uninitialized `Page`s from the heap. In case of
+    /// allocation error or overflow, `Err` is returned.
+    #[inline]
+    pub fn try_alloc(&self, pages: usize) -> Result<UninitAlloc<[Page]>, RawVecError> {
+        RawVec::try_with_capacity(pages).map(UninitAlloc::from)
+    }
+
+    panicking! {
+        /// Allocates enough `Page`s from the heap to hold `bytes` bytes. In
+        /// case of allocation error or overflow, the function panics.
+        #[inline]
+        pub fn alloc_bytes(&self, bytes: usize) -> UninitAlloc<[Page]> {
+            self.alloc(self.pages_for(bytes))
+        }
+    }
+
+    /// Maps enough readable and writable pages of the system page size to
+    /// hold `bytes` bytes, straight from the operating system rather than
+    /// the heap, so that their protection can be changed without affecting
+    /// other allocations. In case of error, `Err` is returned. Panics if the
+    /// size in bytes overflows.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::{PageAlloc, Protection};
+    ///
+    /// let alloc = PageAlloc::new();
+    /// let pages = alloc.map(alloc.page_size() + 1).unwrap();
+    /// assert_eq!(pages.len(), 2 * alloc.page_size());
+    /// unsafe {
+    ///     pages.as_ptr().as_ptr().write(0xC3);
+    ///     pages.protect(0 .. 1, Protection::ReadExecute).unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "os")]
+    pub fn map(&self, bytes: usize) -> Result<MappedPages, OsError> {
+        let len = bytes
+            .div_ceil(self.page_size)
+            .checked_mul(self.page_size)
+            .expect("capacity overflow");
+        if len == 0 {
+            return Ok(MappedPages {
+                ptr: NonNull::dangling(),
+                len,
+                page_size: self.page_size,
+            });
+        }
+        let ptr = sys::reserve(len)?;
+        if let Err(err) = unsafe { sys::commit(ptr, len) } {
+            unsafe { sys::release(ptr, len) }
+            return Err(err);
+        }
+        Ok(MappedPages {
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            len,
+            page_size: self.page_size,
+        })
+    }
+}
+
+impl Default for PageAlloc {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pages mapped by `PageAlloc::map`, unmapped when dropped. Their memory is
+/// only reachable through raw pointers, since `protect` can revoke access
+/// to it.
+#[cfg(feature = "os")]
+pub struct MappedPages {
+    ptr: NonNull<u8>,
+    len: usize,
+    page_size: usize,
+}
+
+#[cfg(feature = "os")]
+impl MappedPages {
+    /// The start of the pages.
+    #[inline]
+    pub const fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    /// Size of the pages in bytes.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if no page is mapped.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Changes the access protection of the given range of pages, counted in
+    /// system pages. If the range is out of bounds, the function panics.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because no live reference or pointer access
+    /// may rely on a permission being revoked, and executing the pages runs
+    /// whatever was written to them.
+    #[track_caller]
+    pub unsafe fn protect(&self, pages: Range<usize>, prot: Protection) -> Result<(), OsError> {
+        let count = self.len / self.page_size;
+        assert!(
+            pages.start <= pages.end && pages.end <= count,
+            "Page range out of the mapping"
+        );
+        if pages.start == pages.end {
+            return Ok(());
+        }
+        let start = self.ptr.as_ptr().add(pages.start * self.page_size);
+        sys::protect(start, (pages.end - pages.start) * self.page_size, prot)
+    }
+}
+
+#[cfg(feature = "os")]
+impl Drop for MappedPages {
+    #[inline]
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe { sys::release(self.ptr.as_ptr(), self.len) }
+        }
+    }
+}
+
+#[cfg(feature = "os")]
+impl fmt::Debug for MappedPages {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MappedPages {{ ptr: {:?}, len: {} }}", self.ptr, self.len)
+    }
+}
+
+#[cfg(feature = "os")]
+unsafe impl Send for MappedPages {}
+#[cfg(feature = "os")]
+unsafe impl Sync for MappedPages {}
+
+#[cfg(test)]
+mod test {
+    use super::{PageAlloc, PAGE_SIZE};
+
+    #[test]
+    fn pages_are_aligned() {
+        let alloc = PageAlloc::new();
+        let pages = alloc.alloc(3);
+        assert_eq!(unsafe { pages.raw().as_ref() }.len(), 3);
+        assert_eq!(pages.raw().cast::<u8>().as_ptr() as usize % PAGE_SIZE, 0);
+        assert_eq!(alloc.pages_for(0), 0);
+        assert_eq!(alloc.pages_for(PAGE_SIZE * 2), 2);
+    }
+
+    #[cfg(feature = "os")]
+    #[test]
+    fn protect_round_trip() {
+        use super::Protection;
+
+        let alloc = PageAlloc::new();
+        let pages = alloc.map(3 * alloc.page_size()).unwrap();
+        assert_eq!(pages.as_ptr().as_ptr() as usize % alloc.page_size(), 0);
+        unsafe {
+            pages.protect(1 .. 2, Protection::NoAccess).unwrap();
+            pages.as_ptr().as_ptr().write(1);
+            pages.protect(0 .. 3, Protection::ReadOnly).unwrap();
+            assert_eq!(pages.as_ptr().as_ptr().read(), 1);
+        }
+        assert!(alloc.map(0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{FromBytesError, OwnedAlloc, RawVec, RawVecError, UninitAlloc};
+use bytemuck::Pod;
+use core::{mem, ptr};
+
+impl<T> OwnedAlloc<T>
+where
+    T: Pod,
+{
+    /// Allocates a value holding a copy of `bytes`, which must be exactly as
+    /// long as a `T`, but need not be aligned, e.g. a packet header read off
+    /// the network.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::OwnedAlloc;
+    ///
+    /// let packet = [1, 0, 0, 0, 9, 0, 0, 0, 0xff];
+    /// let header = OwnedAlloc::<[u32; 2]>::from_bytes(&packet[.. 8]).unwrap();
+    /// assert_eq!(u32::from_le(header[1]), 9);
+    /// assert_eq!(header.as_bytes(), &packet[.. 8]);
+    /// assert!(OwnedAlloc::<u64>::from_bytes(&packet).is_err());
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FromBytesError> {
+        if bytes.len() != mem::size_of::<T>() {
+            return Err(FromBytesError::Size {
+                size: mem::size_of::<T>(),
+                len: bytes.len(),
+            });
+        }
+        let alloc = UninitAlloc::<T>::try_new()
+            .map_err(|err| FromBytesError::Alloc(RawVecError::Alloc(err)))?;
+        unsafe {
+            let dest = alloc.raw().as_ptr().cast::<u8>();
+            ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+            Ok(OwnedAlloc::from_raw(alloc.into_raw()))
+        }
+    }
+
+    /// The bytes of the value.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&**self)
+    }
+
+    /// The bytes of the value, mutably: any bytes make a valid `T`.
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        bytemuck::bytes_of_mut(&mut **self)
+    }
+}
+
+impl<T> OwnedAlloc<[T]>
+where
+    T: Pod,
+{
+    /// Allocates a slice holding a copy of `bytes`, which must be a whole
+    /// number of `T`s, but need not be aligned, e.g. records read from disk.
+    #[track_caller]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FromBytesError> {
+        let size = mem::size_of::<T>();
+        if size == 0 || !bytes.len().is_multiple_of(size) {
+            return Err(FromBytesError::Size {
+                size,
+                len: bytes.len(),
+            });
+        }
+        let storage = RawVec::<T>::try_with_capacity(bytes.len() / size)
+            .map_err(FromBytesError::Alloc)?;
+        unsafe {
+            let dest = storage.raw().as_ptr().cast::<u8>();
+            ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+            Ok(OwnedAlloc::from_raw(storage.into_raw_slice()))
+        }
+    }
+
+    /// The bytes of the elements.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+
+    /// The bytes of the elements, mutably: any bytes make valid `T`s.
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        bytemuck::cast_slice_mut(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{FromBytesError, OwnedAlloc};
+
+    #[test]
+    fn records_from_unaligned_bytes() {
+        let file = [0u8, 1, 0, 2, 0, 3, 0];
+        let mut records = OwnedAlloc::<[u16]>::from_bytes(&file[1 ..]).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records.as_bytes(), &file[1 ..]);
+
+        records.as_bytes_mut()[.. 2].copy_from_slice(&7u16.to_ne_bytes());
+        assert_eq!(records[0], 7);
+
+        match OwnedAlloc::<[u16]>::from_bytes(&file) {
+            Err(FromBytesError::Size { size, len }) => assert_eq!((size, len), (2, 7)),
+            _ => panic!("odd length accepted"),
+        }
+        assert!(OwnedAlloc::<[()]>::from_bytes(&[]).is_err());
+    }
+}
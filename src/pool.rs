@@ -0,0 +1,393 @@
+use crate::{events::Listener, CacheEvent};
+use alloc::vec::Vec;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    hint,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::Waker,
+};
+
+/// A pool of reusable objects, created on demand and recycled when the
+/// handles to them are dropped, with a cap on the objects checked out at
+/// once. When the cap is reached, `try_acquire` fails instead of creating
+/// another object, so the pool doubles as admission control: a server can
+/// turn requests away rather than queue work it has no resources for.
+///
+/// With the `std` feature, `acquire_blocking` waits for an object instead;
+/// with the `async` feature, `poll_acquire` registers the task to be woken
+/// when one is released.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::Pool;
+///
+/// let pool = Pool::with_limit(|| Vec::<u8>::with_capacity(4096), 2);
+/// let mut first = pool.try_acquire().unwrap();
+/// first.extend_from_slice(b"request");
+/// let second = pool.try_acquire().unwrap();
+/// assert!(pool.try_acquire().is_none());
+///
+/// drop(second);
+/// assert_eq!(pool.idle(), 1);
+/// assert!(pool.try_acquire().is_some());
+/// ```
+pub struct Pool<T> {
+    create: fn() -> T,
+    limit: usize,
+    outstanding: AtomicUsize,
+    locked: AtomicBool,
+    idle: UnsafeCell<Vec<T>>,
+    wakers: UnsafeCell<Vec<Waker>>,
+    listener: Listener,
+    #[cfg(feature = "std")]
+    released: (std::sync::Mutex<()>, std::sync::Condvar),
+}
+
+impl<T> Pool<T> {
+    /// Creates a pool making new objects with `create`, without a cap. No
+    /// object is created until one is acquired.
+    #[inline]
+    pub fn new(create: fn() -> T) -> Self {
+        Self::with_limit(create, usize::MAX)
+    }
+
+    /// Creates a pool making new objects with `create`, with at most `limit`
+    /// objects checked out at once.
+    #[inline]
+    pub fn with_limit(create: fn() -> T, limit: usize) -> Self {
+        Self {
+            create,
+            limit,
+            outstanding: AtomicUsize::new(0),
+            locked: AtomicBool::new(false),
+            idle: UnsafeCell::new(Vec::new()),
+            wakers: UnsafeCell::new(Vec::new()),
+            listener: Listener::NONE,
+            #[cfg(feature = "std")]
+            released: (std::sync::Mutex::new(()), std::sync::Condvar::new()),
+        }
+    }
+
+    /// Registers `listener` to be called on the events of the pool,
+    /// replacing the previous one: acquiring reports a hit, a miss, or an
+    /// overflow if `limit` objects are already checked out, and `clear` and
+    /// `shrink_to` report an eviction per object dropped.
+    #[inline]
+    pub fn set_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(CacheEvent) + Send + Sync + 'static,
+    {
+        self.listener = Listener::new(listener);
+    }
+
+    /// Checks out an idle object, or a new one. Returns `None` if `limit`
+    /// objects are already checked out.
+    pub fn try_acquire(&self) -> Option<Pooled<'_, T>> {
+        let limit = self.limit;
+        let size = mem::size_of::<T>();
+        let admitted = self
+            .outstanding
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |outstanding| {
+                Some(outstanding + 1).filter(|&outstanding| outstanding <= limit)
+            });
+        if admitted.is_err() {
+            self.listener.notify(CacheEvent::Overflow { size });
+            return None;
+        }
+        let value = match self.with_idle(|idle, _| idle.pop()) {
+            Some(value) => {
+                self.listener.notify(CacheEvent::Hit { size });
+                value
+            },
+            None => {
+                self.listener.notify(CacheEvent::Miss { size });
+                (self.create)()
+            },
+        };
+        Some(Pooled {
+            value: ManuallyDrop::new(value),
+            pool: self,
+        })
+    }
+
+    /// Checks out an object, waiting for one to be released if `limit`
+    /// objects are already checked out.
+    #[cfg(feature = "std")]
+    pub fn acquire_blocking(&self) -> Pooled<'_, T> {
+        let (lock, released) = &self.released;
+        let mut guard = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        loop {
+            if let Some(pooled) = self.try_acquire() {
+                return pooled;
+            }
+            guard = released.wait(guard).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Checks out an object, or registers the task to be woken when one is
+    /// released if `limit` objects are already checked out. Every waiting
+    /// task is woken on release, and all but one find the pool exhausted
+    /// again.
+    #[cfg(feature = "async")]
+    pub fn poll_acquire(&self, cx: &mut Context<'_>) -> Poll<Pooled<'_, T>> {
+        if let Some(pooled) = self.try_acquire() {
+            return Poll::Ready(pooled);
+        }
+        self.with_idle(|_, wakers| {
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        });
+        // An object released before the waker was registered woke nobody.
+        match self.try_acquire() {
+            Some(pooled) => Poll::Ready(pooled),
+            None => Poll::Pending,
+        }
+    }
+
+    /// The maximum number of objects checked out at once.
+    #[inline]
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of objects currently checked out.
+    #[inline]
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::Relaxed)
+    }
+
+    /// Number of objects which can still be checked out.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.limit.saturating_sub(self.outstanding())
+    }
+
+    /// Number of objects waiting in the pool to be reused.
+    #[inline]
+    pub fn idle(&self) -> usize {
+        self.with_idle(|idle, _| idle.len())
+    }
+
+    /// Drops the idle objects, returning how many there were.
+    #[inline]
+    pub fn clear(&self) -> usize {
+        let idle = self.take_idle();
+        self.evicted(idle.len())
+    }
+
+    /// Drops the idle objects beyond the first `floor`, returning how many
+    /// were dropped. Meant for trim handlers keeping a warm floor of objects.
+    #[inline]
+    pub fn shrink_to(&self, floor: usize) -> usize {
+        let excess = self.with_idle(|idle, _| idle.split_off(floor.min(idle.len())));
+        self.evicted(excess.len())
+    }
+
+    /// Reports `count` evictions, returning `count`.
+    #[inline]
+    fn evicted(&self, count: usize) -> usize {
+        let size = mem::size_of::<T>();
+        self.listener.notify_many(CacheEvent::Evict { size }, count);
+        count
+    }
+
+    /// Adds `value` to the idle objects.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub(crate) fn push_idle(&self, value: T) {
+        self.with_idle(|idle, _| idle.push(value));
+    }
+
+    /// Takes the idle objects out of the pool.
+    #[inline]
+    pub(crate) fn take_idle(&self) -> Vec<T> {
+        self.with_idle(|idle, _| mem::take(idle))
+    }
+
+    /// Runs `f` on the idle objects and the waiting tasks, with the pool
+    /// locked.
+    fn with_idle<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Vec<T>, &mut Vec<Waker>) -> R,
+    {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let ret = f(unsafe { &mut *self.idle.get() }, unsafe { &mut *self.wakers.get() });
+        self.locked.store(false, Ordering::Release);
+        ret
+    }
+
+    /// Gives a checked out object back, if any, and wakes those waiting.
+    fn release(&self, value: Option<T>) {
+        let wakers = self.with_idle(|idle, wakers| {
+            idle.extend(value);
+            mem::take(wakers)
+        });
+        self.outstanding.fetch_sub(1, Ordering::Release);
+        wakers.into_iter().for_each(Waker::wake);
+        #[cfg(feature = "std")]
+        {
+            let (lock, released) = &self.released;
+            drop(lock.lock());
+            released.notify_one();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Pool<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Pool {{ outstanding: {}, idle: {}, limit: {} }}",
+            self.outstanding(),
+            self.idle(),
+            self.limit
+        )
+    }
+}
+
+unsafe impl<T> Send for Pool<T> where T: Send {}
+unsafe impl<T> Sync for Pool<T> where T: Send {}
+
+/// An object checked out of a `Pool`. Dropping it gives the object back to
+/// the pool.
+pub struct Pooled<'p, T> {
+    value: ManuallyDrop<T>,
+    pool: &'p Pool<T>,
+}
+
+impl<'p, T> Pooled<'p, T> {
+    /// Takes the object out of the pool for good, freeing its place under
+    /// the limit.
+    #[inline]
+    pub fn detach(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        let value = unsafe { ManuallyDrop::take(&mut this.value) };
+        this.pool.release(None);
+        value
+    }
+}
+
+impl<'p, T> Deref for Pooled<'p, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<'p, T> DerefMut for Pooled<'p, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<'p, T> Drop for Pooled<'p, T> {
+    #[inline]
+    fn drop(&mut self) {
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        self.pool.release(Some(value));
+    }
+}
+
+impl<'p, T> fmt::Debug for Pooled<'p, T>
+where
+    T: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pooled({:?})", *self.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pool;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn limit_admits_after_release() {
+        let pool = Pool::with_limit(Vec::<u32>::new, 2);
+        let mut a = pool.try_acquire().unwrap();
+        a.push(1);
+        let b = pool.try_acquire().unwrap();
+        assert!(pool.try_acquire().is_none());
+        assert_eq!(pool.available(), 0);
+
+        drop(a);
+        let c = pool.try_acquire().unwrap();
+        assert_eq!(*c, [1]);
+        assert_eq!(b.detach(), []);
+        assert_eq!(pool.outstanding(), 1);
+        drop(c);
+        assert_eq!((pool.idle(), pool.clear(), pool.idle()), (1, 1, 0));
+    }
+
+    #[test]
+    fn listener_sees_overflows_and_evictions() {
+        use crate::CacheEvent;
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+        static OVERFLOWS: AtomicUsize = AtomicUsize::new(0);
+        static EVICTED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut pool = Pool::with_limit(|| [0u16; 4], 1);
+        pool.set_listener(|event| match event {
+            CacheEvent::Overflow { size: 8 } => drop(OVERFLOWS.fetch_add(1, Relaxed)),
+            CacheEvent::Evict { size: 8 } => drop(EVICTED.fetch_add(1, Relaxed)),
+            _ => (),
+        });
+        let held = pool.try_acquire().unwrap();
+        assert!(pool.try_acquire().is_none());
+        drop(held);
+        assert_eq!(pool.shrink_to(0), 1);
+        assert_eq!((OVERFLOWS.load(Relaxed), EVICTED.load(Relaxed)), (1, 1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn blocking_waits_for_release() {
+        use alloc::sync::Arc;
+
+        let pool = Arc::new(Pool::with_limit(|| 0u64, 1));
+        let mut held = pool.try_acquire().unwrap();
+        *held = 41;
+        let waiter = {
+            let pool = pool.clone();
+            std::thread::spawn(move || *pool.acquire_blocking() + 1)
+        };
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        drop(held);
+        assert_eq!(waiter.join().unwrap(), 42);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn pending_until_release() {
+        use core::task::{Context, Waker};
+
+        let pool = Pool::with_limit(|| 7u8, 1);
+        let mut cx = Context::from_waker(Waker::noop());
+        let held = pool.poll_acquire(&mut cx);
+        assert!(held.is_ready());
+        assert!(pool.poll_acquire(&mut cx).is_pending());
+        drop(held);
+        assert!(pool.poll_acquire(&mut cx).is_ready());
+    }
+}
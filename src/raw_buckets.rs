@@ -0,0 +1,366 @@
+use crate::{AllocError, Allocator, LayoutError, RawVecError};
+use core::{alloc::Layout, fmt, marker::PhantomData, ptr::NonNull};
+
+/// Number of control bytes probed together, and mirrored after the end of
+/// the control array so a group can be loaded at any position.
+pub const GROUP_WIDTH: usize = 16;
+
+/// Control byte of a bucket which was never filled.
+pub const CTRL_EMPTY: u8 = 0xFF;
+
+/// Control byte of a bucket whose value was removed (a tombstone).
+pub const CTRL_DELETED: u8 = 0x80;
+
+static EMPTY_GROUP: [u8; GROUP_WIDTH] = [CTRL_EMPTY; GROUP_WIDTH];
+
+/// The first 57 bits of a hash, selecting the first bucket to probe.
+#[inline]
+pub const fn h1(hash: u64) -> usize {
+    hash as usize
+}
+
+/// The last 7 bits of a hash, stored in the control byte of a full bucket.
+#[inline]
+pub const fn h2(hash: u64) -> u8 {
+    (hash >> 57) as u8
+}
+
+/// Number of buckets holding `cap` values at a load factor of 7/8, or `None`
+/// on overflow.
+#[inline]
+pub const fn capacity_to_buckets(cap: usize) -> Option<usize> {
+    if cap < 8 {
+        return Some(if cap < 4 { 4 } else { 8 });
+    }
+    match cap.checked_mul(8) {
+        Some(scaled) => (scaled / 7).checked_next_power_of_two(),
+        None => None,
+    }
+}
+
+/// Number of values `buckets` buckets hold at a load factor of 7/8. Tables
+/// of less than 8 buckets keep one bucket empty instead.
+#[inline]
+pub const fn buckets_to_capacity(buckets: usize) -> usize {
+    if buckets < 8 {
+        buckets.saturating_sub(1)
+    } else {
+        buckets / 8 * 7
+    }
+}
+
+/// Raw storage of an open-addressing hash table in the SwissTable layout: a
+/// power-of-two array of buckets followed by one control byte per bucket,
+/// plus `GROUP_WIDTH` mirrored bytes, in a single allocation.
+///
+/// A control byte is `CTRL_EMPTY`, `CTRL_DELETED`, or the `h2` of the hash
+/// of the value in a full bucket. Like a `RawVec`, the storage never drops
+/// values: the control bytes tell which buckets hold one.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::RawBuckets;
+///
+/// let hash = |key: &u64| key.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+/// let mut table = RawBuckets::<u64>::with_capacity(4);
+/// for key in [3, 14, 15] {
+///     let index = table.find_insert_slot(hash(&key));
+///     unsafe {
+///         table.bucket(index).as_ptr().write(key);
+///         table.set_ctrl_h2(index, hash(&key));
+///     }
+/// }
+///
+/// let found = table.find(hash(&14), |index| unsafe {
+///     *table.bucket(index).as_ptr() == 14
+/// });
+/// assert!(found.is_some());
+/// assert_eq!(table.full_buckets().count(), 3);
+/// ```
+pub struct RawBuckets<T> {
+    data: NonNull<T>,
+    ctrl: NonNull<u8>,
+    buckets: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> RawBuckets<T> {
+    /// Creates a table of no bucket. No allocation is performed.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            data: NonNull::dangling(),
+            ctrl: NonNull::from(&EMPTY_GROUP).cast(),
+            buckets: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    panicking! {
+        /// Creates a table with enough buckets for `cap` values, all empty. In
+        /// case of allocation error or overflow, the function panics.
+        #[inline]
+        pub fn with_capacity(cap: usize) -> Self {
+            match Self::try_with_capacity(cap) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Creates a table with enough buckets for `cap` values, all empty. In
+    /// case of allocation error or overflow, `Err` is returned.
+    #[inline]
+    pub fn try_with_capacity(cap: usize) -> Result<Self, RawVecError> {
+        if cap == 0 {
+            return Ok(Self::new());
+        }
+        let buckets = capacity_to_buckets(cap).ok_or(LayoutError)?;
+        Self::try_with_buckets(buckets)
+    }
+
+    /// Creates a table of `buckets` buckets, all empty. `buckets` must be a
+    /// power of two. In case of allocation error or overflow, `Err` is
+    /// returned.
+    pub fn try_with_buckets(buckets: usize) -> Result<Self, RawVecError> {
+        assert!(buckets.is_power_of_two(), "Bucket count is not a power of two");
+        let (layout, ctrl_offset) = Self::layout(buckets)?;
+        let ptr = crate::alloc_api::Allocator::allocate(&Allocator::new(), layout)
+            .map_err(|_| AllocError { layout })?
+            .cast::<u8>();
+        let mut this = Self {
+            data: ptr.cast(),
+            ctrl: unsafe { NonNull::new_unchecked(ptr.as_ptr().add(ctrl_offset)) },
+            buckets,
+            _marker: PhantomData,
+        };
+        this.clear_ctrl();
+        Ok(this)
+    }
+
+    /// Number of buckets.
+    #[inline]
+    pub fn buckets(&self) -> usize {
+        self.buckets
+    }
+
+    /// Number of values the table holds at its maximum load factor.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        buckets_to_capacity(self.buckets)
+    }
+
+    /// The raw pointer to the control bytes: `buckets() + GROUP_WIDTH` of
+    /// them, the last `GROUP_WIDTH` mirroring the first ones.
+    #[inline]
+    pub fn ctrl_ptr(&self) -> NonNull<u8> {
+        self.ctrl
+    }
+
+    /// The control byte of bucket `index`.
+    ///
+    /// # Safety
+    /// `index` must be less than `buckets()`.
+    #[inline]
+    pub unsafe fn ctrl(&self, index: usize) -> u8 {
+        *self.ctrl.as_ptr().add(index)
+    }
+
+    /// Sets the control byte of bucket `index`, and its mirror.
+    ///
+    /// # Safety
+    /// `index` must be less than `buckets()`.
+    #[inline]
+    pub unsafe fn set_ctrl(&mut self, index: usize, ctrl: u8) {
+        let mirror = (index.wrapping_sub(GROUP_WIDTH) & (self.buckets - 1)) + GROUP_WIDTH;
+        *self.ctrl.as_ptr().add(index) = ctrl;
+        *self.ctrl.as_ptr().add(mirror) = ctrl;
+    }
+
+    /// Marks bucket `index` as full with a value of hash `hash`.
+    ///
+    /// # Safety
+    /// `index` must be less than `buckets()`.
+    #[inline]
+    pub unsafe fn set_ctrl_h2(&mut self, index: usize, hash: u64) {
+        self.set_ctrl(index, h2(hash))
+    }
+
+    /// The raw pointer to bucket `index`, initialized or not.
+    ///
+    /// # Safety
+    /// `index` must be less than `buckets()`.
+    #[inline]
+    pub unsafe fn bucket(&self, index: usize) -> NonNull<T> {
+        NonNull::new_unchecked(self.data.as_ptr().add(index))
+    }
+
+    /// Marks every bucket as empty, without dropping values.
+    pub fn clear_ctrl(&mut self) {
+        if self.buckets > 0 {
+            unsafe {
+                self.ctrl
+                    .as_ptr()
+                    .write_bytes(CTRL_EMPTY, self.buckets + GROUP_WIDTH)
+            }
+        }
+    }
+
+    /// Probes the table for a full bucket with the `h2` of `hash` accepted by
+    /// `eq`, stopping at the first empty bucket.
+    pub fn find<F>(&self, hash: u64, mut eq: F) -> Option<usize>
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let h2 = h2(hash);
+        for index in self.probe_seq(hash) {
+            let ctrl = unsafe { self.ctrl(index) };
+            if ctrl == h2 && eq(index) {
+                return Some(index);
+            }
+            if ctrl == CTRL_EMPTY {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// The first empty or deleted bucket of the probe sequence of `hash`.
+    /// Panics if every bucket is full, which cannot happen while the table
+    /// holds fewer values than its capacity.
+    pub fn find_insert_slot(&self, hash: u64) -> usize {
+        let found = self
+            .probe_seq(hash)
+            .find(|&index| unsafe { self.ctrl(index) } & 0x80 != 0);
+        match found {
+            Some(index) => index,
+            None => panic!("No free bucket in the table"),
+        }
+    }
+
+    /// Iterates over the indices of full buckets.
+    #[inline]
+    pub fn full_buckets(&self) -> impl Iterator<Item = usize> + '_ {
+        (0 .. self.buckets).filter(move |&index| unsafe { self.ctrl(index) } & 0x80 == 0)
+    }
+
+    /// Moves the values of full buckets into a new table with enough buckets
+    /// for `cap` values, rehashing them with `hasher`. In case of allocation
+    /// error or overflow, `Err` is returned and the table is untouched.
+    ///
+    /// # Safety
+    /// Full buckets must hold initialized values, and `cap` must be at least
+    /// the number of full buckets.
+    pub unsafe fn try_resize<H>(&mut self, cap: usize, hasher: H) -> Result<(), RawVecError>
+    where
+        H: Fn(&T) -> u64,
+    {
+        let mut new = Self::try_with_capacity(cap)?;
+        for index in self.full_buckets() {
+            let src = self.bucket(index);
+            let hash = hasher(src.as_ref());
+            let dst = new.find_insert_slot(hash);
+            new.bucket(dst).as_ptr().copy_from_nonoverlapping(src.as_ptr(), 1);
+            new.set_ctrl_h2(dst, hash);
+        }
+        // The values now belong to the new table.
+        self.clear_ctrl();
+        *self = new;
+        Ok(())
+    }
+
+    /// Visits every bucket of the probe sequence of `hash` once: groups of
+    /// `GROUP_WIDTH` buckets, at triangular offsets.
+    fn probe_seq(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let mask = self.buckets.wrapping_sub(1);
+        let groups = self.buckets.div_ceil(GROUP_WIDTH);
+        let width = GROUP_WIDTH.min(self.buckets);
+        let mut pos = h1(hash) & mask;
+        (0 .. groups).flat_map(move |stride| {
+            let start = pos;
+            pos = (pos + (stride + 1) * GROUP_WIDTH) & mask;
+            (0 .. width).map(move |offset| (start + offset) & mask)
+        })
+    }
+
+    fn layout(buckets: usize) -> Result<(Layout, usize), LayoutError> {
+        let data = Layout::array::<T>(buckets)?;
+        let ctrl = Layout::array::<u8>(buckets + GROUP_WIDTH)?;
+        let (layout, ctrl_offset) = data.extend(ctrl)?;
+        Ok((layout.align_to(GROUP_WIDTH)?.pad_to_align(), ctrl_offset))
+    }
+}
+
+impl<T> Default for RawBuckets<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for RawBuckets<T> {
+    fn drop(&mut self) {
+        if self.buckets > 0 {
+            let (layout, _) = Self::layout(self.buckets).unwrap();
+            unsafe {
+                crate::alloc_api::Allocator::deallocate(&Allocator::new(), self.data.cast(), layout)
+            }
+        }
+    }
+}
+
+unsafe impl<T> Send for RawBuckets<T> where T: Send {}
+unsafe impl<T> Sync for RawBuckets<T> where T: Sync {}
+
+impl<T> fmt::Debug for RawBuckets<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawBuckets {{ buckets: {}, capacity: {} }}",
+            self.buckets,
+            self.capacity()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{capacity_to_buckets, RawBuckets};
+
+    fn hash(key: &u64) -> u64 {
+        // Everything in the same bucket, to exercise probing.
+        key & 0xFF00_0000_0000_0000
+    }
+
+    #[test]
+    fn collisions_survive_resize() {
+        assert_eq!(capacity_to_buckets(7), Some(8));
+        assert_eq!(capacity_to_buckets(100), Some(128));
+
+        let mut table = RawBuckets::<u64>::with_capacity(7);
+        for key in 0 .. 7u64 {
+            let index = table.find_insert_slot(hash(&key));
+            unsafe {
+                table.bucket(index).as_ptr().write(key);
+                table.set_ctrl_h2(index, hash(&key));
+            }
+        }
+        unsafe { table.try_resize(40, hash).unwrap() };
+        assert_eq!(table.buckets(), 64);
+        for key in 0 .. 7u64 {
+            let found = table.find(hash(&key), |index| unsafe {
+                *table.bucket(index).as_ptr() == key
+            });
+            assert!(found.is_some());
+        }
+        let mirrored = unsafe { *table.ctrl_ptr().as_ptr().add(64) };
+        assert_eq!(mirrored, unsafe { table.ctrl(0) });
+        assert_eq!(table.full_buckets().count(), 7);
+    }
+}
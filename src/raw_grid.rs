@@ -0,0 +1,229 @@
+use crate::{LayoutError, RawVec, RawVecError};
+use core::{fmt, ptr::NonNull};
+
+/// A raw two-dimensional buffer of `rows` × `cols` elements, stored row by
+/// row with `stride` elements between the starts of two rows. The stride is
+/// at least `cols`, leaving padding at the end of each row, e.g. to align
+/// rows of an image for SIMD.
+///
+/// Like a `RawVec`, the grid never initializes nor drops elements.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::RawGrid;
+///
+/// let mut grid = RawGrid::<u8>::with_stride(2, 3, 4);
+/// for row in 0 .. 2 {
+///     for col in 0 .. 3 {
+///         unsafe { grid.get_ptr(row, col).as_ptr().write((row * 3 + col) as u8) };
+///     }
+/// }
+///
+/// grid.resize(3, 2);
+/// unsafe {
+///     assert_eq!(grid.row(0), [0, 1]);
+///     assert_eq!(grid.row(1), [3, 4]);
+/// }
+/// ```
+pub struct RawGrid<T> {
+    storage: RawVec<T>,
+    rows: usize,
+    cols: usize,
+    stride: usize,
+}
+
+impl<T> RawGrid<T> {
+    /// Creates an empty grid. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            storage: RawVec::new(),
+            rows: 0,
+            cols: 0,
+            stride: 0,
+        }
+    }
+
+    panicking! {
+        /// Creates a grid of `rows` × `cols` elements with no padding. In case
+        /// of allocation error or overflow, the function panics.
+        #[inline]
+        pub fn with_dims(rows: usize, cols: usize) -> Self {
+            Self::with_stride(rows, cols, cols)
+        }
+    }
+
+    panicking! {
+        /// Creates a grid of `rows` × `cols` elements with `stride` elements per
+        /// row. In case of allocation error or overflow, or if `stride` is less
+        /// than `cols`, the function panics.
+        #[inline]
+        pub fn with_stride(rows: usize, cols: usize, stride: usize) -> Self {
+            match Self::try_with_stride(rows, cols, stride) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Creates a grid of `rows` × `cols` elements with `stride` elements per
+    /// row. In case of allocation error or overflow, `Err` is returned. If
+    /// `stride` is less than `cols`, the function panics.
+    #[inline]
+    pub fn try_with_stride(rows: usize, cols: usize, stride: usize) -> Result<Self, RawVecError> {
+        assert!(stride >= cols, "Stride is less than the number of columns");
+        let len = rows.checked_mul(stride).ok_or(LayoutError)?;
+        Ok(Self {
+            storage: RawVec::try_with_capacity(len)?,
+            rows,
+            cols,
+            stride,
+        })
+    }
+
+    /// Number of rows.
+    #[inline]
+    pub const fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of columns.
+    #[inline]
+    pub const fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Number of elements between the starts of two consecutive rows.
+    #[inline]
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The raw non-null pointer to the first element.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<T> {
+        self.storage.raw()
+    }
+
+    /// The offset of the element at `row`, `col` from the first element.
+    /// Panics if the position is out of bounds.
+    #[inline]
+    pub fn offset(&self, row: usize, col: usize) -> usize {
+        assert!(
+            row < self.rows && col < self.cols,
+            "Position ({}, {}) out of bounds of a {}x{} grid",
+            row,
+            col,
+            self.rows,
+            self.cols
+        );
+        row * self.stride + col
+    }
+
+    /// The raw non-null pointer to the element at `row`, `col`. Panics if
+    /// the position is out of bounds.
+    #[inline]
+    pub fn get_ptr(&self, row: usize, col: usize) -> NonNull<T> {
+        let offset = self.offset(row, col);
+        unsafe { NonNull::new_unchecked(self.raw().as_ptr().add(offset)) }
+    }
+
+    /// The raw non-null pointer to the `cols` elements of row `row`. Panics
+    /// if the row is out of bounds.
+    #[inline]
+    pub fn row_ptr(&self, row: usize) -> NonNull<[T]> {
+        assert!(row < self.rows, "Row {} out of bounds of {} rows", row, self.rows);
+        let start = unsafe { self.raw().as_ptr().add(row * self.stride) };
+        NonNull::slice_from_raw_parts(unsafe { NonNull::new_unchecked(start) }, self.cols)
+    }
+
+    /// The elements of row `row`. Panics if the row is out of bounds.
+    ///
+    /// # Safety
+    /// The elements of the row must be initialized.
+    #[inline]
+    pub unsafe fn row(&self, row: usize) -> &[T] {
+        &*self.row_ptr(row).as_ptr()
+    }
+
+    /// The elements of row `row`. Panics if the row is out of bounds.
+    ///
+    /// # Safety
+    /// The elements of the row must be initialized.
+    #[inline]
+    pub unsafe fn row_mut(&mut self, row: usize) -> &mut [T] {
+        &mut *self.row_ptr(row).as_ptr()
+    }
+
+    panicking! {
+        /// Resizes the grid to `rows` × `cols` elements with no padding, keeping
+        /// the elements in the overlap of the old and new dimensions at the same
+        /// positions. Elements outside the overlap are neither dropped nor
+        /// initialized. In case of allocation error or overflow, the function
+        /// panics.
+        #[inline]
+        pub fn resize(&mut self, rows: usize, cols: usize) {
+            self.resize_with_stride(rows, cols, cols)
+        }
+    }
+
+    /// Resizes the grid to `rows` × `cols` elements with `stride` elements
+    /// per row. See `resize`.
+    pub fn resize_with_stride(&mut self, rows: usize, cols: usize, stride: usize) {
+        let new = Self::with_stride(rows, cols, stride);
+        let keep = cols.min(self.cols);
+        for row in 0 .. rows.min(self.rows) {
+            unsafe {
+                let src = self.raw().as_ptr().add(row * self.stride);
+                let dst = new.raw().as_ptr().add(row * stride);
+                dst.copy_from_nonoverlapping(src, keep);
+            }
+        }
+        *self = new;
+    }
+}
+
+impl<T> Default for RawGrid<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for RawGrid<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawGrid {{ pointer: {:?}, rows: {}, cols: {}, stride: {} }}",
+            self.raw(),
+            self.rows,
+            self.cols,
+            self.stride
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RawGrid;
+
+    #[test]
+    fn resize_keeps_overlap() {
+        let mut grid = RawGrid::<u32>::with_dims(3, 3);
+        for row in 0 .. 3 {
+            let start = grid.row_ptr(row).cast::<u32>();
+            unsafe { start.as_ptr().write_bytes(row as u8, 3) };
+        }
+        grid.resize_with_stride(4, 2, 8);
+        assert_eq!(grid.stride(), 8);
+        for row in 0 .. 3 {
+            unsafe { assert_eq!(grid.row(row), [u32::from_ne_bytes([row as u8; 4]); 2]) };
+        }
+    }
+}
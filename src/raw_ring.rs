@@ -0,0 +1,240 @@
+use crate::{LayoutError, RawVec, RawVecError};
+use core::{fmt, ptr::NonNull};
+
+/// Raw storage of a ring buffer, with a power-of-two capacity so positions
+/// map to slots by masking. Positions are free-running counters, such as
+/// the head and tail of a queue: position `i` lives in slot `i & mask`.
+///
+/// Like a `RawVec`, the ring never initializes nor drops elements; the
+/// caller tracks which positions are occupied.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::RawRing;
+///
+/// let mut ring = RawRing::<u32>::with_capacity(4);
+/// let head = 6;
+/// for pos in head .. head + 4 {
+///     unsafe { ring.slot(pos).as_ptr().write(pos as u32) };
+/// }
+///
+/// // Elements 6 and 7 sit at the end of the storage, 8 and 9 at its start.
+/// unsafe { ring.resize(8, head, 4) };
+/// for pos in head .. head + 4 {
+///     assert_eq!(unsafe { ring.slot(pos).as_ptr().read() }, pos as u32);
+/// }
+/// ```
+pub struct RawRing<T> {
+    storage: RawVec<T>,
+}
+
+impl<T> RawRing<T> {
+    /// Creates a ring of capacity `0`. No allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            storage: RawVec::new(),
+        }
+    }
+
+    panicking! {
+        /// Creates a ring with a capacity of `cap` rounded up to a power of two.
+        /// No allocation is performed if `cap` is `0`. In case of allocation
+        /// error or overflow, the function panics.
+        #[inline]
+        pub fn with_capacity(cap: usize) -> Self {
+            match Self::try_with_capacity(cap) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Creates a ring with a capacity of `cap` rounded up to a power of two.
+    /// No allocation is performed if `cap` is `0`. In case of allocation
+    /// error or overflow, `Err` is returned.
+    #[inline]
+    pub fn try_with_capacity(cap: usize) -> Result<Self, RawVecError> {
+        if cap == 0 {
+            return Ok(Self::new());
+        }
+        let cap = cap.checked_next_power_of_two().ok_or(LayoutError)?;
+        Ok(Self {
+            storage: RawVec::try_with_capacity(cap)?,
+        })
+    }
+
+    /// The number of slots, a power of two unless it is `0`.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.storage.cap()
+    }
+
+    /// The mask mapping positions to slots.
+    #[inline]
+    pub const fn mask(&self) -> usize {
+        self.storage.cap().wrapping_sub(1)
+    }
+
+    /// The raw non-null pointer to the slot of position `pos`. Panics if the
+    /// capacity is `0`.
+    #[inline]
+    pub fn slot(&self, pos: usize) -> NonNull<T> {
+        assert!(self.capacity() > 0, "Slot of an empty ring");
+        unsafe { NonNull::new_unchecked(self.storage.raw().as_ptr().add(pos & self.mask())) }
+    }
+
+    /// The raw non-null pointers to the slots of the `len` positions from
+    /// `head`, as two contiguous parts: up to the end of the storage, then
+    /// from its start. `len` must not exceed the capacity. Both parts are
+    /// empty if the capacity is `0`.
+    #[inline]
+    pub fn as_slices(&self, head: usize, len: usize) -> (NonNull<[T]>, NonNull<[T]>) {
+        assert!(len <= self.capacity(), "Length exceeds the capacity");
+        let base = self.storage.raw();
+        if self.capacity() == 0 {
+            let empty = NonNull::slice_from_raw_parts(base, 0);
+            return (empty, empty);
+        }
+        let start = head & self.mask();
+        let first = len.min(self.capacity() - start);
+        unsafe {
+            let first_ptr = NonNull::new_unchecked(base.as_ptr().add(start));
+            (
+                NonNull::slice_from_raw_parts(first_ptr, first),
+                NonNull::slice_from_raw_parts(base, len - first),
+            )
+        }
+    }
+
+    panicking! {
+        /// Resizes the ring to a capacity of `cap` rounded up to a power of two,
+        /// moving the `len` elements from position `head` so each one stays at
+        /// its position. In case of allocation error or overflow, the function
+        /// panics.
+        ///
+        /// # Safety
+        /// `len` must not exceed the capacity, nor `cap`.
+        #[inline]
+        pub unsafe fn resize(&mut self, cap: usize, head: usize, len: usize) {
+            match self.try_resize(cap, head, len) {
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+                Ok(_) => (),
+            }
+        }
+    }
+
+    /// Resizes the ring to a capacity of `cap` rounded up to a power of two,
+    /// moving the `len` elements from position `head` so each one stays at
+    /// its position. In case of allocation error or overflow, `Err` is
+    /// returned and the ring is untouched.
+    ///
+    /// # Safety
+    /// `len` must not exceed the capacity, nor `cap`.
+    pub unsafe fn try_resize(
+        &mut self,
+        cap: usize,
+        head: usize,
+        len: usize,
+    ) -> Result<(), RawVecError> {
+        let new = Self::try_with_capacity(cap)?;
+        let mut pos = head;
+        let mut remaining = len;
+        // Copies runs contiguous in both storages: at most three of them.
+        while remaining > 0 {
+            let src = pos & self.mask();
+            let dst = pos & new.mask();
+            let run = remaining
+                .min(self.capacity() - src)
+                .min(new.capacity() - dst);
+            new.slot(pos)
+                .as_ptr()
+                .copy_from_nonoverlapping(self.slot(pos).as_ptr(), run);
+            pos = pos.wrapping_add(run);
+            remaining -= run;
+        }
+        *self = new;
+        Ok(())
+    }
+
+    /// Doubles the capacity of the ring (or makes it `1`), moving the `len`
+    /// elements from position `head`. See `resize`.
+    ///
+    /// # Safety
+    /// `len` must not exceed the capacity.
+    #[inline]
+    pub unsafe fn grow(&mut self, head: usize, len: usize) {
+        let cap = (self.capacity() * 2).max(1);
+        self.resize(cap, head, len)
+    }
+}
+
+impl<T> Default for RawRing<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for RawRing<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawRing {{ pointer: {:?}, cap: {} }}",
+            self.storage.raw(),
+            self.capacity()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RawRing;
+
+    #[test]
+    fn wrapped_order_survives_growth() {
+        let mut ring = RawRing::<usize>::with_capacity(3);
+        assert_eq!(ring.capacity(), 4);
+        let head = usize::MAX - 1;
+        for offset in 0 .. 4 {
+            let pos = head.wrapping_add(offset);
+            unsafe { ring.slot(pos).as_ptr().write(offset) };
+        }
+        unsafe { ring.grow(head, 4) };
+        unsafe { ring.grow(head, 4) };
+        assert_eq!(ring.capacity(), 16);
+
+        let (first, second) = ring.as_slices(head, 4);
+        unsafe {
+            assert_eq!(*first.as_ptr(), [0, 1]);
+            assert_eq!(*second.as_ptr(), [2, 3]);
+        }
+    }
+
+    #[test]
+    fn zero_capacity_is_unallocated() {
+        let ring = RawRing::<u64>::with_capacity(0);
+        assert_eq!(ring.capacity(), 0);
+        let (first, second) = ring.as_slices(5, 0);
+        assert_eq!((first.len(), second.len()), (0, 0));
+        let mut ring = RawRing::<u64>::try_with_capacity(0).unwrap();
+        assert_eq!(ring.capacity(), 0);
+        unsafe { ring.grow(0, 0) };
+        assert_eq!(ring.capacity(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Slot of an empty ring")]
+    fn no_slot_in_empty_ring() {
+        RawRing::<u64>::new().slot(3);
+    }
+}
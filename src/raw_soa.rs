@@ -0,0 +1,366 @@
+use crate::{AllocError, Allocator, LayoutError, RawVecError};
+use core::{
+    alloc::Layout,
+    fmt,
+    marker::PhantomData,
+    ptr::NonNull,
+    slice,
+};
+
+/// Builds the layout of several arrays packed in a single allocation, each
+/// one aligned for its element type.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::LayoutBuilder;
+///
+/// let mut builder = LayoutBuilder::new();
+/// assert_eq!(builder.array::<u8>(3).unwrap(), 0);
+/// assert_eq!(builder.array::<u32>(2).unwrap(), 4);
+/// let layout = builder.finish();
+/// assert_eq!((layout.size(), layout.align()), (12, 4));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutBuilder {
+    layout: Layout,
+}
+
+impl LayoutBuilder {
+    /// Creates a builder of an empty layout.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            layout: Layout::new::<()>(),
+        }
+    }
+
+    /// Appends a field of the given layout, returning its offset.
+    #[inline]
+    pub fn field(&mut self, layout: Layout) -> Result<usize, LayoutError> {
+        let (layout, offset) = self.layout.extend(layout)?;
+        self.layout = layout;
+        Ok(offset)
+    }
+
+    /// Appends an array of `len` elements of type `T`, returning its offset.
+    #[inline]
+    pub fn array<T>(&mut self, len: usize) -> Result<usize, LayoutError> {
+        self.field(Layout::array::<T>(len)?)
+    }
+
+    /// The layout of all fields appended so far, padded to its alignment.
+    #[inline]
+    pub fn finish(self) -> Layout {
+        self.layout.pad_to_align()
+    }
+}
+
+impl Default for LayoutBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The fields of a `RawSoA`: a tuple of the element types of its arrays.
+/// Implemented for tuples of up to eight types.
+///
+/// # Safety
+/// Implementors must place the arrays within `layout(cap)` and give
+/// pointers, slices and copies consistent with it.
+pub unsafe trait SoaFields {
+    /// A tuple of pointers to the first element of each array.
+    type Ptrs: Copy;
+    /// A tuple of slices of each array.
+    type Slices<'a>
+    where
+        Self: 'a;
+    /// A tuple of mutable slices of each array.
+    type SlicesMut<'a>
+    where
+        Self: 'a;
+
+    /// The layout of arrays of `cap` elements.
+    fn layout(cap: usize) -> Result<Layout, LayoutError>;
+
+    /// Pointers to the arrays of `cap` elements in the allocation at `base`.
+    ///
+    /// # Safety
+    /// `layout(cap)` must be valid.
+    unsafe fn ptrs(base: NonNull<u8>, cap: usize) -> Self::Ptrs;
+
+    /// Slices of the first `len` elements of each array.
+    ///
+    /// # Safety
+    /// Those elements must be initialized, and live for `'a`.
+    unsafe fn slices<'a>(ptrs: Self::Ptrs, len: usize) -> Self::Slices<'a>;
+
+    /// Mutable slices of the first `len` elements of each array.
+    ///
+    /// # Safety
+    /// Those elements must be initialized, live for `'a`, and not be aliased.
+    unsafe fn slices_mut<'a>(ptrs: Self::Ptrs, len: usize) -> Self::SlicesMut<'a>;
+
+    /// Copies the first `len` elements of each array from `src` to `dst`.
+    ///
+    /// # Safety
+    /// Both sets of arrays must hold at least `len` elements, and not
+    /// overlap.
+    unsafe fn copy(src: Self::Ptrs, dst: Self::Ptrs, len: usize);
+}
+
+macro_rules! soa_fields {
+    ($($field:ident . $index:tt),+) => {
+        unsafe impl<$($field),+> SoaFields for ($($field,)+) {
+            type Ptrs = ($(NonNull<$field>,)+);
+            type Slices<'a> = ($(&'a [$field],)+) where Self: 'a;
+            type SlicesMut<'a> = ($(&'a mut [$field],)+) where Self: 'a;
+
+            #[inline]
+            fn layout(cap: usize) -> Result<Layout, LayoutError> {
+                let mut builder = LayoutBuilder::new();
+                $(builder.array::<$field>(cap)?;)+
+                Ok(builder.finish())
+            }
+
+            #[inline]
+            unsafe fn ptrs(base: NonNull<u8>, cap: usize) -> Self::Ptrs {
+                let mut builder = LayoutBuilder::new();
+                ($({
+                    let offset = builder.array::<$field>(cap).unwrap();
+                    NonNull::new_unchecked(base.as_ptr().add(offset).cast::<$field>())
+                },)+)
+            }
+
+            #[inline]
+            unsafe fn slices<'a>(ptrs: Self::Ptrs, len: usize) -> Self::Slices<'a> {
+                ($(slice::from_raw_parts(ptrs.$index.as_ptr(), len),)+)
+            }
+
+            #[inline]
+            unsafe fn slices_mut<'a>(ptrs: Self::Ptrs, len: usize) -> Self::SlicesMut<'a> {
+                ($(slice::from_raw_parts_mut(ptrs.$index.as_ptr(), len),)+)
+            }
+
+            #[inline]
+            unsafe fn copy(src: Self::Ptrs, dst: Self::Ptrs, len: usize) {
+                $(dst.$index.as_ptr().copy_from_nonoverlapping(src.$index.as_ptr(), len);)+
+            }
+        }
+    };
+}
+
+soa_fields!(A.0);
+soa_fields!(A.0, B.1);
+soa_fields!(A.0, B.1, C.2);
+soa_fields!(A.0, B.1, C.2, D.3);
+soa_fields!(A.0, B.1, C.2, D.3, E.4);
+soa_fields!(A.0, B.1, C.2, D.3, E.4, F.5);
+soa_fields!(A.0, B.1, C.2, D.3, E.4, F.5, G.6);
+soa_fields!(A.0, B.1, C.2, D.3, E.4, F.5, G.6, H.7);
+
+/// Raw storage of several parallel arrays of the same capacity, one per
+/// element type of the tuple `F`, packed in a single allocation. Handy for
+/// structure-of-arrays containers such as ECS component stores.
+///
+/// Like a `RawVec`, the storage never initializes nor drops elements; the
+/// caller tracks the length.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::RawSoA;
+///
+/// let mut particles = RawSoA::<(f32, u8)>::with_capacity(2);
+/// let (positions, colors) = particles.ptrs();
+/// for i in 0 .. 2 {
+///     unsafe {
+///         positions.as_ptr().add(i).write(i as f32);
+///         colors.as_ptr().add(i).write(i as u8);
+///     }
+/// }
+///
+/// particles.resize(100, 2);
+/// let (positions, colors) = unsafe { particles.slices(2) };
+/// assert_eq!(positions, [0.0, 1.0]);
+/// assert_eq!(colors, [0, 1]);
+/// ```
+pub struct RawSoA<F>
+where
+    F: SoaFields,
+{
+    base: NonNull<u8>,
+    cap: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F> RawSoA<F>
+where
+    F: SoaFields,
+{
+    /// Creates arrays of capacity `0`. No allocation is performed.
+    #[inline]
+    pub fn new() -> Self {
+        Self::try_with_capacity(0).unwrap()
+    }
+
+    panicking! {
+        /// Creates arrays of capacity `cap`. In case of allocation error or
+        /// overflow, the function panics.
+        #[inline]
+        pub fn with_capacity(cap: usize) -> Self {
+            match Self::try_with_capacity(cap) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Creates arrays of capacity `cap`. In case of allocation error or
+    /// overflow, `Err` is returned.
+    pub fn try_with_capacity(cap: usize) -> Result<Self, RawVecError> {
+        let layout = F::layout(cap)?;
+        let base = if layout.size() == 0 {
+            unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+        } else {
+            let block = crate::alloc_api::Allocator::allocate(&Allocator::new(), layout);
+            block.map_err(|_| AllocError { layout })?.cast()
+        };
+        Ok(Self {
+            base,
+            cap,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The capacity of each array.
+    #[inline]
+    pub const fn cap(&self) -> usize {
+        self.cap
+    }
+
+    /// Pointers to the first element of each array.
+    #[inline]
+    pub fn ptrs(&self) -> F::Ptrs {
+        unsafe { F::ptrs(self.base, self.cap) }
+    }
+
+    /// Slices of the first `len` elements of each array. Panics if `len`
+    /// exceeds the capacity.
+    ///
+    /// # Safety
+    /// Those elements must be initialized.
+    #[inline]
+    pub unsafe fn slices(&self, len: usize) -> F::Slices<'_> {
+        assert!(len <= self.cap, "Length exceeds the capacity");
+        F::slices(self.ptrs(), len)
+    }
+
+    /// Mutable slices of the first `len` elements of each array. Panics if
+    /// `len` exceeds the capacity.
+    ///
+    /// # Safety
+    /// Those elements must be initialized.
+    #[inline]
+    pub unsafe fn slices_mut(&mut self, len: usize) -> F::SlicesMut<'_> {
+        assert!(len <= self.cap, "Length exceeds the capacity");
+        F::slices_mut(self.ptrs(), len)
+    }
+
+    panicking! {
+        /// Resizes every array to a capacity of `cap`, keeping their first `len`
+        /// elements. Panics if `len` exceeds either capacity. In case of
+        /// allocation error or overflow, the function panics.
+        #[inline]
+        pub fn resize(&mut self, cap: usize, len: usize) {
+            match self.try_resize(cap, len) {
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+                Ok(_) => (),
+            }
+        }
+    }
+
+    /// Resizes every array to a capacity of `cap`, keeping their first `len`
+    /// elements. Panics if `len` exceeds either capacity. In case of
+    /// allocation error or overflow, `Err` is returned and the arrays are
+    /// untouched.
+    pub fn try_resize(&mut self, cap: usize, len: usize) -> Result<(), RawVecError> {
+        assert!(len <= self.cap.min(cap), "Length exceeds the capacity");
+        let new = Self::try_with_capacity(cap)?;
+        unsafe { F::copy(self.ptrs(), new.ptrs(), len) };
+        *self = new;
+        Ok(())
+    }
+}
+
+impl<F> Default for RawSoA<F>
+where
+    F: SoaFields,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> Drop for RawSoA<F>
+where
+    F: SoaFields,
+{
+    fn drop(&mut self) {
+        let layout = F::layout(self.cap).unwrap();
+        if layout.size() != 0 {
+            unsafe { crate::alloc_api::Allocator::deallocate(&Allocator::new(), self.base, layout) }
+        }
+    }
+}
+
+unsafe impl<F> Send for RawSoA<F> where F: SoaFields + Send {}
+unsafe impl<F> Sync for RawSoA<F> where F: SoaFields + Sync {}
+
+impl<F> fmt::Debug for RawSoA<F>
+where
+    F: SoaFields,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RawSoA {{ pointer: {:?}, cap: {} }}", self.base, self.cap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RawSoA;
+
+    #[test]
+    fn fields_are_aligned_and_survive_resize() {
+        let mut soa = RawSoA::<(u8, u64, (), u16)>::with_capacity(3);
+        let (bytes, words, _, halves) = soa.ptrs();
+        assert_eq!(words.as_ptr() as usize % 8, 0);
+        assert_eq!(halves.as_ptr() as usize % 2, 0);
+        for i in 0 .. 3 {
+            unsafe {
+                bytes.as_ptr().add(i).write(i as u8);
+                words.as_ptr().add(i).write(i as u64 * 1000);
+                halves.as_ptr().add(i).write(i as u16 + 7);
+            }
+        }
+
+        soa.resize(17, 3);
+        let (bytes, words, units, halves) = unsafe { soa.slices_mut(3) };
+        words[2] += 1;
+        assert_eq!(bytes, [0, 1, 2]);
+        assert_eq!(words, [0, 1000, 2001]);
+        assert_eq!(units.len(), 3);
+        assert_eq!(halves, [7, 8, 9]);
+    }
+}
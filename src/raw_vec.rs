@@ -1,9 +1,22 @@
-use crate::{Allocator, Layout, LayoutError, NonNull, RawVecError, UninitAlloc, ALLOCATOR};
+use crate::{
+    AllocError,
+    Allocator,
+    Layout,
+    LayoutError,
+    NonNull,
+    RawVecError,
+    UninitAlloc,
+    VecCastError,
+};
 use alloc::vec::Vec;
-use core::{alloc::GlobalAlloc, marker::PhantomData, mem};
-pub struct RawVec<T> {
+use core::{marker::PhantomData, mem};
+pub struct RawVec<T, A = Allocator>
+where
+    A: crate::alloc_api::Allocator,
+{
     ptr: NonNull<T>,
     cap: usize,
+    alloc: A,
     _marker: PhantomData<T>,
 }
 
@@ -12,23 +25,22 @@ impl<T> RawVec<T> {
     /// allocation is performed.
     #[inline]
     pub const fn new() -> Self {
-        Self {
-            ptr: NonNull::dangling(),
-            cap: 0,
-            _marker: PhantomData,
-        }
+        Self::new_in(Allocator::new())
     }
 
-    /// Creates a new `RawVec` with a given capacity. In case of allocation
-    /// error, the handler registered via stdlib is called. In case of overflow
-    /// calculating the total size, the function panics.
-    #[inline]
-    pub fn with_capacity(cap: usize) -> Self {
-        match Self::try_with_capacity(cap) {
-            Ok(this) => this,
-            Err(RawVecError::Alloc(err)) => panic!("{}", err),
-            Err(RawVecError::Layout(err)) => {
-                panic!("Capacity overflows memory size: {}", err)
+    panicking! {
+        /// Creates a new `RawVec` with a given capacity. In case of allocation
+        /// error, the handler registered via stdlib is called. In case of overflow
+        /// calculating the total size, the function panics.
+        #[inline]
+        #[track_caller]
+        pub fn with_capacity(cap: usize) -> Self {
+            match Self::try_with_capacity(cap) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
             }
         }
     }
@@ -36,19 +48,17 @@ impl<T> RawVec<T> {
     // Creates a new `RawVec` with a given capacity. In case of allocation
     /// error or overflow calculating the total size, `Err` is returned.
     #[inline]
+    #[track_caller]
     pub fn try_with_capacity(cap: usize) -> Result<Self, RawVecError> {
-        let layout = Self::make_layout(cap)?;
-        let res = if layout.size() == 0 {
-            Ok(NonNull::dangling())
-        } else {
-            Ok(unsafe { NonNull::new_unchecked(Allocator::alloc(&ALLOCATOR, layout) as *mut T) })
-        };
-
-        res.map(|ptr| Self {
-            ptr,
-            cap,
-            _marker: PhantomData,
-        })
+        let res = Self::try_with_capacity_in(cap, Allocator::new());
+        #[cfg(feature = "track-callers")]
+        if let Ok(this) = &res {
+            let size = mem::size_of::<T>() * cap;
+            if size != 0 {
+                crate::leak::record(this.ptr.as_ptr().cast(), size);
+            }
+        }
+        res
     }
 
     // Creates a `RawVec` from a plain old standard library `Vec`. Beware, only
@@ -63,15 +73,103 @@ impl<T> RawVec<T> {
     /// you are using, but there are no future guarantees.
     #[inline]
     pub unsafe fn from_vec(mut vec: Vec<T>) -> Self {
-        let this = Self {
-            ptr: NonNull::new_unchecked(vec.as_mut_ptr()),
-            cap: vec.capacity(),
-            _marker: PhantomData,
-        };
+        let this = Self::from_raw_parts(NonNull::new_unchecked(vec.as_mut_ptr()), vec.capacity());
         mem::forget(vec);
         this
     }
 
+    /// Takes the buffer of a standard library `Vec` over, checking that the
+    /// crate allocates from the global allocator, as `Vec` does. Otherwise,
+    /// the `Vec` is given back. Like with `from_vec`, the length is discarded
+    /// and no element is ever dropped.
+    ///
+    /// The check holds for the lifetime of the `RawVec`: registering a
+    /// foreign allocator or installing a heap is `unsafe`, and requires that
+    /// no such buffer exists.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::RawVec;
+    ///
+    /// let raw = RawVec::try_from_vec(vec![1u32, 2, 3]).unwrap();
+    /// assert_eq!(unsafe { raw.as_slice() }, [1, 2, 3]);
+    ///
+    /// let vec = raw.try_into_vec().unwrap();
+    /// assert!(vec.is_empty());
+    /// assert_eq!(vec.capacity(), 3);
+    /// ```
+    #[inline]
+    pub fn try_from_vec(vec: Vec<T>) -> Result<Self, Vec<T>> {
+        if !Allocator::is_global() {
+            return Err(vec);
+        }
+        debug_assert!(Self::make_layout(vec.capacity()).is_ok());
+        Ok(unsafe { Self::from_vec(vec) })
+    }
+
+    /// Takes the buffer of a standard library `Vec` of another element type
+    /// over, for zero-copy reinterpretation (e.g. of a decoding buffer). The
+    /// element types must have the same alignment, and the buffer must be a
+    /// whole number of elements of `T`. Otherwise, the error is returned
+    /// with the `Vec`. The elements of the `Vec` are never dropped.
+    ///
+    /// Along with the `RawVec`, the number of elements of `T` fully covered
+    /// by the elements of the `Vec` is returned. Reading them is only sound
+    /// if their bytes are valid values of `T`.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::{RawVec, VecCastError};
+    ///
+    /// let pixels: Vec<[u8; 4]> = vec![[1, 2, 3, 4], [5, 6, 7, 8]];
+    /// let (raw, len) = RawVec::<u8>::try_cast_vec(pixels).unwrap();
+    /// assert_eq!((raw.cap(), len), (8, 8));
+    /// assert_eq!(unsafe { &raw.as_slice()[.. len] }, [1, 2, 3, 4, 5, 6, 7, 8]);
+    ///
+    /// let words = Vec::<u16>::with_capacity(3);
+    /// let (err, words) = RawVec::<u8>::try_cast_vec(words).unwrap_err();
+    /// assert_eq!(err, VecCastError::Align);
+    /// assert_eq!(words.capacity(), 3);
+    /// ```
+    pub fn try_cast_vec<U>(mut vec: Vec<U>) -> Result<(Self, usize), (VecCastError, Vec<U>)> {
+        let (size, from_size) = (mem::size_of::<T>(), mem::size_of::<U>());
+        if mem::align_of::<T>() != mem::align_of::<U>() {
+            return Err((VecCastError::Align, vec));
+        }
+        if size == 0 || from_size == 0 || !(vec.capacity() * from_size).is_multiple_of(size) {
+            return Err((VecCastError::Size, vec));
+        }
+        if !Allocator::is_global() {
+            return Err((VecCastError::Allocator, vec));
+        }
+        let this = unsafe {
+            Self::from_raw_parts(
+                NonNull::new_unchecked(vec.as_mut_ptr().cast()),
+                vec.capacity() * from_size / size,
+            )
+        };
+        let len = vec.len() * from_size / size;
+        mem::forget(vec);
+        Ok((this, len))
+    }
+
+    /// Hands the buffer over to an empty standard library `Vec`, checking
+    /// that the crate allocates from the global allocator, as `Vec` does.
+    /// Otherwise, the `RawVec` is given back. For a `Vec` of some initialized
+    /// elements, see the unsafe `into_vec`.
+    #[inline]
+    pub fn try_into_vec(self) -> Result<Vec<T>, Self> {
+        if !Allocator::is_global() {
+            return Err(self);
+        }
+        debug_assert!(Self::make_layout(self.cap).is_ok());
+        Ok(unsafe { self.into_vec(0) })
+    }
+
     /// Recreate the `RawVec` from a raw non-null pointer and a capacity.
     ///
     /// # Safety
@@ -80,28 +178,132 @@ impl<T> RawVec<T> {
     /// behaviour.
     #[inline]
     pub const unsafe fn from_raw_parts(ptr: NonNull<T>, cap: usize) -> Self {
+        Self::from_raw_parts_in(ptr, cap, Allocator::new())
+    }
+
+    /// Recreate the `RawVec` from a raw non-null pointer to a slice with length
+    /// equal to the `RawVec`'s capacity.
+    ///
+    /// # Safety
+    /// This functions is `unsafe` because passing the wrong pointer leads to
+    /// undefined behaviour, including passing a pointer with the wrong length.
+    #[inline]
+    pub const unsafe fn from_raw_slice(raw: NonNull<[T]>) -> Self {
+        Self::from_raw_parts(raw.cast(), raw.len())
+    }
+
+    /// Creates a plain old standard library `Vec` from the `RawVec` and a given
+    /// length.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because there are no guarantees that `Vec` and
+    /// `RawVec` allocate in the same way. They probably do in the Rust version
+    /// you are using, but there are no future guarantees. Also, the length
+    /// argument must be passed correctly, since the elements until the given
+    /// length will be considered correctly, but the `RawVec` initialize no
+    /// element.
+    #[inline]
+    pub unsafe fn into_vec(self, len: usize) -> Vec<T> {
+        #[cfg(feature = "track-callers")]
+        crate::leak::forget(self.ptr.as_ptr().cast());
+        let vec = Vec::from_raw_parts(self.ptr.as_ptr(), len, self.cap);
+        mem::forget(self);
+        vec
+    }
+}
+
+impl<T, A> RawVec<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Creates a new `RawVec` of capacity `0` and a dangling pointer, which
+    /// will allocate in `alloc`. No allocation is performed.
+    #[inline]
+    pub const fn new_in(alloc: A) -> Self {
         Self {
+            ptr: NonNull::dangling(),
+            cap: 0,
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+
+    panicking! {
+        /// Creates a new `RawVec` with a given capacity in `alloc`. In case of
+        /// allocation error or overflow calculating the total size, the
+        /// function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::{Allocator, LimitedAlloc, RawVec};
+        ///
+        /// let limited = LimitedAlloc::new(Allocator::new(), 1024);
+        /// let mut buf = RawVec::<u32, _>::with_capacity_in(16, &limited);
+        /// assert_eq!(limited.used(), 64);
+        /// buf.resize(200);
+        /// assert_eq!(limited.used(), 800);
+        /// assert!(buf.try_resize(300).is_err());
+        /// assert_eq!(buf.cap(), 200);
+        /// drop(buf);
+        /// assert_eq!(limited.used(), 0);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn with_capacity_in(cap: usize, alloc: A) -> Self {
+            match Self::try_with_capacity_in(cap, alloc) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
+            }
+        }
+    }
+
+    /// Creates a new `RawVec` with a given capacity in `alloc`. In case of
+    /// allocation error or overflow calculating the total size, `Err` is
+    /// returned.
+    #[inline]
+    pub fn try_with_capacity_in(cap: usize, alloc: A) -> Result<Self, RawVecError> {
+        let layout = Self::make_layout(cap)?;
+        let ptr = if layout.size() == 0 {
+            NonNull::dangling()
+        } else {
+            alloc.allocate(layout).map_err(|_| AllocError { layout })?.cast()
+        };
+        Ok(Self {
             ptr,
             cap,
+            alloc,
             _marker: PhantomData,
-        }
+        })
     }
 
-    /// Recreate the `RawVec` from a raw non-null pointer to a slice with length
-    /// equal to the `RawVec`'s capacity.
+    /// Recreate the `RawVec` from a raw non-null pointer, a capacity and the
+    /// allocator the memory was allocated in.
     ///
     /// # Safety
     /// This functions is `unsafe` because passing the wrong pointer leads to
-    /// undefined behaviour, including passing a pointer with the wrong length.
+    /// undefined behaviour. Passing wrong capacity or allocator also leads to
+    /// undefined behaviour.
     #[inline]
-    pub const unsafe fn from_raw_slice(mut raw: NonNull<[T]>) -> Self {
+    pub const unsafe fn from_raw_parts_in(ptr: NonNull<T>, cap: usize, alloc: A) -> Self {
         Self {
-            ptr: NonNull::new_unchecked(raw.as_mut().as_mut_ptr()),
-            cap: raw.as_ref().len(),
+            ptr,
+            cap,
+            alloc,
             _marker: PhantomData,
         }
     }
 
+    /// The allocator the memory is allocated in.
+    #[inline]
+    pub const fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
     /// The requested allocation capacity. It is guaranteed to be the capacity
     /// passed to the last capacity-modifier method. Those are
     /// `with_capacity`, `try_with_capacity` and `resize`. The methods `new`
@@ -121,7 +323,7 @@ impl<T> RawVec<T> {
     /// `RawVec`'s capacity.
     #[inline]
     pub const fn raw_slice(&self) -> NonNull<[T]> {
-        unsafe { NonNull::from(self.as_slice()) }
+        NonNull::slice_from_raw_parts(self.ptr, self.cap)
     }
 
     /// "Forgets" dropping the allocation and returns a raw non-null pointer to
@@ -133,6 +335,17 @@ impl<T> RawVec<T> {
         ptr
     }
 
+    /// "Forgets" dropping the allocation and returns a raw non-null pointer to
+    /// the slice with length equal to the `RawVec`'s capacity, along with the
+    /// allocator.
+    #[inline]
+    pub const fn into_raw_slice_with_allocator(self) -> (NonNull<[T]>, A) {
+        let ptr = self.raw_slice();
+        let alloc = unsafe { (&self.alloc as *const A).read() };
+        mem::forget(self);
+        (ptr, alloc)
+    }
+
     /// Encodes the `RawVec` as an immutable reference to a slice with length
     /// equal to the capacity.
     ///
@@ -155,35 +368,20 @@ impl<T> RawVec<T> {
         core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.cap())
     }
 
-    /// Creates a plain old standard library `Vec` from the `RawVec` and a given
-    /// length.
-    ///
-    /// # Safety
-    /// This function is `unsafe` because there are no guarantees that `Vec` and
-    /// `RawVec` allocate in the same way. They probably do in the Rust version
-    /// you are using, but there are no future guarantees. Also, the length
-    /// argument must be passed correctly, since the elements until the given
-    /// length will be considered correctly, but the `RawVec` initialize no
-    /// element.
-    #[inline]
-    pub unsafe fn into_vec(self, len: usize) -> Vec<T> {
-        let vec = Vec::from_raw_parts(self.ptr.as_ptr(), len, self.cap);
-        mem::forget(self);
-        vec
-    }
+    panicking! {
+        /// Resizes the `RawVec` with a given capacity. In case of allocation
+        /// error, the handler registered via stdlib is called. In case of overflow
+        /// calculating the total size, the function panics.
+        #[inline]
+        pub fn resize(&mut self, new_cap: usize) {
+            match self.try_resize(new_cap) {
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                }
 
-    /// Resizes the `RawVec` with a given capacity. In case of allocation
-    /// error, the handler registered via stdlib is called. In case of overflow
-    /// calculating the total size, the function panics.
-    #[inline]
-    pub fn resize(&mut self, new_cap: usize) {
-        match self.try_resize(new_cap) {
-            Err(RawVecError::Alloc(err)) => panic!("{}", err),
-            Err(RawVecError::Layout(err)) => {
-                panic!("Capacity overflows memory size: {}", err)
+                Ok(_) => (),
             }
-
-            Ok(_) => (),
         }
     }
 
@@ -193,74 +391,183 @@ impl<T> RawVec<T> {
     #[inline]
     pub fn try_resize(&mut self, new_cap: usize) -> Result<(), RawVecError> {
         let layout = Self::make_layout(new_cap)?;
+        let old = Self::make_layout(self.cap)?;
 
-        let res = if layout.size() == 0 {
+        let ptr = if layout.size() == 0 {
             self.free();
-            Ok(NonNull::dangling())
+            NonNull::dangling()
         } else {
-            let new = unsafe {
-                NonNull::new_unchecked(Allocator::reallocate(
-                    &ALLOCATOR,
-                    self.ptr.as_ptr(),
-                    layout,
-                    new_cap,
-                ))
+            let res = if old.size() == 0 {
+                self.alloc.allocate(layout)
+            } else if layout.size() >= old.size() {
+                unsafe { self.alloc.grow(self.ptr.cast(), old, layout) }
+            } else {
+                unsafe { self.alloc.shrink(self.ptr.cast(), old, layout) }
             };
-            Ok(new)
+            let new = res.map_err(|_| AllocError { layout })?.cast::<T>();
+            #[cfg(feature = "track-callers")]
+            if crate::leak::tracks::<A>() {
+                crate::leak::relocate(self.ptr.as_ptr().cast(), new.as_ptr().cast(), layout.size());
+            }
+            new
         };
-        res.map(|ptr| {
-            self.ptr = ptr;
-            self.cap = new_cap;
-        })
+        self.ptr = ptr;
+        self.cap = new_cap;
+        Ok(())
     }
 
     #[inline]
     fn free(&self) {
-        if self.cap != 0 && mem::size_of::<T>() != 0 {
-            let layout = Self::make_layout(self.cap).unwrap();
-            unsafe {
-                Allocator::alloc(&ALLOCATOR, layout);
+        let layout = Self::make_layout(self.cap).unwrap();
+        if layout.size() != 0 {
+            #[cfg(feature = "track-callers")]
+            if crate::leak::tracks::<A>() {
+                crate::leak::forget(self.ptr.as_ptr().cast());
             }
+            unsafe { self.alloc.deallocate(self.ptr.cast(), layout) }
         }
     }
 
     #[inline]
     const fn make_layout(cap: usize) -> Result<Layout, LayoutError> {
-        let total_size = mem::size_of::<T>().checked_mul(cap).ok_or(LayoutError)?;
-        match Layout::from_size_align(total_size, mem::align_of::<T>()) {
-            Ok(v) => Ok(v),
-            Err(err) => Err(err.into()),
+        crate::layout::array::<T>(cap)
+    }
+}
+
+#[cfg(feature = "const-heap")]
+impl<T> RawVec<T> {
+    panicking! {
+        /// Creates a new `RawVec` with a given capacity, usable in constant
+        /// evaluation: there, the memory comes from the compile-time heap, and
+        /// must be frozen with `into_static_slice` before the evaluation ends.
+        /// At runtime, this is `with_capacity`. In case of overflow calculating
+        /// the total size, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::RawVec;
+        ///
+        /// static SQUARES: &[u32] = unsafe {
+        ///     let buf = RawVec::<u32>::const_with_capacity(5);
+        ///     let mut i = 0;
+        ///     while i < 5 {
+        ///         buf.raw().as_ptr().add(i).write((i * i) as u32);
+        ///         i += 1;
+        ///     }
+        ///     buf.into_static_slice(5)
+        /// };
+        ///
+        /// assert_eq!(SQUARES, [0, 1, 4, 9, 16]);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub const fn const_with_capacity(cap: usize) -> Self {
+            core::intrinsics::const_eval_select((cap,), Self::const_alloc, Self::with_capacity)
         }
     }
+
+    /// Freezes the first `len` elements into a `&'static` slice. In constant
+    /// evaluation, this makes the memory of the compile-time heap part of
+    /// the final value. At runtime, the allocation is leaked.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because the first `len` elements must be
+    /// initialized, and `len` must not exceed the capacity.
+    #[inline]
+    pub const unsafe fn into_static_slice(self, len: usize) -> &'static [T] {
+        let size = mem::size_of::<T>() * self.cap;
+        let ptr = self.ptr.as_ptr();
+        mem::forget(self);
+        let ptr = if size == 0 {
+            ptr
+        } else {
+            core::intrinsics::const_eval_select((ptr,), freeze_const::<T>, freeze_runtime::<T>)
+        };
+        core::slice::from_raw_parts(ptr, len)
+    }
+
+    const fn const_alloc(cap: usize) -> Self {
+        let layout = match Self::make_layout(cap) {
+            Ok(layout) => layout,
+            Err(_) => panic!("Capacity overflows memory size"),
+        };
+        if layout.size() == 0 {
+            return unsafe { Self::from_raw_parts(NonNull::dangling(), cap) };
+        }
+        unsafe {
+            let ptr = core::intrinsics::const_allocate(layout.size(), layout.align());
+            Self::from_raw_parts(NonNull::new_unchecked(ptr.cast()), cap)
+        }
+    }
+}
+
+#[cfg(feature = "const-heap")]
+const fn freeze_const<T>(ptr: *mut T) -> *mut T {
+    unsafe { core::intrinsics::const_make_global(ptr.cast()).cast_mut().cast() }
+}
+
+#[cfg(feature = "const-heap")]
+fn freeze_runtime<T>(ptr: *mut T) -> *mut T {
+    #[cfg(feature = "track-callers")]
+    crate::leak::forget(ptr.cast());
+    ptr
 }
 
-impl<T> core::fmt::Debug for RawVec<T> {
+impl<T, A> core::fmt::Debug for RawVec<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
     #[inline]
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "RawVec {{ pointer {:?}, cap: {} }}", self.ptr, self.cap)
     }
 }
 
-impl<T> Drop for RawVec<T> {
+impl<T, A> Drop for RawVec<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
     #[inline]
     fn drop(&mut self) {
         self.free();
     }
 }
 
-impl<T> const From<UninitAlloc<T>> for RawVec<T> {
-    #[inline]
-    fn from(alloc: UninitAlloc<T>) -> Self {
-        Self {
-            ptr: alloc.into_raw(),
-            cap: 1,
-            _marker: PhantomData,
+const_impl! {
+    impl<T, A> From<UninitAlloc<T, A>> for RawVec<T, A>
+    where
+        A: crate::alloc_api::Allocator,
+    {
+        #[inline]
+        fn from(alloc: UninitAlloc<T, A>) -> Self {
+            let (ptr, alloc) = alloc.into_raw_with_allocator();
+            unsafe { Self::from_raw_parts_in(ptr, 1, alloc) }
         }
     }
 }
 
-unsafe impl<T> const Send for RawVec<T> where T: Send {}
-unsafe impl<T> const Sync for RawVec<T> where T: Sync {}
+impl<T> Default for RawVec<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<T, A> Send for RawVec<T, A>
+where
+    T: Send,
+    A: crate::alloc_api::Allocator + Send,
+{
+}
+
+unsafe impl<T, A> Sync for RawVec<T, A>
+where
+    T: Sync,
+    A: crate::alloc_api::Allocator + Sync,
+{
+}
 
 #[cfg(test)]
 mod test {
@@ -278,6 +585,24 @@ mod test {
         assert_eq!(alloc.cap(), 5);
     }
 
+    #[test]
+    fn resize_keeps_contents_in_the_allocator() {
+        use crate::{Allocator, LimitedAlloc};
+
+        let limited = LimitedAlloc::new(Allocator::new(), 256);
+        let mut alloc = RawVec::<u64, _>::with_capacity_in(4, &limited);
+        for i in 0 .. 4 {
+            unsafe { alloc.raw().as_ptr().add(i).write(i as u64) };
+        }
+        alloc.resize(16);
+        assert_eq!(limited.used(), 128);
+        alloc.resize(2);
+        assert_eq!(unsafe { alloc.as_slice() }, [0, 1]);
+        assert_eq!(limited.used(), 16);
+        alloc.resize(0);
+        assert_eq!(limited.used(), 0);
+    }
+
     #[test]
     fn from_into_std_vec() {
         let vec = unsafe { RawVec::<u128>::with_capacity(465).into_vec(0) };
@@ -285,4 +610,56 @@ mod test {
         let raw = unsafe { RawVec::from_vec(vec) };
         assert_eq!(raw.cap(), 465);
     }
+
+    #[test]
+    fn checked_std_vec() {
+        let raw = RawVec::try_from_vec(alloc::vec![7u16; 30]).unwrap();
+        assert_eq!(raw.cap(), 30);
+        assert_eq!(unsafe { raw.as_slice() }[29], 7);
+        let mut vec = raw.try_into_vec().unwrap();
+        vec.push(1);
+        assert_eq!((vec.len(), vec.capacity()), (1, 30));
+    }
+
+    #[test]
+    fn cast_std_vec() {
+        use crate::VecCastError;
+
+        let mut samples = alloc::vec::Vec::<u16>::with_capacity(5);
+        samples.extend([1, 2, 3]);
+        let (err, samples) = RawVec::<[u16; 2]>::try_cast_vec(samples).unwrap_err();
+        assert_eq!(err, VecCastError::Size);
+        let (raw, len) = RawVec::<[u16; 5]>::try_cast_vec(samples).unwrap();
+        assert_eq!((raw.cap(), len), (1, 0));
+        let (err, _) = RawVec::<()>::try_cast_vec(alloc::vec![0u8]).unwrap_err();
+        assert_eq!(err, VecCastError::Size);
+    }
+
+    #[cfg(feature = "const-heap")]
+    #[test]
+    fn const_heap_tables() {
+        const fn table(len: usize) -> &'static [u64] {
+            let buf = RawVec::<u64>::const_with_capacity(len);
+            let mut i = 0;
+            while i < len {
+                unsafe { buf.raw().as_ptr().add(i).write(1 << i) };
+                i += 1;
+            }
+            unsafe { buf.into_static_slice(len) }
+        }
+        static POWERS: &[u64] = table(8);
+        static EMPTY: &[u64] = table(0);
+        static NAMES: &[&str] = unsafe {
+            let alloc = crate::UninitAlloc::<[&str]>::const_new_slice(2);
+            let names = alloc.raw().cast::<&str>().as_ptr();
+            names.write("const");
+            names.add(1).write("heap");
+            alloc.into_static()
+        };
+
+        assert_eq!(POWERS[7], 128);
+        assert!(EMPTY.is_empty());
+        assert_eq!(table(3), [1, 2, 4]);
+        assert_eq!(NAMES, ["const", "heap"]);
+    }
 }
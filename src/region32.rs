@@ -0,0 +1,323 @@
+use crate::{LayoutError, RawVec, RawVecError};
+use core::{
+    cell::Cell,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+    num::NonZeroU32,
+    ops::{Index, IndexMut},
+    ptr::NonNull,
+};
+
+/// A 32-bit reference to a value of a `Region32`: the byte offset of the
+/// value from the base of the region. Half the size of a pointer on 64-bit
+/// targets, and so is an `Option<Handle32<T>>`, which packs the links of
+/// trees and graphs twice as densely.
+pub struct Handle32<T> {
+    /// The offset plus one, leaving a niche for `Option`.
+    biased: NonZeroU32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle32<T> {
+    #[inline]
+    const fn new(offset: u32) -> Self {
+        Self {
+            biased: match NonZeroU32::new(offset.wrapping_add(1)) {
+                Some(biased) => biased,
+                None => panic!("Offset out of the 32-bit range"),
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// The byte offset of the value from the base of its region.
+    #[inline]
+    pub const fn offset(self) -> u32 {
+        self.biased.get() - 1
+    }
+}
+
+impl<T> Clone for Handle32<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle32<T> {}
+
+impl<T> PartialEq for Handle32<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.biased == other.biased
+    }
+}
+
+impl<T> Eq for Handle32<T> {}
+
+impl<T> Hash for Handle32<T> {
+    #[inline]
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.biased.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Handle32<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle32 {{ offset: {} }}", self.offset())
+    }
+}
+
+/// A region of values of one type, allocated once with a fixed capacity and
+/// referenced by `Handle32` offsets from its base instead of pointers. The
+/// region never moves, so allocating only needs a shared reference, and
+/// values are dropped all at once with the region.
+///
+/// Resolving a handle checks it against the length of the region, so a
+/// handle of another region resolves to some value, or to `None`, but never
+/// to unallocated memory.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{Handle32, Region32};
+///
+/// struct Node {
+///     value: u32,
+///     next: Option<Handle32<Node>>,
+/// }
+///
+/// assert_eq!(core::mem::size_of::<Option<Handle32<Node>>>(), 4);
+///
+/// let region = Region32::with_capacity(16);
+/// let tail = region.alloc(Node { value: 2, next: None });
+/// let head = region.alloc(Node { value: 1, next: Some(tail) });
+///
+/// let mut sum = 0;
+/// let mut cursor = Some(head);
+/// while let Some(handle) = cursor {
+///     sum += region[handle].value;
+///     cursor = region[handle].next;
+/// }
+/// assert_eq!(sum, 3);
+/// ```
+pub struct Region32<T> {
+    storage: RawVec<T>,
+    len: Cell<usize>,
+}
+
+impl<T> Region32<T> {
+    /// The largest capacity whose offsets fit in 32 bits.
+    pub const MAX_CAP: usize = (u32::MAX as usize - 1) / if mem::size_of::<T>() == 0 {
+        1
+    } else {
+        mem::size_of::<T>()
+    };
+
+    panicking! {
+        /// Creates a region holding up to `cap` values. In case of allocation
+        /// error, or if `cap` exceeds `MAX_CAP`, the function panics.
+        #[inline]
+        #[track_caller]
+        pub fn with_capacity(cap: usize) -> Self {
+            match Self::try_with_capacity(cap) {
+                Ok(this) => this,
+                Err(err) => panic!("Region32::with_capacity: {}", err),
+            }
+        }
+    }
+
+    /// Creates a region holding up to `cap` values. In case of allocation
+    /// error, or if `cap` exceeds `MAX_CAP`, `Err` is returned.
+    #[inline]
+    pub fn try_with_capacity(cap: usize) -> Result<Self, RawVecError> {
+        if cap > Self::MAX_CAP {
+            return Err(RawVecError::Layout(LayoutError));
+        }
+        Ok(Self {
+            storage: RawVec::try_with_capacity(cap)?,
+            len: Cell::new(0),
+        })
+    }
+
+    /// Moves `value` into the region. If the region is full, the function
+    /// panics.
+    #[inline]
+    #[track_caller]
+    pub fn alloc(&self, value: T) -> Handle32<T> {
+        match self.try_alloc(value) {
+            Ok(handle) => handle,
+            Err(_) => panic!("Region32 is full"),
+        }
+    }
+
+    /// Moves `value` into the region. If the region is full, the value is
+    /// given back.
+    #[inline]
+    pub fn try_alloc(&self, value: T) -> Result<Handle32<T>, T> {
+        let index = self.len.get();
+        if index == self.storage.cap() {
+            return Err(value);
+        }
+        unsafe { self.storage.raw().as_ptr().add(index).write(value) }
+        self.len.set(index + 1);
+        Ok(Handle32::new((index * mem::size_of::<T>()) as u32))
+    }
+
+    /// The value of `handle`, if it is in the allocated part of the region.
+    #[inline]
+    pub fn get(&self, handle: Handle32<T>) -> Option<&T> {
+        let ptr = self.resolve(handle)?;
+        unsafe { Some(&*ptr.as_ptr()) }
+    }
+
+    /// The value of `handle`, if it is in the allocated part of the region.
+    #[inline]
+    pub fn get_mut(&mut self, handle: Handle32<T>) -> Option<&mut T> {
+        let ptr = self.resolve(handle)?;
+        unsafe { Some(&mut *ptr.as_ptr()) }
+    }
+
+    /// The value of `handle`, without checking it.
+    ///
+    /// # Safety
+    /// The handle must come from this region.
+    #[inline]
+    pub unsafe fn get_unchecked(&self, handle: Handle32<T>) -> &T {
+        unsafe { &*self.storage.raw().as_ptr().byte_add(handle.offset() as usize) }
+    }
+
+    /// The pointer `handle` resolves to, if it is in the allocated part of
+    /// the region.
+    #[inline]
+    pub fn resolve(&self, handle: Handle32<T>) -> Option<NonNull<T>> {
+        let offset = handle.offset() as usize;
+        let size = mem::size_of::<T>();
+        let in_bounds = match size {
+            0 => offset == 0 && self.len.get() > 0,
+            _ => offset.is_multiple_of(size) && offset / size < self.len.get(),
+        };
+        if in_bounds {
+            unsafe { Some(NonNull::new_unchecked(self.storage.raw().as_ptr().byte_add(offset))) }
+        } else {
+            None
+        }
+    }
+
+    /// The handle of `value`, if it is a value of the region.
+    #[inline]
+    pub fn handle_of(&self, value: &T) -> Option<Handle32<T>> {
+        let offset = (value as *const T).addr().wrapping_sub(self.storage.raw().as_ptr().addr());
+        let handle = Handle32::new(u32::try_from(offset).ok().filter(|&o| o < u32::MAX)?);
+        self.resolve(handle).map(|_| handle)
+    }
+
+    /// The address handles are offsets from.
+    #[inline]
+    pub const fn base(&self) -> NonNull<T> {
+        self.storage.raw()
+    }
+
+    /// Number of values in the region.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Tests if the region holds no value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of values the region can hold.
+    #[inline]
+    pub const fn cap(&self) -> usize {
+        self.storage.cap()
+    }
+
+    /// The values of the region, in allocation order.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.storage.raw().as_ptr(), self.len.get()) }
+    }
+
+    /// The values of the region, in allocation order.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.storage.raw().as_ptr(), self.len.get()) }
+    }
+}
+
+impl<T> Index<Handle32<T>> for Region32<T> {
+    type Output = T;
+
+    #[inline]
+    #[track_caller]
+    fn index(&self, handle: Handle32<T>) -> &T {
+        self.get(handle).expect("Handle32 out of the region")
+    }
+}
+
+impl<T> IndexMut<Handle32<T>> for Region32<T> {
+    #[inline]
+    #[track_caller]
+    fn index_mut(&mut self, handle: Handle32<T>) -> &mut T {
+        self.get_mut(handle).expect("Handle32 out of the region")
+    }
+}
+
+impl<T> Drop for Region32<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { core::ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+impl<T> fmt::Debug for Region32<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Region32 {{ base: {:?}, len: {}, cap: {} }}",
+            self.base(),
+            self.len(),
+            self.cap()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Handle32, Region32};
+    use core::mem;
+
+    #[test]
+    fn oversized_capacity_is_an_error() {
+        assert!(Region32::<u64>::try_with_capacity(Region32::<u64>::MAX_CAP + 1).is_err());
+    }
+
+    #[test]
+    fn foreign_handles_stay_in_bounds() {
+        assert_eq!(mem::size_of::<Option<Handle32<u64>>>(), 4);
+
+        let big = Region32::with_capacity(8);
+        let small = Region32::with_capacity(2);
+        let handles: alloc::vec::Vec<_> = (0 .. 8u64).map(|i| big.alloc(i)).collect();
+        small.alloc(10u64);
+        assert_eq!(handles[5].offset(), 40);
+        assert_eq!(small.get(handles[0]), Some(&10));
+        assert_eq!(small.get(handles[5]), None);
+        assert_eq!(big.handle_of(&big[handles[3]]), Some(handles[3]));
+        assert_eq!(big.handle_of(&small[handles[0]]), None);
+        assert_eq!(small.try_alloc(11), Ok(Handle32::new(8)));
+        assert_eq!(small.try_alloc(12), Err(12));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::alloc_api::AllocError;
+use core::{
+    alloc::Layout,
+    fmt,
+    hint,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
+
+/// Largest shift of the backoff: a retry never spins more than `2^10` times.
+const MAX_BACKOFF_SHIFT: usize = 10;
+
+/// What a `RetryAlloc` does between two attempts to free some memory: flush
+/// caches, drop low-priority buffers, trim pools...
+pub trait Reclaim {
+    /// Tries to free memory before attempt number `attempt` (starting at 1)
+    /// to allocate `layout`. Returns `false` if nothing can be freed anymore,
+    /// so the allocation fails without the remaining attempts.
+    fn reclaim(&self, layout: Layout, attempt: usize) -> bool;
+}
+
+impl<F> Reclaim for F
+where
+    F: Fn(Layout, usize) -> bool,
+{
+    #[inline]
+    fn reclaim(&self, layout: Layout, attempt: usize) -> bool {
+        self(layout, attempt)
+    }
+}
+
+/// A `Reclaim` freeing nothing: the attempts are only spaced by the backoff,
+/// for failures expected to go away by themselves, e.g. memory being freed
+/// by another thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoReclaim;
+
+impl Reclaim for NoReclaim {
+    #[inline]
+    fn reclaim(&self, _layout: Layout, _attempt: usize) -> bool {
+        true
+    }
+}
+
+/// A wrapper retrying the failed allocations of its backend a given number
+/// of times, calling a `Reclaim` hook and spinning for an exponentially
+/// growing time before each new attempt, for systems where running out of
+/// memory is transient and recoverable. Deallocations and shrinks are
+/// forwarded as is.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, LimitedAlloc, RetryAlloc};
+///
+/// let limited = LimitedAlloc::new(Allocator::new(), 32);
+/// let alloc = RetryAlloc::with_reclaim(&limited, 3, |_, attempt| {
+///     limited.set_limit(32usize << attempt);
+///     true
+/// });
+///
+/// let layout = Layout::new::<[u8; 100]>();
+/// let block = alloc.allocate(layout).unwrap();
+/// assert_eq!((alloc.retries(), alloc.recovered(), alloc.failures()), (2, 1, 0));
+/// unsafe { alloc.deallocate(block.cast(), layout) };
+/// ```
+pub struct RetryAlloc<A, R = NoReclaim>
+where
+    A: crate::alloc_api::Allocator,
+    R: Reclaim,
+{
+    backend: A,
+    reclaim: R,
+    attempts: usize,
+    retries: AtomicUsize,
+    recovered: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl<A> RetryAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Retries the failed allocations of `backend` up to `retries` times,
+    /// without reclaiming anything in between.
+    #[inline]
+    pub const fn new(backend: A, retries: usize) -> Self {
+        Self::with_reclaim(backend, retries, NoReclaim)
+    }
+}
+
+impl<A, R> RetryAlloc<A, R>
+where
+    A: crate::alloc_api::Allocator,
+    R: Reclaim,
+{
+    /// Retries the failed allocations of `backend` up to `retries` times,
+    /// calling `reclaim` before each retry.
+    #[inline]
+    pub const fn with_reclaim(backend: A, retries: usize, reclaim: R) -> Self {
+        Self {
+            backend,
+            reclaim,
+            attempts: retries.saturating_add(1),
+            retries: AtomicUsize::new(0),
+            recovered: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// The allocator whose failures are retried.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// Number of retries made so far.
+    #[inline]
+    pub fn retries(&self) -> usize {
+        self.retries.load(Relaxed)
+    }
+
+    /// Number of requests which failed at first, and succeeded when retried.
+    #[inline]
+    pub fn recovered(&self) -> usize {
+        self.recovered.load(Relaxed)
+    }
+
+    /// Number of requests which failed even after the retries.
+    #[inline]
+    pub fn failures(&self) -> usize {
+        self.failures.load(Relaxed)
+    }
+
+    /// Runs `request` until it succeeds or the attempts run out.
+    fn retry<F>(&self, layout: Layout, mut request: F) -> Result<NonNull<[u8]>, AllocError>
+    where
+        F: FnMut() -> Result<NonNull<[u8]>, AllocError>,
+    {
+        if let Ok(block) = request() {
+            return Ok(block);
+        }
+        for attempt in 1 .. self.attempts {
+            if !self.reclaim.reclaim(layout, attempt) {
+                break;
+            }
+            for _ in 0 .. 1 << (attempt - 1).min(MAX_BACKOFF_SHIFT) {
+                hint::spin_loop();
+            }
+            self.retries.fetch_add(1, Relaxed);
+            if let Ok(block) = request() {
+                self.recovered.fetch_add(1, Relaxed);
+                return Ok(block);
+            }
+        }
+        self.failures.fetch_add(1, Relaxed);
+        Err(AllocError)
+    }
+}
+
+unsafe impl<A, R> crate::alloc_api::Allocator for RetryAlloc<A, R>
+where
+    A: crate::alloc_api::Allocator,
+    R: Reclaim,
+{
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(layout, || self.backend.allocate(layout))
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(layout, || self.backend.allocate_zeroed(layout))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.backend.deallocate(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(new_layout, || self.backend.grow(ptr, old_layout, new_layout))
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(new_layout, || self.backend.grow_zeroed(ptr, old_layout, new_layout))
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.backend.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+impl<A, R> fmt::Debug for RetryAlloc<A, R>
+where
+    A: crate::alloc_api::Allocator,
+    R: Reclaim,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RetryAlloc {{ retries: {}, recovered: {}, failures: {} }}",
+            self.retries(),
+            self.recovered(),
+            self.failures()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RetryAlloc;
+    use crate::{alloc_api::Allocator as _, Allocator, LimitedAlloc};
+    use core::{alloc::Layout, cell::Cell};
+
+    #[test]
+    fn gives_up_when_nothing_is_reclaimed() {
+        let limited = LimitedAlloc::new(Allocator::new(), 16);
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let calls = Cell::new(0);
+        let alloc = RetryAlloc::with_reclaim(&limited, 5, |_, attempt| {
+            calls.set(attempt);
+            attempt < 3
+        });
+        assert!(alloc.allocate(layout).is_err());
+        assert_eq!((calls.get(), alloc.retries(), alloc.failures()), (3, 2, 1));
+
+        let alloc = RetryAlloc::new(&limited, 2);
+        assert!(alloc.allocate(layout).is_err());
+        assert_eq!((alloc.retries(), alloc.recovered()), (2, 0));
+        let small = Layout::from_size_align(8, 8).unwrap();
+        let block = alloc.allocate(small).unwrap();
+        unsafe { alloc.deallocate(block.cast(), small) };
+        assert_eq!((alloc.retries(), alloc.failures()), (2, 1));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    alloc_api::{AllocError, Allocator as _},
+    freelist::{class_layout, class_of},
+    Tlsf, FREE_LIST_CLASSES,
+};
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    fmt,
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+};
+
+/// An allocator for real-time threads (audio callbacks, control loops):
+/// after a prefill phase, every operation runs in bounded time, without
+/// locks nor system calls.
+///
+/// Memory comes from a caller-provided region managed by a `Tlsf`, fronted
+/// by per-class free lists of the `FreeListAlloc` size classes. During the
+/// prefill phase, `prefill` fills the lists with the blocks the real-time
+/// code will need. Once `seal`ed, allocations are served from the lists in
+/// constant time, and freed blocks go back to them. A request the lists
+/// cannot serve (empty class, or a block bigger than the classes) is a slow
+/// path: it is counted, fires a debug assertion, and is served by the `Tlsf`
+/// in release builds, still in bounded time.
+///
+/// The allocator is not `Sync`: each real-time thread owns its own.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::{alloc::Layout, mem::MaybeUninit};
+/// use owned_alloc::{alloc_api::Allocator as _, RtAlloc};
+///
+/// let mut region = vec![MaybeUninit::uninit(); 1 << 16];
+/// let alloc = RtAlloc::new(&mut region);
+/// let voice = Layout::new::<[f32; 64]>();
+/// alloc.prefill(voice, 8).unwrap();
+/// alloc.seal();
+///
+/// // In the audio callback.
+/// let block = alloc.allocate(voice).unwrap();
+/// unsafe { alloc.deallocate(block.cast(), voice) };
+/// assert_eq!(alloc.slow_paths(), 0);
+/// ```
+pub struct RtAlloc<'r> {
+    tlsf: Tlsf<'r>,
+    heads: [Cell<*mut u8>; FREE_LIST_CLASSES],
+    cached: Cell<usize>,
+    sealed: Cell<bool>,
+    slow_paths: Cell<usize>,
+}
+
+impl<'r> RtAlloc<'r> {
+    /// Creates an allocator managing the given region, in the prefill phase.
+    #[inline]
+    pub fn new(region: &'r mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            tlsf: Tlsf::new(region),
+            heads: [const { Cell::new(ptr::null_mut()) }; FREE_LIST_CLASSES],
+            cached: Cell::new(0),
+            sealed: Cell::new(false),
+            slow_paths: Cell::new(0),
+        }
+    }
+
+    /// Takes `count` blocks of the class serving `layout` from the region and
+    /// keeps them for the real-time phase. Panics if the allocator is sealed
+    /// or no class serves `layout`. In case of allocation error, `Err` is
+    /// returned; the blocks taken so far are kept.
+    pub fn prefill(&self, layout: Layout, count: usize) -> Result<(), AllocError> {
+        assert!(!self.is_sealed(), "Prefilling a sealed RtAlloc");
+        let index = class_of(layout).expect("Layout bigger than the size classes");
+        let class_layout = class_layout(index);
+        for _ in 0 .. count {
+            let block = self.tlsf.allocate(class_layout)?;
+            unsafe { self.push(index, block.cast()) };
+        }
+        Ok(())
+    }
+
+    /// Ends the prefill phase: from now on, a request which misses the
+    /// prefilled blocks fires a debug assertion.
+    #[inline]
+    pub fn seal(&self) {
+        self.sealed.set(true);
+    }
+
+    /// Tests if the prefill phase is over.
+    #[inline]
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.get()
+    }
+
+    /// Number of blocks kept in the free lists.
+    #[inline]
+    pub fn cached(&self) -> usize {
+        self.cached.get()
+    }
+
+    /// Number of requests which missed the free lists after sealing.
+    #[inline]
+    pub fn slow_paths(&self) -> usize {
+        self.slow_paths.get()
+    }
+
+    #[inline]
+    unsafe fn push(&self, index: usize, block: NonNull<u8>) {
+        block.as_ptr().cast::<*mut u8>().write(self.heads[index].get());
+        self.heads[index].set(block.as_ptr());
+        self.cached.set(self.cached.get() + 1);
+    }
+
+    #[inline]
+    fn pop(&self, index: usize) -> Option<NonNull<u8>> {
+        let block = NonNull::new(self.heads[index].get())?;
+        self.heads[index].set(unsafe { block.as_ptr().cast::<*mut u8>().read() });
+        self.cached.set(self.cached.get() - 1);
+        Some(block)
+    }
+
+    /// Serves a request from the `Tlsf`, reporting it if sealed.
+    fn slow_path(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if self.is_sealed() {
+            self.slow_paths.set(self.slow_paths.get() + 1);
+            debug_assert!(
+                false,
+                "Real-time allocation of {} bytes missed the prefilled blocks",
+                layout.size()
+            );
+        }
+        self.tlsf.allocate(layout)
+    }
+}
+
+unsafe impl<'r> crate::alloc_api::Allocator for RtAlloc<'r> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match class_of(layout) {
+            Some(index) => {
+                let class_layout = class_layout(index);
+                match self.pop(index) {
+                    Some(block) => Ok(NonNull::slice_from_raw_parts(block, class_layout.size())),
+                    None => self.slow_path(class_layout),
+                }
+            },
+            None => self.slow_path(layout),
+        }
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match class_of(layout) {
+            Some(index) => self.push(index, ptr),
+            None => self.tlsf.deallocate(ptr, layout),
+        }
+    }
+}
+
+impl<'r> fmt::Debug for RtAlloc<'r> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RtAlloc {{ cached: {}, sealed: {}, slow_paths: {} }}",
+            self.cached(),
+            self.is_sealed(),
+            self.slow_paths()
+        )
+    }
+}
+
+unsafe impl<'r> Send for RtAlloc<'r> {}
+
+#[cfg(test)]
+mod test {
+    use super::RtAlloc;
+    use crate::alloc_api::Allocator;
+    use alloc::vec::Vec;
+    use core::{
+        alloc::Layout,
+        mem::MaybeUninit,
+    };
+
+    #[test]
+    fn sealed_requests_reuse_prefilled_blocks() {
+        let mut region = alloc::vec![MaybeUninit::<u8>::uninit(); 1 << 14];
+        let alloc = RtAlloc::new(&mut region);
+        let layout = Layout::new::<[u64; 6]>();
+        alloc.prefill(layout, 4).unwrap();
+        alloc.seal();
+
+        for _ in 0 .. 3 {
+            let blocks: Vec<_> = (0 .. 4).map(|_| alloc.allocate(layout).unwrap()).collect();
+            assert_eq!(alloc.cached(), 0);
+            for block in blocks {
+                assert_eq!(block.len(), 64);
+                unsafe { alloc.deallocate(block.cast(), layout) };
+            }
+        }
+        assert_eq!((alloc.cached(), alloc.slow_paths()), (4, 0));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "missed the prefilled blocks")]
+    fn sealed_miss_fires_assertion() {
+        let mut region = alloc::vec![MaybeUninit::<u8>::uninit(); 1 << 14];
+        let alloc = RtAlloc::new(&mut region);
+        alloc.seal();
+        let _ = alloc.allocate(Layout::new::<u32>());
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static COUNTERS: (core::cell::Cell<usize>, core::cell::Cell<usize>) = const {
+        (core::cell::Cell::new(0), core::cell::Cell::new(0))
+    };
+}
+
+#[cfg(not(feature = "std"))]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(not(feature = "std"))]
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocations requested through the crate's allocator, as measured by an
+/// `AllocScope`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScopeStats {
+    /// Number of allocations requested, reallocations included.
+    pub allocations: usize,
+    /// Bytes requested by those allocations.
+    pub bytes: usize,
+}
+
+/// A guard measuring the allocations requested through the crate's
+/// allocator since it was created, e.g. by `OwnedAlloc`, `RawVec` and the
+/// containers built on them. Counts are per-thread with the `std` feature,
+/// and global otherwise. Scopes can be nested, each one counting the
+/// allocations of the scopes it encloses.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{AllocScope, OwnedAlloc, ScopeStats};
+///
+/// let node = OwnedAlloc::new([0u64; 4]);
+/// let (sum, stats) = AllocScope::measure(|| node.iter().sum::<u64>());
+/// assert_eq!(sum, 0);
+/// assert_eq!(stats, ScopeStats::default());
+///
+/// let (_, stats) = AllocScope::measure(|| OwnedAlloc::new([0u64; 4]));
+/// assert_eq!((stats.allocations, stats.bytes), (1, 32));
+/// ```
+#[derive(Debug)]
+pub struct AllocScope {
+    start: ScopeStats,
+}
+
+impl AllocScope {
+    /// Starts measuring from now on.
+    #[inline]
+    pub fn new() -> Self {
+        Self { start: totals() }
+    }
+
+    /// Runs `f`, returning its result along with the allocations it
+    /// requested.
+    #[inline]
+    pub fn measure<F, R>(f: F) -> (R, ScopeStats)
+    where
+        F: FnOnce() -> R,
+    {
+        let scope = Self::new();
+        let ret = f();
+        (ret, scope.stats())
+    }
+
+    /// The allocations requested since the scope was created.
+    #[inline]
+    pub fn stats(&self) -> ScopeStats {
+        let now = totals();
+        ScopeStats {
+            allocations: now.allocations.wrapping_sub(self.start.allocations),
+            bytes: now.bytes.wrapping_sub(self.start.bytes),
+        }
+    }
+}
+
+impl Default for AllocScope {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts an allocation of `size` bytes requested through the crate's
+/// allocator.
+#[inline]
+pub(crate) fn record(size: usize) {
+    #[cfg(feature = "std")]
+    {
+        // The thread-locals are gone while the thread is being torn down.
+        let _ = COUNTERS.try_with(|(allocations, bytes)| {
+            allocations.set(allocations.get().wrapping_add(1));
+            bytes.set(bytes.get().wrapping_add(size));
+        });
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+fn totals() -> ScopeStats {
+    #[cfg(feature = "std")]
+    {
+        COUNTERS
+            .try_with(|(allocations, bytes)| ScopeStats {
+                allocations: allocations.get(),
+                bytes: bytes.get(),
+            })
+            .unwrap_or_default()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        ScopeStats {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Without thread-locals, the tests running in parallel would be counted.
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::AllocScope;
+    use crate::OwnedAlloc;
+
+    #[test]
+    fn nested_scopes_count_their_inner_scopes() {
+        let outer = AllocScope::new();
+        let small = OwnedAlloc::new([0u32; 4]);
+        let (big, inner) = AllocScope::measure(|| OwnedAlloc::new([0u32; 8]));
+        assert_eq!((inner.allocations, inner.bytes), (1, 32));
+
+        drop((small, big, OwnedAlloc::new(0u64)));
+        let stats = outer.stats();
+        assert_eq!((stats.allocations, stats.bytes), (3, 56));
+    }
+}
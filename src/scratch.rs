@@ -0,0 +1,187 @@
+use crate::RawVec;
+use core::{mem::MaybeUninit, ptr::NonNull};
+
+/// Size of the scratch stack of each thread, taken from the heap on first
+/// use by `with_scratch`.
+pub const SCRATCH_SIZE: usize = 64 * 1024;
+
+panicking! {
+    /// Runs `f` on `len` uninitialized elements of type `T`, taken from a
+    /// per-thread stack, so short-lived buffers of variable length don't hit the
+    /// heap. Like `alloca`, but a request too big for the stack falls back to the
+    /// heap instead of overflowing it.
+    ///
+    /// Calls can be nested: each one takes the memory past the one enclosing it,
+    /// and gives it back when `f` returns or panics. Values written to the
+    /// buffer are never dropped.
+    ///
+    /// Without the `std` feature, there are no thread-locals, and the buffer
+    /// always comes from the heap.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::with_scratch;
+    ///
+    /// let words = ["scratch", "memory"];
+    /// let len = with_scratch::<u8, _, _>(64, |buf| {
+    ///     let mut len = 0;
+    ///     for byte in words.iter().flat_map(|word| word.bytes()) {
+    ///         buf[len].write(byte);
+    ///         len += 1;
+    ///     }
+    ///     len
+    /// });
+    /// assert_eq!(len, 13);
+    /// ```
+    pub fn with_scratch<T, R, F>(len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [MaybeUninit<T>]) -> R,
+    {
+        #[cfg(feature = "std")]
+        let f = match stack::with(len, f) {
+            Ok(ret) => return ret,
+            Err(f) => f,
+        };
+        let buf = RawVec::<T>::with_capacity(len);
+        f(unsafe { slice(buf.raw(), len) })
+    }
+}
+
+/// Frees the scratch stack of the calling thread if no call is using it,
+/// returning how many bytes were freed.
+#[inline]
+pub(crate) fn release_local() -> usize {
+    #[cfg(feature = "std")]
+    {
+        stack::release()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        0
+    }
+}
+
+#[inline]
+unsafe fn slice<'a, T>(ptr: NonNull<T>, len: usize) -> &'a mut [MaybeUninit<T>] {
+    core::slice::from_raw_parts_mut(ptr.as_ptr().cast(), len)
+}
+
+#[cfg(feature = "std")]
+mod stack {
+    use super::{slice, SCRATCH_SIZE};
+    use crate::{RawVec, UninitAlloc};
+    use core::{
+        alloc::Layout,
+        cell::{Cell, UnsafeCell},
+        mem::{self, MaybeUninit},
+        ptr::NonNull,
+    };
+
+    struct Stack {
+        buf: UnsafeCell<RawVec<u8>>,
+        top: Cell<usize>,
+    }
+
+    std::thread_local! {
+        static STACK: Stack = const {
+            Stack {
+                buf: UnsafeCell::new(RawVec::new()),
+                top: Cell::new(0),
+            }
+        };
+    }
+
+    /// Gives the memory back to the stack, even if `f` panics.
+    struct Pop<'s> {
+        top: &'s Cell<usize>,
+        saved: usize,
+    }
+
+    impl<'s> Drop for Pop<'s> {
+        #[inline]
+        fn drop(&mut self) {
+            self.top.set(self.saved);
+        }
+    }
+
+    /// Runs `f` on memory of the stack, or gives it back if the stack has no
+    /// room left.
+    pub(super) fn with<T, R, F>(len: usize, f: F) -> Result<R, F>
+    where
+        F: FnOnce(&mut [MaybeUninit<T>]) -> R,
+    {
+        let layout = match Layout::array::<T>(len) {
+            Ok(layout) if layout.size() != 0 => layout,
+            _ => return Err(f),
+        };
+        let stack = match STACK.try_with(|stack| stack as *const Stack) {
+            Ok(stack) => unsafe { &*stack },
+            Err(_) => return Err(f),
+        };
+        let buf = unsafe { &mut *stack.buf.get() };
+        if buf.cap() == 0 {
+            match RawVec::try_with_capacity(SCRATCH_SIZE) {
+                Ok(new) => *buf = new,
+                Err(_) => return Err(f),
+            }
+        }
+        let saved = stack.top.get();
+        let base = buf.raw().as_ptr();
+        let start = saved + unsafe { base.add(saved) }.align_offset(layout.align());
+        match start.checked_add(layout.size()) {
+            Some(end) if end <= buf.cap() => {
+                stack.top.set(end);
+                let _pop = Pop {
+                    top: &stack.top,
+                    saved,
+                };
+                let ptr = unsafe { NonNull::new_unchecked(base.add(start).cast::<T>()) };
+                Ok(f(unsafe { slice(ptr, len) }))
+            },
+            _ => Err(f),
+        }
+    }
+
+    /// Frees the stack if it is allocated and unused.
+    pub(super) fn release() -> usize {
+        STACK
+            .try_with(|stack| {
+                let buf = unsafe { &mut *stack.buf.get() };
+                if stack.top.get() != 0 || buf.cap() == 0 {
+                    return 0;
+                }
+                let cap = buf.cap();
+                drop(UninitAlloc::from(mem::take(buf)));
+                cap
+            })
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::with_scratch;
+
+    #[test]
+    fn nested_and_oversized() {
+        let (outer, inner) = with_scratch::<u64, _, _>(100, |outer| {
+            assert_eq!(outer.len(), 100);
+            assert_eq!(outer.as_ptr() as usize % 8, 0);
+            let inner = with_scratch::<u8, _, _>(10, |inner| inner.as_ptr() as usize);
+            (outer.as_ptr() as usize, inner)
+        });
+        let again = with_scratch::<u64, _, _>(1, |buf| buf.as_ptr() as usize);
+        if cfg!(feature = "std") {
+            assert_eq!(inner, outer + 800);
+            assert_eq!(again, outer);
+        }
+
+        let big = with_scratch::<u64, _, _>(1 << 20, |buf| {
+            buf[(1 << 20) - 1].write(7);
+            buf.len()
+        });
+        assert_eq!(big, 1 << 20);
+    }
+}
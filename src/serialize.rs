@@ -0,0 +1,180 @@
+use crate::{OwnedAlloc, UninitAlloc};
+use core::{fmt, marker::PhantomData};
+use serde::{
+    de::{self, Deserialize, Deserializer, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+
+/// Serializes the value, as `Box` does. Deserializing an `OwnedAlloc<T>`,
+/// `OwnedAlloc<[T]>` or `OwnedAlloc<str>` allocates through the crate, and
+/// fails with a custom error in case of allocation error.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+/// extern crate serde;
+///
+/// use owned_alloc::OwnedAlloc;
+/// use serde::de::{
+///     value::{Error, SeqDeserializer, StrDeserializer},
+///     Deserialize,
+/// };
+///
+/// let name = OwnedAlloc::<str>::deserialize(StrDeserializer::<Error>::new("eth0"));
+/// assert_eq!(&*name.unwrap(), "eth0");
+///
+/// let ports = SeqDeserializer::<_, Error>::new([80u16, 443].into_iter());
+/// assert_eq!(*OwnedAlloc::<[u16]>::deserialize(ports).unwrap(), [80, 443]);
+/// ```
+impl<T, A> Serialize for OwnedAlloc<T, A>
+where
+    T: ?Sized + Serialize,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for OwnedAlloc<T>
+where
+    T: Deserialize<'de>,
+{
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        OwnedAlloc::try_new(value).map_err(de::Error::custom)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for OwnedAlloc<[T]>
+where
+    T: Deserialize<'de>,
+{
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SliceVisitor(PhantomData))
+    }
+}
+
+impl<'de> Deserialize<'de> for OwnedAlloc<str> {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+/// Collects a sequence with `OwnedAlloc::try_collect_slice`.
+struct SliceVisitor<T>(PhantomData<fn() -> T>);
+
+impl<'de, T> Visitor<'de> for SliceVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = OwnedAlloc<[T]>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence")
+    }
+
+    fn visit_seq<S>(self, seq: S) -> Result<Self::Value, S::Error>
+    where
+        S: SeqAccess<'de>,
+    {
+        let mut items = SeqItems {
+            seq,
+            error: None,
+            _marker: PhantomData,
+        };
+        let res = OwnedAlloc::try_collect_slice(&mut items);
+        match items.error {
+            Some(err) => Err(err),
+            None => res.map_err(de::Error::custom),
+        }
+    }
+}
+
+/// The elements of a sequence, ending at the first error, which is kept.
+struct SeqItems<S, E, T> {
+    seq: S,
+    error: Option<E>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'de, S, E, T> Iterator for SeqItems<S, E, T>
+where
+    S: SeqAccess<'de, Error = E>,
+    T: Deserialize<'de>,
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.error.is_some() {
+            return None;
+        }
+        self.seq.next_element().unwrap_or_else(|err| {
+            self.error = Some(err);
+            None
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match (&self.error, self.seq.size_hint()) {
+            (None, Some(len)) => (len, Some(len)),
+            (None, None) => (0, None),
+            (Some(_), _) => (0, Some(0)),
+        }
+    }
+}
+
+/// Copies a string with `UninitAlloc::<str>::try_new_bytes`.
+struct StrVisitor;
+
+impl<'de> Visitor<'de> for StrVisitor {
+    type Value = OwnedAlloc<str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string")
+    }
+
+    fn visit_str<E>(self, string: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let alloc = UninitAlloc::<str>::try_new_bytes(string.len()).map_err(E::custom)?;
+        Ok(alloc.init_from_str(string))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::OwnedAlloc;
+    use serde::de::{
+        value::{Error, SeqDeserializer},
+        Deserialize,
+    };
+
+    #[test]
+    fn sequence_errors_stop_collecting() {
+        let items = SeqDeserializer::<_, Error>::new([1u32, 2, 300, 4].into_iter());
+        assert!(OwnedAlloc::<[OwnedAlloc<u8>]>::deserialize(items).is_err());
+
+        let empty = SeqDeserializer::<_, Error>::new(core::iter::empty::<bool>());
+        assert!(OwnedAlloc::<[bool]>::deserialize(empty).unwrap().is_empty());
+    }
+}
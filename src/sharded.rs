@@ -0,0 +1,311 @@
+use crate::{
+    alloc_api::AllocError,
+    freelist::{class_layout, class_of, FreeList},
+    CachePadded, ReleaseFree, ReleaseReport, DEFAULT_FREE_LIST_CAP, FREE_LIST_CLASSES,
+};
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    fmt,
+    ptr::NonNull,
+};
+
+/// Default number of shards of a `ShardedAlloc`.
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// A hint of the CPU the calling thread runs on, used to pick a shard. With
+/// the `os` feature, this is the actual CPU on Linux, Android and Windows.
+/// Elsewhere, it is a hash identifying the calling thread, so each thread at
+/// least sticks to a shard.
+#[inline]
+pub fn cpu_hint() -> usize {
+    #[cfg(feature = "os")]
+    if let Some(cpu) = crate::sys::current_cpu() {
+        return cpu;
+    }
+    thread_hash()
+}
+
+#[cfg(feature = "std")]
+#[inline]
+fn thread_hash() -> usize {
+    std::thread_local! {
+        static KEY: u8 = const { 0 };
+    }
+    KEY.with(|key| mix(key as *const u8 as usize))
+}
+
+/// Without thread-locals, threads are told apart by their stacks, which are
+/// far more than a megabyte apart on every common platform.
+#[cfg(not(feature = "std"))]
+#[inline]
+fn thread_hash() -> usize {
+    let local = 0u8;
+    mix(&local as *const u8 as usize >> 20)
+}
+
+#[inline]
+fn mix(key: usize) -> usize {
+    (key as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(32) as usize
+}
+
+struct Shard {
+    classes: [FreeList; FREE_LIST_CLASSES],
+}
+
+/// A wrapper fronting any allocator with free lists sharded by CPU, so
+/// threads on different cores almost never touch the same cache lines.
+///
+/// Shards rebalance through a shared depot: a shard whose free list reaches
+/// the cap moves it to the depot, and a shard running out of blocks refills
+/// from the depot before asking the backend. `rebalance` moves every shard's
+/// blocks to the depot, for periodic calls by long-running programs whose
+/// threads migrate between cores.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, ShardedAlloc};
+///
+/// let alloc = ShardedAlloc::new(Allocator::new());
+/// let layout = Layout::new::<[u8; 40]>();
+/// let block = alloc.allocate(layout).unwrap();
+/// unsafe { alloc.deallocate(block.cast(), layout) };
+/// assert_eq!(alloc.cached(), 1);
+///
+/// alloc.rebalance();
+/// assert_eq!(alloc.allocate(layout).unwrap(), block);
+/// ```
+pub struct ShardedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    backend: A,
+    shards: Vec<CachePadded<Shard>>,
+    depot: [FreeList; FREE_LIST_CLASSES],
+    cap: usize,
+}
+
+impl<A> ShardedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Fronts `backend` with `DEFAULT_SHARDS` shards, each keeping up to
+    /// `DEFAULT_FREE_LIST_CAP` blocks per size class.
+    #[inline]
+    pub fn new(backend: A) -> Self {
+        Self::with_shards(backend, DEFAULT_SHARDS, DEFAULT_FREE_LIST_CAP)
+    }
+
+    /// Fronts `backend` with `shards` shards (at least one), each keeping up
+    /// to `cap` blocks per size class. The depot keeps up to `cap` blocks
+    /// per class for every shard.
+    pub fn with_shards(backend: A, shards: usize, cap: usize) -> Self {
+        let shards = (0 .. shards.max(1))
+            .map(|_| {
+                CachePadded::new(Shard {
+                    classes: [FreeList::EMPTY; FREE_LIST_CLASSES],
+                })
+            })
+            .collect();
+        Self {
+            backend,
+            shards,
+            depot: [FreeList::EMPTY; FREE_LIST_CLASSES],
+            cap,
+        }
+    }
+
+    /// The allocator behind the shards.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// Number of shards.
+    #[inline]
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Number of blocks kept in all shards and in the depot.
+    pub fn cached(&self) -> usize {
+        let shards: usize = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.classes.iter())
+            .map(FreeList::len)
+            .sum();
+        shards + self.depot.iter().map(FreeList::len).sum::<usize>()
+    }
+
+    /// Moves the blocks of every shard to the depot, where any shard can
+    /// pick them up.
+    pub fn rebalance(&self) {
+        for shard in &self.shards {
+            for (index, class) in shard.classes.iter().enumerate() {
+                self.spill(index, class);
+            }
+        }
+    }
+
+    /// Gives every cached block back to the backend, returning how many bytes
+    /// were released. Suitable as a trim handler.
+    pub fn flush(&self) -> usize {
+        let mut released = 0;
+        let lists = self.shards.iter().map(|shard| &shard.classes);
+        for classes in lists.chain(Some(&self.depot)) {
+            for (index, class) in classes.iter().enumerate() {
+                released += unsafe { class.flush(&self.backend, class_layout(index)) };
+            }
+        }
+        released
+    }
+
+    #[inline]
+    fn shard(&self) -> &Shard {
+        &self.shards[cpu_hint() % self.shards.len()]
+    }
+
+    /// Moves the blocks of a shard's list to the depot, or to the backend if
+    /// the depot is full.
+    fn spill(&self, index: usize, list: &FreeList) {
+        if let Some(chain) = list.take_all() {
+            if self.depot[index].len() < self.cap * self.shards.len() {
+                self.depot[index].push_all(chain);
+            } else {
+                unsafe { chain.free(&self.backend, class_layout(index)) };
+            }
+        }
+    }
+}
+
+/// Smaller classes are retained first, the depot before the shards.
+impl<A> ReleaseFree for ShardedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn release_free_memory(&self, mut retain: usize) -> ReleaseReport {
+        let mut report = ReleaseReport::default();
+        for index in 0 .. FREE_LIST_CLASSES {
+            let shards = self.shards.iter().map(|shard| &shard.classes[index]);
+            for list in Some(&self.depot[index]).into_iter().chain(shards) {
+                report += unsafe { list.release(&self.backend, class_layout(index), &mut retain) };
+            }
+        }
+        report
+    }
+}
+
+unsafe impl<A> crate::alloc_api::Allocator for ShardedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let index = match class_of(layout) {
+            Some(index) => index,
+            None => return self.backend.allocate(layout),
+        };
+        let class_layout = class_layout(index);
+        let list = &self.shard().classes[index];
+        let block = match list.pop() {
+            Some(block) => block,
+            None => match self.depot[index].take_all() {
+                Some(chain) => {
+                    let (first, rest) = chain.split_first();
+                    if let Some(rest) = rest {
+                        list.push_all(rest);
+                    }
+                    first
+                },
+                None => return self.backend.allocate(class_layout),
+            },
+        };
+        Ok(NonNull::slice_from_raw_parts(block, class_layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let index = match class_of(layout) {
+            Some(index) => index,
+            None => return self.backend.deallocate(ptr, layout),
+        };
+        let list = &self.shard().classes[index];
+        if list.len() >= self.cap {
+            self.spill(index, list);
+        }
+        list.push(ptr);
+    }
+}
+
+impl<A> Drop for ShardedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl<A> fmt::Debug for ShardedAlloc<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ShardedAlloc {{ shards: {}, cached: {} }}",
+            self.shards.len(),
+            self.cached()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ShardedAlloc;
+    use crate::{alloc_api::Allocator as _, Allocator, ReleaseFree};
+    use alloc::vec::Vec;
+    use core::alloc::Layout;
+
+    #[test]
+    fn shards_refill_from_depot() {
+        let alloc = ShardedAlloc::with_shards(Allocator::new(), 1, 4);
+        let layout = Layout::new::<u64>();
+        let blocks: Vec<_> = (0 .. 6).map(|_| alloc.allocate(layout).unwrap()).collect();
+        for block in blocks {
+            unsafe { alloc.deallocate(block.cast(), layout) }
+        }
+        assert_eq!(alloc.cached(), 6);
+        assert_eq!(alloc.depot[0].len(), 4);
+
+        for _ in 0 .. 3 {
+            alloc.allocate(layout).unwrap();
+        }
+        assert_eq!(alloc.shards[0].classes[0].len(), 3);
+        assert_eq!(alloc.depot[0].len(), 0);
+    }
+
+    #[test]
+    fn release_keeps_the_floor() {
+        let alloc = ShardedAlloc::with_shards(Allocator::new(), 2, 2);
+        let small = Layout::new::<u64>();
+        let big = Layout::new::<[u8; 256]>();
+        let blocks: Vec<_> = [small, small, small, big]
+            .into_iter()
+            .map(|layout| (alloc.allocate(layout).unwrap(), layout))
+            .collect();
+        for (block, layout) in blocks {
+            unsafe { alloc.deallocate(block.cast(), layout) }
+        }
+
+        let report = alloc.release_free_memory(40);
+        assert_eq!((report.retained, report.released), (32, 16 + 256));
+        assert_eq!(alloc.cached(), 2);
+        assert_eq!(alloc.release_free_memory(0).released, 32);
+    }
+}
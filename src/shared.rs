@@ -0,0 +1,680 @@
+use crate::{AllocError, Allocator};
+use core::{
+    alloc::Layout,
+    fmt, hint,
+    marker::PhantomData,
+    mem,
+    mem::ManuallyDrop,
+    ops::Deref,
+    ptr::{self, NonNull},
+    sync::atomic::{self, AtomicUsize, Ordering},
+};
+
+/// Counts above this are considered leaked handles, just like `Arc` does.
+const MAX_COUNT: usize = isize::MAX as usize;
+
+/// Weak count of an allocation locked by `get_mut`, during which no weak
+/// handle may be created.
+const LOCKED: usize = usize::MAX;
+
+/// Laid out by hand in `from_slice_in`, from the layout of `Inner<()>`.
+#[repr(C)]
+struct Inner<T>
+where
+    T: ?Sized,
+{
+    strong: AtomicUsize,
+    /// Weak handles, plus one held collectively by the strong handles, or
+    /// `LOCKED`.
+    weak: AtomicUsize,
+    value: T,
+}
+
+impl<T> Inner<T>
+where
+    T: ?Sized,
+{
+    /// Counts a new weak handle, waiting for `get_mut` to release the count.
+    #[inline]
+    fn acquire_weak(&self) {
+        let mut old = self.weak.load(Ordering::Relaxed);
+        loop {
+            if old == LOCKED {
+                hint::spin_loop();
+                old = self.weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if old > MAX_COUNT {
+                panic!("Too many weak handles");
+            }
+            match self.weak.compare_exchange_weak(
+                old,
+                old + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => old = actual,
+            }
+        }
+    }
+}
+
+/// A thread-safe reference-counted allocation, like `Arc`, but allocated by
+/// any allocator. The value is dropped with the last `AtomicShared`, and the
+/// memory freed with the last `AtomicShared` or `AtomicWeak`.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::AtomicShared;
+/// use std::thread;
+///
+/// let shared = AtomicShared::new([1, 2, 3]);
+/// let thread = thread::spawn({
+///     let shared = shared.clone();
+///     move || shared.iter().sum::<i32>()
+/// });
+/// assert_eq!(thread.join().unwrap(), 6);
+/// assert_eq!(AtomicShared::strong_count(&shared), 1);
+/// ```
+pub struct AtomicShared<T, A = Allocator>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    ptr: NonNull<Inner<T>>,
+    /// Moved into an `AtomicWeak` by the last strong handle, dropped by the
+    /// others.
+    alloc: ManuallyDrop<A>,
+    _marker: PhantomData<Inner<T>>,
+}
+
+impl<T> AtomicShared<T> {
+    panicking! {
+        /// Moves `value` into a new shared allocation. In case of allocation
+        /// error, the function panics.
+        #[inline]
+        pub fn new(value: T) -> Self {
+            Self::new_in(value, Allocator::new())
+        }
+    }
+
+    /// Moves `value` into a new shared allocation. In case of allocation
+    /// error, `Err` is returned.
+    #[inline]
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        Self::try_new_in(value, Allocator::new())
+    }
+}
+
+impl<T> AtomicShared<[T]>
+where
+    T: Clone,
+{
+    panicking! {
+        /// Clones the elements of `slice` into a new shared allocation. In case
+        /// of allocation error, the function panics.
+        #[inline]
+        pub fn from_slice(slice: &[T]) -> Self {
+            Self::from_slice_in(slice, Allocator::new())
+        }
+    }
+}
+
+impl<T, A> AtomicShared<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    panicking! {
+        /// Moves `value` into a new shared allocation made by `alloc`. In case of
+        /// allocation error, the function panics.
+        #[inline]
+        pub fn new_in(value: T, alloc: A) -> Self {
+            match Self::try_new_in(value, alloc) {
+                Ok(this) => this,
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+
+    /// Moves `value` into a new shared allocation made by `alloc`. In case of
+    /// allocation error, `Err` is returned.
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, AllocError> {
+        let layout = Layout::new::<Inner<T>>();
+        let ptr = alloc
+            .allocate(layout)
+            .map_err(|_| AllocError { layout })?
+            .cast::<Inner<T>>();
+        unsafe {
+            ptr.as_ptr().write(Inner {
+                strong: AtomicUsize::new(1),
+                weak: AtomicUsize::new(1),
+                value,
+            })
+        }
+        Ok(Self {
+            ptr,
+            alloc: ManuallyDrop::new(alloc),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Moves the value out if `this` is its only strong handle, or gives
+    /// `this` back otherwise.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        let inner = this.inner();
+        if inner
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            let value = ptr::read(&this.inner().value);
+            drop(AtomicWeak {
+                ptr: this.ptr,
+                alloc: ptr::read(&*this.alloc),
+                _marker: PhantomData,
+            });
+            Ok(value)
+        }
+    }
+}
+
+impl<T, A> AtomicShared<[T], A>
+where
+    T: Clone,
+    A: crate::alloc_api::Allocator,
+{
+    panicking! {
+        /// Clones the elements of `slice` into a new shared allocation made by
+        /// `alloc`. In case of allocation error or overflow, the function panics.
+        pub fn from_slice_in(slice: &[T], alloc: A) -> Self {
+            let layout = match Layout::new::<Inner<()>>().extend(Layout::for_value(slice)) {
+                Ok((layout, _)) => layout.pad_to_align(),
+                Err(err) => panic!("Capacity overflows memory size: {}", err),
+            };
+            let raw = match alloc.allocate(layout) {
+                Ok(raw) => raw.cast::<T>(),
+                Err(_) => panic!("{}", AllocError { layout }),
+            };
+            let ptr = ptr::slice_from_raw_parts_mut(raw.as_ptr(), slice.len()) as *mut Inner<[T]>;
+            unsafe {
+                ptr::addr_of_mut!((*ptr).strong).write(AtomicUsize::new(1));
+                ptr::addr_of_mut!((*ptr).weak).write(AtomicUsize::new(1));
+                let mut guard = CloneGuard {
+                    raw: raw.cast(),
+                    layout,
+                    alloc: &alloc,
+                    elems: ptr::addr_of_mut!((*ptr).value).cast::<T>(),
+                    written: 0,
+                };
+                for elem in slice {
+                    guard.elems.add(guard.written).write(elem.clone());
+                    guard.written += 1;
+                }
+                mem::forget(guard);
+                Self {
+                    ptr: NonNull::new_unchecked(ptr),
+                    alloc: ManuallyDrop::new(alloc),
+                    _marker: PhantomData,
+                }
+            }
+        }
+    }
+}
+
+impl<T, A> AtomicShared<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    /// Number of strong handles to the value.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Acquire)
+    }
+
+    /// Number of weak handles to the value.
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        let weak = this.inner().weak.load(Ordering::Acquire);
+        let strong = this.inner().strong.load(Ordering::Acquire);
+        if weak == LOCKED {
+            // Only locked by a `get_mut` that found no weak handle.
+            0
+        } else if strong == 0 {
+            weak
+        } else {
+            weak - 1
+        }
+    }
+
+    /// Creates a weak handle to the value.
+    pub fn downgrade(this: &Self) -> AtomicWeak<T, A>
+    where
+        A: Clone,
+    {
+        this.inner().acquire_weak();
+        AtomicWeak {
+            ptr: this.ptr,
+            alloc: (*this.alloc).clone(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// A mutable reference to the value if `this` is its only handle, strong
+    /// or weak.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        let inner = this.inner();
+        if inner
+            .weak
+            .compare_exchange(1, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        let unique = inner.strong.load(Ordering::Acquire) == 1;
+        inner.weak.store(1, Ordering::Release);
+        if unique {
+            unsafe { Some(&mut (*this.ptr.as_ptr()).value) }
+        } else {
+            None
+        }
+    }
+
+    /// Tests if both handles point to the same allocation.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        ptr::eq(this.ptr.as_ptr() as *const u8, other.ptr.as_ptr() as *const u8)
+    }
+
+    /// A raw pointer to the value.
+    #[inline]
+    pub fn as_ptr(this: &Self) -> *const T {
+        unsafe { ptr::addr_of!((*this.ptr.as_ptr()).value) }
+    }
+
+    /// The allocator of the value.
+    #[inline]
+    pub fn allocator(this: &Self) -> &A {
+        &this.alloc
+    }
+
+    #[inline]
+    fn inner(&self) -> &Inner<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, A> Clone for AtomicShared<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator + Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        let old = self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_COUNT {
+            panic!("Too many strong handles");
+        }
+        Self {
+            ptr: self.ptr,
+            alloc: self.alloc.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The elements of a shared slice cloned so far, dropped and freed along
+/// with the allocation if cloning panics.
+struct CloneGuard<'a, T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    raw: NonNull<u8>,
+    layout: Layout,
+    alloc: &'a A,
+    elems: *mut T,
+    written: usize,
+}
+
+impl<'a, T, A> Drop for CloneGuard<'a, T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.elems, self.written));
+            self.alloc.deallocate(self.raw, self.layout);
+        }
+    }
+}
+
+impl<T, A> Deref for AtomicShared<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T, A> Drop for AtomicShared<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    fn drop(&mut self) {
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            unsafe { ManuallyDrop::drop(&mut self.alloc) }
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        unsafe {
+            ptr::drop_in_place(ptr::addr_of_mut!((*self.ptr.as_ptr()).value));
+            drop(AtomicWeak {
+                ptr: self.ptr,
+                alloc: ManuallyDrop::take(&mut self.alloc),
+                _marker: PhantomData,
+            });
+        }
+    }
+}
+
+/// An allocator shared between threads: every container handed a clone
+/// allocates from the same one, which is dropped with the last clone. With a
+/// plain reference, `&A` is an allocator already.
+unsafe impl<T, A> crate::alloc_api::Allocator for AtomicShared<T, A>
+where
+    T: ?Sized + crate::alloc_api::Allocator,
+    A: crate::alloc_api::Allocator,
+{
+    forward_allocator!(|this| (**this));
+}
+
+unsafe impl<T, A> Send for AtomicShared<T, A>
+where
+    T: ?Sized + Send + Sync,
+    A: crate::alloc_api::Allocator + Send,
+{
+}
+
+unsafe impl<T, A> Sync for AtomicShared<T, A>
+where
+    T: ?Sized + Send + Sync,
+    A: crate::alloc_api::Allocator + Sync,
+{
+}
+
+impl<T, A> fmt::Debug for AtomicShared<T, A>
+where
+    T: ?Sized + fmt::Debug,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A weak handle to the value of an `AtomicShared`, which does not keep the
+/// value alive, only its memory.
+pub struct AtomicWeak<T, A = Allocator>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    ptr: NonNull<Inner<T>>,
+    alloc: A,
+    _marker: PhantomData<Inner<T>>,
+}
+
+impl<T, A> AtomicWeak<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    /// A strong handle to the value, if it is still alive.
+    pub fn upgrade(&self) -> Option<AtomicShared<T, A>>
+    where
+        A: Clone,
+    {
+        let strong = &self.inner().strong;
+        let mut count = strong.load(Ordering::Relaxed);
+        loop {
+            if count == 0 {
+                return None;
+            }
+            if count > MAX_COUNT {
+                panic!("Too many strong handles");
+            }
+            match strong.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(new) => count = new,
+            }
+        }
+        Some(AtomicShared {
+            ptr: self.ptr,
+            alloc: ManuallyDrop::new(self.alloc.clone()),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Number of strong handles to the value.
+    #[inline]
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    fn inner(&self) -> &Inner<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T, A> Clone for AtomicWeak<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator + Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        self.inner().acquire_weak();
+        Self {
+            ptr: self.ptr,
+            alloc: self.alloc.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A> Drop for AtomicWeak<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    fn drop(&mut self) {
+        if self.inner().weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        unsafe {
+            let layout = Layout::for_value(self.ptr.as_ref());
+            self.alloc.deallocate(self.ptr.cast(), layout);
+        }
+    }
+}
+
+unsafe impl<T, A> Send for AtomicWeak<T, A>
+where
+    T: ?Sized + Send + Sync,
+    A: crate::alloc_api::Allocator + Send,
+{
+}
+
+unsafe impl<T, A> Sync for AtomicWeak<T, A>
+where
+    T: ?Sized + Send + Sync,
+    A: crate::alloc_api::Allocator + Sync,
+{
+}
+
+impl<T, A> fmt::Debug for AtomicWeak<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AtomicWeak {{ strong: {} }}", self.strong_count())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AtomicShared;
+    use alloc::{rc::Rc, vec::Vec};
+
+    #[test]
+    fn weak_outlives_value() {
+        let tracker = Rc::new(());
+        let shared = AtomicShared::from_slice(&[tracker.clone(), tracker.clone()]);
+        assert_eq!(shared.len(), 2);
+        let weak = AtomicShared::downgrade(&shared);
+        let clones: Vec<_> = (0 .. 3).map(|_| shared.clone()).collect();
+        assert_eq!(AtomicShared::strong_count(&shared), 4);
+        assert_eq!(AtomicShared::weak_count(&shared), 1);
+
+        drop(clones);
+        drop(shared);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn unique_access() {
+        let mut shared = AtomicShared::new(5);
+        *AtomicShared::get_mut(&mut shared).unwrap() += 1;
+        let weak = AtomicShared::downgrade(&shared);
+        assert!(AtomicShared::get_mut(&mut shared).is_none());
+        drop(weak);
+
+        let other = shared.clone();
+        let shared = AtomicShared::try_unwrap(shared).unwrap_err();
+        drop(other);
+        assert_eq!(AtomicShared::try_unwrap(shared).ok(), Some(6));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn downgrade_while_locked() {
+        let mut shared = AtomicShared::new(0u64);
+        let other = shared.clone();
+        let weaks = std::thread::scope(|scope| {
+            let downgrading = scope.spawn(|| {
+                (0 .. 1000).map(|_| AtomicShared::downgrade(&other)).collect::<Vec<_>>()
+            });
+            for _ in 0 .. 1000 {
+                assert!(AtomicShared::get_mut(&mut shared).is_none());
+            }
+            downgrading.join().unwrap()
+        });
+        assert_eq!(AtomicShared::weak_count(&shared), weaks.len());
+    }
+
+    #[test]
+    fn shared_allocator() {
+        use crate::{alloc_api::Vec as AllocVec, Allocator, FreeListAlloc};
+
+        let alloc = AtomicShared::new(FreeListAlloc::new(Allocator::new()));
+        let mut owning = AllocVec::new_in(alloc.clone());
+        owning.extend_from_slice(&[1u32, 2, 3]);
+        let mut borrowing = AllocVec::new_in(&alloc);
+        borrowing.push(4u64);
+        assert_eq!(AtomicShared::strong_count(&alloc), 2);
+        drop(owning);
+        assert_eq!(AtomicShared::strong_count(&alloc), 1);
+        assert_eq!(borrowing, [4]);
+    }
+
+    #[test]
+    fn allocator_dropped_once_per_handle() {
+        use crate::Allocator;
+        use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+        #[derive(Clone)]
+        struct Counted<'a>(&'a Cell<usize>);
+
+        unsafe impl crate::alloc_api::Allocator for Counted<'_> {
+            fn allocate(
+                &self,
+                layout: Layout,
+            ) -> Result<NonNull<[u8]>, crate::alloc_api::AllocError> {
+                Allocator::new().allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                unsafe { Allocator::new().deallocate(ptr, layout) }
+            }
+        }
+
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        drop(AtomicShared::new_in(1, Counted(&drops)));
+        assert_eq!(drops.get(), 1);
+
+        let shared = AtomicShared::new_in(2, Counted(&drops));
+        let weak = AtomicShared::downgrade(&shared);
+        let other = weak.upgrade().unwrap();
+        drop((shared, other, weak));
+        assert_eq!(drops.get(), 4);
+
+        let unique = AtomicShared::new_in(3, Counted(&drops));
+        assert_eq!(AtomicShared::try_unwrap(unique).ok(), Some(3));
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn panicking_clone_frees_the_slice() {
+        use crate::{Allocator, LimitedAlloc};
+
+        struct Bomb(Rc<()>);
+
+        impl Clone for Bomb {
+            fn clone(&self) -> Self {
+                assert!(Rc::strong_count(&self.0) < 4, "clone bomb");
+                Bomb(self.0.clone())
+            }
+        }
+
+        let tracker = Rc::new(());
+        let bombs = [Bomb(tracker.clone()), Bomb(tracker.clone()), Bomb(tracker.clone())];
+        let limited = LimitedAlloc::new(Allocator::new(), 1024);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            AtomicShared::from_slice_in(&bombs, &limited)
+        }));
+        assert!(res.is_err());
+        assert_eq!(limited.used(), 0);
+        assert_eq!(Rc::strong_count(&tracker), 4);
+    }
+}
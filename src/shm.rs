@@ -0,0 +1,179 @@
+use crate::{sys, FileHandle, OsError};
+use alloc::string::String;
+use core::{fmt, ptr::NonNull, slice, sync::atomic::AtomicU8};
+
+/// An owned region of memory shared between processes: a POSIX shared
+/// memory object or a `memfd` on Unix, a section object on Windows.
+///
+/// Regions are exchanged either by name (`create` on one side, `open` on
+/// the other) or by handle (`anonymous` and `handle` on one side, after
+/// passing the handle to the other process, `from_handle`). The region is
+/// unmapped on drop, and a named region is also unlinked when its creator
+/// drops it.
+///
+/// The contents are plain bytes written by other processes at any time, so
+/// they are only handed out as atomics (`as_atomic`), or as a raw pointer
+/// for accesses under a protocol agreed upon by all processes.
+pub struct ShmAlloc {
+    ptr: NonNull<u8>,
+    len: usize,
+    handle: FileHandle,
+    name: Option<String>,
+    owner: bool,
+}
+
+impl ShmAlloc {
+    /// Creates a new named region of `len` bytes. Fails if a region with the
+    /// same name already exists. On Unix, names should start with a `/` and
+    /// contain no other slashes.
+    #[inline]
+    pub fn create(name: &str, len: usize) -> Result<Self, OsError> {
+        let (handle, ptr) = sys::shm_create(name, len)?;
+        Ok(unsafe { Self::from_parts(ptr, len, handle, Some(name.into()), true) })
+    }
+
+    /// Opens a named region previously created by `create`, mapping its
+    /// first `len` bytes. Fails if the region is shorter than `len`.
+    #[inline]
+    pub fn open(name: &str, len: usize) -> Result<Self, OsError> {
+        let (handle, ptr) = sys::shm_open(name, len)?;
+        Ok(unsafe { Self::from_parts(ptr, len, handle, Some(name.into()), false) })
+    }
+
+    /// Creates a new region without a name, which is shared by passing its
+    /// `handle` to another process (e.g. through fork/exec inheritance or
+    /// `SCM_RIGHTS` on Unix, `DuplicateHandle` on Windows). On Unix, this is
+    /// only supported on Linux and Android.
+    #[inline]
+    pub fn anonymous(len: usize) -> Result<Self, OsError> {
+        let (handle, ptr) = sys::shm_anonymous(len)?;
+        Ok(unsafe { Self::from_parts(ptr, len, handle, None, false) })
+    }
+
+    /// Maps `len` bytes of a region received from another process. The handle
+    /// is owned by the region from now on, and closed on drop.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because the handle must refer to a shared
+    /// memory region at least `len` bytes long, not owned by anything else.
+    #[inline]
+    pub unsafe fn from_handle(handle: FileHandle, len: usize) -> Result<Self, OsError> {
+        let ptr = sys::shm_from_handle(handle, len)?;
+        Ok(Self::from_parts(ptr, len, handle, None, false))
+    }
+
+    /// The name of the region, if it was created or opened by name.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The handle to exchange with other processes.
+    #[inline]
+    pub fn handle(&self) -> FileHandle {
+        self.handle
+    }
+
+    /// Length of the region in bytes.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if the region is empty.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The raw non-null pointer to the start of the region.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    /// The contents of the region, as atomic bytes which other processes may
+    /// read and write concurrently.
+    #[inline]
+    pub fn as_atomic(&self) -> &[AtomicU8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr().cast::<AtomicU8>(), self.len) }
+    }
+
+    #[inline]
+    unsafe fn from_parts(
+        ptr: *mut u8,
+        len: usize,
+        handle: FileHandle,
+        name: Option<String>,
+        owner: bool,
+    ) -> Self {
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            len,
+            handle,
+            name,
+            owner,
+        }
+    }
+}
+
+impl Drop for ShmAlloc {
+    fn drop(&mut self) {
+        unsafe {
+            sys::unmap(self.ptr.as_ptr(), self.len);
+            sys::shm_close(self.handle);
+        }
+        if let (true, Some(name)) = (self.owner, &self.name) {
+            sys::shm_unlink(name);
+        }
+    }
+}
+
+impl fmt::Debug for ShmAlloc {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ShmAlloc {{ pointer: {:?}, len: {}, name: {:?} }}",
+            self.ptr, self.len, self.name
+        )
+    }
+}
+
+unsafe impl Send for ShmAlloc {}
+unsafe impl Sync for ShmAlloc {}
+
+#[cfg(test)]
+mod test {
+    use super::ShmAlloc;
+    use core::sync::atomic::Ordering::Relaxed;
+
+    #[test]
+    fn named_regions_share_memory() {
+        let first = ShmAlloc::create("/owned-alloc-shm-test", 64).unwrap();
+        assert!(ShmAlloc::create("/owned-alloc-shm-test", 64).is_err());
+        for (byte, &value) in first.as_atomic().iter().zip(b"abc") {
+            byte.store(value, Relaxed);
+        }
+
+        assert!(ShmAlloc::open("/owned-alloc-shm-test", 1 << 20).is_err());
+        let second = ShmAlloc::open("/owned-alloc-shm-test", 64).unwrap();
+        let read = second.as_atomic()[.. 3].iter().map(|byte| byte.load(Relaxed));
+        assert!(read.eq(b"abc".iter().copied()));
+        assert_ne!(first.raw(), second.raw());
+
+        drop(second);
+        drop(first);
+        assert!(ShmAlloc::open("/owned-alloc-shm-test", 64).is_err());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn anonymous_through_handle() {
+        let region = ShmAlloc::anonymous(16).unwrap();
+        region.as_atomic()[0].store(42, Relaxed);
+        let dup = unsafe { libc::dup(region.handle()) };
+        let other = unsafe { ShmAlloc::from_handle(dup, 16).unwrap() };
+        assert_eq!(other.as_atomic()[0].load(Relaxed), 42);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::alloc_api::AllocError;
+use core::{
+    alloc::Layout,
+    fmt,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering::*},
+};
+
+/// Number of buckets of a `SizeHistogram`: bucket `i` counts the requests of
+/// more than `2^(i - 1)` bytes, and up to `2^i`, bucket 0 counting empty
+/// requests too.
+pub const SIZE_BUCKETS: usize = usize::BITS as usize;
+
+/// Requests seen by a `SizeProfiler`, bucketed by size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeHistogram {
+    /// Number of requests, per bucket.
+    pub counts: [usize; SIZE_BUCKETS],
+    /// Bytes requested, per bucket.
+    pub bytes: [usize; SIZE_BUCKETS],
+}
+
+impl SizeHistogram {
+    /// A histogram with no request.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            counts: [0; SIZE_BUCKETS],
+            bytes: [0; SIZE_BUCKETS],
+        }
+    }
+
+    /// The bucket of a request of `size` bytes.
+    #[inline]
+    pub const fn bucket(size: usize) -> usize {
+        match size.checked_next_power_of_two() {
+            Some(limit) => limit.trailing_zeros() as usize,
+            None => SIZE_BUCKETS - 1,
+        }
+    }
+
+    /// Biggest size counted by a bucket.
+    #[inline]
+    pub const fn limit(bucket: usize) -> usize {
+        1 << bucket
+    }
+
+    /// Number of requests.
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The biggest size of each bucket which saw a request, with its number
+    /// of requests, smallest first.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count != 0)
+            .map(|(bucket, &count)| (Self::limit(bucket), count))
+    }
+
+    /// The smallest bucket limit at or below which the fraction `ratio`
+    /// (between 0 and 1) of the requests fall, e.g. `0.99` for the size a
+    /// size class must reach to serve 99% of the requests. Zero if there
+    /// was no request.
+    pub fn percentile(&self, ratio: f64) -> usize {
+        let exact = self.total() as f64 * ratio.clamp(0.0, 1.0);
+        let wanted = exact as usize + ((exact as usize as f64) < exact) as usize;
+        let mut seen = 0;
+        for (limit, count) in self.iter() {
+            seen += count;
+            if seen >= wanted {
+                return limit;
+            }
+        }
+        0
+    }
+
+    /// Bytes that would be lost if every request were rounded up to the
+    /// limit of its bucket, as with power-of-two size classes.
+    #[inline]
+    pub fn rounding_waste(&self) -> usize {
+        self.counts
+            .iter()
+            .zip(&self.bytes)
+            .enumerate()
+            .map(|(bucket, (&count, &bytes))| (count * Self::limit(bucket)).saturating_sub(bytes))
+            .sum()
+    }
+}
+
+impl Default for SizeHistogram {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A wrapper recording a histogram of the sizes requested from it, since its
+/// creation or the last `reset`, to pick the size classes of pools and free
+/// lists from the actual workload. Growing and shrinking count as requests
+/// of the new size.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{alloc_api::Vec, Allocator, SizeProfiler};
+///
+/// let alloc = SizeProfiler::new(Allocator::new());
+/// let mut words = Vec::new_in(&alloc);
+/// for size in [3, 20, 24, 30, 500] {
+///     words.push(Vec::<u8, _>::with_capacity_in(size, &alloc));
+/// }
+///
+/// let histogram = alloc.reset();
+/// assert!(histogram.iter().any(|(limit, count)| (limit, count) == (32, 3)));
+/// assert_eq!(histogram.percentile(0.5), 32);
+/// assert_eq!(alloc.histogram().total(), 0);
+/// # drop(words);
+/// ```
+pub struct SizeProfiler<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    backend: A,
+    counts: [AtomicUsize; SIZE_BUCKETS],
+    bytes: [AtomicUsize; SIZE_BUCKETS],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+impl<A> SizeProfiler<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    /// Records the requests made to `backend`.
+    #[inline]
+    pub const fn new(backend: A) -> Self {
+        Self {
+            backend,
+            counts: [ZERO; SIZE_BUCKETS],
+            bytes: [ZERO; SIZE_BUCKETS],
+        }
+    }
+
+    /// The allocator being profiled.
+    #[inline]
+    pub const fn backend(&self) -> &A {
+        &self.backend
+    }
+
+    /// The requests recorded so far.
+    #[inline]
+    pub fn histogram(&self) -> SizeHistogram {
+        SizeHistogram {
+            counts: self.counts.each_ref().map(|count| count.load(Relaxed)),
+            bytes: self.bytes.each_ref().map(|bytes| bytes.load(Relaxed)),
+        }
+    }
+
+    /// Returns the requests recorded so far, and starts over from an empty
+    /// histogram.
+    #[inline]
+    pub fn reset(&self) -> SizeHistogram {
+        SizeHistogram {
+            counts: self.counts.each_ref().map(|count| count.swap(0, Relaxed)),
+            bytes: self.bytes.each_ref().map(|bytes| bytes.swap(0, Relaxed)),
+        }
+    }
+}
+
+unsafe impl<A> crate::alloc_api::Allocator for SizeProfiler<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let bucket = SizeHistogram::bucket(layout.size());
+        self.counts[bucket].fetch_add(1, Relaxed);
+        self.bytes[bucket].fetch_add(layout.size(), Relaxed);
+        self.backend.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.backend.deallocate(ptr, layout)
+    }
+}
+
+impl<A> fmt::Debug for SizeProfiler<A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SizeProfiler {{ requests: {} }}", self.histogram().total())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SizeHistogram, SizeProfiler};
+    use crate::{alloc_api::Allocator as _, Allocator};
+    use core::alloc::Layout;
+
+    #[test]
+    fn buckets_round_up_to_powers_of_two() {
+        assert_eq!(SizeHistogram::bucket(0), 0);
+        assert_eq!(SizeHistogram::bucket(1), 0);
+        assert_eq!(SizeHistogram::bucket(17), 5);
+        assert_eq!(SizeHistogram::bucket(usize::MAX), super::SIZE_BUCKETS - 1);
+
+        let alloc = SizeProfiler::new(Allocator::new());
+        for size in [17, 24, 32, 100] {
+            let layout = Layout::from_size_align(size, 1).unwrap();
+            let block = alloc.allocate(layout).unwrap();
+            unsafe { alloc.deallocate(block.cast(), layout) };
+        }
+        let histogram = alloc.histogram();
+        assert_eq!((histogram.counts[5], histogram.bytes[5]), (3, 73));
+        assert_eq!(histogram.rounding_waste(), 96 - 73 + 128 - 100);
+        assert_eq!(histogram.percentile(0.75), 32);
+        assert_eq!(histogram.percentile(1.0), 128);
+        assert_eq!(alloc.reset(), histogram);
+        assert_eq!(alloc.histogram(), SizeHistogram::new());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::RawVec;
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A fixed-capacity arena of values of type `T`, whose occupied slots are
+/// tracked by a bitmap. Values are reached through the index of their slot.
+///
+/// Allocation starts its search at a cached hint (the word of the last
+/// freed slot, or of the last allocation), so it is O(1) in the common
+/// case. Any slot can be freed at any time, and iteration visits occupied
+/// slots only, skipping empty words of the bitmap.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::Slab;
+///
+/// let mut sessions = Slab::with_capacity(2);
+/// let alice = sessions.alloc("alice").unwrap();
+/// let bob = sessions.alloc("bob").unwrap();
+/// assert_eq!(sessions.alloc("carol"), Err("carol"));
+///
+/// assert_eq!(sessions.free(alice), Some("alice"));
+/// let carol = sessions.alloc("carol").unwrap();
+/// assert_eq!(carol, alice);
+/// assert_eq!(sessions.get(bob), Some(&"bob"));
+/// assert_eq!(sessions.iter().map(|(_, name)| *name).collect::<Vec<_>>(), ["carol", "bob"]);
+/// ```
+pub struct Slab<T> {
+    storage: RawVec<T>,
+    bitmap: Vec<u64>,
+    hint: usize,
+    len: usize,
+}
+
+impl<T> Slab<T> {
+    panicking! {
+        /// Creates a slab of `cap` slots. In case of allocation error or
+        /// overflow, the function panics.
+        pub fn with_capacity(cap: usize) -> Self {
+            let mut bitmap = vec![0; cap.div_ceil(WORD_BITS)];
+            // Bits past the capacity are set, so they are never allocated.
+            let used_bits = cap % WORD_BITS;
+            if used_bits > 0 {
+                bitmap[cap / WORD_BITS] = u64::MAX << used_bits;
+            }
+            Self {
+                storage: RawVec::with_capacity(cap),
+                bitmap,
+                hint: 0,
+                len: 0,
+            }
+        }
+    }
+
+    /// Moves `value` into a free slot and returns the index of the slot, or
+    /// gives `value` back if the slab is full.
+    pub fn alloc(&mut self, value: T) -> Result<usize, T> {
+        if self.len == self.capacity() {
+            return Err(value);
+        }
+        let words = self.bitmap.len();
+        let mut word = self.hint;
+        while self.bitmap[word] == u64::MAX {
+            word = (word + 1) % words;
+        }
+        let bit = (!self.bitmap[word]).trailing_zeros() as usize;
+        let index = word * WORD_BITS + bit;
+        self.bitmap[word] |= 1 << bit;
+        self.hint = word;
+        self.len += 1;
+        unsafe { self.storage.raw().as_ptr().add(index).write(value) }
+        Ok(index)
+    }
+
+    /// Moves the value of slot `index` out of the slab, if the slot is
+    /// occupied.
+    pub fn free(&mut self, index: usize) -> Option<T> {
+        if !self.is_occupied(index) {
+            return None;
+        }
+        let word = index / WORD_BITS;
+        self.bitmap[word] &= !(1 << (index % WORD_BITS));
+        self.hint = word;
+        self.len -= 1;
+        unsafe { Some(self.storage.raw().as_ptr().add(index).read()) }
+    }
+
+    /// Tests if slot `index` holds a value.
+    #[inline]
+    pub fn is_occupied(&self, index: usize) -> bool {
+        let mask = 1 << (index % WORD_BITS);
+        index < self.capacity() && self.bitmap[index / WORD_BITS] & mask != 0
+    }
+
+    /// The value of slot `index`, if the slot is occupied.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if self.is_occupied(index) {
+            unsafe { Some(&*self.storage.raw().as_ptr().add(index)) }
+        } else {
+            None
+        }
+    }
+
+    /// The value of slot `index`, if the slot is occupied.
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if self.is_occupied(index) {
+            unsafe { Some(&mut *self.storage.raw().as_ptr().add(index)) }
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the occupied slots and their values, by index. Freed
+    /// slots are reused, so this is allocation order only until the first
+    /// `free`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        let base = self.storage.raw().as_ptr();
+        self.occupied().map(move |index| unsafe { (index, &*base.add(index)) })
+    }
+
+    /// Iterates mutably over the occupied slots and their values, by index.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        let base = self.storage.raw().as_ptr();
+        self.occupied().map(move |index| unsafe { (index, &mut *base.add(index)) })
+    }
+
+    /// Number of occupied slots.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if no slot is occupied.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Tests if every slot is occupied.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    /// Number of slots.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.storage.cap()
+    }
+
+    /// Indices of occupied slots, in order.
+    fn occupied(&self) -> impl Iterator<Item = usize> + '_ {
+        let cap = self.capacity();
+        let words = self.bitmap.iter().enumerate();
+        let indices = words.flat_map(|(word, &bits)| {
+            let mut bits = bits;
+            core::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(word * WORD_BITS + bit)
+            })
+        });
+        indices.take_while(move |&index| index < cap)
+    }
+}
+
+impl<T> Drop for Slab<T> {
+    fn drop(&mut self) {
+        let base = self.storage.raw().as_ptr();
+        for index in self.occupied() {
+            unsafe { base.add(index).drop_in_place() }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Slab<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Slab {{ len: {}, capacity: {} }}", self.len, self.capacity())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Slab;
+    use alloc::{rc::Rc, vec::Vec};
+
+    #[test]
+    fn sparse_frees_across_words() {
+        let tracker = Rc::new(());
+        let mut slab = Slab::with_capacity(130);
+        for _ in 0 .. 130 {
+            slab.alloc(tracker.clone()).unwrap();
+        }
+        assert!(slab.alloc(tracker.clone()).is_err());
+        for index in (0 .. 130).filter(|index| index % 3 != 0) {
+            slab.free(index).unwrap();
+        }
+        assert_eq!(slab.free(1), None);
+
+        let occupied: Vec<_> = slab.iter().map(|(index, _)| index).collect();
+        assert_eq!(occupied, (0 .. 130).step_by(3).collect::<Vec<_>>());
+        for (_, value) in slab.iter_mut() {
+            *value = tracker.clone();
+        }
+        assert_eq!(slab.alloc(tracker.clone()), Ok(128));
+
+        drop(slab);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
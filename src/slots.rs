@@ -0,0 +1,108 @@
+use crate::OwnedAlloc;
+use core::{
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+/// A per-thread slot in a `SlotList`. Slots are never unlinked while the list
+/// is alive; releasing one only marks it as available for reuse.
+pub(crate) struct Slot<T> {
+    pub(crate) value: T,
+    active: AtomicBool,
+    next: *mut Slot<T>,
+}
+
+impl<T> Slot<T> {
+    /// Tests if the slot is currently acquired.
+    #[inline]
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Makes the slot available to the next `SlotList::acquire`.
+    #[inline]
+    pub(crate) fn release(&self) {
+        self.active.store(false, Ordering::Release);
+    }
+}
+
+/// A lock-free, append-only list of slots, used to register the readers of
+/// the reclamation schemes (hazard pointers, epochs).
+pub(crate) struct SlotList<T> {
+    head: AtomicPtr<Slot<T>>,
+}
+
+impl<T> SlotList<T> {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Acquires an inactive slot, or appends a new one initialized by
+    /// `create` if all slots are taken.
+    pub(crate) fn acquire<F>(&self, create: F) -> &Slot<T>
+    where
+        F: FnOnce() -> T,
+    {
+        for slot in self.iter() {
+            let acquired = slot
+                .active
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok();
+            if acquired {
+                return slot;
+            }
+        }
+
+        let slot = OwnedAlloc::new(Slot {
+            value: create(),
+            active: AtomicBool::new(true),
+            next: ptr::null_mut(),
+        })
+        .into_raw()
+        .as_ptr();
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*slot).next = head }
+            match self.head.compare_exchange_weak(
+                head,
+                slot,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(new) => head = new,
+            }
+        }
+        unsafe { &*slot }
+    }
+
+    /// Iterates over every slot, active or not.
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Slot<T>> {
+        let mut curr = self.head.load(Ordering::Acquire);
+        core::iter::from_fn(move || {
+            let slot = unsafe { curr.as_ref()? };
+            curr = slot.next;
+            Some(slot)
+        })
+    }
+
+    /// The head pointer, for debugging output.
+    #[inline]
+    pub(crate) fn head(&self) -> *mut Slot<T> {
+        self.head.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for SlotList<T> {
+    fn drop(&mut self) {
+        let mut curr = *self.head.get_mut();
+        while let Some(slot) = NonNull::new(curr) {
+            let slot = unsafe { OwnedAlloc::from_raw(slot) };
+            curr = slot.next;
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{tag, SizeClassUsage, Tag};
+use core::{fmt, iter};
+
+/// Magic bytes starting a binary heap snapshot.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"OAHS";
+
+/// Version of the binary heap snapshot format, following the magic bytes.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const END: u8 = 0;
+const TAG: u8 = 1;
+const CLASS: u8 = 2;
+#[cfg(feature = "track-callers")]
+const LIVE: u8 = 3;
+
+/// A dump of the current state of the heap, for diagnostics of devices
+/// without a debugger: the live memory of every tag, the usage of size
+/// classes given with `with_classes`, and, with the `track-callers` feature,
+/// every live allocation with its size, tag and call site.
+///
+/// The snapshot is written without allocating (except for the list of live
+/// allocations), either as text to a `fmt::Write`, one record per line:
+///
+/// ```text
+/// tag <name or #id> <live bytes> <live count>
+/// class <block size> <cached blocks>
+/// live <address> <size> <tag> <file>:<line>
+/// ```
+///
+/// or in a compact binary form to a byte sink, such as a socket or flash.
+/// The binary form is `SNAPSHOT_MAGIC`, the `SNAPSHOT_VERSION` byte, then
+/// records made of a kind byte followed by unsigned LEB128 integers, ending
+/// with a `0` byte:
+///
+/// - `1` tag: tag, live bytes, live count;
+/// - `2` class: block size, cached blocks;
+/// - `3` live: address, size, tag, line, file.
+///
+/// A tag is `0` then a string for names, or `1` then the number for ids. A
+/// string is its length then its UTF-8 bytes.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, Allocator, FreeListAlloc, HeapSnapshot, SNAPSHOT_MAGIC};
+///
+/// let alloc = FreeListAlloc::new(Allocator::new());
+/// let layout = Layout::new::<[u8; 32]>();
+/// let block = alloc.allocate(layout).unwrap();
+/// unsafe { alloc.deallocate(block.cast(), layout) };
+///
+/// let mut text = String::new();
+/// HeapSnapshot::new().with_classes(alloc.class_usage()).write_text(&mut text).unwrap();
+/// assert!(text.lines().any(|line| line == "class 32 1"));
+///
+/// let mut bytes = Vec::new();
+/// HeapSnapshot::new()
+///     .with_classes(alloc.class_usage())
+///     .write_binary(|chunk| {
+///         bytes.extend_from_slice(chunk);
+///         Ok::<_, ()>(())
+///     })
+///     .unwrap();
+/// assert_eq!(bytes[.. 4], SNAPSHOT_MAGIC);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeapSnapshot<I> {
+    classes: I,
+}
+
+impl HeapSnapshot<iter::Empty<SizeClassUsage>> {
+    /// Creates a snapshot of the tags (and live allocations), with no size
+    /// classes.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            classes: iter::empty(),
+        }
+    }
+}
+
+impl Default for HeapSnapshot<iter::Empty<SizeClassUsage>> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> HeapSnapshot<I>
+where
+    I: Iterator<Item = SizeClassUsage>,
+{
+    /// Includes the usage of the given size classes, e.g. from
+    /// `FreeListAlloc::class_usage`.
+    #[inline]
+    pub fn with_classes<J>(self, classes: J) -> HeapSnapshot<J::IntoIter>
+    where
+        J: IntoIterator<Item = SizeClassUsage>,
+    {
+        HeapSnapshot {
+            classes: classes.into_iter(),
+        }
+    }
+
+    /// Writes the snapshot as text, one record per line.
+    pub fn write_text<W>(self, out: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        for report in tag::report() {
+            writeln!(out, "tag {} {} {}", report.tag, report.live_bytes, report.live_count)?;
+        }
+        for class in self.classes {
+            writeln!(out, "class {} {}", class.size, class.cached)?;
+        }
+        #[cfg(feature = "track-callers")]
+        for live in crate::leak::live_allocations() {
+            writeln!(
+                out,
+                "live {:#x} {} {} {}",
+                live.address, live.size, live.tag, live.location
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the snapshot in binary form, passing chunks of bytes to `sink`
+    /// and stopping at its first error.
+    pub fn write_binary<F, E>(self, mut sink: F) -> Result<(), E>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        sink(&SNAPSHOT_MAGIC)?;
+        sink(&[SNAPSHOT_VERSION])?;
+        for report in tag::report() {
+            sink(&[TAG])?;
+            write_tag(&mut sink, report.tag)?;
+            write_uint(&mut sink, report.live_bytes as u64)?;
+            write_uint(&mut sink, report.live_count as u64)?;
+        }
+        for class in self.classes {
+            sink(&[CLASS])?;
+            write_uint(&mut sink, class.size as u64)?;
+            write_uint(&mut sink, class.cached as u64)?;
+        }
+        #[cfg(feature = "track-callers")]
+        for live in crate::leak::live_allocations() {
+            sink(&[LIVE])?;
+            write_uint(&mut sink, live.address as u64)?;
+            write_uint(&mut sink, live.size as u64)?;
+            write_tag(&mut sink, live.tag)?;
+            write_uint(&mut sink, live.location.line().into())?;
+            write_str(&mut sink, live.location.file())?;
+        }
+        sink(&[END])
+    }
+}
+
+/// Writes `value` as unsigned LEB128.
+fn write_uint<F, E>(sink: &mut F, mut value: u64) -> Result<(), E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        buf[len] = value as u8 & 0x7F;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+        buf[len] |= 0x80;
+        len += 1;
+    }
+    sink(&buf[.. len + 1])
+}
+
+fn write_str<F, E>(sink: &mut F, string: &str) -> Result<(), E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    write_uint(sink, string.len() as u64)?;
+    sink(string.as_bytes())
+}
+
+fn write_tag<F, E>(sink: &mut F, tag: Tag) -> Result<(), E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    match tag {
+        Tag::Name(name) => {
+            sink(&[0])?;
+            write_str(sink, name)
+        }
+        Tag::Id(id) => {
+            sink(&[1])?;
+            write_uint(sink, id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_uint, HeapSnapshot, SNAPSHOT_MAGIC, SNAPSHOT_VERSION, TAG};
+    use crate::{SizeClassUsage, Tag, TaggedAlloc};
+    use alloc::{string::String, vec::Vec};
+
+    #[test]
+    fn text_and_binary_list_tags_and_classes() {
+        let tag = Tag::Name("snapshot-test");
+        let _alloc = TaggedAlloc::with_tag([0u8; 200], tag);
+        let classes = [SizeClassUsage { size: 64, cached: 3 }];
+
+        let mut text = String::new();
+        HeapSnapshot::new().with_classes(classes).write_text(&mut text).unwrap();
+        assert!(text.lines().any(|line| line == "tag snapshot-test 200 1"));
+        assert!(text.lines().any(|line| line == "class 64 3"));
+
+        let mut bytes = Vec::new();
+        HeapSnapshot::new()
+            .with_classes(classes)
+            .write_binary(|chunk| {
+                bytes.extend_from_slice(chunk);
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert_eq!(bytes[.. 4], SNAPSHOT_MAGIC);
+        assert_eq!(bytes[4], SNAPSHOT_VERSION);
+        assert_eq!(bytes.last(), Some(&0));
+        let record = [&[TAG, 0, 13][..], b"snapshot-test", &[0xC8, 0x01, 1]].concat();
+        assert!(bytes.windows(record.len()).any(|window| window == record));
+        assert!(bytes.windows(3).any(|window| window == [2, 64, 3]));
+    }
+
+    #[test]
+    fn uint_is_leb128() {
+        let mut bytes = Vec::new();
+        let mut sink = |chunk: &[u8]| {
+            bytes.extend_from_slice(chunk);
+            Ok::<_, ()>(())
+        };
+        for value in [0, 127, 128, u64::MAX] {
+            write_uint(&mut sink, value).unwrap();
+        }
+        let max = [&[0xFF; 9][..], &[0x01]].concat();
+        assert_eq!(bytes, [&[0, 0x7F, 0x80, 0x01][..], &max].concat());
+    }
+}
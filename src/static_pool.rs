@@ -0,0 +1,246 @@
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Declares a `static` `StaticPool`.
+///
+/// # Example
+/// ```rust
+/// #[macro_use]
+/// extern crate owned_alloc;
+///
+/// static_pool!(static NODES: [u64; 4]; 8);
+///
+/// fn main() {
+///     let node = NODES.alloc([1, 2, 3, 4]).unwrap();
+///     assert_eq!(node[2], 3);
+///     assert_eq!(NODES.available(), 7);
+/// }
+/// ```
+#[macro_export]
+macro_rules! static_pool {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty; $cap:expr) => {
+        $(#[$attr])*
+        $vis static $name: $crate::StaticPool<$ty, { $cap }> = $crate::StaticPool::new();
+    };
+}
+
+/// A fixed-capacity pool of `N` slots for values of type `T`, stored inline,
+/// so it can live in a `static` on targets without any heap. Occupancy is
+/// tracked with one atomic flag per slot, and a rotating hint makes finding a
+/// free slot cheap when the pool is not nearly full.
+///
+/// Values are handed out as `StaticAlloc` handles, which behave like an
+/// `OwnedAlloc` and give the slot back on drop.
+pub struct StaticPool<T, const N: usize> {
+    slots: UnsafeCell<MaybeUninit<[T; N]>>,
+    used: [AtomicBool; N],
+    hint: AtomicUsize,
+    len: AtomicUsize,
+}
+
+impl<T, const N: usize> StaticPool<T, N> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const FREE: AtomicBool = AtomicBool::new(false);
+
+    /// Creates a new pool with all slots free.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            slots: UnsafeCell::new(MaybeUninit::uninit()),
+            used: [Self::FREE; N],
+            hint: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Moves `value` into a free slot. If the pool is full, the value is
+    /// given back as `Err`.
+    pub fn alloc(&self, value: T) -> Result<StaticAlloc<'_, T>, T> {
+        let start = self.hint.load(Ordering::Relaxed);
+        for offset in 0 .. N {
+            let index = (start + offset) % N;
+            let acquired = self.used[index]
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok();
+            if acquired {
+                self.hint.store((index + 1) % N, Ordering::Relaxed);
+                self.len.fetch_add(1, Ordering::Relaxed);
+                let ptr = unsafe {
+                    let ptr = self.slot(index);
+                    ptr.as_ptr().write(value);
+                    ptr
+                };
+                return Ok(StaticAlloc {
+                    ptr,
+                    pool: self.bookkeeping(),
+                    index,
+                });
+            }
+        }
+        Err(value)
+    }
+
+    /// Total number of slots.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of occupied slots.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Tests if no slot is occupied.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of free slots.
+    #[inline]
+    pub fn available(&self) -> usize {
+        N - self.len()
+    }
+
+    #[inline]
+    unsafe fn slot(&self, index: usize) -> NonNull<T> {
+        NonNull::new_unchecked((self.slots.get() as *mut T).add(index))
+    }
+
+    #[inline]
+    fn bookkeeping(&self) -> PoolRef<'_> {
+        PoolRef {
+            used: &self.used,
+            hint: &self.hint,
+            len: &self.len,
+        }
+    }
+}
+
+impl<T, const N: usize> Default for StaticPool<T, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for StaticPool<T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StaticPool {{ len: {}, capacity: {} }}", self.len(), N)
+    }
+}
+
+unsafe impl<T, const N: usize> Send for StaticPool<T, N> where T: Send {}
+unsafe impl<T, const N: usize> Sync for StaticPool<T, N> where T: Send {}
+
+/// The bookkeeping of a pool, without the capacity in its type.
+#[derive(Clone, Copy)]
+struct PoolRef<'p> {
+    used: &'p [AtomicBool],
+    hint: &'p AtomicUsize,
+    len: &'p AtomicUsize,
+}
+
+impl<'p> PoolRef<'p> {
+    #[inline]
+    fn release(&self, index: usize) {
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        self.used[index].store(false, Ordering::Release);
+        self.hint.store(index, Ordering::Relaxed);
+    }
+}
+
+/// An owned value living in a slot of a `StaticPool`. Dropping it drops the
+/// value and frees the slot.
+pub struct StaticAlloc<'p, T> {
+    ptr: NonNull<T>,
+    pool: PoolRef<'p>,
+    index: usize,
+}
+
+impl<'p, T> StaticAlloc<'p, T> {
+    /// Moves the value out, freeing the slot.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        let value = unsafe { self.ptr.as_ptr().read() };
+        self.pool.release(self.index);
+        mem::forget(self);
+        value
+    }
+
+    /// The raw non-null pointer to the value.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<T> {
+        self.ptr
+    }
+
+    /// The index of the slot holding the value.
+    #[inline]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<'p, T> Deref for StaticAlloc<'p, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<'p, T> DerefMut for StaticAlloc<'p, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<'p, T> Drop for StaticAlloc<'p, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { self.ptr.as_ptr().drop_in_place() }
+        self.pool.release(self.index);
+    }
+}
+
+impl<'p, T> fmt::Debug for StaticAlloc<'p, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StaticAlloc({:?})", self.ptr)
+    }
+}
+
+unsafe impl<'p, T> Send for StaticAlloc<'p, T> where T: Send {}
+unsafe impl<'p, T> Sync for StaticAlloc<'p, T> where T: Sync {}
+
+#[cfg(test)]
+mod test {
+    use super::StaticPool;
+
+    #[test]
+    fn full_pool_gives_value_back() {
+        let pool = StaticPool::<u32, 2>::new();
+        let a = pool.alloc(1).unwrap();
+        let b = pool.alloc(2).unwrap();
+        assert_eq!(pool.alloc(3).unwrap_err(), 3);
+
+        let index = a.index();
+        drop(a);
+        let c = pool.alloc(4).unwrap();
+        assert_eq!(c.index(), index);
+        assert_eq!(*b + *c, 6);
+        assert_eq!(c.into_inner(), 4);
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::Bump;
+#[cfg(feature = "str-dedup")]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "str-dedup")]
+use core::{borrow::Borrow, cell::UnsafeCell, cmp::Ordering, ptr::NonNull};
+use core::{fmt, str};
+
+/// An arena for strings and byte slices: each one is copied next to the
+/// previous ones in large chunks, freed all at once with the arena, so the
+/// names of an AST or a symbol table take no allocation of their own and sit
+/// densely in the cache.
+///
+/// With the `str-dedup` feature, `intern` and `intern_bytes` copy a string
+/// only once, handing out the same slice for equal strings.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::StrArena;
+///
+/// let arena = StrArena::new();
+/// let names: Vec<&str> = ["main", "argc", "argv"]
+///     .iter()
+///     .map(|name| arena.alloc_str(name))
+///     .collect();
+/// assert_eq!(names, ["main", "argc", "argv"]);
+/// assert_eq!(arena.len(), 3);
+/// assert_eq!(arena.alloc_bytes(b"\0\x01"), b"\0\x01");
+/// ```
+pub struct StrArena {
+    bump: Bump,
+    len: core::cell::Cell<usize>,
+    #[cfg(feature = "str-dedup")]
+    interned: UnsafeCell<BTreeSet<Interned>>,
+}
+
+impl StrArena {
+    /// Creates an empty arena with chunks of `DEFAULT_CHUNK_SIZE` bytes. No
+    /// allocation is performed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::with_chunk_size(crate::DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates an empty arena with chunks of at least `chunk_size` bytes.
+    /// Longer strings get a chunk of their own size.
+    #[inline]
+    pub const fn with_chunk_size(chunk_size: usize) -> Self {
+        Self {
+            bump: Bump::with_chunk_size(chunk_size),
+            len: core::cell::Cell::new(0),
+            #[cfg(feature = "str-dedup")]
+            interned: UnsafeCell::new(BTreeSet::new()),
+        }
+    }
+
+    panicking! {
+        /// Copies `string` into the arena. In case of allocation error, the
+        /// function panics.
+        #[inline]
+        pub fn alloc_str(&self, string: &str) -> &str {
+            unsafe { str::from_utf8_unchecked(self.alloc_bytes(string.as_bytes())) }
+        }
+    }
+
+    panicking! {
+        /// Copies `bytes` into the arena. In case of allocation error, the
+        /// function panics.
+        #[inline]
+        pub fn alloc_bytes(&self, bytes: &[u8]) -> &[u8] {
+            self.len.set(self.len.get() + 1);
+            self.bump.alloc_slice_copy(bytes)
+        }
+    }
+
+    /// Number of strings and byte slices copied into the arena.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Tests if nothing was copied into the arena.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes of memory held in chunks, used or not.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.bump.capacity()
+    }
+
+    /// Frees every string of the arena, keeping the chunks for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        #[cfg(feature = "str-dedup")]
+        self.interned.get_mut().clear();
+        self.len.set(0);
+        self.bump.reset();
+    }
+}
+
+#[cfg(feature = "str-dedup")]
+impl StrArena {
+    panicking! {
+        /// Copies `string` into the arena, unless an equal one was already
+        /// interned, in which case that one is returned. In case of allocation
+        /// error, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::StrArena;
+        ///
+        /// let arena = StrArena::new();
+        /// let first = arena.intern("symbol");
+        /// let second = arena.intern(&String::from("symbol"));
+        /// assert!(core::ptr::eq(first, second));
+        /// assert_eq!(arena.len(), 1);
+        /// ```
+        #[inline]
+        pub fn intern(&self, string: &str) -> &str {
+            unsafe { str::from_utf8_unchecked(self.intern_bytes(string.as_bytes())) }
+        }
+    }
+
+    panicking! {
+        /// Copies `bytes` into the arena, unless equal bytes were already
+        /// interned, in which case those are returned. Strings and byte slices
+        /// share the same table. In case of allocation error, the function
+        /// panics.
+        pub fn intern_bytes(&self, bytes: &[u8]) -> &[u8] {
+            let interned = unsafe { &mut *self.interned.get() };
+            if let Some(found) = interned.get(bytes) {
+                // Interned bytes live as long as the arena, not the table.
+                return unsafe { found.0.as_ref() };
+            }
+            let copy = self.alloc_bytes(bytes);
+            interned.insert(Interned(NonNull::from(copy)));
+            copy
+        }
+    }
+}
+
+impl Default for StrArena {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StrArena {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "StrArena {{ len: {}, capacity: {} }}",
+            self.len(),
+            self.capacity()
+        )
+    }
+}
+
+/// Bytes interned in a `StrArena`, compared by contents. They live as long
+/// as the arena, or until it is cleared, which empties the table as well.
+#[cfg(feature = "str-dedup")]
+struct Interned(NonNull<[u8]>);
+
+#[cfg(feature = "str-dedup")]
+impl Borrow<[u8]> for Interned {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        unsafe { self.0.as_ref() }
+    }
+}
+
+#[cfg(feature = "str-dedup")]
+impl PartialEq for Interned {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        <Self as Borrow<[u8]>>::borrow(self) == <Self as Borrow<[u8]>>::borrow(other)
+    }
+}
+
+#[cfg(feature = "str-dedup")]
+impl Eq for Interned {}
+
+#[cfg(feature = "str-dedup")]
+impl PartialOrd for Interned {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "str-dedup")]
+impl Ord for Interned {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        <Self as Borrow<[u8]>>::borrow(self).cmp(<Self as Borrow<[u8]>>::borrow(other))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StrArena;
+
+    #[test]
+    fn long_strings_get_their_own_chunk() {
+        let mut arena = StrArena::with_chunk_size(16);
+        let short = arena.alloc_str("short");
+        let long = arena.alloc_str("a string longer than a chunk");
+        assert_eq!((short, long), ("short", "a string longer than a chunk"));
+        assert_eq!(arena.len(), 2);
+
+        let capacity = arena.capacity();
+        arena.clear();
+        assert!(arena.is_empty());
+        arena.alloc_str("reused");
+        assert_eq!(arena.capacity(), capacity);
+    }
+
+    #[cfg(feature = "str-dedup")]
+    #[test]
+    fn strings_and_bytes_share_the_table() {
+        let arena = StrArena::new();
+        let text = arena.intern("shared");
+        let bytes = arena.intern_bytes(b"shared");
+        assert_eq!(text.as_ptr(), bytes.as_ptr());
+        assert_ne!(arena.alloc_str("shared").as_ptr(), text.as_ptr());
+        assert_eq!(arena.len(), 2);
+    }
+}
@@ -0,0 +1,675 @@
+//! Thin wrappers over the operating system's virtual memory API, enabled by
+//! the `os` feature.
+
+pub(crate) use imp::*;
+
+#[cfg(unix)]
+mod imp {
+    use crate::{Advice, OsError, Protection};
+    use alloc::vec::Vec;
+    use core::{alloc::Layout, mem, ptr};
+
+    pub(crate) type FileHandle = libc::c_int;
+
+    #[cfg(any(target_os = "linux", target_os = "emscripten"))]
+    use libc::__errno_location as errno_location;
+
+    #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+    use libc::__errno as errno_location;
+
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))]
+    use libc::__error as errno_location;
+
+    #[inline]
+    pub(crate) fn last_error() -> OsError {
+        OsError {
+            code: unsafe { *errno_location() },
+        }
+    }
+
+    #[inline]
+    pub(crate) fn page_size() -> usize {
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn protect(
+        ptr: *mut u8,
+        len: usize,
+        prot: Protection,
+    ) -> Result<(), OsError> {
+        let flags = match prot {
+            Protection::NoAccess => libc::PROT_NONE,
+            Protection::ReadOnly => libc::PROT_READ,
+            Protection::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+            Protection::ReadExecute => libc::PROT_READ | libc::PROT_EXEC,
+            Protection::ReadWriteExecute => libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC,
+        };
+        if libc::mprotect(ptr.cast(), len, flags) == 0 {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+
+    pub(crate) unsafe fn map_file(
+        file: FileHandle,
+        offset: u64,
+        len: usize,
+        writable: bool,
+        private: bool,
+    ) -> Result<*mut u8, OsError> {
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        let flags = if private {
+            libc::MAP_PRIVATE
+        } else {
+            libc::MAP_SHARED
+        };
+        let ptr = libc::mmap(ptr::null_mut(), len, prot, flags, file, offset as libc::off_t);
+        if ptr == libc::MAP_FAILED {
+            Err(last_error())
+        } else {
+            Ok(ptr.cast())
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn unmap(ptr: *mut u8, len: usize) {
+        libc::munmap(ptr.cast(), len);
+    }
+
+    #[inline]
+    pub(crate) unsafe fn flush(ptr: *mut u8, len: usize) -> Result<(), OsError> {
+        if libc::msync(ptr.cast(), len, libc::MS_SYNC) == 0 {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn advise(ptr: *mut u8, len: usize, advice: Advice) -> Result<(), OsError> {
+        let advice = match advice {
+            Advice::Normal => libc::MADV_NORMAL,
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::Random => libc::MADV_RANDOM,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::DontNeed => libc::MADV_DONTNEED,
+        };
+        if libc::madvise(ptr.cast(), len, advice) == 0 {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[inline]
+    pub(crate) fn current_cpu() -> Option<usize> {
+        // glibc answers this through the vDSO or rseq, without a system call.
+        let cpu = unsafe { libc::sched_getcpu() };
+        if cpu < 0 {
+            None
+        } else {
+            Some(cpu as usize)
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    #[inline]
+    pub(crate) fn current_cpu() -> Option<usize> {
+        None
+    }
+
+    pub(crate) fn reserve(len: usize) -> Result<*mut u8, OsError> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            Err(last_error())
+        } else {
+            Ok(ptr.cast())
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn commit(ptr: *mut u8, len: usize) -> Result<(), OsError> {
+        protect(ptr, len, Protection::ReadWrite)
+    }
+
+    #[inline]
+    pub(crate) unsafe fn release(ptr: *mut u8, len: usize) {
+        unmap(ptr, len)
+    }
+
+    /// Alignment `malloc` guarantees, that of `max_align_t`.
+    const MALLOC_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+    #[inline]
+    pub(crate) unsafe fn heap_alloc(layout: Layout) -> *mut u8 {
+        if layout.align() <= MALLOC_ALIGN && layout.align() <= layout.size() {
+            return libc::malloc(layout.size()).cast();
+        }
+        let align = layout.align().max(mem::size_of::<usize>());
+        let mut ptr = ptr::null_mut();
+        if libc::posix_memalign(&mut ptr, align, layout.size()) == 0 {
+            ptr.cast()
+        } else {
+            ptr::null_mut()
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn heap_free(ptr: *mut u8, _layout: Layout) {
+        libc::free(ptr.cast())
+    }
+
+    /// Asks the C library to give the free memory at the top of its heap
+    /// back to the system.
+    #[inline]
+    pub(crate) fn trim_heap() {
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        unsafe {
+            libc::malloc_trim(0);
+        }
+    }
+
+    /// A memory pressure trigger of the pressure stall information interface.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    pub(crate) struct PressureWatcher {
+        fd: libc::c_int,
+    }
+
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    impl PressureWatcher {
+        pub(crate) fn new() -> Result<Self, OsError> {
+            let path = c_name("/proc/pressure/memory");
+            // Tasks stalled on memory for 150ms within a 2s window. Windows
+            // must be multiples of 2s for unprivileged processes.
+            let trigger = c_name("some 150000 2000000");
+            unsafe {
+                let flags = libc::O_RDWR | libc::O_NONBLOCK | libc::O_CLOEXEC;
+                let fd = check(libc::open(path.as_ptr().cast(), flags))?;
+                if libc::write(fd, trigger.as_ptr().cast(), trigger.len()) < 0 {
+                    let err = last_error();
+                    libc::close(fd);
+                    return Err(err);
+                }
+                Ok(Self { fd })
+            }
+        }
+
+        /// Blocks until the next pressure event. Returns `false` if no event
+        /// can be received anymore.
+        pub(crate) fn wait(&self) -> bool {
+            let mut poll = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLPRI,
+                revents: 0,
+            };
+            loop {
+                if unsafe { libc::poll(&mut poll, 1, -1) } >= 0 {
+                    break poll.revents & libc::POLLERR == 0;
+                }
+                if last_error().code != libc::EINTR {
+                    break false;
+                }
+            }
+        }
+    }
+
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    impl Drop for PressureWatcher {
+        #[inline]
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+
+    #[cfg(all(feature = "std", not(any(target_os = "linux", target_os = "android"))))]
+    pub(crate) struct PressureWatcher;
+
+    #[cfg(all(feature = "std", not(any(target_os = "linux", target_os = "android"))))]
+    impl PressureWatcher {
+        #[inline]
+        pub(crate) fn new() -> Result<Self, OsError> {
+            Err(OsError {
+                code: libc::ENOSYS,
+            })
+        }
+
+        #[inline]
+        pub(crate) fn wait(&self) -> bool {
+            false
+        }
+    }
+
+    #[inline]
+    fn c_name(name: &str) -> Vec<u8> {
+        name.bytes().chain(Some(0)).collect()
+    }
+
+    #[inline]
+    fn check(ret: libc::c_int) -> Result<libc::c_int, OsError> {
+        if ret < 0 {
+            Err(last_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    unsafe fn map_shared(fd: FileHandle, len: usize) -> Result<*mut u8, OsError> {
+        match map_file(fd, 0, len, true, false) {
+            Ok(ptr) => Ok(ptr),
+            Err(err) => {
+                libc::close(fd);
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn shm_create(name: &str, len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        let name = c_name(name);
+        unsafe {
+            let flags = libc::O_CREAT | libc::O_EXCL | libc::O_RDWR;
+            let fd = check(libc::shm_open(name.as_ptr().cast(), flags, 0o600))?;
+            if let Err(err) = check(libc::ftruncate(fd, len as libc::off_t)) {
+                libc::close(fd);
+                libc::shm_unlink(name.as_ptr().cast());
+                return Err(err);
+            }
+            match map_shared(fd, len) {
+                Ok(ptr) => Ok((fd, ptr)),
+                Err(err) => {
+                    libc::shm_unlink(name.as_ptr().cast());
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    pub(crate) fn shm_open(name: &str, len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        let name = c_name(name);
+        unsafe {
+            let fd = check(libc::shm_open(name.as_ptr().cast(), libc::O_RDWR, 0o600))?;
+            // Pages past the end of the object fault with `SIGBUS` when
+            // accessed.
+            let mut stat = mem::zeroed::<libc::stat>();
+            let size = check(libc::fstat(fd, &mut stat)).map(|_| stat.st_size as u64);
+            match size {
+                Ok(size) if size >= len as u64 => map_shared(fd, len).map(|ptr| (fd, ptr)),
+                Ok(_) => {
+                    libc::close(fd);
+                    Err(OsError {
+                        code: libc::EINVAL,
+                    })
+                },
+                Err(err) => {
+                    libc::close(fd);
+                    Err(err)
+                },
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn shm_anonymous(len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        let name = c_name("owned-alloc");
+        unsafe {
+            let fd = check(libc::memfd_create(name.as_ptr().cast(), 0))?;
+            if let Err(err) = check(libc::ftruncate(fd, len as libc::off_t)) {
+                libc::close(fd);
+                return Err(err);
+            }
+            map_shared(fd, len).map(|ptr| (fd, ptr))
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub(crate) fn shm_anonymous(_len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        Err(OsError {
+            code: libc::ENOSYS,
+        })
+    }
+
+    #[inline]
+    pub(crate) unsafe fn shm_from_handle(handle: FileHandle, len: usize) -> Result<*mut u8, OsError> {
+        map_shared(handle, len)
+    }
+
+    #[inline]
+    pub(crate) unsafe fn shm_close(handle: FileHandle) {
+        libc::close(handle);
+    }
+
+    #[inline]
+    pub(crate) fn shm_unlink(name: &str) {
+        let name = c_name(name);
+        unsafe {
+            libc::shm_unlink(name.as_ptr().cast());
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use crate::{Advice, OsError, Protection};
+    use alloc::vec::Vec;
+    use core::{alloc::Layout, mem, ptr};
+    use windows_sys::Win32::{
+        Foundation::{
+            CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE, INVALID_HANDLE_VALUE,
+        },
+        System::{
+            Memory::{
+                CreateFileMappingW, FlushViewOfFile, GetProcessHeap, HeapAlloc, HeapFree,
+                MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, VirtualAlloc, VirtualFree,
+                VirtualProtect, FILE_MAP_ALL_ACCESS, FILE_MAP_COPY, FILE_MAP_READ,
+                FILE_MAP_WRITE, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_EXECUTE_READ,
+                PAGE_EXECUTE_READWRITE, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE,
+                PAGE_WRITECOPY,
+            },
+            SystemInformation::{GetSystemInfo, SYSTEM_INFO},
+            Threading::GetCurrentProcessorNumber,
+        },
+    };
+    #[cfg(feature = "std")]
+    use windows_sys::Win32::System::{
+        Memory::{CreateMemoryResourceNotification, LowMemoryResourceNotification},
+        Threading::{WaitForSingleObject, WAIT_OBJECT_0},
+        WindowsProgramming::INFINITE,
+    };
+
+    pub(crate) type FileHandle = HANDLE;
+
+    #[inline]
+    pub(crate) fn last_error() -> OsError {
+        OsError {
+            code: unsafe { GetLastError() } as i32,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn page_size() -> usize {
+        unsafe {
+            let mut info: SYSTEM_INFO = mem::zeroed();
+            GetSystemInfo(&mut info);
+            info.dwPageSize as usize
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn protect(
+        ptr: *mut u8,
+        len: usize,
+        prot: Protection,
+    ) -> Result<(), OsError> {
+        let flags = match prot {
+            Protection::NoAccess => PAGE_NOACCESS,
+            Protection::ReadOnly => PAGE_READONLY,
+            Protection::ReadWrite => PAGE_READWRITE,
+            Protection::ReadExecute => PAGE_EXECUTE_READ,
+            Protection::ReadWriteExecute => PAGE_EXECUTE_READWRITE,
+        };
+        let mut old = 0;
+        if VirtualProtect(ptr.cast(), len, flags, &mut old) != 0 {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+
+    pub(crate) unsafe fn map_file(
+        file: FileHandle,
+        offset: u64,
+        len: usize,
+        writable: bool,
+        private: bool,
+    ) -> Result<*mut u8, OsError> {
+        let (protect, access) = match (writable, private) {
+            (false, _) => (PAGE_READONLY, FILE_MAP_READ),
+            (true, false) => (PAGE_READWRITE, FILE_MAP_WRITE),
+            (true, true) => (PAGE_WRITECOPY, FILE_MAP_COPY),
+        };
+        let end = offset + len as u64;
+        let mapping = CreateFileMappingW(
+            file,
+            ptr::null(),
+            protect,
+            (end >> 32) as u32,
+            end as u32,
+            ptr::null(),
+        );
+        if mapping == 0 {
+            return Err(last_error());
+        }
+        let view = MapViewOfFile(mapping, access, (offset >> 32) as u32, offset as u32, len);
+        let res = if view.is_null() {
+            Err(last_error())
+        } else {
+            Ok(view.cast())
+        };
+        // The view keeps the mapping object alive.
+        CloseHandle(mapping);
+        res
+    }
+
+    #[inline]
+    pub(crate) unsafe fn unmap(ptr: *mut u8, _len: usize) {
+        UnmapViewOfFile(ptr.cast());
+    }
+
+    #[inline]
+    pub(crate) unsafe fn flush(ptr: *mut u8, len: usize) -> Result<(), OsError> {
+        if FlushViewOfFile(ptr.cast(), len) != 0 {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn advise(_ptr: *mut u8, _len: usize, _advice: Advice) -> Result<(), OsError> {
+        // Views have no equivalent of `madvise`; the advice is only a hint.
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn current_cpu() -> Option<usize> {
+        Some(unsafe { GetCurrentProcessorNumber() } as usize)
+    }
+
+    #[inline]
+    pub(crate) fn reserve(len: usize) -> Result<*mut u8, OsError> {
+        let ptr = unsafe { VirtualAlloc(ptr::null(), len, MEM_RESERVE, PAGE_NOACCESS) };
+        if ptr.is_null() {
+            Err(last_error())
+        } else {
+            Ok(ptr.cast())
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn commit(ptr: *mut u8, len: usize) -> Result<(), OsError> {
+        if VirtualAlloc(ptr.cast(), len, MEM_COMMIT, PAGE_READWRITE).is_null() {
+            Err(last_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn release(ptr: *mut u8, _len: usize) {
+        VirtualFree(ptr.cast(), 0, MEM_RELEASE);
+    }
+
+    /// Alignment `HeapAlloc` guarantees, `MEMORY_ALLOCATION_ALIGNMENT`.
+    const HEAP_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+    /// Blocks aligned beyond `HEAP_ALIGN` are carved out of a bigger block,
+    /// with the pointer to free stored right before them.
+    #[inline]
+    pub(crate) unsafe fn heap_alloc(layout: Layout) -> *mut u8 {
+        let heap = GetProcessHeap();
+        if layout.align() <= HEAP_ALIGN {
+            return HeapAlloc(heap, 0, layout.size()).cast();
+        }
+        let header = mem::size_of::<*mut u8>();
+        let raw: *mut u8 = match layout.size().checked_add(layout.align() + header) {
+            Some(size) => HeapAlloc(heap, 0, size).cast(),
+            None => return ptr::null_mut(),
+        };
+        if raw.is_null() {
+            return raw;
+        }
+        let ptr = raw.add(header);
+        let ptr = ptr.add(ptr.align_offset(layout.align()));
+        ptr.cast::<*mut u8>().sub(1).write(raw);
+        ptr
+    }
+
+    #[inline]
+    pub(crate) unsafe fn heap_free(ptr: *mut u8, layout: Layout) {
+        let raw = if layout.align() <= HEAP_ALIGN {
+            ptr
+        } else {
+            ptr.cast::<*mut u8>().sub(1).read()
+        };
+        HeapFree(GetProcessHeap(), 0, raw.cast());
+    }
+
+    #[inline]
+    pub(crate) fn trim_heap() {
+        // The process heap decommits its free pages on its own.
+    }
+
+    /// A low memory resource notification.
+    #[cfg(feature = "std")]
+    pub(crate) struct PressureWatcher {
+        handle: HANDLE,
+    }
+
+    #[cfg(feature = "std")]
+    impl PressureWatcher {
+        #[inline]
+        pub(crate) fn new() -> Result<Self, OsError> {
+            let handle =
+                unsafe { CreateMemoryResourceNotification(LowMemoryResourceNotification) };
+            if handle == 0 {
+                Err(last_error())
+            } else {
+                Ok(Self { handle })
+            }
+        }
+
+        /// Blocks until memory is low. Returns `false` if the notification
+        /// cannot be waited on anymore.
+        #[inline]
+        pub(crate) fn wait(&self) -> bool {
+            unsafe { WaitForSingleObject(self.handle, INFINITE) == WAIT_OBJECT_0 }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl Drop for PressureWatcher {
+        #[inline]
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.handle) };
+        }
+    }
+
+    #[inline]
+    fn wide_name(name: &str) -> Vec<u16> {
+        name.encode_utf16().chain(Some(0)).collect()
+    }
+
+    unsafe fn create_section(name: *const u16, len: usize) -> Result<FileHandle, OsError> {
+        let len = len as u64;
+        let handle = CreateFileMappingW(
+            INVALID_HANDLE_VALUE,
+            ptr::null(),
+            PAGE_READWRITE,
+            (len >> 32) as u32,
+            len as u32,
+            name,
+        );
+        if handle == 0 {
+            return Err(last_error());
+        }
+        if GetLastError() == ERROR_ALREADY_EXISTS {
+            CloseHandle(handle);
+            return Err(OsError {
+                code: ERROR_ALREADY_EXISTS as i32,
+            });
+        }
+        Ok(handle)
+    }
+
+    unsafe fn map_section(handle: FileHandle, len: usize) -> Result<*mut u8, OsError> {
+        let view = MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, len);
+        if view.is_null() {
+            let err = last_error();
+            CloseHandle(handle);
+            Err(err)
+        } else {
+            Ok(view.cast())
+        }
+    }
+
+    pub(crate) fn shm_create(name: &str, len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        let name = wide_name(name);
+        unsafe {
+            let handle = create_section(name.as_ptr(), len)?;
+            map_section(handle, len).map(|ptr| (handle, ptr))
+        }
+    }
+
+    pub(crate) fn shm_open(name: &str, len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        let name = wide_name(name);
+        unsafe {
+            let handle = OpenFileMappingW(FILE_MAP_ALL_ACCESS, 0, name.as_ptr());
+            if handle == 0 {
+                return Err(last_error());
+            }
+            map_section(handle, len).map(|ptr| (handle, ptr))
+        }
+    }
+
+    pub(crate) fn shm_anonymous(len: usize) -> Result<(FileHandle, *mut u8), OsError> {
+        unsafe {
+            let handle = create_section(ptr::null(), len)?;
+            map_section(handle, len).map(|ptr| (handle, ptr))
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn shm_from_handle(handle: FileHandle, len: usize) -> Result<*mut u8, OsError> {
+        map_section(handle, len)
+    }
+
+    #[inline]
+    pub(crate) unsafe fn shm_close(handle: FileHandle) {
+        CloseHandle(handle);
+    }
+
+    #[inline]
+    pub(crate) fn shm_unlink(_name: &str) {
+        // Sections are destroyed once their last handle is closed.
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{alloc_api::AllocError, sys, Allocator};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::NonNull,
+};
+
+/// Where a `SystemAlloc` takes its memory from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemBackend {
+    /// The crate `Allocator`, i.e. the Rust global allocator.
+    Global,
+    /// The heap of the platform, bypassing the Rust global allocator:
+    /// `malloc` (or `posix_memalign` for big alignments) and `free` on Unix,
+    /// `HeapAlloc` and `HeapFree` on the process heap on Windows.
+    Platform,
+}
+
+/// An allocator taking its memory from a backend chosen per instance. With
+/// `SystemBackend::Platform`, blocks come straight from the platform heap,
+/// so platform heap debugging tools (`MallocScribble`, page heap, `ltrace`)
+/// see them, even when the Rust global allocator is something else.
+///
+/// It can be the `#[global_allocator]` with `SystemBackend::Platform` only,
+/// since the `Global` backend would call itself.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::alloc::Layout;
+/// use owned_alloc::{alloc_api::Allocator as _, SystemAlloc, SystemBackend};
+///
+/// let alloc = SystemAlloc::new(SystemBackend::Platform);
+/// let layout = Layout::from_size_align(100, 256).unwrap();
+/// let block = alloc.allocate(layout).unwrap();
+/// assert_eq!(block.cast::<u8>().as_ptr() as usize % 256, 0);
+/// unsafe { alloc.deallocate(block.cast(), layout) };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemAlloc {
+    backend: SystemBackend,
+}
+
+impl SystemAlloc {
+    /// An allocator taking its memory from `backend`.
+    #[inline]
+    pub const fn new(backend: SystemBackend) -> Self {
+        Self { backend }
+    }
+
+    /// An allocator taking its memory from the platform heap.
+    #[inline]
+    pub const fn platform() -> Self {
+        Self::new(SystemBackend::Platform)
+    }
+
+    /// Where the allocator takes its memory from.
+    #[inline]
+    pub const fn backend(&self) -> SystemBackend {
+        self.backend
+    }
+}
+
+unsafe impl GlobalAlloc for SystemAlloc {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.backend {
+            SystemBackend::Global => GlobalAlloc::alloc(&Allocator::new(), layout),
+            SystemBackend::Platform => sys::heap_alloc(layout),
+        }
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        match self.backend {
+            SystemBackend::Global => GlobalAlloc::dealloc(&Allocator::new(), ptr, layout),
+            SystemBackend::Platform => sys::heap_free(ptr, layout),
+        }
+    }
+}
+
+unsafe impl crate::alloc_api::Allocator for SystemAlloc {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+        let ptr = NonNull::new(unsafe { self.alloc(layout) }).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.dealloc(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+impl Default for SystemAlloc {
+    #[inline]
+    fn default() -> Self {
+        Self::platform()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SystemAlloc, SystemBackend};
+    use crate::alloc_api::Allocator as _;
+    use core::alloc::Layout;
+
+    #[test]
+    fn backends_honor_alignment() {
+        for backend in [SystemBackend::Global, SystemBackend::Platform] {
+            let alloc = SystemAlloc::new(backend);
+            for align in [1, 8, 16, 64, 4096] {
+                let layout = Layout::from_size_align(24, align).unwrap();
+                let block = alloc.allocate(layout).unwrap();
+                assert_eq!(block.cast::<u8>().as_ptr() as usize % align, 0);
+                unsafe {
+                    block.cast::<u8>().as_ptr().write_bytes(0xA5, 24);
+                    alloc.deallocate(block.cast(), layout);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::OwnedAlloc;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    hint,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+
+/// Maximum number of distinct tags. Allocations with tags beyond this limit
+/// are accounted to `Tag::UNTAGGED`.
+pub const MAX_TAGS: usize = 64;
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+static REGISTRY: Registry = Registry::new();
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static CURRENT: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+#[cfg(not(feature = "std"))]
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Identifies the subsystem owning an allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tag {
+    /// A tag identified by name.
+    Name(&'static str),
+    /// A tag identified by number.
+    Id(u64),
+}
+
+impl Tag {
+    /// Tag of allocations made outside of any `TagScope`.
+    pub const UNTAGGED: Tag = Tag::Name("untagged");
+
+    /// The tag set by the innermost live `TagScope`. Scopes are per-thread
+    /// with the `std` feature, and global otherwise.
+    #[inline]
+    pub fn current() -> Tag {
+        REGISTRY.tag(current_index())
+    }
+
+    /// Live bytes allocated with this tag.
+    #[inline]
+    pub fn live_bytes(self) -> usize {
+        REGISTRY.entries[REGISTRY.index(self)]
+            .live_bytes
+            .load(Ordering::Relaxed)
+    }
+
+    /// Makes this tag the current one until the returned guard is dropped.
+    #[inline]
+    pub fn scope(self) -> TagScope {
+        let previous = current_index();
+        set_current_index(REGISTRY.index(self));
+        TagScope { previous }
+    }
+}
+
+impl From<&'static str> for Tag {
+    #[inline]
+    fn from(name: &'static str) -> Self {
+        Tag::Name(name)
+    }
+}
+
+impl From<u64> for Tag {
+    #[inline]
+    fn from(id: u64) -> Self {
+        Tag::Id(id)
+    }
+}
+
+impl fmt::Display for Tag {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tag::Name(name) => write!(f, "{}", name),
+            Tag::Id(id) => write!(f, "#{}", id),
+        }
+    }
+}
+
+/// Restores the previously current tag on drop. Created by `Tag::scope`.
+#[derive(Debug)]
+pub struct TagScope {
+    previous: usize,
+}
+
+impl Drop for TagScope {
+    #[inline]
+    fn drop(&mut self) {
+        set_current_index(self.previous);
+    }
+}
+
+/// Live memory of one tag, as yielded by `report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagReport {
+    /// The tag.
+    pub tag: Tag,
+    /// Bytes currently allocated with the tag.
+    pub live_bytes: usize,
+    /// Number of allocations currently alive with the tag.
+    pub live_count: usize,
+}
+
+/// Reports live memory of every tag seen so far, in registration order.
+#[inline]
+pub fn report() -> impl Iterator<Item = TagReport> {
+    REGISTRY
+        .entries
+        .iter()
+        .enumerate()
+        .take_while(|(_, entry)| entry.state.load(Ordering::Acquire) == READY)
+        .map(|(index, entry)| TagReport {
+            tag: REGISTRY.tag(index),
+            live_bytes: entry.live_bytes.load(Ordering::Relaxed),
+            live_count: entry.live_count.load(Ordering::Relaxed),
+        })
+}
+
+/// An `OwnedAlloc` accounted to a tag for as long as it is alive.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{Tag, TaggedAlloc};
+///
+/// let parser = Tag::Name("doc-parser");
+/// let _scope = parser.scope();
+/// let nodes = TaggedAlloc::new([0u64; 16]);
+///
+/// assert_eq!(nodes.tag(), parser);
+/// assert_eq!(parser.live_bytes(), 128);
+/// drop(nodes);
+/// assert_eq!(parser.live_bytes(), 0);
+/// ```
+pub struct TaggedAlloc<T>
+where
+    T: ?Sized,
+{
+    alloc: OwnedAlloc<T>,
+    index: usize,
+}
+
+impl<T> TaggedAlloc<T> {
+    /// Allocates `value` with the current tag.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self::from_owned(OwnedAlloc::new(value), Tag::current())
+    }
+
+    /// Allocates `value` with the given tag.
+    #[inline]
+    pub fn with_tag(value: T, tag: Tag) -> Self {
+        Self::from_owned(OwnedAlloc::new(value), tag)
+    }
+}
+
+impl<T> TaggedAlloc<T>
+where
+    T: ?Sized,
+{
+    /// Accounts an existing allocation to the given tag.
+    #[inline]
+    pub fn from_owned(alloc: OwnedAlloc<T>, tag: Tag) -> Self {
+        let index = REGISTRY.index(tag);
+        REGISTRY.charge(index, mem::size_of_val(&*alloc));
+        Self { alloc, index }
+    }
+
+    /// The tag of this allocation.
+    #[inline]
+    pub fn tag(&self) -> Tag {
+        REGISTRY.tag(self.index)
+    }
+
+    /// Stops accounting the allocation and returns it.
+    #[inline]
+    pub fn into_owned(self) -> OwnedAlloc<T> {
+        REGISTRY.release(self.index, mem::size_of_val(&*self.alloc));
+        let alloc = unsafe { (&self.alloc as *const OwnedAlloc<T>).read() };
+        mem::forget(self);
+        alloc
+    }
+}
+
+impl<T> Deref for TaggedAlloc<T>
+where
+    T: ?Sized,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.alloc
+    }
+}
+
+impl<T> DerefMut for TaggedAlloc<T>
+where
+    T: ?Sized,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.alloc
+    }
+}
+
+impl<T> Drop for TaggedAlloc<T>
+where
+    T: ?Sized,
+{
+    #[inline]
+    fn drop(&mut self) {
+        REGISTRY.release(self.index, mem::size_of_val(&*self.alloc));
+    }
+}
+
+impl<T> fmt::Debug for TaggedAlloc<T>
+where
+    T: ?Sized,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TaggedAlloc({:?}, {})", self.alloc.raw(), self.tag())
+    }
+}
+
+struct Entry {
+    state: AtomicU8,
+    tag: UnsafeCell<MaybeUninit<Tag>>,
+    live_bytes: AtomicUsize,
+    live_count: AtomicUsize,
+}
+
+impl Entry {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Entry = Entry {
+        state: AtomicU8::new(EMPTY),
+        tag: UnsafeCell::new(MaybeUninit::uninit()),
+        live_bytes: AtomicUsize::new(0),
+        live_count: AtomicUsize::new(0),
+    };
+}
+
+/// Tags are registered in the first empty entry, so a lookup stops at the
+/// first empty entry, and waits on entries being written since they could
+/// hold the tag being looked up.
+struct Registry {
+    entries: [Entry; MAX_TAGS],
+}
+
+impl Registry {
+    const fn new() -> Self {
+        let mut entries = [Entry::EMPTY; MAX_TAGS];
+        entries[0] = Entry {
+            state: AtomicU8::new(READY),
+            tag: UnsafeCell::new(MaybeUninit::new(Tag::UNTAGGED)),
+            live_bytes: AtomicUsize::new(0),
+            live_count: AtomicUsize::new(0),
+        };
+        Self { entries }
+    }
+
+    fn index(&self, tag: Tag) -> usize {
+        for (index, entry) in self.entries.iter().enumerate() {
+            loop {
+                match entry.state.load(Ordering::Acquire) {
+                    READY if self.tag(index) == tag => return index,
+                    READY => break,
+                    WRITING => hint::spin_loop(),
+                    _ => {
+                        let claimed = entry
+                            .state
+                            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Acquire)
+                            .is_ok();
+                        if claimed {
+                            unsafe { (*entry.tag.get()).write(tag) };
+                            entry.state.store(READY, Ordering::Release);
+                            return index;
+                        }
+                    },
+                }
+            }
+        }
+        0
+    }
+
+    #[inline]
+    fn tag(&self, index: usize) -> Tag {
+        unsafe { (*self.entries[index].tag.get()).assume_init() }
+    }
+
+    #[inline]
+    fn charge(&self, index: usize, bytes: usize) {
+        self.entries[index]
+            .live_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+        self.entries[index].live_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn release(&self, index: usize, bytes: usize) {
+        self.entries[index]
+            .live_bytes
+            .fetch_sub(bytes, Ordering::Relaxed);
+        self.entries[index].live_count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl Sync for Registry {}
+
+/// The tag registered at `index`, or `Tag::UNTAGGED` if there is none, e.g.
+/// because the index was read from corrupted memory.
+pub(crate) fn tag_at(index: usize) -> Tag {
+    match REGISTRY.entries.get(index) {
+        Some(entry) if entry.state.load(Ordering::Acquire) == READY => REGISTRY.tag(index),
+        _ => Tag::UNTAGGED,
+    }
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn current_index() -> usize {
+    CURRENT.with(|current| current.get())
+}
+
+#[cfg(feature = "std")]
+#[inline]
+fn set_current_index(index: usize) {
+    CURRENT.with(|current| current.set(index))
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub(crate) fn current_index() -> usize {
+    CURRENT.load(Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+fn set_current_index(index: usize) {
+    CURRENT.store(index, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{report, Tag, TaggedAlloc};
+    use crate::OwnedAlloc;
+
+    #[test]
+    fn report_live_bytes_per_tag() {
+        let tag = Tag::Id(0x7a6);
+        let a = TaggedAlloc::with_tag(0u32, tag);
+        let slice: OwnedAlloc<[u8]> = unsafe {
+            OwnedAlloc::from_box(alloc::vec![1u8; 10].into_boxed_slice())
+        };
+        let b = TaggedAlloc::from_owned(slice, tag);
+
+        let entry = report().find(|entry| entry.tag == tag).unwrap();
+        assert_eq!(entry.live_bytes, 14);
+        assert_eq!(entry.live_count, 2);
+
+        drop(a);
+        let _owned = b.into_owned();
+        assert_eq!(tag.live_bytes(), 0);
+    }
+}
@@ -0,0 +1,495 @@
+#[cfg(feature = "os")]
+use crate::{Advice, ReleaseFree, ReleaseReport};
+use crate::{alloc_api::AllocError, Fragmentation};
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+/// Granularity of block sizes, and minimum alignment of allocations.
+const ALIGN: usize = 2 * mem::size_of::<usize>();
+const ALIGN_LOG2: u32 = ALIGN.trailing_zeros();
+/// Each first-level class (a power of two) is split in `SL_COUNT` lists.
+const SL_LOG2: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+/// Sizes below `SMALL` all go to the first first-level class, split linearly.
+const FL_SHIFT: u32 = SL_LOG2 + ALIGN_LOG2;
+const SMALL: usize = 1 << FL_SHIFT;
+const FL_COUNT: usize = (usize::BITS - FL_SHIFT + 1) as usize;
+/// Biggest request served, so that rounding sizes up never overflows.
+const MAX_SIZE: usize = 1 << (usize::BITS - 2);
+
+const FREE: usize = 1;
+const PREV_FREE: usize = 2;
+const FLAGS: usize = FREE | PREV_FREE;
+
+/// Header of a block. Only `prev_phys` and `size` are present in used blocks;
+/// the free-list links overlap the payload of free blocks.
+#[repr(C)]
+struct Block {
+    prev_phys: *mut Block,
+    size: usize,
+    next_free: *mut Block,
+    prev_free: *mut Block,
+}
+
+const HEADER: usize = 2 * mem::size_of::<usize>();
+const MIN_PAYLOAD: usize = mem::size_of::<Block>() - HEADER;
+
+impl Block {
+    #[inline]
+    unsafe fn size(this: *mut Self) -> usize {
+        (*this).size & !FLAGS
+    }
+
+    #[inline]
+    unsafe fn set_size(this: *mut Self, size: usize) {
+        (*this).size = size | ((*this).size & FLAGS);
+    }
+
+    #[inline]
+    unsafe fn is_free(this: *mut Self) -> bool {
+        (*this).size & FREE != 0
+    }
+
+    #[inline]
+    unsafe fn is_prev_free(this: *mut Self) -> bool {
+        (*this).size & PREV_FREE != 0
+    }
+
+    #[inline]
+    unsafe fn payload(this: *mut Self) -> *mut u8 {
+        (this as *mut u8).add(HEADER)
+    }
+
+    #[inline]
+    unsafe fn from_payload(ptr: *mut u8) -> *mut Self {
+        ptr.sub(HEADER) as *mut Self
+    }
+
+    #[inline]
+    unsafe fn next_phys(this: *mut Self) -> *mut Self {
+        Self::payload(this).add(Self::size(this)) as *mut Self
+    }
+
+    /// Points the next physical block back at this one, and returns it.
+    #[inline]
+    unsafe fn link_next(this: *mut Self) -> *mut Self {
+        let next = Self::next_phys(this);
+        (*next).prev_phys = this;
+        next
+    }
+
+    #[inline]
+    unsafe fn mark_free(this: *mut Self) {
+        (*this).size |= FREE;
+        (*Self::link_next(this)).size |= PREV_FREE;
+    }
+
+    #[inline]
+    unsafe fn mark_used(this: *mut Self) {
+        (*this).size &= !FREE;
+        (*Self::next_phys(this)).size &= !PREV_FREE;
+    }
+}
+
+/// Free lists indexed by first and second level, with bitmaps of the
+/// non-empty ones.
+struct Control {
+    fl_bitmap: usize,
+    sl_bitmap: [usize; FL_COUNT],
+    heads: [[*mut Block; SL_COUNT]; FL_COUNT],
+    /// Bytes of used blocks beyond the sizes requested.
+    wasted: usize,
+}
+
+impl Control {
+    /// The list a free block of `size` bytes belongs to.
+    #[inline]
+    fn mapping_insert(size: usize) -> (usize, usize) {
+        if size < SMALL {
+            (0, size / (SMALL / SL_COUNT))
+        } else {
+            let log2 = usize::BITS - 1 - size.leading_zeros();
+            let sl = (size >> (log2 - SL_LOG2)) ^ SL_COUNT;
+            ((log2 - FL_SHIFT + 1) as usize, sl)
+        }
+    }
+
+    /// The first list whose blocks are all at least `size` bytes.
+    #[inline]
+    fn mapping_search(size: usize) -> (usize, usize) {
+        let size = if size < SMALL {
+            size
+        } else {
+            let log2 = usize::BITS - 1 - size.leading_zeros();
+            size + (1 << (log2 - SL_LOG2)) - 1
+        };
+        Self::mapping_insert(size)
+    }
+
+    unsafe fn insert(&mut self, block: *mut Block) {
+        let (fl, sl) = Self::mapping_insert(Block::size(block));
+        let head = self.heads[fl][sl];
+        (*block).next_free = head;
+        (*block).prev_free = ptr::null_mut();
+        if !head.is_null() {
+            (*head).prev_free = block;
+        }
+        self.heads[fl][sl] = block;
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmap[fl] |= 1 << sl;
+    }
+
+    unsafe fn remove(&mut self, block: *mut Block) {
+        let (fl, sl) = Self::mapping_insert(Block::size(block));
+        let (next, prev) = ((*block).next_free, (*block).prev_free);
+        if !next.is_null() {
+            (*next).prev_free = prev;
+        }
+        if !prev.is_null() {
+            (*prev).next_free = next;
+        }
+        if self.heads[fl][sl] == block {
+            self.heads[fl][sl] = next;
+            if next.is_null() {
+                self.sl_bitmap[fl] &= !(1 << sl);
+                if self.sl_bitmap[fl] == 0 {
+                    self.fl_bitmap &= !(1 << fl);
+                }
+            }
+        }
+    }
+
+    /// Takes a free block of at least `size` bytes out of its list.
+    unsafe fn take(&mut self, size: usize) -> Option<*mut Block> {
+        let (mut fl, sl) = Self::mapping_search(size);
+        if fl >= FL_COUNT {
+            return None;
+        }
+        let mut sl_map = self.sl_bitmap[fl] & (!0 << sl);
+        if sl_map == 0 {
+            let fl_map = self.fl_bitmap & (!0 << (fl + 1));
+            if fl_map == 0 {
+                return None;
+            }
+            fl = fl_map.trailing_zeros() as usize;
+            sl_map = self.sl_bitmap[fl];
+        }
+        let block = self.heads[fl][sl_map.trailing_zeros() as usize];
+        self.remove(block);
+        Some(block)
+    }
+
+    /// Frees the first `offset` bytes of a free block, returning the rest.
+    unsafe fn split_front(&mut self, block: *mut Block, offset: usize) -> *mut Block {
+        let rest = (block as *mut u8).add(offset) as *mut Block;
+        (*rest).size = Block::size(block) - offset;
+        Block::link_next(rest);
+        Block::set_size(block, offset - HEADER);
+        Block::mark_free(block);
+        self.insert(block);
+        rest
+    }
+
+    /// Frees the end of a block past `size` bytes, if big enough to be a
+    /// block of its own.
+    unsafe fn trim(&mut self, block: *mut Block, size: usize) {
+        if Block::size(block) >= size + HEADER + MIN_PAYLOAD {
+            let rest = Block::payload(block).add(size) as *mut Block;
+            (*rest).size = Block::size(block) - size - HEADER;
+            Block::set_size(block, size);
+            Block::link_next(block);
+            Block::mark_free(rest);
+            self.insert(rest);
+        }
+    }
+
+    unsafe fn allocate(&mut self, layout: Layout) -> Option<NonNull<[u8]>> {
+        if layout.size() > MAX_SIZE || layout.align() > MAX_SIZE {
+            return None;
+        }
+        let size = layout.size().max(MIN_PAYLOAD).div_ceil(ALIGN) * ALIGN;
+        let align = layout.align();
+        let gap = if align > ALIGN { align + HEADER + MIN_PAYLOAD } else { 0 };
+        let mut block = self.take(size + gap)?;
+
+        if align > ALIGN {
+            let payload = Block::payload(block) as usize;
+            let mut offset = payload.next_multiple_of(align) - payload;
+            if offset != 0 && offset < HEADER + MIN_PAYLOAD {
+                offset = (payload + HEADER + MIN_PAYLOAD).next_multiple_of(align) - payload;
+            }
+            if offset != 0 {
+                block = self.split_front(block, offset);
+            }
+        }
+        self.trim(block, size);
+        Block::mark_used(block);
+        let payload = NonNull::new_unchecked(Block::payload(block));
+        Some(NonNull::slice_from_raw_parts(payload, Block::size(block)))
+    }
+
+    unsafe fn deallocate(&mut self, ptr: *mut u8) {
+        let mut block = Block::from_payload(ptr);
+        Block::mark_free(block);
+        if Block::is_prev_free(block) {
+            let prev = (*block).prev_phys;
+            self.remove(prev);
+            Block::set_size(prev, Block::size(prev) + HEADER + Block::size(block));
+            Block::link_next(prev);
+            block = prev;
+        }
+        let next = Block::next_phys(block);
+        if Block::is_free(next) {
+            self.remove(next);
+            Block::set_size(block, Block::size(block) + HEADER + Block::size(next));
+            Block::link_next(block);
+        }
+        self.insert(block);
+    }
+
+    fn fragmentation(&self) -> Fragmentation {
+        let mut frag = Fragmentation::new();
+        frag.wasted = self.wasted;
+        for head in self.heads.iter().flatten() {
+            let mut block = *head;
+            while !block.is_null() {
+                unsafe {
+                    frag.add_free(Block::size(block), 1);
+                    block = (*block).next_free;
+                }
+            }
+        }
+        frag
+    }
+
+    /// Gives the whole pages inside free blocks back to the system, but the
+    /// first `retain` bytes of them. The pages are zeroed on next use.
+    #[cfg(feature = "os")]
+    fn decommit_free(&self, page: usize, mut retain: usize) -> ReleaseReport {
+        let mut report = ReleaseReport::default();
+        for head in self.heads.iter().flatten() {
+            let mut block = *head;
+            while !block.is_null() {
+                unsafe {
+                    // The free-list links must survive.
+                    let payload = Block::payload(block) as usize;
+                    let start = (payload + MIN_PAYLOAD).next_multiple_of(page);
+                    let end = (payload + Block::size(block)) / page * page;
+                    let kept = retain.min(end.saturating_sub(start)) / page * page;
+                    retain -= kept;
+                    report.retained += kept;
+                    let start = start + kept;
+                    if start < end {
+                        let advised =
+                            crate::sys::advise(start as *mut u8, end - start, Advice::DontNeed);
+                        if advised.is_ok() {
+                            report.released += end - start;
+                        }
+                    }
+                    block = (*block).next_free;
+                }
+            }
+        }
+        report
+    }
+}
+
+/// A Two-Level Segregated Fit allocator over a fixed region of memory, for
+/// real-time code that needs variable-size allocations in bounded time.
+/// Allocating and freeing take O(1) worst-case time: free blocks are kept in
+/// segregated lists found with two bitmap lookups, and neighbours are merged
+/// immediately on free.
+///
+/// The allocator implements `core::alloc::Allocator`, and is not `Sync`:
+/// share it between threads behind a lock, or give each thread its own.
+///
+/// # Example
+/// ```rust
+/// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// extern crate owned_alloc;
+///
+/// use core::{alloc::Layout, mem::MaybeUninit};
+/// use owned_alloc::{alloc_api::Allocator, Tlsf};
+///
+/// let mut region = [MaybeUninit::<u8>::uninit(); 4096];
+/// let tlsf = Tlsf::new(&mut region);
+///
+/// let layout = Layout::from_size_align(100, 64).unwrap();
+/// let block = tlsf.allocate(layout).unwrap();
+/// assert_eq!(block.cast::<u8>().as_ptr() as usize % 64, 0);
+/// unsafe { tlsf.deallocate(block.cast(), layout) };
+/// ```
+pub struct Tlsf<'r> {
+    control: UnsafeCell<Control>,
+    _region: PhantomData<&'r mut [MaybeUninit<u8>]>,
+}
+
+impl<'r> Tlsf<'r> {
+    /// Creates an allocator managing the given region. A region too small to
+    /// hold a single block yields an allocator which always fails.
+    #[inline]
+    pub fn new(region: &'r mut [MaybeUninit<u8>]) -> Self {
+        unsafe { Self::from_raw(NonNull::new_unchecked(region.as_mut_ptr().cast()), region.len()) }
+    }
+
+    /// Creates an allocator managing `len` bytes starting at `ptr`.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because the memory must be valid for reads
+    /// and writes and not used by anything else for the lifetime `'r`.
+    pub unsafe fn from_raw(ptr: NonNull<u8>, len: usize) -> Self {
+        let mut control = Control {
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            heads: [[ptr::null_mut(); SL_COUNT]; FL_COUNT],
+            wasted: 0,
+        };
+        let start = ptr.as_ptr() as usize;
+        let offset = start.next_multiple_of(ALIGN) - start;
+        let usable = len.saturating_sub(offset) / ALIGN * ALIGN;
+        // One block and the zero-sized sentinel block ending the region.
+        if usable >= 2 * HEADER + MIN_PAYLOAD {
+            let block = ptr.as_ptr().add(offset) as *mut Block;
+            (*block).prev_phys = ptr::null_mut();
+            (*block).size = usable - 2 * HEADER;
+            let sentinel = Block::link_next(block);
+            (*sentinel).size = 0;
+            Block::mark_free(block);
+            control.insert(block);
+        }
+        Self {
+            control: UnsafeCell::new(control),
+            _region: PhantomData,
+        }
+    }
+
+    /// Reports the free blocks of the region, and the padding of the used
+    /// ones. Takes time linear in the number of free blocks.
+    #[inline]
+    pub fn fragmentation(&self) -> Fragmentation {
+        unsafe { (*self.control.get()).fragmentation() }
+    }
+
+    /// Gives the whole pages inside free blocks back to the operating
+    /// system, returning how many bytes were given back. The region stays
+    /// reserved, and the pages come back zeroed when allocated again.
+    #[cfg(feature = "os")]
+    #[inline]
+    pub fn decommit_free(&self) -> usize {
+        self.release_free_memory(0).released
+    }
+}
+
+/// Free pages are decommitted; `retain` bytes of them are kept committed,
+/// rounded down to whole pages.
+#[cfg(feature = "os")]
+impl<'r> ReleaseFree for Tlsf<'r> {
+    #[inline]
+    fn release_free_memory(&self, retain: usize) -> ReleaseReport {
+        unsafe { (*self.control.get()).decommit_free(crate::page_size(), retain) }
+    }
+}
+
+unsafe impl<'r> crate::alloc_api::Allocator for Tlsf<'r> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+        let control = unsafe { &mut *self.control.get() };
+        let block = unsafe { control.allocate(layout).ok_or(AllocError)? };
+        control.wasted += block.len() - layout.size();
+        Ok(block)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            let control = &mut *self.control.get();
+            control.wasted -= Block::size(Block::from_payload(ptr.as_ptr())) - layout.size();
+            control.deallocate(ptr.as_ptr());
+        }
+    }
+}
+
+impl<'r> fmt::Debug for Tlsf<'r> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tlsf {{ control: {:p} }}", self.control.get())
+    }
+}
+
+unsafe impl<'r> Send for Tlsf<'r> {}
+
+#[cfg(test)]
+mod test {
+    use super::Tlsf;
+    use crate::alloc_api::Allocator;
+    use alloc::vec::Vec;
+    use core::{
+        alloc::Layout,
+        mem::MaybeUninit,
+    };
+
+    #[test]
+    fn free_blocks_are_merged() {
+        let mut region = alloc::vec![MaybeUninit::<u8>::uninit(); 1 << 16];
+        let tlsf = Tlsf::new(&mut region);
+        let whole = Layout::from_size_align(60_000, 8).unwrap();
+
+        let mut blocks = Vec::new();
+        for i in 1 .. 100 {
+            let layout = Layout::from_size_align(i * 5, 1 << (i % 8)).unwrap();
+            let block = tlsf.allocate(layout).unwrap();
+            assert_eq!(block.cast::<u8>().as_ptr() as usize % layout.align(), 0);
+            assert!(block.len() >= layout.size());
+            blocks.push((block, layout));
+        }
+        assert!(tlsf.allocate(whole).is_err());
+
+        let mut i = 0;
+        while !blocks.is_empty() {
+            i = (i + 37) % blocks.len();
+            let (block, layout) = blocks.swap_remove(i);
+            unsafe { tlsf.deallocate(block.cast(), layout) };
+        }
+        #[cfg(feature = "os")]
+        assert!(tlsf.decommit_free() >= (1 << 16) - 2 * crate::page_size());
+        assert!(tlsf.allocate(whole).is_ok());
+    }
+
+    #[test]
+    fn holes_are_reported() {
+        let mut region = alloc::vec![MaybeUninit::<u8>::uninit(); 4096];
+        let tlsf = Tlsf::new(&mut region);
+        let layout = Layout::from_size_align(250, 8).unwrap();
+        let blocks: Vec<_> = (0 .. 4).map(|_| tlsf.allocate(layout).unwrap()).collect();
+        let padding = blocks[0].len() - 250;
+        assert_eq!(tlsf.fragmentation().wasted, 4 * padding);
+
+        unsafe {
+            tlsf.deallocate(blocks[0].cast(), layout);
+            tlsf.deallocate(blocks[2].cast(), layout);
+        }
+        let frag = tlsf.fragmentation();
+        assert_eq!(frag.free_blocks, 3);
+        assert_eq!(frag.wasted, 2 * padding);
+        assert!(frag.largest_free > blocks[0].len());
+        assert!(frag.external() > 0.0);
+        assert_eq!(frag.histogram[blocks[0].len().ilog2() as usize], 2);
+    }
+
+    #[test]
+    fn tiny_region_always_fails() {
+        let mut region = [MaybeUninit::<u8>::uninit(); 8];
+        let tlsf = Tlsf::new(&mut region);
+        assert!(tlsf.allocate(Layout::new::<u8>()).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::Tag;
+use core::sync::atomic::{AtomicUsize, Ordering::*};
+
+/// Default size from which allocations and frees are traced.
+pub const DEFAULT_LARGE_ALLOCATION: usize = 64 * 1024;
+
+static LARGE_ALLOCATION: AtomicUsize = AtomicUsize::new(DEFAULT_LARGE_ALLOCATION);
+
+/// The size from which allocations and frees of the crate `Allocator` emit
+/// `tracing` events.
+///
+/// With the `tracing` feature, the crate emits `DEBUG` events with the
+/// `owned_alloc` target for:
+///
+/// - large allocations, with `size`, `address` and the current `tag`;
+/// - frees of large blocks, with `size` and `address`;
+/// - resets of `Arena`s and `Bump`s, with the `held` bytes kept for reuse;
+/// - cache evictions: a value replaced in a `Cache` (with its `kind`), a
+///   block a full `FreeListAlloc` list gives back (with its `size`), and the
+///   bytes a `BufferPool::trim` releases (as `size`).
+///
+/// Since they are emitted inside the current span, allocation storms show up
+/// under the requests causing them. The events are emitted while allocating,
+/// so the subscriber must not allocate through the crate `Allocator`.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::trace;
+///
+/// trace::set_large_allocation(1 << 20);
+/// assert_eq!(trace::large_allocation(), 1 << 20);
+/// ```
+#[inline]
+pub fn large_allocation() -> usize {
+    LARGE_ALLOCATION.load(Relaxed)
+}
+
+/// Changes the size from which allocations and frees are traced.
+#[inline]
+pub fn set_large_allocation(size: usize) {
+    LARGE_ALLOCATION.store(size, Relaxed);
+}
+
+/// Traces an allocation of `size` bytes at `ptr`, if large.
+#[inline]
+pub(crate) fn alloc(ptr: *const u8, size: usize) {
+    if size >= large_allocation() {
+        tracing::debug!(
+            target: "owned_alloc",
+            size,
+            address = ptr as usize,
+            tag = %Tag::current(),
+            "large allocation"
+        );
+    }
+}
+
+/// Traces a free of `size` bytes at `ptr`, if large.
+#[inline]
+pub(crate) fn free(ptr: *const u8, size: usize) {
+    if size >= large_allocation() {
+        tracing::debug!(target: "owned_alloc", size, address = ptr as usize, "large free");
+    }
+}
+
+/// Traces the reset of an arena of the given kind, holding `held` bytes.
+#[inline]
+pub(crate) fn reset(kind: &'static str, held: usize) {
+    tracing::debug!(target: "owned_alloc", kind, held, "arena reset");
+}
+
+/// Traces the eviction of a cached value of the given kind, of `size`
+/// bytes if known.
+#[inline]
+pub(crate) fn evict(kind: &'static str, size: Option<usize>) {
+    tracing::debug!(target: "owned_alloc", kind, size, "cache eviction");
+}
+
+#[cfg(test)]
+mod test {
+    use super::set_large_allocation;
+    use crate::{alloc_api::Allocator as _, Allocator, Bump, Cache};
+    use alloc::{format, string::String};
+    use core::{
+        alloc::Layout,
+        fmt,
+        sync::atomic::{AtomicUsize, Ordering::*},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Dispatch, Event, Metadata, Subscriber,
+    };
+
+    const SIZE: u64 = 100_003;
+    const HELD: u64 = 4_099;
+
+    struct Evicted;
+
+    /// Counts the events of this test, told apart from the events of other
+    /// tests by their fields.
+    struct Counter {
+        allocs: AtomicUsize,
+        frees: AtomicUsize,
+        resets: AtomicUsize,
+        evictions: AtomicUsize,
+    }
+
+    #[derive(Default)]
+    struct Fields {
+        message: String,
+        kind: String,
+        size: Option<u64>,
+        held: Option<u64>,
+    }
+
+    impl Visit for Fields {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            match field.name() {
+                "size" => self.size = Some(value),
+                "held" => self.held = Some(value),
+                _ => (),
+            }
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "kind" {
+                self.kind = value.into();
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.message = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for &'static Counter {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let counter = match &*fields.message {
+                "large allocation" if fields.size == Some(SIZE) => &self.allocs,
+                "large free" if fields.size == Some(SIZE) => &self.frees,
+                "arena reset" if fields.held == Some(HELD) => &self.resets,
+                "cache eviction" if fields.kind.ends_with("Evicted") => &self.evictions,
+                _ => return,
+            };
+            counter.fetch_add(1, Relaxed);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    static COUNTER: Counter = Counter {
+        allocs: AtomicUsize::new(0),
+        frees: AtomicUsize::new(0),
+        resets: AtomicUsize::new(0),
+        evictions: AtomicUsize::new(0),
+    };
+
+    #[test]
+    fn lifecycle_events_are_emitted() {
+        tracing::dispatcher::set_global_default(Dispatch::new(&COUNTER)).unwrap();
+        set_large_allocation(SIZE as usize);
+
+        let layout = Layout::from_size_align(SIZE as usize, 8).unwrap();
+        let block = Allocator::new().allocate(layout).unwrap();
+        unsafe { crate::alloc_api::Allocator::deallocate(&Allocator::new(), block.cast(), layout) };
+
+        let mut bump = Bump::with_chunk_size(HELD as usize);
+        bump.alloc(0u32);
+        assert_eq!(bump.capacity(), HELD as usize);
+        bump.reset();
+
+        let mut cache = Cache::new();
+        cache.store(Evicted);
+        cache.store(Evicted);
+
+        assert_eq!(COUNTER.allocs.load(Relaxed), 1);
+        assert_eq!(COUNTER.frees.load(Relaxed), 1);
+        assert_eq!(COUNTER.resets.load(Relaxed), 1);
+        assert_eq!(COUNTER.evictions.load(Relaxed), 1);
+    }
+}
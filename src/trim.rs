@@ -0,0 +1,353 @@
+#[cfg(all(feature = "os", feature = "std"))]
+use crate::OsError;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    fmt, hint,
+    ops::AddAssign,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+type Handler = Box<dyn Fn() -> usize + Send + Sync>;
+
+/// What a call to `ReleaseFree::release_free_memory` did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReleaseReport {
+    /// Bytes given back to the backend or to the operating system.
+    pub released: usize,
+    /// Free bytes kept to honour the retention floor.
+    pub retained: usize,
+}
+
+impl AddAssign for ReleaseReport {
+    #[inline]
+    fn add_assign(&mut self, other: Self) {
+        self.released += other.released;
+        self.retained += other.retained;
+    }
+}
+
+/// Allocators holding on to free memory (free lists, caches, heaps over a
+/// committed region) that can give it back, like `malloc_trim`.
+pub trait ReleaseFree {
+    /// Gives the free memory held beyond `retain` bytes back to the backend,
+    /// or decommits it, and reports how many bytes were released. Keeping a
+    /// floor spares the next burst of allocations from going to the backend
+    /// all over again; a floor of zero releases everything possible.
+    ///
+    /// # Example
+    /// ```rust
+    /// #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// extern crate owned_alloc;
+    ///
+    /// use core::alloc::Layout;
+    /// use owned_alloc::{alloc_api::Allocator as _, Allocator, FreeListAlloc, ReleaseFree};
+    ///
+    /// let alloc = FreeListAlloc::new(Allocator::new());
+    /// let layout = Layout::new::<[u8; 64]>();
+    /// let blocks: Vec<_> = (0 .. 4).map(|_| alloc.allocate(layout).unwrap()).collect();
+    /// for block in blocks {
+    ///     unsafe { alloc.deallocate(block.cast(), layout) };
+    /// }
+    ///
+    /// let report = alloc.release_free_memory(100);
+    /// assert_eq!((report.released, report.retained), (192, 64));
+    /// assert_eq!(alloc.cached(), 1);
+    /// ```
+    fn release_free_memory(&self, retain: usize) -> ReleaseReport;
+}
+
+/// A set of trim handlers: callbacks releasing memory held by caches, pools
+/// and arenas, invoked together when memory runs short.
+///
+/// Handlers run one after the other, with the registry locked. They must not
+/// register or unregister handlers of the same registry, or they deadlock.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::TrimRegistry;
+///
+/// let registry = TrimRegistry::new();
+/// let handle = registry.register(|| 4096);
+/// assert_eq!(registry.trim(), 4096);
+///
+/// drop(handle);
+/// assert_eq!(registry.trim(), 0);
+/// ```
+pub struct TrimRegistry {
+    locked: AtomicBool,
+    next_id: AtomicUsize,
+    handlers: UnsafeCell<Vec<(usize, Handler)>>,
+}
+
+impl TrimRegistry {
+    /// Creates a registry with no handler.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            next_id: AtomicUsize::new(0),
+            handlers: UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    /// Registers a handler releasing memory and returning how many bytes it
+    /// released. The handler is unregistered when the returned handle is
+    /// dropped.
+    pub fn register<F>(&self, handler: F) -> TrimHandle<'_>
+    where
+        F: Fn() -> usize + Send + Sync + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.with_handlers(|handlers| handlers.push((id, Box::new(handler))));
+        TrimHandle { registry: self, id }
+    }
+
+    /// Runs every handler, returning the total of bytes released.
+    #[inline]
+    pub fn trim(&self) -> usize {
+        self.with_handlers(|handlers| handlers.iter().map(|(_, handler)| handler()).sum())
+    }
+
+    /// Number of registered handlers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.with_handlers(|handlers| handlers.len())
+    }
+
+    /// Tests if no handler is registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn with_handlers<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Vec<(usize, Handler)>) -> R,
+    {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let _unlock = Unlock(&self.locked);
+        f(unsafe { &mut *self.handlers.get() })
+    }
+}
+
+/// Releases the lock of a registry when dropped, even if a handler panics.
+struct Unlock<'r>(&'r AtomicBool);
+
+impl<'r> Drop for Unlock<'r> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+unsafe impl Send for TrimRegistry {}
+unsafe impl Sync for TrimRegistry {}
+
+impl Default for TrimRegistry {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TrimRegistry {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TrimRegistry {{ handlers: {} }}", self.len())
+    }
+}
+
+/// A registered trim handler. Dropping it unregisters the handler.
+pub struct TrimHandle<'r> {
+    registry: &'r TrimRegistry,
+    id: usize,
+}
+
+impl<'r> Drop for TrimHandle<'r> {
+    #[inline]
+    fn drop(&mut self) {
+        let id = self.id;
+        self.registry
+            .with_handlers(|handlers| handlers.retain(|(handler, _)| *handler != id));
+    }
+}
+
+impl<'r> fmt::Debug for TrimHandle<'r> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TrimHandle {{ id: {} }}", self.id)
+    }
+}
+
+static GLOBAL: TrimRegistry = TrimRegistry::new();
+
+/// Registers a handler in the global registry, run by `trim_all`. See
+/// `TrimRegistry::register`.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{register_trim, trim_all, Bump};
+/// use std::sync::{Arc, Mutex};
+///
+/// let bump = Arc::new(Mutex::new(Bump::with_chunk_size(64)));
+/// let handle = register_trim({
+///     let bump = bump.clone();
+///     move || bump.lock().unwrap().trim()
+/// });
+///
+/// {
+///     let mut bump = bump.lock().unwrap();
+///     bump.alloc(1u8);
+///     bump.alloc([0u8; 100]);
+///     bump.reset();
+/// }
+/// assert_eq!(trim_all(), 100);
+/// assert_eq!(bump.lock().unwrap().capacity(), 64);
+/// # drop(handle);
+/// ```
+#[inline]
+pub fn register_trim<F>(handler: F) -> TrimHandle<'static>
+where
+    F: Fn() -> usize + Send + Sync + 'static,
+{
+    GLOBAL.register(handler)
+}
+
+/// Runs every handler of the global registry, returning the total of bytes
+/// released.
+#[inline]
+pub fn trim_all() -> usize {
+    GLOBAL.trim()
+}
+
+/// Releases as much cached memory as possible, returning how many bytes were
+/// released, so that a long-lived program can give memory back after a burst
+/// of allocations without restarting.
+///
+/// It runs the handlers of the global registry (where caches and pools
+/// register their flushing and shrinking to a floor), frees the scratch
+/// stack of the calling thread if unused, and, with the `os` feature, gives
+/// the free pages of the heap installed with `init_heap` back to the system
+/// and asks the C library to trim its own heap. Bytes released by the C
+/// library are not counted.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{register_trim, trim, Pool};
+/// use std::sync::Arc;
+///
+/// let pool = Arc::new(Pool::new(|| vec![0u8; 1024]));
+/// drop((pool.try_acquire(), pool.try_acquire(), pool.try_acquire()));
+/// assert_eq!(pool.idle(), 3);
+///
+/// let handle = register_trim({
+///     let pool = pool.clone();
+///     move || pool.shrink_to(1) * 1024
+/// });
+/// assert!(trim() >= 2048);
+/// assert_eq!(pool.idle(), 1);
+/// # drop(handle);
+/// ```
+pub fn trim() -> usize {
+    let released = trim_all() + crate::scratch::release_local();
+    #[cfg(feature = "os")]
+    let released = {
+        crate::sys::trim_heap();
+        released + crate::heap::installed().map_or(0, |heap| heap.decommit_free())
+    };
+    released
+}
+
+/// Starts a background thread calling `trim_all` whenever the operating
+/// system reports memory pressure: a memory stall notification of the
+/// pressure stall information interface on Linux and Android, or a low
+/// memory notification on Windows. Elsewhere, an error is returned.
+#[cfg(all(feature = "os", feature = "std"))]
+pub fn watch_memory_pressure() -> Result<(), OsError> {
+    let watcher = crate::sys::PressureWatcher::new()?;
+    let spawned = std::thread::Builder::new()
+        .name("owned-alloc-trim".into())
+        .spawn(move || {
+            while watcher.wait() {
+                trim_all();
+                // Windows keeps the notification signaled as long as memory
+                // stays low.
+                std::thread::sleep(core::time::Duration::from_secs(1));
+            }
+        });
+    match spawned {
+        Ok(_) => Ok(()),
+        Err(err) => Err(OsError {
+            code: err.raw_os_error().unwrap_or(0),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TrimRegistry;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn handles_unregister_on_drop() {
+        let registry = TrimRegistry::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let first = registry.register({
+            let calls = calls.clone();
+            move || {
+                calls.fetch_add(1, Ordering::Relaxed);
+                10
+            }
+        });
+        let second = registry.register(|| 5);
+        assert_eq!(registry.trim(), 15);
+
+        drop(first);
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.trim(), 5);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        drop(second);
+        assert!(registry.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn panicking_handler_unlocks() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let registry = TrimRegistry::new();
+        let handle = registry.register(|| panic!("handler failed"));
+        assert!(catch_unwind(AssertUnwindSafe(|| registry.trim())).is_err());
+        drop(handle);
+        assert!(registry.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn trim_frees_the_scratch_stack() {
+        use crate::{with_scratch, SCRATCH_SIZE};
+
+        let handle = super::register_trim(|| 7);
+        let byte = with_scratch::<u8, _, _>(16, |buf| {
+            assert!(super::trim() >= 7);
+            *buf[15].write(3)
+        });
+        assert_eq!(byte, 3);
+        assert!(super::trim() >= SCRATCH_SIZE + 7);
+        drop(handle);
+    }
+}
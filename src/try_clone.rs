@@ -0,0 +1,114 @@
+use crate::{AllocError, OwnedAlloc, RawVec, RawVecError, UninitAlloc};
+
+/// Cloning which reports allocation errors instead of calling
+/// `handle_alloc_error`, for targets where running out of memory must be
+/// recoverable. Every `Copy` type is `TryClone`, so `OwnedAlloc`s of plain
+/// values, and of other `OwnedAlloc`s, can be cloned without ever aborting.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{Allocator, LimitedAlloc, OwnedAlloc, TryClone};
+///
+/// let limited = LimitedAlloc::new(Allocator::new(), 16);
+/// let sample = OwnedAlloc::new_in([0u32; 3], &limited);
+/// assert!(sample.try_clone().is_err());
+///
+/// let tree = OwnedAlloc::new(OwnedAlloc::new(7u8));
+/// assert_eq!(**tree.try_clone().unwrap(), 7);
+/// ```
+pub trait TryClone: Sized {
+    /// Clones the value. In case of allocation error, `Err` is returned.
+    fn try_clone(&self) -> Result<Self, AllocError>;
+}
+
+impl<T> TryClone for T
+where
+    T: Copy,
+{
+    #[inline]
+    fn try_clone(&self) -> Result<Self, AllocError> {
+        Ok(*self)
+    }
+}
+
+impl<T, A> TryClone for OwnedAlloc<T, A>
+where
+    T: TryClone,
+    A: crate::alloc_api::Allocator + Clone,
+{
+    #[inline]
+    fn try_clone(&self) -> Result<Self, AllocError> {
+        let alloc = UninitAlloc::try_new_in(self.allocator().clone())?;
+        Ok(alloc.init((**self).try_clone()?))
+    }
+}
+
+impl<T, A> TryClone for OwnedAlloc<[T], A>
+where
+    T: Copy,
+    A: crate::alloc_api::Allocator + Clone,
+{
+    #[inline]
+    fn try_clone(&self) -> Result<Self, AllocError> {
+        let alloc = UninitAlloc::try_new_slice_in(self.len(), self.allocator().clone())
+            .map_err(alloc_error)?;
+        Ok(alloc.init_from_slice(self))
+    }
+}
+
+/// Creates another uninitialized allocation in the same allocator: nothing
+/// is copied, as the contents are not known to be initialized.
+impl<T, A> TryClone for UninitAlloc<T, A>
+where
+    A: crate::alloc_api::Allocator + Clone,
+{
+    #[inline]
+    fn try_clone(&self) -> Result<Self, AllocError> {
+        Self::try_new_in(self.allocator().clone())
+    }
+}
+
+/// Creates another `RawVec` of the same capacity in the same allocator:
+/// nothing is copied, as the contents are not known to be initialized.
+impl<T, A> TryClone for RawVec<T, A>
+where
+    A: crate::alloc_api::Allocator + Clone,
+{
+    #[inline]
+    fn try_clone(&self) -> Result<Self, AllocError> {
+        Self::try_with_capacity_in(self.cap(), self.allocator().clone()).map_err(alloc_error)
+    }
+}
+
+#[inline]
+fn alloc_error(err: RawVecError) -> AllocError {
+    match err {
+        RawVecError::Alloc(err) => err,
+        RawVecError::Layout(_) => unreachable!("the layout of an existing allocation overflowed"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TryClone;
+    use crate::{Allocator, LimitedAlloc, OwnedAlloc, RawVec, UninitAlloc};
+
+    #[test]
+    fn clones_stay_in_the_allocator() {
+        let limited = LimitedAlloc::new(Allocator::new(), 64);
+        let raw = RawVec::<u64, _>::with_capacity_in(4, &limited);
+        let copy = raw.try_clone().unwrap();
+        assert_eq!((copy.cap(), limited.used()), (4, 64));
+        assert!(copy.try_clone().is_err());
+        drop((raw, copy));
+
+        let uninit = UninitAlloc::<[u64; 4], _>::new_in(&limited);
+        let slice = OwnedAlloc::collect_slice(0 .. 3u16);
+        let slice_copy = slice.try_clone().unwrap();
+        assert_eq!(*slice_copy, [0, 1, 2]);
+        assert!(uninit.try_clone().is_ok());
+        assert_eq!(limited.used(), 32);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{AllocError, ALLOCATOR};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::atomic::{fence, AtomicUsize, Ordering},
+};
+
+/// Moves the values of a tuple into a single allocation, and returns a tuple
+/// of `TuplePart`s owning them. In case of allocation error, the macro
+/// panics. Tuples of up to 8 values are supported.
+///
+/// # Example
+/// ```rust
+/// #[macro_use]
+/// extern crate owned_alloc;
+///
+/// fn main() {
+///     let (mut header, name, body) = alloc_tuple!((7u32, String::from("node"), [0u8; 64]));
+///     *header += 1;
+///     assert_eq!(*header, 8);
+///     assert_eq!(*name, "node");
+///
+///     // The block is freed with its last part.
+///     drop(name);
+///     drop(header);
+///     assert_eq!(body.len(), 64);
+/// }
+/// ```
+#[macro_export]
+#[cfg_attr(feature = "panic-free", deprecated(note = "panics on allocation failure or overflow"))]
+macro_rules! alloc_tuple {
+    ($tuple:expr) => {
+        $crate::AllocTuple::alloc_tuple($tuple)
+    };
+}
+
+/// Bookkeeping at the start of the block holding the values of a tuple.
+struct Header {
+    parts: AtomicUsize,
+    layout: Layout,
+}
+
+/// Allocates a block holding a header followed by values of the given
+/// layouts, returning the block and the offset of each value in it.
+fn alloc_block<const N: usize>(
+    layouts: [Layout; N],
+) -> Result<(NonNull<Header>, [usize; N]), AllocError> {
+    let mut layout = Layout::new::<Header>();
+    let mut offsets = [0; N];
+    for (offset, part) in offsets.iter_mut().zip(layouts) {
+        let (extended, at) = layout.extend(part).map_err(|_| AllocError { layout: part })?;
+        layout = extended;
+        *offset = at;
+    }
+    let layout = layout.pad_to_align();
+    let block = NonNull::new(unsafe { ALLOCATOR.alloc(layout) }).ok_or(AllocError { layout })?;
+    let header = block.cast::<Header>();
+    unsafe {
+        header.as_ptr().write(Header {
+            parts: AtomicUsize::new(N),
+            layout,
+        })
+    };
+    Ok((header, offsets))
+}
+
+/// A value of a tuple moved into a single allocation by `alloc_tuple!`. It
+/// behaves like an `OwnedAlloc`, but the memory is shared with the other
+/// values of the tuple, and freed when the last of their parts is dropped.
+pub struct TuplePart<T> {
+    ptr: NonNull<T>,
+    header: NonNull<Header>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> TuplePart<T> {
+    /// Moves `value` to `offset` in the block.
+    #[inline]
+    unsafe fn new(header: NonNull<Header>, offset: usize, value: T) -> Self {
+        let ptr = header.cast::<u8>().as_ptr().add(offset).cast::<T>();
+        ptr.write(value);
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            header,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The raw non-null pointer to the value.
+    #[inline]
+    pub const fn raw(&self) -> NonNull<T> {
+        self.ptr
+    }
+
+    /// Moves the value out, freeing the block if this was its last part.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        let value = unsafe { self.ptr.as_ptr().read() };
+        unsafe { Self::release(self.header) };
+        mem::forget(self);
+        value
+    }
+
+    /// Gives a part of the block up, freeing it if it was the last one.
+    #[inline]
+    unsafe fn release(header: NonNull<Header>) {
+        let header = header.as_ptr();
+        if (*header).parts.fetch_sub(1, Ordering::Release) == 1 {
+            fence(Ordering::Acquire);
+            ALLOCATOR.dealloc(header.cast(), (*header).layout);
+        }
+    }
+}
+
+impl<T> Deref for TuplePart<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for TuplePart<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for TuplePart<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            self.ptr.as_ptr().drop_in_place();
+            Self::release(self.header);
+        }
+    }
+}
+
+impl<T> fmt::Debug for TuplePart<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TuplePart({:?})", self.ptr)
+    }
+}
+
+unsafe impl<T> Send for TuplePart<T> where T: Send {}
+unsafe impl<T> Sync for TuplePart<T> where T: Sync {}
+
+/// Tuples whose values can be moved into a single allocation. Used by
+/// `alloc_tuple!`.
+pub trait AllocTuple: Sized {
+    /// The tuple of parts owning the values.
+    type Parts;
+
+    panicking! {
+        /// Moves the values into a single allocation. In case of allocation
+        /// error, the function panics.
+        #[inline]
+        fn alloc_tuple(self) -> Self::Parts {
+            match self.try_alloc_tuple() {
+                Ok(parts) => parts,
+                Err((err, _)) => panic!("{}", err),
+            }
+        }
+    }
+
+    /// Moves the values into a single allocation. In case of allocation
+    /// error, the tuple is given back.
+    fn try_alloc_tuple(self) -> Result<Self::Parts, (AllocError, Self)>;
+}
+
+macro_rules! alloc_tuple_impl {
+    ($($ty:ident $value:ident),+) => {
+        impl<$($ty),+> AllocTuple for ($($ty,)+) {
+            type Parts = ($(TuplePart<$ty>,)+);
+
+            fn try_alloc_tuple(self) -> Result<Self::Parts, (AllocError, Self)> {
+                let (header, offsets) = match alloc_block([$(Layout::new::<$ty>()),+]) {
+                    Ok(block) => block,
+                    Err(err) => return Err((err, self)),
+                };
+                let ($($value,)+) = self;
+                let mut offsets = offsets.into_iter();
+                Ok(($(unsafe { TuplePart::new(header, offsets.next().unwrap(), $value) },)+))
+            }
+        }
+    };
+}
+
+alloc_tuple_impl!(A a);
+alloc_tuple_impl!(A a, B b);
+alloc_tuple_impl!(A a, B b, C c);
+alloc_tuple_impl!(A a, B b, C c, D d);
+alloc_tuple_impl!(A a, B b, C c, D d, E e);
+alloc_tuple_impl!(A a, B b, C c, D d, E e, F f);
+alloc_tuple_impl!(A a, B b, C c, D d, E e, F f, G g);
+alloc_tuple_impl!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+#[cfg(test)]
+mod test {
+    use super::AllocTuple;
+    use alloc::rc::Rc;
+
+    #[test]
+    fn parts_share_one_block() {
+        let tracker = Rc::new(());
+        let (a, b, c) = (1u8, tracker.clone(), [3u64; 4]).alloc_tuple();
+        let (a_addr, c_addr) = (a.raw().as_ptr() as usize, c.raw().as_ptr() as usize);
+        assert!(a_addr.abs_diff(c_addr) < 64);
+        assert_eq!(c.raw().as_ptr() as usize % 8, 0);
+
+        assert_eq!(a.into_inner(), 1);
+        drop(c);
+        assert_eq!(Rc::strong_count(&tracker), 2);
+        drop(b);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
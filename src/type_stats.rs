@@ -0,0 +1,312 @@
+use crate::OwnedAlloc;
+use core::{
+    any::{self, TypeId},
+    cell::UnsafeCell,
+    fmt,
+    hint,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+
+/// Maximum number of distinct types tracked. Allocations of types beyond
+/// this limit are accounted to an entry named `OTHER_TYPES`.
+pub const MAX_TYPES: usize = 64;
+
+/// Name of the entry of the types beyond `MAX_TYPES`.
+pub const OTHER_TYPES: &str = "(other types)";
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+static REGISTRY: Registry = Registry::new();
+
+/// Live and peak memory of one type, as yielded by `type_report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeStats {
+    /// Name of the type, as given by `type_name`.
+    pub name: &'static str,
+    /// Number of allocations currently alive.
+    pub live_count: usize,
+    /// Bytes currently allocated.
+    pub live_bytes: usize,
+    /// Highest number of allocations alive at once.
+    pub peak_count: usize,
+    /// Highest number of bytes allocated at once.
+    pub peak_bytes: usize,
+}
+
+/// Statistics of the type `T`, all zero if it was never tracked.
+#[inline]
+pub fn type_stats<T>() -> TypeStats
+where
+    T: ?Sized + 'static,
+{
+    match REGISTRY.find(TypeId::of::<T>()) {
+        Some(index) => REGISTRY.stats(index),
+        None => TypeStats {
+            name: any::type_name::<T>(),
+            live_count: 0,
+            live_bytes: 0,
+            peak_count: 0,
+            peak_bytes: 0,
+        },
+    }
+}
+
+/// Reports statistics of every type tracked so far, in registration order,
+/// followed by the entry of the types beyond `MAX_TYPES` if it was used.
+#[inline]
+pub fn type_report() -> impl Iterator<Item = TypeStats> {
+    let other = Some(REGISTRY.stats(0)).filter(|stats| stats.peak_count != 0);
+    (1 .. MAX_TYPES)
+        .take_while(|&index| REGISTRY.entries[index].state.load(Ordering::Acquire) == READY)
+        .map(|index| REGISTRY.stats(index))
+        .chain(other)
+}
+
+/// Lowers the peaks of every type to their current live values, to measure
+/// the peaks of a new phase of the program.
+#[inline]
+pub fn reset_type_peaks() {
+    for entry in &REGISTRY.entries {
+        entry
+            .peak_count
+            .store(entry.live_count.load(Ordering::Relaxed), Ordering::Relaxed);
+        entry
+            .peak_bytes
+            .store(entry.live_bytes.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+/// An `OwnedAlloc` accounted to the statistics of its type for as long as it
+/// is alive. Tracking is opt-in: only allocations made through this type are
+/// counted, so the hot paths of the program can be tracked selectively.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::{type_stats, TypeTracked};
+///
+/// struct Edge([u32; 4]);
+///
+/// let edges: Vec<_> = (0 .. 3).map(|_| TypeTracked::new(Edge([0; 4]))).collect();
+/// drop(edges);
+/// let kept = TypeTracked::new(Edge([1; 4]));
+///
+/// let stats = type_stats::<Edge>();
+/// assert_eq!((stats.live_count, stats.live_bytes), (1, 16));
+/// assert_eq!((stats.peak_count, stats.peak_bytes), (3, 48));
+/// # drop(kept);
+/// ```
+pub struct TypeTracked<T>
+where
+    T: ?Sized + 'static,
+{
+    alloc: OwnedAlloc<T>,
+    index: usize,
+}
+
+impl<T> TypeTracked<T> {
+    /// Allocates `value`, accounting it to `T`.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self::from_owned(OwnedAlloc::new(value))
+    }
+}
+
+impl<T> TypeTracked<T>
+where
+    T: ?Sized + 'static,
+{
+    /// Accounts an existing allocation to `T`.
+    #[inline]
+    pub fn from_owned(alloc: OwnedAlloc<T>) -> Self {
+        let index = REGISTRY.index(TypeId::of::<T>(), any::type_name::<T>());
+        REGISTRY.charge(index, mem::size_of_val(&*alloc));
+        Self { alloc, index }
+    }
+
+    /// Stops accounting the allocation and returns it.
+    #[inline]
+    pub fn into_owned(self) -> OwnedAlloc<T> {
+        REGISTRY.release(self.index, mem::size_of_val(&*self.alloc));
+        let alloc = unsafe { (&self.alloc as *const OwnedAlloc<T>).read() };
+        mem::forget(self);
+        alloc
+    }
+}
+
+impl<T> Deref for TypeTracked<T>
+where
+    T: ?Sized + 'static,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.alloc
+    }
+}
+
+impl<T> DerefMut for TypeTracked<T>
+where
+    T: ?Sized + 'static,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.alloc
+    }
+}
+
+impl<T> Drop for TypeTracked<T>
+where
+    T: ?Sized + 'static,
+{
+    #[inline]
+    fn drop(&mut self) {
+        REGISTRY.release(self.index, mem::size_of_val(&*self.alloc));
+    }
+}
+
+impl<T> fmt::Debug for TypeTracked<T>
+where
+    T: ?Sized + 'static,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TypeTracked({:?}, {})", self.alloc.raw(), any::type_name::<T>())
+    }
+}
+
+struct Entry {
+    state: AtomicU8,
+    key: UnsafeCell<MaybeUninit<(TypeId, &'static str)>>,
+    live_count: AtomicUsize,
+    live_bytes: AtomicUsize,
+    peak_count: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+impl Entry {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Entry = Entry {
+        state: AtomicU8::new(EMPTY),
+        key: UnsafeCell::new(MaybeUninit::uninit()),
+        live_count: AtomicUsize::new(0),
+        live_bytes: AtomicUsize::new(0),
+        peak_count: AtomicUsize::new(0),
+        peak_bytes: AtomicUsize::new(0),
+    };
+}
+
+/// Types are registered in the first empty entry after the first one, which
+/// is kept for the types beyond `MAX_TYPES`, just like tags.
+struct Registry {
+    entries: [Entry; MAX_TYPES],
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            entries: [Entry::EMPTY; MAX_TYPES],
+        }
+    }
+
+    fn find(&self, id: TypeId) -> Option<usize> {
+        for (index, entry) in self.entries.iter().enumerate().skip(1) {
+            loop {
+                match entry.state.load(Ordering::Acquire) {
+                    READY if self.key(index).0 == id => return Some(index),
+                    READY => break,
+                    WRITING => hint::spin_loop(),
+                    _ => return None,
+                }
+            }
+        }
+        None
+    }
+
+    fn index(&self, id: TypeId, name: &'static str) -> usize {
+        for (index, entry) in self.entries.iter().enumerate().skip(1) {
+            loop {
+                match entry.state.load(Ordering::Acquire) {
+                    READY if self.key(index).0 == id => return index,
+                    READY => break,
+                    WRITING => hint::spin_loop(),
+                    _ => {
+                        let claimed = entry
+                            .state
+                            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Acquire)
+                            .is_ok();
+                        if claimed {
+                            unsafe { (*entry.key.get()).write((id, name)) };
+                            entry.state.store(READY, Ordering::Release);
+                            return index;
+                        }
+                    },
+                }
+            }
+        }
+        0
+    }
+
+    #[inline]
+    fn key(&self, index: usize) -> (TypeId, &'static str) {
+        unsafe { (*self.entries[index].key.get()).assume_init() }
+    }
+
+    #[inline]
+    fn stats(&self, index: usize) -> TypeStats {
+        let entry = &self.entries[index];
+        TypeStats {
+            name: if index == 0 { OTHER_TYPES } else { self.key(index).1 },
+            live_count: entry.live_count.load(Ordering::Relaxed),
+            live_bytes: entry.live_bytes.load(Ordering::Relaxed),
+            peak_count: entry.peak_count.load(Ordering::Relaxed),
+            peak_bytes: entry.peak_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    #[inline]
+    fn charge(&self, index: usize, bytes: usize) {
+        let entry = &self.entries[index];
+        let count = entry.live_count.fetch_add(1, Ordering::Relaxed) + 1;
+        entry.peak_count.fetch_max(count, Ordering::Relaxed);
+        let bytes = entry.live_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        entry.peak_bytes.fetch_max(bytes, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn release(&self, index: usize, bytes: usize) {
+        let entry = &self.entries[index];
+        entry.live_count.fetch_sub(1, Ordering::Relaxed);
+        entry.live_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+unsafe impl Sync for Registry {}
+
+#[cfg(test)]
+mod test {
+    use super::{type_report, type_stats, TypeTracked};
+    use crate::OwnedAlloc;
+
+    #[test]
+    fn peaks_outlive_frees() {
+        struct Node(#[allow(dead_code)] u64);
+
+        let nodes: alloc::vec::Vec<_> = (0 .. 4).map(|i| TypeTracked::new(Node(i))).collect();
+        let slice = TypeTracked::from_owned(OwnedAlloc::collect_slice(0u8 .. 10));
+        drop(nodes);
+
+        let stats = type_stats::<Node>();
+        assert_eq!((stats.live_count, stats.peak_count, stats.peak_bytes), (0, 4, 32));
+        let slices = type_report().find(|stats| stats.name == "[u8]").unwrap();
+        assert_eq!(slices.live_bytes, 10);
+        assert_eq!(slice.into_owned().len(), 10);
+        assert_eq!(type_stats::<[u8]>().live_bytes, 0);
+    }
+}
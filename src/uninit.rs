@@ -2,33 +2,173 @@ use core::{
     alloc::{GlobalAlloc, Layout},
     marker::PhantomData,
     mem,
-    ptr::NonNull,
+    ptr::{self, NonNull},
+    str::{self, Utf8Error},
 };
 
-use crate::{AllocError, OwnedAlloc, RawVec, ALLOCATOR};
+use crate::{AllocError, Allocator, OwnedAlloc, RawVec, RawVecError, ALLOCATOR};
 
-pub struct UninitAlloc<T>
+/// Constructs a struct field by field directly inside an `UninitAlloc`,
+/// returning the initialized `OwnedAlloc`, so that the struct as a whole is
+/// never materialized on the stack first, as it would be with `init`. Each
+/// field is written in place, in the given order; a field of struct type can
+/// itself be built in place with `field: in Type { ... }`. Structs are named
+/// without their generic arguments, which are inferred. Missing or
+/// duplicated fields are rejected at compile time.
+///
+/// If a field expression panics, the allocation is freed, but the fields
+/// already written are leaked.
+///
+/// # Example
+/// ```rust
+/// #[macro_use]
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::UninitAlloc;
+///
+/// struct Header {
+///     id: u32,
+///     len: usize,
+/// }
+///
+/// struct Frame {
+///     header: Header,
+///     payload: [u8; 16384],
+///     checksum: u64,
+/// }
+///
+/// fn main() {
+///     let frame = emplace!(UninitAlloc::<Frame>::new() => Frame {
+///         header: in Header { id: 7, len: 3 },
+///         payload: [0; 16384],
+///         checksum: 0xfeed,
+///     });
+///     assert_eq!((frame.header.id, frame.header.len), (7, 3));
+///     assert_eq!(frame.payload.iter().max(), Some(&0));
+///     assert_eq!(frame.checksum, 0xfeed);
+/// }
+/// ```
+///
+/// Every field must be given:
+/// ```rust,compile_fail
+/// #[macro_use]
+/// extern crate owned_alloc;
+///
+/// struct Pair {
+///     first: u64,
+///     second: u64,
+/// }
+///
+/// fn main() {
+///     let pair = emplace!(owned_alloc::UninitAlloc::<Pair>::new() => Pair { first: 1 });
+/// }
+/// ```
+#[macro_export]
+macro_rules! emplace {
+    ($alloc:expr => $($ty:ident)::+ { $($fields:tt)* }) => {{
+        let alloc: $crate::UninitAlloc<_> = $alloc;
+        let ptr = alloc.raw().as_ptr();
+        $crate::emplace!(@check ptr; $($ty)::+; []; $($fields)*);
+        $crate::emplace!(@fields ptr; $($fields)*);
+        unsafe { $crate::OwnedAlloc::from_raw(alloc.into_raw()) }
+    }};
+
+    (@check $ptr:ident; $($ty:ident)::+; [$($done:ident)*];) => {
+        // Never run: gives its type to the pointer, and fails to compile if
+        // a field is missing or given twice.
+        #[allow(unreachable_code)]
+        if false {
+            unsafe { ::core::ptr::write($ptr, $($ty)::+ { $($done: unreachable!()),* }) };
+        }
+    };
+
+    (
+        @check $ptr:ident; $($ty:ident)::+; [$($done:ident)*];
+        $field:ident: in $($inner:ident)::+ { $($fields:tt)* } $(, $($rest:tt)*)?
+    ) => {
+        $crate::emplace!(@check $ptr; $($ty)::+; [$($done)* $field]; $($($rest)*)?);
+    };
+
+    (
+        @check $ptr:ident; $($ty:ident)::+; [$($done:ident)*];
+        $field:ident: $value:expr $(, $($rest:tt)*)?
+    ) => {
+        $crate::emplace!(@check $ptr; $($ty)::+; [$($done)* $field]; $($($rest)*)?);
+    };
+
+    (@fields $ptr:ident;) => {};
+
+    (
+        @fields $ptr:ident;
+        $field:ident: in $($inner:ident)::+ { $($fields:tt)* } $(, $($rest:tt)*)?
+    ) => {
+        let field = unsafe { ::core::ptr::addr_of_mut!((*$ptr).$field) };
+        $crate::emplace!(@check field; $($inner)::+; []; $($fields)*);
+        $crate::emplace!(@fields field; $($fields)*);
+        $crate::emplace!(@fields $ptr; $($($rest)*)?);
+    };
+
+    (@fields $ptr:ident; $field:ident: $value:expr $(, $($rest:tt)*)?) => {
+        unsafe { $crate::FieldSlot::new(::core::ptr::addr_of_mut!((*$ptr).$field)) }
+            .write($value);
+        $crate::emplace!(@fields $ptr; $($($rest)*)?);
+    };
+}
+
+/// A field of a struct being built by `emplace!`, so that the expression of
+/// its value is evaluated outside of any `unsafe` block.
+#[doc(hidden)]
+pub struct FieldSlot<T> {
+    ptr: *mut T,
+}
+
+impl<T> FieldSlot<T> {
+    /// `ptr` must be valid for writes. It may be unaligned, as the fields of
+    /// `#[repr(packed)]` structs are.
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn new(ptr: *mut T) -> Self {
+        Self { ptr }
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn write(self, value: T) {
+        unsafe { self.ptr.write_unaligned(value) }
+    }
+}
+
+/// An uninitialized allocation, freed through `A`, by default the crate's
+/// `Allocator`, when dropped.
+pub struct UninitAlloc<T, A = Allocator>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator,
 {
     ptr: NonNull<T>,
+    alloc: A,
     _marker: PhantomData<T>,
 }
 
 impl<T> Default for UninitAlloc<T> {
     #[inline]
+    #[track_caller]
     fn default() -> Self {
         Self::new()
     }
 }
 
 impl<T> UninitAlloc<T> {
-    #[inline]
-    pub fn new() -> Self {
-        Self::try_new().unwrap_or_else(|err| panic!("UninitAlloc::new: {}", err))
+    panicking! {
+        #[inline]
+        #[track_caller]
+        pub fn new() -> Self {
+            Self::try_new().unwrap_or_else(|err| panic!("UninitAlloc::new: {}", err))
+        }
     }
 
     #[inline]
+    #[track_caller]
     pub fn try_new() -> Result<Self, AllocError> {
         let layout = Layout::new::<T>();
         let res = if layout.size() == 0 {
@@ -38,35 +178,66 @@ impl<T> UninitAlloc<T> {
                 .map(NonNull::cast::<T>)
                 .ok_or(AllocError { layout })
         };
-        res.map(|ptr| Self {
-            ptr,
-            _marker: PhantomData,
-        })
+        #[cfg(feature = "track-callers")]
+        if let (Ok(ptr), true) = (&res, layout.size() != 0) {
+            crate::leak::record(ptr.as_ptr().cast(), layout.size());
+        }
+        res.map(|ptr| unsafe { Self::from_raw(ptr) })
     }
+}
 
+impl<T, A> UninitAlloc<T, A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    panicking! {
+        /// Creates an allocation in `alloc`. In case of allocation error, the
+        /// function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::{Allocator, LimitedAlloc, UninitAlloc};
+        ///
+        /// let limited = LimitedAlloc::new(Allocator::new(), 1024);
+        /// let alloc = UninitAlloc::<[u64; 8], _>::new_in(&limited);
+        /// assert_eq!(limited.used(), 64);
+        /// drop(alloc);
+        /// assert_eq!(limited.used(), 0);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn new_in(alloc: A) -> Self {
+            Self::try_new_in(alloc).unwrap_or_else(|err| panic!("UninitAlloc::new_in: {}", err))
+        }
+    }
+
+    /// Creates an allocation in `alloc`. In case of allocation error, `Err`
+    /// is returned.
     #[inline]
-    pub const fn init(self, value: T) -> OwnedAlloc<T> {
-        let raw = self.into_raw();
+    pub fn try_new_in(alloc: A) -> Result<Self, AllocError> {
+        let layout = Layout::new::<T>();
+        let ptr = alloc.allocate(layout).map_err(|_| AllocError { layout })?;
+        Ok(unsafe { Self::from_raw_in(ptr.cast(), alloc) })
+    }
+
+    #[inline]
+    pub const fn init(self, value: T) -> OwnedAlloc<T, A> {
+        let raw = self.ptr;
         unsafe {
             raw.as_ptr().write(value);
-            OwnedAlloc::from_raw(raw)
+            let alloc = (&self.alloc as *const A).read();
+            mem::forget(self);
+            OwnedAlloc::from_raw_in(raw, alloc)
         }
     }
 }
+
 impl<T> UninitAlloc<T>
 where
     T: ?Sized,
 {
-    #[inline]
-    pub unsafe fn init_in_place<F>(self, init: F) -> OwnedAlloc<T>
-    where
-        F: FnOnce(&mut T),
-    {
-        let mut raw = self.into_raw();
-        init(raw.as_mut());
-        OwnedAlloc::from_raw(raw)
-    }
-
     #[inline]
     pub const fn into_raw(self) -> NonNull<T> {
         let ptr = self.ptr;
@@ -76,21 +247,365 @@ where
 
     #[inline]
     pub const unsafe fn from_raw(ptr: NonNull<T>) -> Self {
+        Self::from_raw_in(ptr, Allocator::new())
+    }
+}
+
+impl<T, A> UninitAlloc<T, A>
+where
+    T: ?Sized,
+    A: crate::alloc_api::Allocator,
+{
+    #[inline]
+    pub unsafe fn init_in_place<F>(self, init: F) -> OwnedAlloc<T, A>
+    where
+        F: FnOnce(&mut T),
+    {
+        let (mut raw, alloc) = self.into_raw_with_allocator();
+        init(raw.as_mut());
+        OwnedAlloc::from_raw_in(raw, alloc)
+    }
+
+    /// Takes ownership of a block allocated in `alloc`.
+    ///
+    /// # Safety
+    /// `ptr` must point to a block allocated in `alloc` with the layout of
+    /// the value.
+    #[inline]
+    pub const unsafe fn from_raw_in(ptr: NonNull<T>, alloc: A) -> Self {
         Self {
             ptr,
+            alloc,
             _marker: PhantomData,
         }
     }
 
+    /// "Forgets" freeing the allocation, and returns the raw pointer along
+    /// with the allocator.
+    #[inline]
+    pub const fn into_raw_with_allocator(self) -> (NonNull<T>, A) {
+        let ptr = self.ptr;
+        let alloc = unsafe { (&self.alloc as *const A).read() };
+        mem::forget(self);
+        (ptr, alloc)
+    }
+
     #[inline]
     pub const fn raw(&self) -> NonNull<T> {
         self.ptr
     }
+
+    /// The allocator the block is freed through.
+    #[inline]
+    pub const fn allocator(&self) -> &A {
+        &self.alloc
+    }
+}
+
+impl<T> UninitAlloc<[T]> {
+    panicking! {
+        /// Creates an allocation for a slice of `len` elements. In case of
+        /// allocation error or overflow, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::UninitAlloc;
+        ///
+        /// let squares = UninitAlloc::<[u32]>::new_slice(4).init_from_fn(|i| (i * i) as u32);
+        /// assert_eq!(*squares, [0, 1, 4, 9]);
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn new_slice(len: usize) -> Self {
+            match Self::try_new_slice(len) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                },
+            }
+        }
+    }
+
+    /// Creates an allocation for a slice of `len` elements. In case of
+    /// allocation error or overflow, `Err` is returned.
+    #[inline]
+    #[track_caller]
+    pub fn try_new_slice(len: usize) -> Result<Self, RawVecError> {
+        let raw = RawVec::<T>::try_with_capacity(len)?.into_raw_slice();
+        Ok(unsafe { Self::from_raw(raw) })
+    }
 }
 
-impl<T> Drop for UninitAlloc<T>
+impl<T, A> UninitAlloc<[T], A>
+where
+    A: crate::alloc_api::Allocator,
+{
+    panicking! {
+        /// Creates an allocation for a slice of `len` elements in `alloc`. In
+        /// case of allocation error or overflow, the function panics.
+        #[inline]
+        #[track_caller]
+        pub fn new_slice_in(len: usize, alloc: A) -> Self {
+            match Self::try_new_slice_in(len, alloc) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                },
+            }
+        }
+    }
+
+    /// Creates an allocation for a slice of `len` elements in `alloc`. In
+    /// case of allocation error or overflow, `Err` is returned.
+    #[inline]
+    pub fn try_new_slice_in(len: usize, alloc: A) -> Result<Self, RawVecError> {
+        let (raw, alloc) = RawVec::try_with_capacity_in(len, alloc)?.into_raw_slice_with_allocator();
+        Ok(unsafe { Self::from_raw_in(raw, alloc) })
+    }
+
+    /// Number of elements of the allocation.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.ptr.len()
+    }
+
+    /// Whether the allocation has no elements.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Initializes each element with `init` called on its index, in order.
+    /// If `init` panics, the elements initialized so far are dropped and the
+    /// allocation is freed.
+    pub fn init_from_fn<F>(self, mut init: F) -> OwnedAlloc<[T], A>
+    where
+        F: FnMut(usize) -> T,
+    {
+        // Dropped before `self` when unwinding.
+        let mut guard = SliceGuard {
+            start: self.ptr.cast::<T>().as_ptr(),
+            written: 0,
+        };
+        while guard.written < self.len() {
+            unsafe { guard.start.add(guard.written).write(init(guard.written)) };
+            guard.written += 1;
+        }
+        mem::forget(guard);
+        let (raw, alloc) = self.into_raw_with_allocator();
+        unsafe { OwnedAlloc::from_raw_in(raw, alloc) }
+    }
+
+    /// Initializes the elements with clones of the ones of `slice`. If the
+    /// lengths differ, the function panics.
+    #[inline]
+    #[track_caller]
+    pub fn init_from_slice(self, slice: &[T]) -> OwnedAlloc<[T], A>
+    where
+        T: Clone,
+    {
+        assert_eq!(self.len(), slice.len(), "length of the slice and of the allocation differ");
+        self.init_from_fn(|i| slice[i].clone())
+    }
+}
+
+/// The elements of a slice allocation initialized so far, dropped if the
+/// initialization panics.
+struct SliceGuard<T> {
+    start: *mut T,
+    written: usize,
+}
+
+impl<T> Drop for SliceGuard<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ptr::slice_from_raw_parts_mut(self.start, self.written).drop_in_place() }
+    }
+}
+
+impl UninitAlloc<str> {
+    panicking! {
+        /// Creates an allocation for a string of `len` bytes. In case of
+        /// allocation error, the function panics.
+        ///
+        /// # Example
+        /// ```rust
+        /// extern crate owned_alloc;
+        ///
+        /// use owned_alloc::UninitAlloc;
+        ///
+        /// let greeting = UninitAlloc::<str>::new_bytes(5).init_from_str("hello");
+        /// assert_eq!(&*greeting, "hello");
+        ///
+        /// let alloc = UninitAlloc::<str>::new_bytes(2);
+        /// let (err, alloc) = alloc.init_utf8(&[0xc3, 0x28]).unwrap_err();
+        /// assert_eq!(err.valid_up_to(), 0);
+        /// assert_eq!(&*alloc.init_utf8("é".as_bytes()).unwrap(), "é");
+        /// ```
+        #[inline]
+        #[track_caller]
+        pub fn new_bytes(len: usize) -> Self {
+            match Self::try_new_bytes(len) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                },
+            }
+        }
+    }
+
+    /// Creates an allocation for a string of `len` bytes. In case of
+    /// allocation error or overflow, `Err` is returned.
+    #[inline]
+    #[track_caller]
+    pub fn try_new_bytes(len: usize) -> Result<Self, RawVecError> {
+        let bytes = RawVec::<u8>::try_with_capacity(len)?.into_raw_slice();
+        Ok(unsafe { Self::from_raw(NonNull::new_unchecked(bytes.as_ptr() as *mut str)) })
+    }
+
+    /// Number of bytes of the allocation.
+    #[inline]
+    pub fn len(&self) -> usize {
+        (self.ptr.as_ptr() as *mut [u8]).len()
+    }
+
+    /// Whether the allocation has no bytes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Initializes the allocation with a copy of `string`. If the lengths
+    /// differ, the function panics.
+    #[inline]
+    #[track_caller]
+    pub fn init_from_str(self, string: &str) -> OwnedAlloc<str> {
+        match self.init_utf8(string.as_bytes()) {
+            Ok(alloc) => alloc,
+            Err(_) => unreachable!("a str is valid UTF-8"),
+        }
+    }
+
+    /// Initializes the allocation with a copy of `bytes`, if they are valid
+    /// UTF-8. Otherwise, the error is returned with the allocation. If the
+    /// lengths differ, the function panics.
+    #[track_caller]
+    pub fn init_utf8(self, bytes: &[u8]) -> Result<OwnedAlloc<str>, (Utf8Error, Self)> {
+        assert_eq!(self.len(), bytes.len(), "length of the string and of the allocation differ");
+        if let Err(err) = str::from_utf8(bytes) {
+            return Err((err, self));
+        }
+        let raw = self.into_raw();
+        unsafe {
+            raw.cast::<u8>()
+                .as_ptr()
+                .copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+            Ok(OwnedAlloc::from_raw(raw))
+        }
+    }
+}
+
+#[cfg(feature = "const-heap")]
+impl<T> UninitAlloc<[T]> {
+    /// Creates an allocation for `len` elements, usable in constant
+    /// evaluation. See `RawVec::const_with_capacity`.
+    #[inline]
+    #[track_caller]
+    pub const fn const_new_slice(len: usize) -> Self {
+        unsafe { Self::from_raw(RawVec::const_with_capacity(len).into_raw_slice()) }
+    }
+
+    /// Freezes the allocation into a `&'static` slice. See
+    /// `RawVec::into_static_slice`.
+    ///
+    /// # Safety
+    /// This function is `unsafe` because every element must be initialized.
+    #[inline]
+    pub const unsafe fn into_static(self) -> &'static [T] {
+        let raw = self.into_raw();
+        RawVec::from_raw_slice(raw).into_static_slice(raw.len())
+    }
+}
+
+#[cfg(feature = "std")]
+impl UninitAlloc<[u8]> {
+    /// Fills the buffer from `reader`, reading until it is full or the end of
+    /// the input is reached, and returns it initialized with the number of
+    /// bytes read. The bytes past that number are zeroed. Reads interrupted
+    /// by a signal are retried; on any other error, the buffer is freed.
+    ///
+    /// With the `nightly` feature, the bytes are read straight into the
+    /// uninitialized memory; otherwise, the buffer is zeroed first.
+    ///
+    /// # Example
+    /// ```rust
+    /// extern crate owned_alloc;
+    ///
+    /// use owned_alloc::{RawVec, UninitAlloc};
+    ///
+    /// let alloc = UninitAlloc::from(RawVec::<u8>::with_capacity(8));
+    /// let (buf, len) = alloc.init_from_reader(&b"header"[..]).unwrap();
+    /// assert_eq!(len, 6);
+    /// assert_eq!(*buf, *b"header\0\0");
+    /// ```
+    pub fn init_from_reader<R>(self, mut reader: R) -> std::io::Result<(OwnedAlloc<[u8]>, usize)>
+    where
+        R: std::io::Read,
+    {
+        let raw = self.raw();
+        let buf = unsafe {
+            core::slice::from_raw_parts_mut(raw.as_ptr().cast::<mem::MaybeUninit<u8>>(), raw.len())
+        };
+        #[cfg(not(feature = "nightly"))]
+        buf.fill(mem::MaybeUninit::new(0));
+        let mut filled = 0;
+        while filled < buf.len() {
+            match read_uninit(&mut reader, &mut buf[filled ..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        buf[filled ..].fill(mem::MaybeUninit::new(0));
+        Ok((unsafe { OwnedAlloc::from_raw(self.into_raw()) }, filled))
+    }
+}
+
+/// Reads once from `reader` into the uninitialized `buf`, returning the
+/// number of bytes read.
+#[cfg(all(feature = "std", feature = "nightly"))]
+#[inline]
+fn read_uninit<R>(reader: &mut R, buf: &mut [mem::MaybeUninit<u8>]) -> std::io::Result<usize>
+where
+    R: std::io::Read,
+{
+    let mut buf = std::io::BorrowedBuf::from(buf);
+    reader.read_buf(buf.unfilled())?;
+    Ok(buf.len())
+}
+
+/// Reads once from `reader` into `buf`, returning the number of bytes read.
+/// Stable Rust cannot read into uninitialized memory: `buf` must have been
+/// zeroed.
+#[cfg(all(feature = "std", not(feature = "nightly")))]
+#[inline]
+fn read_uninit<R>(reader: &mut R, buf: &mut [mem::MaybeUninit<u8>]) -> std::io::Result<usize>
+where
+    R: std::io::Read,
+{
+    reader.read(unsafe { &mut *(buf as *mut [mem::MaybeUninit<u8>] as *mut [u8]) })
+}
+
+impl<T, A> Drop for UninitAlloc<T, A>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator,
 {
     #[inline]
     fn drop(&mut self) {
@@ -98,15 +613,20 @@ where
             let layout = Layout::for_value(self.ptr.as_ref());
 
             if layout.size() != 0 {
-                ALLOCATOR.dealloc(self.ptr.cast().as_ptr(), layout);
+                #[cfg(feature = "track-callers")]
+                if crate::leak::tracks::<A>() {
+                    crate::leak::forget(self.ptr.as_ptr().cast());
+                }
+                self.alloc.deallocate(self.ptr.cast(), layout);
             }
         }
     }
 }
 
-impl<T> core::fmt::Debug for UninitAlloc<T>
+impl<T, A> core::fmt::Debug for UninitAlloc<T, A>
 where
     T: ?Sized,
+    A: crate::alloc_api::Allocator,
 {
     #[inline]
     fn fmt(&self, fmtr: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -114,18 +634,32 @@ where
     }
 }
 
-impl<T> const From<RawVec<T>> for UninitAlloc<[T]> {
-    #[inline]
-    fn from(alloc: RawVec<T>) -> Self {
-        Self {
-            ptr: alloc.into_raw_slice(),
-            _marker: PhantomData,
+const_impl! {
+    impl<T, A> From<RawVec<T, A>> for UninitAlloc<[T], A>
+    where
+        A: crate::alloc_api::Allocator,
+    {
+        #[inline]
+        fn from(alloc: RawVec<T, A>) -> Self {
+            let (ptr, alloc) = alloc.into_raw_slice_with_allocator();
+            unsafe { Self::from_raw_in(ptr, alloc) }
         }
     }
 }
 
-unsafe impl<T> const Send for UninitAlloc<T> where T: ?Sized + Send {}
-unsafe impl<T> const Sync for UninitAlloc<T> where T: ?Sized + Sync {}
+unsafe impl<T, A> Send for UninitAlloc<T, A>
+where
+    T: ?Sized + Send,
+    A: crate::alloc_api::Allocator + Send,
+{
+}
+
+unsafe impl<T, A> Sync for UninitAlloc<T, A>
+where
+    T: ?Sized + Sync,
+    A: crate::alloc_api::Allocator + Sync,
+{
+}
 
 #[cfg(test)]
 mod test {
@@ -142,4 +676,97 @@ mod test {
         let alloc = unsafe { UninitAlloc::from_raw(raw) };
         assert_eq!(alloc.raw(), raw_borrowed);
     }
+
+    #[test]
+    fn allocator_outlives_init_and_drop() {
+        use crate::{Allocator, LimitedAlloc};
+
+        let limited = LimitedAlloc::new(Allocator::new(), 16);
+        let alloc = UninitAlloc::<[u32; 4], _>::try_new_in(&limited).unwrap();
+        assert!(UninitAlloc::<u8, _>::try_new_in(&limited).is_err());
+        let (val, alloc) = alloc.init([1, 2, 3, 4]).move_inner();
+        assert_eq!((val, limited.used()), ([1, 2, 3, 4], 16));
+        drop(alloc);
+        assert_eq!(limited.used(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn slice_init_panic_drops_the_prefix() {
+        use alloc::rc::Rc;
+
+        let counted = Rc::new(());
+        let alloc = UninitAlloc::<[Rc<()>]>::new_slice(4);
+        let res = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+            alloc.init_from_fn(|i| if i < 3 { counted.clone() } else { panic!("fourth") })
+        }));
+        assert!(res.is_err());
+        assert_eq!(Rc::strong_count(&counted), 1);
+
+        let pair = [counted.clone(), counted.clone()];
+        let clones = UninitAlloc::<[Rc<()>]>::new_slice(2).init_from_slice(&pair);
+        drop(pair);
+        assert_eq!((clones.len(), Rc::strong_count(&counted)), (2, 3));
+    }
+
+    #[test]
+    fn str_checked_init() {
+        let alloc = UninitAlloc::<str>::new_bytes(3);
+        assert_eq!(alloc.len(), 3);
+        let (err, alloc) = alloc.init_utf8(b"a\xffb").unwrap_err();
+        assert_eq!(err.valid_up_to(), 1);
+        assert_eq!(&*alloc.init_utf8(b"abc").unwrap(), "abc");
+        assert!(UninitAlloc::<str>::new_bytes(0).init_from_str("").is_empty());
+    }
+
+    #[test]
+    fn emplace_nested_fields() {
+        struct Inner {
+            tags: alloc::vec::Vec<&'static str>,
+            count: u8,
+        }
+
+        struct Outer {
+            inner: Inner,
+            table: [u32; 1024],
+        }
+
+        let mut count = 0;
+        let outer = crate::emplace!(UninitAlloc::new() => Outer {
+            table: [3; 1024],
+            inner: in Inner {
+                count: { count += 1; count },
+                tags: alloc::vec!["a", "b"],
+            },
+        });
+        assert_eq!(outer.inner.tags, ["a", "b"]);
+        assert_eq!((outer.inner.count, outer.table[1023]), (1, 3));
+    }
+
+    #[test]
+    fn emplace_packed_fields() {
+        #[repr(C, packed)]
+        struct Packed {
+            tag: u8,
+            value: u64,
+        }
+
+        let packed = crate::emplace!(UninitAlloc::new() => Packed { tag: 1, value: 0xfeed });
+        let value = packed.value;
+        assert_eq!((packed.tag, value), (1, 0xfeed));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader_split_in_chunks() {
+        use crate::RawVec;
+        use std::io::Read;
+
+        let input = (0 .. 100).collect::<alloc::vec::Vec<u8>>();
+        // Chained readers stop at the end of the first one.
+        let reader = input[.. 30].chain(&input[30 ..]);
+        let alloc = UninitAlloc::from(RawVec::<u8>::with_capacity(64));
+        let (buf, len) = alloc.init_from_reader(reader).unwrap();
+        assert_eq!((len, &*buf), (64, &input[.. 64]));
+    }
 }
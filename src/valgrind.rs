@@ -0,0 +1,130 @@
+//! Valgrind client requests describing the memory the crate recycles, so
+//! memcheck reports accesses to blocks sitting in its free lists and arenas
+//! instead of drowning in false positives about its pools.
+//!
+//! With the `valgrind` feature, on x86-64 and AArch64, the requests are the
+//! magic instruction sequences of `valgrind.h`, which do nothing when not
+//! running under Valgrind. Otherwise, these functions do nothing.
+
+const MALLOCLIKE_BLOCK: usize = 0x1301;
+const FREELIKE_BLOCK: usize = 0x1302;
+const CREATE_MEMPOOL: usize = 0x1303;
+const DESTROY_MEMPOOL: usize = 0x1304;
+const MEMPOOL_ALLOC: usize = 0x1305;
+const MEMPOOL_FREE: usize = 0x1306;
+const MEMPOOL_TRIM: usize = 0x1307;
+const CHANGE_ERR_DISABLEMENT: usize = 0x1801;
+/// Base of the requests of memcheck, `VG_USERREQ_TOOL_BASE('M', 'C')`.
+const MEMCHECK: usize = (b'M' as usize) << 24 | (b'C' as usize) << 16;
+const MAKE_MEM_NOACCESS: usize = MEMCHECK;
+const MAKE_MEM_UNDEFINED: usize = MEMCHECK + 1;
+
+#[cfg(all(feature = "valgrind", target_arch = "x86_64"))]
+#[inline(always)]
+fn request(args: [usize; 6]) {
+    unsafe {
+        core::arch::asm!(
+            "rol rdi, 3",
+            "rol rdi, 13",
+            "rol rdi, 61",
+            "rol rdi, 51",
+            "xchg rbx, rbx",
+            in("rax") args.as_ptr(),
+            inout("rdx") 0usize => _,
+            options(nostack, preserves_flags),
+        );
+    }
+}
+
+#[cfg(all(feature = "valgrind", target_arch = "aarch64"))]
+#[inline(always)]
+fn request(args: [usize; 6]) {
+    unsafe {
+        core::arch::asm!(
+            "ror x12, x12, #3",
+            "ror x12, x12, #13",
+            "ror x12, x12, #51",
+            "ror x12, x12, #61",
+            "orr x10, x10, x10",
+            in("x4") args.as_ptr(),
+            inout("x3") 0usize => _,
+            options(nostack),
+        );
+    }
+}
+
+#[cfg(not(all(feature = "valgrind", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+#[inline(always)]
+fn request(args: [usize; 6]) {
+    let _ = args;
+}
+
+/// Tells that a block of `size` bytes was handed out at `ptr`, by an
+/// allocator Valgrind does not know.
+#[inline(always)]
+pub(crate) fn malloclike(ptr: *const u8, size: usize, zeroed: bool) {
+    request([MALLOCLIKE_BLOCK, ptr as usize, size, 0, zeroed as usize, 0]);
+}
+
+/// Tells that a block given to `malloclike` was freed.
+#[inline(always)]
+pub(crate) fn freelike(ptr: *const u8) {
+    request([FREELIKE_BLOCK, ptr as usize, 0, 0, 0, 0]);
+}
+
+/// Tells that blocks will be handed out from a pool anchored at `pool`,
+/// which must not move.
+#[inline(always)]
+pub(crate) fn create_mempool(pool: *const u8) {
+    request([CREATE_MEMPOOL, pool as usize, 0, 0, 0, 0]);
+}
+
+/// Tells that the pool anchored at `pool` and all its blocks are gone.
+#[inline(always)]
+pub(crate) fn destroy_mempool(pool: *const u8) {
+    request([DESTROY_MEMPOOL, pool as usize, 0, 0, 0, 0]);
+}
+
+/// Tells that a block of `size` bytes was handed out at `ptr` from a pool.
+#[inline(always)]
+pub(crate) fn mempool_alloc(pool: *const u8, ptr: *const u8, size: usize) {
+    request([MEMPOOL_ALLOC, pool as usize, ptr as usize, size, 0, 0]);
+}
+
+/// Tells that the block at `ptr` was given back to a pool.
+#[inline(always)]
+pub(crate) fn mempool_free(pool: *const u8, ptr: *const u8) {
+    request([MEMPOOL_FREE, pool as usize, ptr as usize, 0, 0, 0]);
+}
+
+/// Tells that the blocks of a pool outside of `size` bytes from `ptr` were
+/// all freed at once.
+#[inline(always)]
+pub(crate) fn mempool_trim(pool: *const u8, ptr: *const u8, size: usize) {
+    request([MEMPOOL_TRIM, pool as usize, ptr as usize, size, 0, 0]);
+}
+
+/// Stops reporting errors of the current thread, while an allocator reads
+/// and writes its bookkeeping in memory it told is free.
+#[inline(always)]
+pub(crate) fn disable_errors() {
+    request([CHANGE_ERR_DISABLEMENT, 1, 0, 0, 0, 0]);
+}
+
+/// Reports errors again, after `disable_errors`.
+#[inline(always)]
+pub(crate) fn enable_errors() {
+    request([CHANGE_ERR_DISABLEMENT, usize::MAX, 0, 0, 0, 0]);
+}
+
+/// Marks `size` bytes from `ptr` as unaddressable.
+#[inline(always)]
+pub(crate) fn make_noaccess(ptr: *const u8, size: usize) {
+    request([MAKE_MEM_NOACCESS, ptr as usize, size, 0, 0, 0]);
+}
+
+/// Marks `size` bytes from `ptr` as addressable, with undefined contents.
+#[inline(always)]
+pub(crate) fn make_undefined(ptr: *const u8, size: usize) {
+    request([MAKE_MEM_UNDEFINED, ptr as usize, size, 0, 0, 0]);
+}
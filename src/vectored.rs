@@ -0,0 +1,145 @@
+use crate::{OwnedAlloc, RawVec, RawVecError};
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// A set of zeroed byte buffers allocated in one call, from a list of
+/// lengths like the one of an `iovec` array, and freed together when the set
+/// is dropped. Meant to set up scatter/gather I/O: with the `std` feature,
+/// the buffers are readily turned into `IoSlice`s and `IoSliceMut`s.
+///
+/// The set dereferences to the slice of its buffers, and `into_vec` takes
+/// them apart.
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::IoBuffers;
+///
+/// let mut bufs = IoBuffers::new(&[4, 16, 0]);
+/// assert_eq!(bufs.len(), 3);
+/// assert_eq!(bufs.total_len(), 20);
+///
+/// bufs[1][.. 5].copy_from_slice(b"hello");
+/// assert_eq!(&bufs[1][.. 5], b"hello");
+/// assert!(bufs[2].is_empty());
+/// ```
+pub struct IoBuffers {
+    bufs: Vec<OwnedAlloc<[u8]>>,
+}
+
+impl IoBuffers {
+    panicking! {
+        /// Allocates one zeroed buffer per length. In case of allocation error
+        /// or overflow, the function panics.
+        #[inline]
+        #[track_caller]
+        pub fn new(lens: &[usize]) -> Self {
+            match Self::try_new(lens) {
+                Ok(this) => this,
+                Err(RawVecError::Alloc(err)) => panic!("{}", err),
+                Err(RawVecError::Layout(err)) => {
+                    panic!("Capacity overflows memory size: {}", err)
+                },
+            }
+        }
+    }
+
+    /// Allocates one zeroed buffer per length. In case of allocation error
+    /// or overflow, `Err` is returned and the buffers allocated so far are
+    /// freed.
+    #[track_caller]
+    pub fn try_new(lens: &[usize]) -> Result<Self, RawVecError> {
+        let mut bufs = Vec::with_capacity(lens.len());
+        for &len in lens {
+            let buf = RawVec::<u8>::try_with_capacity(len)?;
+            unsafe {
+                buf.raw().as_ptr().write_bytes(0, len);
+                bufs.push(OwnedAlloc::from_raw(buf.into_raw_slice()));
+            }
+        }
+        Ok(Self { bufs })
+    }
+
+    /// Total length of the buffers, in bytes.
+    #[inline]
+    pub fn total_len(&self) -> usize {
+        self.bufs.iter().map(|buf| buf.len()).sum()
+    }
+
+    /// Takes the buffers apart, so each one is freed on its own.
+    #[inline]
+    pub fn into_vec(self) -> Vec<OwnedAlloc<[u8]>> {
+        self.bufs
+    }
+
+    /// The buffers, as slices to write out with vectored I/O.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn io_slices(&self) -> Vec<std::io::IoSlice<'_>> {
+        self.bufs.iter().map(|buf| std::io::IoSlice::new(buf)).collect()
+    }
+
+    /// The buffers, as slices to read into with vectored I/O.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn io_slices_mut(&mut self) -> Vec<std::io::IoSliceMut<'_>> {
+        self.bufs
+            .iter_mut()
+            .map(|buf| std::io::IoSliceMut::new(buf))
+            .collect()
+    }
+}
+
+impl Deref for IoBuffers {
+    type Target = [OwnedAlloc<[u8]>];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.bufs
+    }
+}
+
+impl DerefMut for IoBuffers {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bufs
+    }
+}
+
+impl fmt::Debug for IoBuffers {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IoBuffers {{ len: {}, total_len: {} }}", self.len(), self.total_len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IoBuffers;
+
+    #[test]
+    fn zeroed_and_taken_apart() {
+        let bufs = IoBuffers::new(&[3, 0, 1000]);
+        assert!(bufs.iter().all(|buf| buf.iter().all(|&byte| byte == 0)));
+        let lens: alloc::vec::Vec<_> = bufs.into_vec().iter().map(|buf| buf.len()).collect();
+        assert_eq!(lens, [3, 0, 1000]);
+        assert!(IoBuffers::try_new(&[1, usize::MAX]).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn scatter_read() {
+        use std::io::Read;
+
+        let mut bufs = IoBuffers::new(&[2, 3]);
+        let mut source: &[u8] = b"abcde";
+        let read = source.read_vectored(&mut bufs.io_slices_mut()).unwrap();
+        assert_eq!(read, 5);
+        assert_eq!((&*bufs[0], &*bufs[1]), (&b"ab"[..], &b"cde"[..]));
+        assert_eq!(bufs.io_slices().len(), 2);
+    }
+}
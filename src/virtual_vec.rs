@@ -0,0 +1,205 @@
+use crate::{page_size, sys, OsError};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    slice,
+};
+
+/// A vector which reserves address space for its maximum capacity up front
+/// and commits pages only as its length grows. Elements never move, so
+/// pushing never copies and pointers to elements stay valid until the
+/// elements are removed.
+///
+/// Reserved but uncommitted address space costs no memory, so the maximum
+/// capacity can be generous (e.g. gigabytes on 64-bit targets).
+///
+/// # Example
+/// ```rust
+/// extern crate owned_alloc;
+///
+/// use owned_alloc::VirtualVec;
+///
+/// let mut log = VirtualVec::<u64>::with_capacity(1 << 24).unwrap();
+/// log.push(1);
+/// let first = &log[0] as *const u64;
+/// for i in 0 .. 10_000 {
+///     log.push(i);
+/// }
+/// assert_eq!(first, &log[0] as *const u64);
+/// assert!(log.committed() < log.reserved());
+/// ```
+pub struct VirtualVec<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    committed: usize,
+    reserved: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> VirtualVec<T> {
+    /// Reserves address space for `cap` elements, without committing any.
+    /// Panics if the size in bytes overflows.
+    pub fn with_capacity(cap: usize) -> Result<Self, OsError> {
+        let size = mem::size_of::<T>();
+        let bytes = size.checked_mul(cap).expect("capacity overflow");
+        let page = page_size();
+        let reserved = bytes
+            .div_ceil(page)
+            .checked_mul(page)
+            .expect("capacity overflow");
+        let ptr = if reserved == 0 {
+            NonNull::dangling()
+        } else {
+            unsafe { NonNull::new_unchecked(sys::reserve(reserved)?.cast()) }
+        };
+        Ok(Self {
+            ptr,
+            len: 0,
+            cap: if size == 0 { usize::MAX } else { cap },
+            committed: 0,
+            reserved,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Maximum number of elements.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Bytes of address space reserved.
+    #[inline]
+    pub const fn reserved(&self) -> usize {
+        self.reserved
+    }
+
+    /// Bytes currently committed, i.e. backed by memory.
+    #[inline]
+    pub const fn committed(&self) -> usize {
+        self.committed
+    }
+
+    /// Appends an element. Panics if the vector is full or committing memory
+    /// fails.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        if self.try_push(value).is_err() {
+            panic!("VirtualVec is full or out of memory");
+        }
+    }
+
+    /// Appends an element. If the vector is full or committing memory fails,
+    /// the element is given back as `Err`.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.len == self.cap || self.commit_for(self.len + 1).is_err() {
+            return Err(value);
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes and returns the last element, if any. Its memory stays
+    /// committed.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+        }
+    }
+
+    /// Drops all elements. Their memory stays committed.
+    #[inline]
+    pub fn clear(&mut self) {
+        let elems = &mut **self as *mut [T];
+        self.len = 0;
+        unsafe { elems.drop_in_place() }
+    }
+
+    /// Commits enough pages for `len` elements, growing the committed range
+    /// geometrically to keep the number of system calls low.
+    fn commit_for(&mut self, len: usize) -> Result<(), OsError> {
+        let needed = len * mem::size_of::<T>();
+        if needed <= self.committed {
+            return Ok(());
+        }
+        let page = page_size();
+        let target = (needed.max(self.committed * 2).div_ceil(page) * page).min(self.reserved);
+        unsafe {
+            let start = self.ptr.cast::<u8>().as_ptr().add(self.committed);
+            sys::commit(start, target - self.committed)?;
+        }
+        self.committed = target;
+        Ok(())
+    }
+}
+
+impl<T> Deref for VirtualVec<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for VirtualVec<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Drop for VirtualVec<T> {
+    fn drop(&mut self) {
+        self.clear();
+        if self.reserved != 0 {
+            unsafe { sys::release(self.ptr.cast().as_ptr(), self.reserved) }
+        }
+    }
+}
+
+impl<T> fmt::Debug for VirtualVec<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "VirtualVec {{ pointer: {:?}, len: {}, committed: {}, reserved: {} }}",
+            self.ptr, self.len, self.committed, self.reserved
+        )
+    }
+}
+
+unsafe impl<T> Send for VirtualVec<T> where T: Send {}
+unsafe impl<T> Sync for VirtualVec<T> where T: Sync {}
+
+#[cfg(test)]
+mod test {
+    use super::VirtualVec;
+    use crate::page_size;
+
+    #[test]
+    fn commits_on_demand_up_to_capacity() {
+        let cap = 4 * page_size();
+        let mut vec = VirtualVec::<u8>::with_capacity(cap).unwrap();
+        assert_eq!(vec.committed(), 0);
+        vec.push(1);
+        assert_eq!(vec.committed(), page_size());
+
+        for i in 1 .. cap {
+            vec.push(i as u8);
+        }
+        assert_eq!(vec.committed(), cap);
+        assert_eq!(vec.try_push(0), Err(0));
+        assert_eq!(vec.pop(), Some((cap - 1) as u8));
+        assert_eq!(vec[1], 1);
+    }
+}